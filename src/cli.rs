@@ -0,0 +1,183 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use crondes::config::RecordType;
+
+/// Keeps Cloudflare DNS records in sync with the public IP address.
+///
+/// Without a subcommand crondes behaves like `crondes run` and starts the update daemon.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to a TOML or YAML config file. Environment variables override its values.
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Set a configuration value, named like its environment variable, e.g. `--set UPDATE_INTERVAL_SECS=300`.
+    /// Overrides the config file and the environment. Can be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_override, global = true)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Only use the job of the config file with this name instead of all of its jobs, e.g. to list its records.
+    #[arg(long, global = true)]
+    pub job: Option<String>,
+
+    /// Print the result of `update-once`, `verify`, `zones`, `records` and `ctl` as `text` or as `json` for scripts.
+    #[arg(long, value_enum, default_value_t = Output::Text, global = true)]
+    pub output: Output,
+
+    /// Run the update daemon as Windows service. Only used by the service control manager, see `crondes service`.
+    #[arg(long)]
+    pub service: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Format of the results printed to stdout. Log lines always go to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Human-readable lines and tables.
+    Text,
+    /// A single JSON document.
+    Json,
+}
+
+/// Available subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the update daemon (default).
+    Run,
+    /// Perform a single update cycle and exit.
+    UpdateOnce,
+    /// Check credentials and all configured records, then exit.
+    Verify,
+    /// List the zones accessible with the configured credentials.
+    Zones,
+    /// List all DNS records of the configured zone.
+    #[command(alias = "list-records")]
+    Records {
+        /// List this zone of `DNS_PROVIDER` instead, by ID or name.
+        #[arg(long)]
+        zone: Option<String>,
+    },
+    /// Create, delete or change a single DNS record of `DNS_PROVIDER`.
+    Record {
+        /// Use this zone of `DNS_PROVIDER` instead of the configured one, by ID or name.
+        #[arg(long, global = true)]
+        zone: Option<String>,
+        #[command(subcommand)]
+        command: RecordCommand,
+    },
+    /// Show the changes needed to make the zone match `SYNC_FILE`, without changing anything.
+    Plan,
+    /// Show the changes needed to make the zone match `SYNC_FILE` and carry them out after confirmation.
+    Apply {
+        /// Don't ask for confirmation. Required when not run in a terminal.
+        #[arg(long)]
+        auto_approve: bool,
+    },
+    /// Print the loaded configuration.
+    ShowConfig {
+        /// Print where each value comes from: `default`, `file`, `env` or `cli`.
+        #[arg(long)]
+        resolved: bool,
+    },
+    /// Print the most recent IP changes from the history file.
+    History {
+        /// Maximum number of entries to print, newest last.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only print changes of this record.
+        #[arg(long)]
+        record: Option<String>,
+    },
+    /// Print a systemd unit file running the daemon as `Type=notify` service with a watchdog.
+    SystemdUnit {
+        /// Seconds without a sign of life after which systemd restarts the daemon (`WatchdogSec`).
+        #[arg(long, default_value_t = 300)]
+        watchdog_secs: u64,
+    },
+    /// Manage the Windows service running the update daemon.
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    /// Control the running daemon through its control socket.
+    Ctl {
+        /// Path of the control socket. Defaults to `CONTROL_SOCKET` of the environment or the config file.
+        #[arg(long)]
+        socket: Option<String>,
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+/// Subcommands of `crondes service`, which need administrator rights.
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum ServiceCommand {
+    /// Register the service, started automatically at boot with the given `--config`.
+    Install,
+    /// Stop and remove the service.
+    Uninstall,
+    /// Start the service.
+    Start,
+    /// Stop the service.
+    Stop,
+}
+
+/// Subcommands of `crondes record`. Records are given by ID or by name, which must be unique then.
+#[derive(Debug, Clone, Subcommand)]
+pub enum RecordCommand {
+    /// Create a record, an A record unless `--type` says otherwise.
+    Create {
+        /// Name of the record, e.g. `home.example.com`.
+        name: String,
+        /// Content of the record, e.g. the IP address of an A record.
+        content: String,
+        /// Record type like `A`, `AAAA`, `CNAME` or `TXT`.
+        #[arg(long = "type", default_value = "A")]
+        record_type: String,
+        /// TTL in seconds, `CF_TTL` or automatic if not given.
+        #[arg(long)]
+        ttl: Option<u32>,
+        /// Serve the record through the Cloudflare proxy.
+        #[arg(long)]
+        proxied: bool,
+    },
+    /// Delete a record.
+    Delete {
+        /// ID or name of the record.
+        record: String,
+        /// Record type, `A` if not given for providers without record IDs; otherwise picks among records of the same name.
+        #[arg(long = "type")]
+        record_type: Option<RecordType>,
+    },
+    /// Replace the content of a record, keeping its name, type, TTL and everything else.
+    Set {
+        /// ID or name of the record.
+        record: String,
+        /// New content of the record.
+        content: String,
+        /// Record type, `A` if not given for providers without record IDs; otherwise picks among records of the same name.
+        #[arg(long = "type")]
+        record_type: Option<RecordType>,
+    },
+}
+
+/// Commands sent to the running daemon by `crondes ctl`.
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum CtlCommand {
+    /// Print the state of the daemon and its records.
+    Status,
+    /// Start an update cycle right away.
+    Trigger,
+    /// Reload the configuration and start an update cycle with it.
+    Reload,
+}
+
+/// Splits a `--set` argument into key and value.
+fn parse_override(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", arg)),
+    }
+}
@@ -1,22 +1,1517 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::ip::{self, IpFamily};
+use crate::logging::SyslogAddress;
+use crate::quiethours::QuietHours;
 
-/// Configuration for the Cloudflare DNS update tool.
+/// Configuration for the DNS update tool.
 ///
-/// This struct holds all required environment variables for updating a Cloudflare DNS record.
+/// The configuration can be loaded from environment variables only ([`Config::from_env`])
+/// or from a TOML/YAML file with environment variables overriding file values ([`Config::from_file`]).
+/// Every [`Secret`] can also be read from a file named by the variable with a `_FILE` suffix, e.g. `CF_API_TOKEN_FILE`,
+/// and so can the notification URL lists.
 ///
 /// Fields:
+/// - `provider`: The DNS provider hosting records that don't name their own (env: `DNS_PROVIDER`, default `cloudflare`).
 /// - `cloudflare_api_token`: The API token for authenticating with the Cloudflare API (env: `CF_API_TOKEN`).
-/// - `cloudflare_zone_id`: The Cloudflare Zone ID where the DNS record resides (env: `CF_ZONE_ID`).
-/// - `cloudflare_record_id`: The specific DNS record ID to update (env: `CF_RECORD_ID`).
-/// - `cloudflare_record_name`: The DNS record name to update (env: `CF_RECORD_NAME`).
-/// - `update_interval_secs`: The interval in seconds between update attempts (env: `UPDATE_INTERVAL_SECS`).
-#[derive(Debug)]
+///   Stored as a [`Secret`], so it is masked in `Debug` and `Display` output.
+/// - `cloudflare_api_email`, `cloudflare_api_key`: Account email and legacy Global API Key, used instead of
+///   the API token if no token is set (env: `CF_API_EMAIL`, `CF_API_KEY`).
+/// - `cloudflare_zone_id`: The Cloudflare Zone ID where the DNS records reside (env: `CF_ZONE_ID`).
+/// - `cloudflare_zone_name`: Domain of the zone, looked up at startup if `cloudflare_zone_id` is not set (env: `CF_ZONE_NAME`).
+/// - `cloudflare_api_url`: Base URL of the Cloudflare API, e.g. to go through a proxy (env: `CF_API_URL`,
+///   default `https://api.cloudflare.com/client/v4`).
+/// - `cloudflare_ttl`: TTL written on update, `1` means automatic. Keeps the record's TTL if unset (env: `CF_TTL`).
+/// - `cloudflare_proxied`: Proxy status written on update. Keeps the record's proxy status if unset (env: `CF_PROXIED`).
+/// - `cloudflare_comment`: Template for the record comment written on update, with `{timestamp}`, `{old_ip}`,
+///   `{new_ip}` and `{record}` filled in. Keeps the record's comment if empty (env: `CF_COMMENT`).
+/// - `cloudflare_tags`: Tags like `managed-by:crondes` added to records on update and create (env: `CF_TAGS`).
+/// - `porkbun`: Credentials and domain for the Porkbun provider (see [`PorkbunConfig`]).
+/// - `desec`: Token and domain for the deSEC provider (see [`DesecConfig`]).
+/// - `duckdns`: Token for the DuckDNS provider (see [`DuckDnsConfig`]).
+/// - `noip`: Credentials for the No-IP provider (see [`NoIpConfig`]).
+/// - `dyndns2`: Update URL and credentials for the generic dyndns2 provider (see [`Dyndns2Config`]).
+/// - `rfc2136`: Server and TSIG key for RFC 2136 dynamic updates (see [`Rfc2136Config`]).
+/// - `linode`: Token and domain for the Linode provider (see [`LinodeConfig`]).
+/// - `vultr`: API key and domain for the Vultr provider (see [`VultrConfig`]).
+/// - `namecheap`: Domain and DDNS password for the Namecheap provider (see [`NamecheapConfig`]).
+/// - `records`: The DNS records to keep in sync, either from `CF_RECORDS` (JSON list)
+///   or a single record from `CF_RECORD_ID` and/or `CF_RECORD_NAME`.
+/// - `record_pattern`: Glob like `*.home.example.com`; every A record of `DNS_PROVIDER` whose name matches is kept in
+///   sync as well. `*` matches any characters, `?` a single one. Disabled if empty (env: `CF_RECORD_PATTERN`).
+/// - `record_tag`: Tag like `managed-by:crondes`; every A record of `DNS_PROVIDER` carrying it is kept in sync as well.
+///   Cloudflare only, disabled if empty (env: `CF_RECORD_TAG`).
+/// - `update_mode`: Whether the daemon polls the public IP every interval or waits for webhooks
+///   (env: `UPDATE_MODE`, default `poll`, see [`UpdateMode`]).
+/// - `update_interval_secs`: The interval in seconds between update attempts (env: `UPDATE_INTERVAL_SECS`, default `60`).
+/// - `update_interval_secs_v6`: Separate interval in seconds for records holding an IPv6 address, `0` to check them
+///   together with the others every `update_interval_secs` (env: `UPDATE_INTERVAL_SECS_V6`, default `0`).
+/// - `update_interval_jitter_percent`: Randomly shorten or lengthen each interval by up to this percentage, so instances
+///   started at the same time drift apart (env: `UPDATE_INTERVAL_JITTER_PERCENT`, default `0`).
+/// - `network_watch`: Start an update cycle right away when the address or the default route of the host changes,
+///   instead of waiting for the next interval. Only supported on Linux (env: `NETWORK_WATCH`, default `true`).
+/// - `config_watch`: Reload the config file passed via `--config` when it changes and apply it from the next update
+///   cycle on (env: `CONFIG_WATCH`, default `true`).
+/// - `control_socket`: Path of a Unix domain socket accepting commands from `crondes ctl`. Disabled if empty
+///   (env: `CONTROL_SOCKET`).
+/// - `daemonize`: Detach from the terminal and keep running in the background. Only supported on Unix
+///   (env: `DAEMONIZE`, default `false`).
+/// - `pid_file`: Path of a file holding the process ID of the daemon, locked while it runs so a second instance
+///   refuses to start. Disabled if empty (env: `PID_FILE`).
+/// - `startup_splay_secs`: Wait a random time of up to this many seconds before the first update cycle of the daemon
+///   (env: `STARTUP_SPLAY_SECS`, default `0`).
+/// - `startup_delay_secs`: Wait this many seconds before the first update cycle of the daemon, e.g. for the network to
+///   come up at boot (env: `STARTUP_DELAY_SECS`, default `0`).
+/// - `startup_grace_secs`: For this many seconds after the startup delay, failed cycles are retried with backoff without
+///   counting as failed or stopping the daemon, and cycles wait while the system clock is not set
+///   (env: `STARTUP_GRACE_SECS`, default `0`).
+/// - `reread_every_n_cycles`: Read records confirmed to point to the public IP again only every N cycles, `1` reads them
+///   every cycle. Records are always read after failures and IP changes (env: `REREAD_EVERY_N_CYCLES`, default `10`).
+/// - `update_concurrency`: Maximum number of records read and written at the same time, including changes from
+///   `sync_file` (env: `UPDATE_CONCURRENCY`, default `4`).
+/// - `min_update_cooldown_secs`: Write a record at most once within this many seconds, changes detected in between
+///   wait for the next cycle after it. `0` writes every change right away (env: `MIN_UPDATE_COOLDOWN_SECS`, default `0`).
+/// - `force_overwrite`: Overwrite A and AAAA records changed outside of crondes. If disabled, a record no longer holding
+///   the content crondes last wrote or found is left alone and a conflict is notified (env: `FORCE_OVERWRITE`, default `true`).
+/// - `cycle_deadline_secs`: Abort an update cycle that takes longer than this many seconds and retry it with backoff,
+///   `0` to let cycles run as long as they need (env: `CYCLE_DEADLINE_SECS`, default `300`).
+/// - `connectivity_check`: How the daemon checks that the network is up before each cycle. Cycles are skipped without
+///   counting as failed while it is down (env: `CONNECTIVITY_CHECK`, default `none`, see [`ConnectivityCheck`]).
+/// - `connectivity_target`: `host:port` connected to, name resolved or host pinged by `connectivity_check`, a
+///   well-known host of Cloudflare if empty (env: `CONNECTIVITY_TARGET`).
+/// - `quiet_hours`: Time windows like `Mon-Fri 08:00-18:00` during which records are not changed, see
+///   [`crate::quiethours`] (env: `QUIET_HOURS`, comma-separated).
+/// - `quiet_hours_tz`: Time zone of `quiet_hours`, the system's if empty (env: `QUIET_HOURS_TZ`).
+/// - `quiet_hours_mode`: Whether the daemon skips update cycles during `quiet_hours` or only logs the changes it would
+///   make (env: `QUIET_HOURS_MODE`, default `skip`, see [`QuietHoursMode`]).
+/// - `record_check`: Where the current content of a record is read from (env: `RECORD_CHECK`, default `api`,
+///   see [`RecordCheck`]).
+/// - `revalidate_every_n_cycles`: Check the provider credentials again every N cycles. `0` only checks them at startup
+///   and after authentication errors (env: `REVALIDATE_EVERY_N_CYCLES`, default `0`).
+/// - `run_once`: Perform a single update cycle and exit instead of running as a daemon (env: `RUN_ONCE`).
+/// - `exit_on_error`: Stop the daemon on fatal errors like rejected credentials and after `retry.max_consecutive_failures`
+///   failed cycles, instead of retrying with backoff forever (env: `EXIT_ON_ERROR`, default `false`).
+/// - `state_file`: Path of a JSON file remembering the last pushed IP across restarts. Disabled if empty (env: `STATE_FILE`).
+/// - `history_file`: Path of a JSONL file every IP change of a record is appended to. Disabled if empty (env: `HISTORY_FILE`).
+/// - `status_file`: Path of a JSON file the daemon writes its status and records to after every cycle, like
+///   `crondes ctl status`. Disabled if empty (env: `STATUS_FILE`).
+/// - `sync_file`: Path of a TOML or YAML file listing the records the zone of `DNS_PROVIDER` is reconciled with in every
+///   update cycle, see [`crate::zonesync`]. Disabled if empty (env: `SYNC_FILE`).
+/// - `sync_prune`: Delete records of a name and type listed in `sync_file` that no entry asks for
+///   (env: `SYNC_PRUNE`, default `false`).
+/// - `retry`: Backoff settings for failed update cycles (see [`RetryConfig`]).
+/// - `http`: Settings for the shared HTTP client (see [`HttpConfig`]).
+/// - `server`: Settings for the embedded HTTP server (see [`ServerConfig`]).
+/// - `ip`: How the public IP address is detected (see [`IpConfig`]).
+/// - `notify`: Where notifications about IP changes and failures are sent (see [`NotifyConfig`]).
+/// - `hooks`: Shell commands run on update events (see [`HooksConfig`]).
+/// - `propagation`: Whether updated records are checked at public resolvers (see [`PropagationConfig`]).
+/// - `log`: Where log output is written to (see [`LogConfig`]).
+/// - `jobs`: Independent jobs run by the daemon instead of the records of the top level, each with its own provider
+///   credentials, records, interval, notifiers and so on (see [`JobConfig`]). Only read from the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
-    pub cloudflare_api_token: String,
+    pub provider: ProviderKind,
+    pub cloudflare_api_token: Secret,
+    pub cloudflare_api_email: String,
+    pub cloudflare_api_key: Secret,
     pub cloudflare_zone_id: String,
-    pub cloudflare_record_id: String,
-    pub cloudflare_record_name: String,
+    pub cloudflare_zone_name: String,
+    pub cloudflare_api_url: String,
+    pub cloudflare_ttl: Option<u32>,
+    pub cloudflare_proxied: Option<bool>,
+    pub cloudflare_comment: String,
+    pub cloudflare_tags: Vec<String>,
+    pub porkbun: PorkbunConfig,
+    pub desec: DesecConfig,
+    pub duckdns: DuckDnsConfig,
+    pub noip: NoIpConfig,
+    pub dyndns2: Dyndns2Config,
+    pub rfc2136: Rfc2136Config,
+    pub linode: LinodeConfig,
+    pub vultr: VultrConfig,
+    pub namecheap: NamecheapConfig,
+    pub records: Vec<RecordConfig>,
+    pub record_pattern: String,
+    pub record_tag: String,
+    pub update_mode: UpdateMode,
     pub update_interval_secs: u64,
+    pub update_interval_secs_v6: u64,
+    pub update_interval_jitter_percent: u64,
+    pub startup_splay_secs: u64,
+    pub startup_delay_secs: u64,
+    pub startup_grace_secs: u64,
+    pub network_watch: bool,
+    pub config_watch: bool,
+    pub control_socket: String,
+    pub daemonize: bool,
+    pub pid_file: String,
+    pub reread_every_n_cycles: u64,
+    pub update_concurrency: usize,
+    pub min_update_cooldown_secs: u64,
+    pub force_overwrite: bool,
+    pub cycle_deadline_secs: u64,
+    pub connectivity_check: ConnectivityCheck,
+    pub connectivity_target: String,
+    pub quiet_hours: Vec<String>,
+    pub quiet_hours_tz: String,
+    pub quiet_hours_mode: QuietHoursMode,
+    pub record_check: RecordCheck,
+    pub revalidate_every_n_cycles: u64,
+    pub run_once: bool,
+    pub exit_on_error: bool,
+    pub state_file: String,
+    pub history_file: String,
+    pub status_file: String,
+    pub sync_file: String,
+    pub sync_prune: bool,
+    pub retry: RetryConfig,
+    pub http: HttpConfig,
+    pub server: ServerConfig,
+    pub ip: IpConfig,
+    pub notify: NotifyConfig,
+    pub hooks: HooksConfig,
+    pub propagation: PropagationConfig,
+    pub log: LogConfig,
+    pub jobs: Vec<JobConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            provider: ProviderKind::Cloudflare,
+            cloudflare_api_token: Secret::default(),
+            cloudflare_api_email: String::new(),
+            cloudflare_api_key: Secret::default(),
+            cloudflare_zone_id: String::new(),
+            cloudflare_zone_name: String::new(),
+            cloudflare_api_url: "https://api.cloudflare.com/client/v4".to_string(),
+            cloudflare_ttl: None,
+            cloudflare_proxied: None,
+            cloudflare_comment: String::new(),
+            cloudflare_tags: Vec::new(),
+            porkbun: PorkbunConfig::default(),
+            desec: DesecConfig::default(),
+            duckdns: DuckDnsConfig::default(),
+            noip: NoIpConfig::default(),
+            dyndns2: Dyndns2Config::default(),
+            rfc2136: Rfc2136Config::default(),
+            linode: LinodeConfig::default(),
+            vultr: VultrConfig::default(),
+            namecheap: NamecheapConfig::default(),
+            records: Vec::new(),
+            record_pattern: String::new(),
+            record_tag: String::new(),
+            update_mode: UpdateMode::Poll,
+            update_interval_secs: 60,
+            update_interval_secs_v6: 0,
+            update_interval_jitter_percent: 0,
+            startup_splay_secs: 0,
+            startup_delay_secs: 0,
+            startup_grace_secs: 0,
+            network_watch: true,
+            config_watch: true,
+            control_socket: String::new(),
+            daemonize: false,
+            pid_file: String::new(),
+            reread_every_n_cycles: 10,
+            update_concurrency: 4,
+            min_update_cooldown_secs: 0,
+            force_overwrite: true,
+            cycle_deadline_secs: 300,
+            connectivity_check: ConnectivityCheck::None,
+            connectivity_target: String::new(),
+            quiet_hours: Vec::new(),
+            quiet_hours_tz: String::new(),
+            quiet_hours_mode: QuietHoursMode::Skip,
+            record_check: RecordCheck::Api,
+            revalidate_every_n_cycles: 0,
+            run_once: false,
+            exit_on_error: false,
+            state_file: String::new(),
+            history_file: String::new(),
+            status_file: String::new(),
+            sync_file: String::new(),
+            sync_prune: false,
+            retry: RetryConfig::default(),
+            http: HttpConfig::default(),
+            server: ServerConfig::default(),
+            ip: IpConfig::default(),
+            notify: NotifyConfig::default(),
+            hooks: HooksConfig::default(),
+            propagation: PropagationConfig::default(),
+            log: LogConfig::default(),
+            jobs: Vec::new(),
+        }
+    }
+}
+
+/// The DNS providers that can be selected via `DNS_PROVIDER`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Cloudflare API v4, configured via the `CF_*` variables.
+    Cloudflare,
+    /// Porkbun API v3, configured via the `PORKBUN_*` variables.
+    Porkbun,
+    /// deSEC RRset API, configured via the `DESEC_*` variables.
+    Desec,
+    /// DuckDNS update URL, configured via `DUCKDNS_TOKEN`.
+    DuckDns,
+    /// No-IP dynamic update protocol, configured via the `NOIP_*` variables.
+    NoIp,
+    /// Any service speaking the dyndns2 update protocol, configured via the `DYNDNS2_*` variables.
+    Dyndns2,
+    /// RFC 2136 dynamic updates signed with TSIG, configured via the `RFC2136_*` variables.
+    Rfc2136,
+    /// Linode Domains API v4, configured via the `LINODE_*` variables.
+    Linode,
+    /// Vultr DNS API v2, configured via the `VULTR_*` variables.
+    Vultr,
+    /// Namecheap dynamic DNS, configured via the `NAMECHEAP_*` variables.
+    Namecheap,
+}
+
+impl ProviderKind {
+    /// Returns `true` if the provider addresses records by ID, so records can be configured by ID
+    /// and records configured by name are resolved to IDs at startup.
+    pub fn uses_record_ids(self) -> bool {
+        matches!(self, ProviderKind::Cloudflare | ProviderKind::Linode | ProviderKind::Vultr)
+    }
+
+    /// Returns `true` if the provider can keep AAAA records in sync, not just A records.
+    pub fn supports_aaaa(self) -> bool {
+        matches!(self, ProviderKind::Cloudflare | ProviderKind::Porkbun | ProviderKind::Desec | ProviderKind::Linode | ProviderKind::Vultr)
+    }
+
+    /// Returns `true` if the provider can write TXT records with arbitrary content.
+    pub fn supports_txt(self) -> bool {
+        matches!(self, ProviderKind::Cloudflare | ProviderKind::Porkbun | ProviderKind::Linode)
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "cloudflare" => Ok(ProviderKind::Cloudflare),
+            "porkbun" => Ok(ProviderKind::Porkbun),
+            "desec" => Ok(ProviderKind::Desec),
+            "duckdns" => Ok(ProviderKind::DuckDns),
+            "noip" => Ok(ProviderKind::NoIp),
+            "dyndns2" => Ok(ProviderKind::Dyndns2),
+            "rfc2136" => Ok(ProviderKind::Rfc2136),
+            "linode" => Ok(ProviderKind::Linode),
+            "vultr" => Ok(ProviderKind::Vultr),
+            "namecheap" => Ok(ProviderKind::Namecheap),
+            other => Err(format!("unknown DNS provider: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProviderKind::Cloudflare => "cloudflare",
+            ProviderKind::Porkbun => "porkbun",
+            ProviderKind::Desec => "desec",
+            ProviderKind::DuckDns => "duckdns",
+            ProviderKind::NoIp => "noip",
+            ProviderKind::Dyndns2 => "dyndns2",
+            ProviderKind::Rfc2136 => "rfc2136",
+            ProviderKind::Linode => "linode",
+            ProviderKind::Vultr => "vultr",
+            ProviderKind::Namecheap => "namecheap",
+        })
+    }
+}
+
+/// Settings for the Porkbun provider, configured in the `porkbun` section of a config file.
+///
+/// Porkbun addresses records by name and type, so every record needs a `name` within `domain`.
+/// API access must be enabled for the domain in the Porkbun dashboard.
+///
+/// Fields:
+/// - `api_key`: The API key, starting with `pk1_` (env: `PORKBUN_API_KEY`).
+/// - `secret_api_key`: The secret API key, starting with `sk1_` (env: `PORKBUN_SECRET_API_KEY`).
+/// - `domain`: The registered domain containing the records, e.g. `example.com` (env: `PORKBUN_DOMAIN`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PorkbunConfig {
+    pub api_key: Secret,
+    pub secret_api_key: Secret,
+    pub domain: String,
+}
+
+/// Settings for the deSEC provider, configured in the `desec` section of a config file.
+///
+/// deSEC manages records as RRsets addressed by name and type, so every record needs a `name` within `domain`.
+///
+/// Fields:
+/// - `token`: API token of the deSEC account (env: `DESEC_TOKEN`).
+/// - `domain`: The domain at deSEC containing the records, e.g. `example.dedyn.io` (env: `DESEC_DOMAIN`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DesecConfig {
+    pub token: Secret,
+    pub domain: String,
+}
+
+/// Settings for the DuckDNS provider, configured in the `duckdns` section of a config file.
+///
+/// Records are configured by name, either as the subdomain (`home`) or the full name (`home.duckdns.org`).
+///
+/// Fields:
+/// - `token`: The account token shown on the DuckDNS dashboard (env: `DUCKDNS_TOKEN`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DuckDnsConfig {
+    pub token: Secret,
+}
+
+/// Settings for the No-IP provider, configured in the `noip` section of a config file.
+///
+/// Records are configured by their full hostname, e.g. `home.ddns.net`.
+///
+/// Fields:
+/// - `username`: The No-IP username or DDNS key username (env: `NOIP_USERNAME`).
+/// - `password`: The matching password (env: `NOIP_PASSWORD`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoIpConfig {
+    pub username: String,
+    pub password: Secret,
+}
+
+/// Settings for the generic dyndns2 provider, configured in the `dyndns2` section of a config file.
+///
+/// Records are configured by their full hostname.
+///
+/// Fields:
+/// - `url`: The update URL, e.g. `https://api.dynu.com/nic/update` (env: `DYNDNS2_URL`).
+/// - `username`: The username sent via basic auth (env: `DYNDNS2_USERNAME`).
+/// - `password`: The password sent via basic auth (env: `DYNDNS2_PASSWORD`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dyndns2Config {
+    pub url: String,
+    pub username: String,
+    pub password: Secret,
+}
+
+/// Settings for RFC 2136 dynamic updates, configured in the `rfc2136` section of a config file.
+///
+/// Records are configured by name and must be within `zone`. Missing records are created.
+///
+/// Fields:
+/// - `server`: Authoritative DNS server accepting the updates, e.g. `ns1.example.com` or `192.0.2.53:5353`,
+///   port `53` if omitted (env: `RFC2136_SERVER`).
+/// - `zone`: The zone containing the records, e.g. `example.com` (env: `RFC2136_ZONE`).
+/// - `key_name`: Name of the TSIG key as configured on the server (env: `RFC2136_KEY_NAME`).
+/// - `key_secret`: Base64 encoded TSIG key secret (env: `RFC2136_KEY_SECRET`).
+/// - `key_algorithm`: `hmac-sha256`, `hmac-sha384` or `hmac-sha512` (env: `RFC2136_KEY_ALGORITHM`, default `hmac-sha256`).
+/// - `ttl`: TTL of written records (env: `RFC2136_TTL`, default `300`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rfc2136Config {
+    pub server: String,
+    pub zone: String,
+    pub key_name: String,
+    pub key_secret: Secret,
+    pub key_algorithm: String,
+    pub ttl: u32,
+}
+
+impl Default for Rfc2136Config {
+    fn default() -> Self {
+        Rfc2136Config {
+            server: String::new(),
+            zone: String::new(),
+            key_name: String::new(),
+            key_secret: Secret::default(),
+            key_algorithm: "hmac-sha256".to_string(),
+            ttl: 300,
+        }
+    }
+}
+
+/// Settings for the Linode provider, configured in the `linode` section of a config file.
+///
+/// Fields:
+/// - `token`: Personal access token with read/write access to Domains (env: `LINODE_TOKEN`).
+/// - `domain`: The domain containing the records, e.g. `example.com` (env: `LINODE_DOMAIN`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinodeConfig {
+    pub token: Secret,
+    pub domain: String,
+}
+
+/// Settings for the Vultr provider, configured in the `vultr` section of a config file.
+///
+/// Fields:
+/// - `api_key`: Vultr API key (env: `VULTR_API_KEY`).
+/// - `domain`: The domain containing the records, e.g. `example.com` (env: `VULTR_DOMAIN`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VultrConfig {
+    pub api_key: Secret,
+    pub domain: String,
+}
+
+/// Settings for the Namecheap provider, configured in the `namecheap` section of a config file.
+///
+/// Records are configured by name and must be within `domain`.
+///
+/// Fields:
+/// - `domain`: The domain registered at Namecheap, e.g. `example.com` (env: `NAMECHEAP_DOMAIN`).
+/// - `password`: The dynamic DNS password shown in the Namecheap dashboard (env: `NAMECHEAP_PASSWORD`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamecheapConfig {
+    pub domain: String,
+    pub password: Secret,
+}
+
+/// Retry behaviour after failed update cycles, configured in the `retry` section of a config file.
+///
+/// Fields:
+/// - `max_consecutive_failures`: Number of failed cycles in a row after which the daemon gives up if `exit_on_error`
+///   is set, `0` retries forever (env: `RETRY_MAX_FAILURES`, default `10`).
+/// - `backoff_base_secs`: Delay before the first retry, doubled on every further failure
+///   (env: `RETRY_BACKOFF_BASE_SECS`, default `5`).
+/// - `backoff_max_secs`: Upper bound for the retry delay (env: `RETRY_BACKOFF_MAX_SECS`, default `300`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_consecutive_failures: u32,
+    pub backoff_base_secs: u64,
+    pub backoff_max_secs: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_consecutive_failures: 10,
+            backoff_base_secs: 5,
+            backoff_max_secs: 300,
+        }
+    }
+}
+
+/// Settings for the HTTP client, configured in the `http` section of a config file.
+///
+/// Fields:
+/// - `timeout_secs`: Total timeout for a single request (env: `HTTP_TIMEOUT_SECS`, default `30`).
+/// - `connect_timeout_secs`: Timeout for establishing a connection (env: `HTTP_CONNECT_TIMEOUT_SECS`, default `10`).
+/// - `user_agent`: `User-Agent` header sent with every request (env: `HTTP_USER_AGENT`, default `crondes/<version>`).
+/// - `proxy`: URL of a proxy all requests go through, `http://`, `https://`, `socks5://` or `socks5h://`, or `none` to
+///   ignore `HTTP_PROXY`/`HTTPS_PROXY`. Stored as a [`Secret`], since it may contain credentials. If empty, the usual
+///   `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` variables apply (env: `OUTBOUND_PROXY`).
+/// - `ca_file`: Path of a PEM file with additional root certificates to trust, e.g. of a private CA or a
+///   TLS-intercepting proxy (env: `HTTP_CA_FILE`).
+/// - `builtin_roots`: Whether the built-in root certificates are trusted; with `false` only `ca_file` is
+///   (env: `HTTP_BUILTIN_ROOTS`, default `true`).
+/// - `bind_interface`: Network interface all requests are sent from, e.g. `wan2`, to pick the uplink of a multi-WAN
+///   host. Only supported on Linux, Android and macOS. Any interface if empty (env: `BIND_INTERFACE`).
+/// - `bind_address`: Local IP address all requests are sent from (env: `BIND_ADDRESS`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub user_agent: String,
+    pub proxy: Secret,
+    pub ca_file: String,
+    pub builtin_roots: bool,
+    pub bind_interface: String,
+    pub bind_address: Option<IpAddr>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout_secs: 30,
+            connect_timeout_secs: 10,
+            user_agent: crate::http::DEFAULT_USER_AGENT.to_string(),
+            proxy: Secret::default(),
+            ca_file: String::new(),
+            builtin_roots: true,
+            bind_interface: String::new(),
+            bind_address: None,
+        }
+    }
+}
+
+/// Settings for the embedded HTTP server, configured in the `server` section of a config file.
+///
+/// Fields:
+/// - `bind`: Address to listen on, e.g. `0.0.0.0:9184`. The server is disabled if empty (env: `SERVER_BIND`).
+/// - `health_max_intervals`: `/healthz` reports unhealthy if the last successful update cycle is older
+///   than this many update intervals (env: `HEALTH_MAX_INTERVALS`, default `3`).
+/// - `api_token`: Bearer token required by the control API (`/status`, `/trigger`, `/pause`, `/resume`, `/records`,
+///   `/cycles`) and asked for by the dashboard at `/`. Both are disabled if empty (env: `SERVER_API_TOKEN`).
+/// - `webhook_token`: Token required by `/webhook`, as bearer token or `token` query parameter. The webhook is
+///   disabled if empty (env: `SERVER_WEBHOOK_TOKEN`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub health_max_intervals: u32,
+    pub api_token: Secret,
+    pub webhook_token: Secret,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: String::new(),
+            health_max_intervals: 3,
+            api_token: Secret::default(),
+            webhook_token: Secret::default(),
+        }
+    }
+}
+
+/// Settings for public IP detection, configured in the `ip` section of a config file.
+///
+/// Fields:
+/// - `sources`: IP sources to query, in order, until one returns a valid address
+///   (env: `IP_SOURCES`, comma-separated, default `trace`).
+/// - `services`: URLs queried by the `http` source, e.g. an internal echo endpoint. The built-in
+///   list of public services is used if empty, for AAAA records one reachable over IPv6 only
+///   (env: `IP_SERVICES`, comma-separated).
+/// - `interface`: Network interface read by the `interface` source, e.g. `ppp0`. All interfaces
+///   are searched if empty (env: `IP_INTERFACE`).
+/// - `upnp_url`: Control URL of the router's WAN connection service, used by the `upnp` source
+///   (env: `IP_UPNP_URL`, default `http://fritz.box:49000/igdupnp/control/WANIPConn1`).
+/// - `upnp_service`: Service type sent with the `GetExternalIPAddress` request
+///   (env: `IP_UPNP_SERVICE`, default `urn:schemas-upnp-org:service:WANIPConnection:1`).
+/// - `stable_checks`: Number of consecutive update cycles that must detect the same new address before
+///   the records are updated, to ride out flapping connections (env: `IP_STABLE_CHECKS`, default `1`).
+/// - `allow_private`: Publish private, CGNAT and other non-public addresses instead of refusing them,
+///   e.g. for internal-only records (env: `IP_ALLOW_PRIVATE`, default `false`).
+/// - `strategy`: Whether the sources are queried one after another, all at once, or until enough
+///   of them agree (env: `IP_STRATEGY`, default `race`).
+/// - `quorum`: Number of sources that must report the same address with the `consensus` strategy
+///   (env: `IP_QUORUM`, default `2`).
+/// - `timeout_secs`: Time a single source may take before it counts as failed (env: `IP_SOURCE_TIMEOUT_SECS`, default `5`).
+/// - `breaker_threshold`: Number of failures in a row after which a source is skipped for `breaker_cooldown_secs`,
+///   `0` to always query every source (env: `IP_BREAKER_THRESHOLD`, default `3`).
+/// - `breaker_cooldown_secs`: Time a failing source is skipped before a single query probes it again
+///   (env: `IP_BREAKER_COOLDOWN_SECS`, default `300`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpConfig {
+    pub sources: Vec<IpSourceKind>,
+    pub services: Vec<String>,
+    pub interface: String,
+    pub upnp_url: String,
+    pub upnp_service: String,
+    pub stable_checks: u32,
+    pub allow_private: bool,
+    pub strategy: IpStrategy,
+    pub quorum: usize,
+    pub timeout_secs: u64,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown_secs: u64,
+}
+
+impl Default for IpConfig {
+    fn default() -> Self {
+        IpConfig {
+            sources: vec![IpSourceKind::Trace],
+            services: Vec::new(),
+            interface: String::new(),
+            upnp_url: "http://fritz.box:49000/igdupnp/control/WANIPConn1".to_string(),
+            upnp_service: "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+            stable_checks: 1,
+            allow_private: false,
+            strategy: IpStrategy::Race,
+            quorum: 2,
+            timeout_secs: 5,
+            breaker_threshold: 3,
+            breaker_cooldown_secs: 300,
+        }
+    }
+}
+
+/// What starts an update cycle of the daemon, set via `UPDATE_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    /// Detect the public IP every `UPDATE_INTERVAL_SECS`.
+    Poll,
+    /// Only update when a webhook arrives at `/webhook`, e.g. from a router reporting its new WAN address.
+    /// Failed cycles are still retried with backoff.
+    Webhook,
+}
+
+impl FromStr for UpdateMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "poll" => Ok(UpdateMode::Poll),
+            "webhook" => Ok(UpdateMode::Webhook),
+            other => Err(format!("unknown update mode: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for UpdateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UpdateMode::Poll => "poll",
+            UpdateMode::Webhook => "webhook",
+        })
+    }
+}
+
+/// Where the current content of a record is read from, set via `RECORD_CHECK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordCheck {
+    /// Read the record from the provider API.
+    Api,
+    /// Query the authoritative nameservers of the record, so the provider API is only called to write a changed record.
+    /// Records proxied by Cloudflare are still read from the API, since their nameservers answer with proxy addresses.
+    Dns,
+}
+
+impl FromStr for RecordCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "api" => Ok(RecordCheck::Api),
+            "dns" => Ok(RecordCheck::Dns),
+            other => Err(format!("unknown record check: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for RecordCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RecordCheck::Api => "api",
+            RecordCheck::Dns => "dns",
+        })
+    }
+}
+
+/// How the daemon checks that the network is up before an update cycle, set via `CONNECTIVITY_CHECK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectivityCheck {
+    /// Don't check, every cycle runs.
+    None,
+    /// Open a TCP connection to `CONNECTIVITY_TARGET`.
+    Tcp,
+    /// Resolve `CONNECTIVITY_TARGET` with the system resolver.
+    Dns,
+    /// Ping `CONNECTIVITY_TARGET` with the system's `ping` command.
+    Icmp,
+}
+
+impl FromStr for ConnectivityCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(ConnectivityCheck::None),
+            "tcp" => Ok(ConnectivityCheck::Tcp),
+            "dns" => Ok(ConnectivityCheck::Dns),
+            "icmp" => Ok(ConnectivityCheck::Icmp),
+            other => Err(format!("unknown connectivity check: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ConnectivityCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConnectivityCheck::None => "none",
+            ConnectivityCheck::Tcp => "tcp",
+            ConnectivityCheck::Dns => "dns",
+            ConnectivityCheck::Icmp => "icmp",
+        })
+    }
+}
+
+/// What the daemon does during `QUIET_HOURS`, set via `QUIET_HOURS_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietHoursMode {
+    /// Skip update cycles, no requests are sent.
+    Skip,
+    /// Run update cycles but only log the changes instead of writing them.
+    Log,
+}
+
+impl FromStr for QuietHoursMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" | "" => Ok(QuietHoursMode::Skip),
+            "log" => Ok(QuietHoursMode::Log),
+            other => Err(format!("unknown quiet hours mode: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for QuietHoursMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            QuietHoursMode::Skip => "skip",
+            QuietHoursMode::Log => "log",
+        })
+    }
+}
+
+/// How the IP sources are queried, set via `IP_STRATEGY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpStrategy {
+    /// Query the sources one after another in the configured order and take the first valid answer.
+    Sequential,
+    /// Like [`IpStrategy::Sequential`], but try the sources with the shortest recent response times first.
+    Fastest,
+    /// Query all sources at once, take the first valid answer and cancel the rest.
+    Race,
+    /// Query all sources at once and only accept an address once `IP_QUORUM` sources agree on it.
+    /// Protects against a single compromised or misbehaving source.
+    Consensus,
+}
+
+impl FromStr for IpStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sequential" => Ok(IpStrategy::Sequential),
+            "fastest" => Ok(IpStrategy::Fastest),
+            "race" => Ok(IpStrategy::Race),
+            "consensus" => Ok(IpStrategy::Consensus),
+            other => Err(format!("unknown IP strategy: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for IpStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpStrategy::Sequential => "sequential",
+            IpStrategy::Fastest => "fastest",
+            IpStrategy::Race => "race",
+            IpStrategy::Consensus => "consensus",
+        })
+    }
+}
+
+/// The kinds of IP sources that can be listed in `IP_SOURCES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpSourceKind {
+    /// Cloudflare's `/cdn-cgi/trace` endpoint.
+    Trace,
+    /// Plain-text HTTP echo services like `api.ipify.org`.
+    Http,
+    /// DNS lookup of `myip.opendns.com` at the OpenDNS resolvers.
+    Dns,
+    /// STUN binding requests to public STUN servers.
+    Stun,
+    /// The external address reported by the local router via UPnP IGD / TR-064.
+    Upnp,
+    /// The first public address found on the local network interface `IP_INTERFACE`, or on any interface.
+    Interface,
+}
+
+impl IpSourceKind {
+    /// Returns `true` if the source can detect the public IPv6 address as well.
+    pub fn supports_ipv6(self) -> bool {
+        !matches!(self, IpSourceKind::Stun | IpSourceKind::Upnp)
+    }
+}
+
+impl FromStr for IpSourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" => Ok(IpSourceKind::Trace),
+            "http" => Ok(IpSourceKind::Http),
+            "dns" => Ok(IpSourceKind::Dns),
+            "stun" => Ok(IpSourceKind::Stun),
+            "upnp" => Ok(IpSourceKind::Upnp),
+            "interface" => Ok(IpSourceKind::Interface),
+            other => Err(format!("unknown IP source: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for IpSourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpSourceKind::Trace => "trace",
+            IpSourceKind::Http => "http",
+            IpSourceKind::Dns => "dns",
+            IpSourceKind::Stun => "stun",
+            IpSourceKind::Upnp => "upnp",
+            IpSourceKind::Interface => "interface",
+        })
+    }
+}
+
+/// Notification channels, configured in the `notify` section of a config file.
+///
+/// Notifications are sent when a record changes to a new IP, when a component of the update cycle has failed
+/// `failure_threshold` cycles in a row, and when it recovers.
+///
+/// Fields:
+/// - `webhook_urls`: URLs receiving every notification as a JSON POST (env: `NOTIFY_WEBHOOK_URLS`, comma-separated).
+/// - `discord_urls`: Discord webhook URLs, messages are sent as embeds (env: `NOTIFY_DISCORD_URLS`, comma-separated).
+/// - `slack_urls`: Slack incoming webhook URLs, messages are sent as blocks (env: `NOTIFY_SLACK_URLS`, comma-separated).
+/// - `gotify_url`, `gotify_token`: Gotify server URL and application token. Disabled if the URL is empty
+///   (env: `NOTIFY_GOTIFY_URL`, `NOTIFY_GOTIFY_TOKEN`).
+/// - `pushover_token`, `pushover_user`: Pushover application token and user key. Disabled if the token is empty
+///   (env: `NOTIFY_PUSHOVER_TOKEN`, `NOTIFY_PUSHOVER_USER`).
+/// - `heartbeat_url`: healthchecks.io or Uptime Kuma push URL pinged after every update cycle,
+///   so a monitor notices when crondes stops running (env: `HEARTBEAT_URL`). A [`Secret`], since the URL is the token.
+/// - `smtp_host`: SMTP server for email notifications. Email is disabled if empty (env: `NOTIFY_SMTP_HOST`).
+/// - `smtp_port`: SMTP port, defaults to the standard port of `smtp_tls` (env: `NOTIFY_SMTP_PORT`).
+/// - `smtp_tls`: `starttls`, `tls` (implicit TLS) or `none` (env: `NOTIFY_SMTP_TLS`, default `starttls`).
+/// - `smtp_username`, `smtp_password`: SMTP login, no authentication if the username is empty
+///   (env: `NOTIFY_SMTP_USERNAME`, `NOTIFY_SMTP_PASSWORD`).
+/// - `smtp_from`: Sender address, e.g. `crondes <crondes@example.com>` (env: `NOTIFY_SMTP_FROM`).
+/// - `smtp_to`: Recipient addresses (env: `NOTIFY_SMTP_TO`, comma-separated).
+/// - `smtp_subject`, `smtp_body`: Templates for the email, see [`crate::notify::Notification::render`]
+///   (env: `NOTIFY_SMTP_SUBJECT`, `NOTIFY_SMTP_BODY`).
+/// - `failure_threshold`: Number of failed cycles in a row of the IP detection of a family, a provider or the cycle
+///   as a whole after which a failure is notified, `0` to not notify failures (env: `NOTIFY_FAILURE_THRESHOLD`,
+///   default `1`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    pub webhook_urls: Vec<Secret>,
+    pub discord_urls: Vec<Secret>,
+    pub slack_urls: Vec<Secret>,
+    pub gotify_url: String,
+    pub gotify_token: Secret,
+    pub pushover_token: Secret,
+    pub pushover_user: Secret,
+    pub heartbeat_url: Secret,
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub smtp_tls: SmtpTls,
+    pub smtp_username: String,
+    pub smtp_password: Secret,
+    pub smtp_from: String,
+    pub smtp_to: Vec<String>,
+    pub smtp_subject: String,
+    pub smtp_body: String,
+    pub failure_threshold: u32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            webhook_urls: Vec::new(),
+            discord_urls: Vec::new(),
+            slack_urls: Vec::new(),
+            gotify_url: String::new(),
+            gotify_token: Secret::default(),
+            pushover_token: Secret::default(),
+            pushover_user: Secret::default(),
+            heartbeat_url: Secret::default(),
+            smtp_host: String::new(),
+            smtp_port: None,
+            smtp_tls: SmtpTls::Starttls,
+            smtp_username: String::new(),
+            smtp_password: Secret::default(),
+            smtp_from: String::new(),
+            smtp_to: Vec::new(),
+            smtp_subject: "[crondes] {title}".to_string(),
+            smtp_body: "{message}\n\nTime: {timestamp}\n".to_string(),
+            failure_threshold: 1,
+        }
+    }
+}
+
+/// How the connection to the SMTP server is secured, set via `NOTIFY_SMTP_TLS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain connection upgraded with `STARTTLS`, usually on port 587.
+    Starttls,
+    /// Implicit TLS, usually on port 465.
+    Tls,
+    /// Unencrypted, only for relays on the local network, usually on port 25.
+    None,
+}
+
+impl SmtpTls {
+    /// Returns the standard port for this mode.
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpTls::Starttls => 587,
+            SmtpTls::Tls => 465,
+            SmtpTls::None => 25,
+        }
+    }
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "starttls" => Ok(SmtpTls::Starttls),
+            "tls" => Ok(SmtpTls::Tls),
+            "none" => Ok(SmtpTls::None),
+            other => Err(format!("unknown SMTP TLS mode: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for SmtpTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SmtpTls::Starttls => "starttls",
+            SmtpTls::Tls => "tls",
+            SmtpTls::None => "none",
+        })
+    }
+}
+
+/// Shell commands and HTTP calls run around updates, configured in the `hooks` section of a config file.
+///
+/// Commands run through `sh -c` (`cmd /C` on Windows), so they are not available in the `scratch` Docker image.
+///
+/// Fields:
+/// - `pre_update_cmd`: Run before a record is written, with `OLD_IP`, `NEW_IP`, `RECORD_NAME` and
+///   `STATUS=pending` set. A non-zero exit code vetoes the update (env: `PRE_UPDATE_CMD`).
+/// - `pre_update_url`: Requested with GET before a record is written. A non-2xx response or a failed
+///   request vetoes the update (env: `PRE_UPDATE_URL`).
+/// - `on_change_cmd`: Run after a record changed to a new IP, with `OLD_IP`, `NEW_IP`, `RECORD_NAME`
+///   and `STATUS=changed` set (env: `ON_CHANGE_CMD`).
+/// - `on_failure_cmd`: Run when an update cycle fails after a successful one, with `STATUS=failed`
+///   and `ERROR` set, or when an updated record doesn't propagate, with `STATUS=propagation_failed`,
+///   `RECORD_NAME`, `NEW_IP` and `ERROR` set, or when a record was changed outside of crondes, with
+///   `STATUS=conflict`, `RECORD_NAME`, `OLD_IP` (the content found) and `NEW_IP` set (env: `ON_FAILURE_CMD`).
+/// - `timeout_secs`: Time a command or request may take before it counts as failed (env: `HOOK_TIMEOUT_SECS`, default `30`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub pre_update_cmd: String,
+    pub pre_update_url: String,
+    pub on_change_cmd: String,
+    pub on_failure_cmd: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        HooksConfig {
+            pre_update_cmd: String::new(),
+            pre_update_url: String::new(),
+            on_change_cmd: String::new(),
+            on_failure_cmd: String::new(),
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Checks that updated records are served by public resolvers, configured in the `propagation` section of a config file.
+///
+/// Fields:
+/// - `enabled`: Resolve every updated record at the resolvers until they return the new IP (env: `PROPAGATION_CHECK`).
+///   Records proxied by Cloudflare are skipped, since they resolve to Cloudflare's addresses.
+/// - `resolvers`: The resolvers to query (env: `PROPAGATION_RESOLVERS`, default `1.1.1.1,8.8.8.8`).
+/// - `timeout_secs`: Time the resolvers may take to return the new IP before a notification is sent
+///   (env: `PROPAGATION_TIMEOUT_SECS`, default `300`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PropagationConfig {
+    pub enabled: bool,
+    pub resolvers: Vec<IpAddr>,
+    pub timeout_secs: u64,
+}
+
+impl Default for PropagationConfig {
+    fn default() -> Self {
+        PropagationConfig {
+            enabled: false,
+            resolvers: vec![IpAddr::from([1, 1, 1, 1]), IpAddr::from([8, 8, 8, 8])],
+            timeout_secs: 300,
+        }
+    }
+}
+
+/// Where log output is written to, configured in the `log` section of a config file.
+///
+/// The log level is always taken from `RUST_LOG`.
+///
+/// Fields:
+/// - `target`: The log backend (env: `LOG_TARGET`, default `stderr`, see [`LogTarget`]).
+/// - `syslog_address`: Where syslog messages are sent to, `unix:/dev/log`, `udp://host:514` or `tcp://host:601`
+///   (env: `SYSLOG_ADDRESS`, default `unix:/dev/log`).
+/// - `file`: Path of a file log lines are appended to in addition to `target`. Disabled if empty (env: `LOG_FILE`).
+/// - `file_max_size_mb`: Rotate the log file once it grows beyond this size, `0` disables size-based rotation
+///   (env: `LOG_FILE_MAX_SIZE_MB`, default `10`).
+/// - `file_rotate_every`: Rotate the log file at the start of every hour or day (UTC) (env: `LOG_FILE_ROTATE_EVERY`,
+///   default `never`, see [`RotatePeriod`]).
+/// - `file_keep`: Number of rotated files kept as `<file>.1` (newest) to `<file>.N` (env: `LOG_FILE_KEEP`, default `5`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub target: LogTarget,
+    pub syslog_address: String,
+    pub file: String,
+    pub file_max_size_mb: u64,
+    pub file_rotate_every: RotatePeriod,
+    pub file_keep: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            target: LogTarget::Stderr,
+            syslog_address: "unix:/dev/log".to_string(),
+            file: String::new(),
+            file_max_size_mb: 10,
+            file_rotate_every: RotatePeriod::Never,
+            file_keep: 5,
+        }
+    }
+}
+
+/// How often the log file is rotated regardless of its size, set via `LOG_FILE_ROTATE_EVERY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotatePeriod {
+    /// Only rotate by size.
+    Never,
+    /// Rotate when the first line of a new hour is written.
+    Hourly,
+    /// Rotate when the first line of a new day is written.
+    Daily,
+}
+
+impl RotatePeriod {
+    /// Length of a period in seconds, `None` for [`RotatePeriod::Never`].
+    pub fn secs(self) -> Option<u64> {
+        match self {
+            RotatePeriod::Never => None,
+            RotatePeriod::Hourly => Some(3600),
+            RotatePeriod::Daily => Some(86400),
+        }
+    }
+}
+
+impl FromStr for RotatePeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "never" => Ok(RotatePeriod::Never),
+            "hourly" => Ok(RotatePeriod::Hourly),
+            "daily" => Ok(RotatePeriod::Daily),
+            other => Err(format!("unknown rotation period: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for RotatePeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RotatePeriod::Never => "never",
+            RotatePeriod::Hourly => "hourly",
+            RotatePeriod::Daily => "daily",
+        })
+    }
+}
+
+/// The log backends that can be selected via `LOG_TARGET`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogTarget {
+    /// Human-readable lines on stderr.
+    Stderr,
+    /// RFC 5424 messages to a syslog daemon or collector at `SYSLOG_ADDRESS`.
+    Syslog,
+    /// Structured entries via the native protocol of systemd-journald.
+    Journald,
+}
+
+impl FromStr for LogTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "stderr" => Ok(LogTarget::Stderr),
+            "syslog" => Ok(LogTarget::Syslog),
+            "journald" => Ok(LogTarget::Journald),
+            other => Err(format!("unknown log target: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for LogTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogTarget::Stderr => "stderr",
+            LogTarget::Syslog => "syslog",
+            LogTarget::Journald => "journald",
+        })
+    }
+}
+
+/// A single DNS record managed by crondes.
+///
+/// In `CF_RECORDS` each record is a JSON object, e.g. `[{"name": "home.example.com"}]`.
+/// In a config file each record is an entry of the `records` list.
+///
+/// At least one of `id` and `name` must be set. If only the name is given,
+/// the ID is looked up from the provider at startup.
+///
+/// Records can be spread over several providers, e.g. `{"name": "home.duckdns.org", "provider": "duckdns"}`;
+/// records without a `provider` belong to `DNS_PROVIDER`. Likewise each record can override how its address is
+/// detected and written, e.g. `{"name": "v6.example.com", "type": "AAAA", "ttl": 300, "ip_sources": ["interface"]}`.
+/// Records with the same type, IP sources and IP services share a single lookup of the public IP per update cycle.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordConfig {
+    /// The DNS record ID at the provider.
+    pub id: String,
+    /// The DNS record name.
+    pub name: String,
+    /// The provider hosting the record, `DNS_PROVIDER` if not set.
+    pub provider: Option<ProviderKind>,
+    /// Whether the record holds the public IPv4 (`A`) or IPv6 (`AAAA`) address, or text rendered from `content` (`TXT`).
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// Template for the content of a TXT record, with the placeholders `{ip}` (the public IPv4 address), `{timestamp}`
+    /// (RFC 3339) and `{record}` (the record name).
+    pub content: String,
+    /// TTL written on update, `CF_TTL` if not set. Only supported by Cloudflare.
+    pub ttl: Option<u32>,
+    /// Proxy status written on update, `CF_PROXIED` if not set. Only supported by Cloudflare.
+    pub proxied: Option<bool>,
+    /// The sources the address of the record is detected with, `IP_SOURCES` if empty.
+    pub ip_sources: Vec<IpSourceKind>,
+    /// URLs queried by the `http` source for the record, `IP_SERVICES` if empty.
+    pub ip_services: Vec<String>,
+    /// Interface identifier of an AAAA record, an IPv6 suffix like `::1234` or a MAC address for its EUI-64
+    /// identifier. The record then gets the detected prefix with this identifier instead of the detected address.
+    pub interface_id: String,
+    /// Length of the prefix taken from the detected address for `interface_id`, `64` if not set.
+    pub prefix_length: Option<u8>,
+}
+
+/// The type of a record managed by crondes, `type` of a record entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RecordType {
+    /// An IPv4 address.
+    #[default]
+    #[serde(alias = "a")]
+    A,
+    /// An IPv6 address.
+    #[serde(alias = "aaaa")]
+    Aaaa,
+    /// Text rendered from the `content` template of the record.
+    #[serde(alias = "txt")]
+    Txt,
+}
+
+impl RecordType {
+    /// Returns the address family the record holds, or embeds in its content.
+    pub fn family(self) -> IpFamily {
+        match self {
+            RecordType::A | RecordType::Txt => IpFamily::V4,
+            RecordType::Aaaa => IpFamily::V6,
+        }
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "A" => Ok(RecordType::A),
+            "AAAA" => Ok(RecordType::Aaaa),
+            "TXT" => Ok(RecordType::Txt),
+            other => Err(format!("unsupported record type: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RecordType::A => "A",
+            RecordType::Aaaa => "AAAA",
+            RecordType::Txt => "TXT",
+        })
+    }
+}
+
+impl RecordConfig {
+    /// Returns a short label for log output: the name if known, the ID otherwise.
+    pub fn label(&self) -> &str {
+        if self.name.is_empty() { &self.id } else { &self.name }
+    }
+}
+
+/// Problem reported by [`Config::problems`] if no record is configured.
+const NO_RECORDS: &str =
+    "No records configured (set CF_RECORDS, CF_RECORD_ID, CF_RECORD_NAME, CF_RECORD_PATTERN, CF_RECORD_TAG or SYNC_FILE)";
+
+/// Problem reported by [`Config::problems`] if the Cloudflare zone is not configured.
+const NO_ZONE: &str = "CF_ZONE_ID or CF_ZONE_NAME is missing";
+
+/// A job of the config file, run by the daemon next to the other jobs with its own scheduler.
+///
+/// A job takes the settings of the top level of the file and replaces the ones it sets itself, named like the fields of
+/// [`Config`]; tables like `notify` are merged key by key. Environment variables and `--set` apply to every job.
+/// Settings of the daemon as a whole ([`TOP_LEVEL_ONLY`]) can only be set at the top level.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobConfig {
+    /// Name of the job, for logs and `--job`.
+    pub name: String,
+    /// The settings replacing the ones of the top level.
+    #[serde(flatten)]
+    pub settings: Map<String, Value>,
+}
+
+/// Settings of the daemon as a whole, which a job can't set: one HTTP server, control socket, PID file, status file
+/// and log output serve all jobs.
+pub const TOP_LEVEL_ONLY: [&str; 9] =
+    ["jobs", "server", "control_socket", "pid_file", "daemonize", "network_watch", "config_watch", "status_file", "log"];
+
+/// Where the configuration is loaded from, kept so the daemon can load it again on reload.
+///
+/// Values are taken from these layers, each overriding the ones before:
+/// 1. the defaults,
+/// 2. the config `file`, if any,
+/// 3. the environment variables,
+/// 4. the `overrides` from the command line, named like the environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigSource {
+    /// A TOML or YAML file.
+    pub file: Option<String>,
+    /// `KEY=VALUE` pairs passed via `--set`.
+    pub overrides: Vec<(String, String)>,
+    /// Name of the job of the `file` to load instead of its top level, see [`JobConfig`].
+    pub job: Option<String>,
+}
+
+/// The layer the value of a setting comes from, see [`ConfigSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    File,
+    Env,
+    Cli,
+}
+
+/// A line of the configuration as printed by `show-config`, with the layer its value comes from.
+#[derive(Debug, Clone)]
+pub struct ResolvedValue {
+    pub key: String,
+    pub value: String,
+    pub origin: Origin,
+}
+
+impl ConfigSource {
+    /// Loads and validates the configuration from this source.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, a `--set` key is unknown,
+    /// or if the resulting configuration is invalid.
+    pub fn load(&self) -> Result<Config, String> {
+        self.check().map_err(|problems| problems.join("; "))
+    }
+
+    /// Loads the configuration like [`ConfigSource::load`], but collects every problem instead of joining them.
+    ///
+    /// # Errors
+    /// Returns all invalid values, unknown `--set` keys and missing or conflicting settings,
+    /// or only the error of a config file that cannot be read or parsed.
+    ///
+    /// The jobs of the file are loaded and checked as well, their problems are prefixed with the name of the job.
+    pub fn check(&self) -> Result<Config, Vec<String>> {
+        let (env, cli) = (Layer::env(), Layer::cli(&self.overrides));
+        let [.., config] = self.apply_layers(&env, &cli).map_err(|e| vec![e])?;
+        let jobs = config.jobs.clone();
+        let (config, mut problems) = match validated(config, &env, &cli, false) {
+            Ok(config) => (Some(config), Vec::new()),
+            Err(problems) => (None, problems),
+        };
+        let job_problems = self.job_problems(&jobs, &problems);
+        problems.extend(job_problems);
+        match config {
+            Some(config) if problems.is_empty() => Ok(config),
+            _ => Err(problems),
+        }
+    }
+
+    /// Returns the source of the job `name` of the config file, with the same overrides.
+    pub fn job(&self, name: &str) -> ConfigSource {
+        ConfigSource { job: Some(name.to_string()), ..self.clone() }
+    }
+
+    /// Checks every job in `jobs` and returns their problems, except for the `known` problems of the top level every
+    /// job inherits. Jobs sharing a `STATE_FILE` are a problem as well, as they would overwrite each other's state.
+    fn job_problems(&self, jobs: &[JobConfig], known: &[String]) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut state_files: BTreeMap<String, &str> = BTreeMap::new();
+        for job in jobs {
+            match self.job(&job.name).check() {
+                Ok(config) if config.state_file.is_empty() => {}
+                Ok(config) => {
+                    if let Some(other) = state_files.insert(config.state_file.clone(), &job.name) {
+                        problems.push(format!("Jobs {} and {} share the STATE_FILE {}, every job needs its own", other, job.name, config.state_file));
+                    }
+                }
+                Err(job_problems) => problems.extend(
+                    job_problems.into_iter().filter(|problem| !known.contains(problem)).map(|problem| format!("Job {}: {}", job.name, problem)),
+                ),
+            }
+        }
+        problems
+    }
+
+    /// Loads the configuration for commands that only read from the provider like `zones` and `records`:
+    /// records and the Cloudflare zone are optional, and `zone` replaces the zone of `DNS_PROVIDER`
+    /// (see [`Config::set_zone`]).
+    ///
+    /// # Errors
+    /// Returns the same errors as [`ConfigSource::load`], except for missing records or zone.
+    pub fn load_for_listing(&self, zone: Option<&str>) -> Result<Config, String> {
+        let (env, cli) = (Layer::env(), Layer::cli(&self.overrides));
+        let [.., mut config] = self.apply_layers(&env, &cli)?;
+        if let Some(zone) = zone {
+            config.set_zone(zone)?;
+        }
+        validated(config, &env, &cli, true).map_err(|problems| problems.join("; "))
+    }
+
+    /// Loads the configuration like [`ConfigSource::load`] and determines the layer every value comes from.
+    ///
+    /// Values from the environment and the command line are attributed by the variables set there. Values from the
+    /// file are recognized by differing from the defaults, so a file value equal to its default counts as default.
+    /// Lines only shown because of another setting, like `LOG_FILE_KEEP` with `LOG_FILE`, count for the layer of
+    /// that setting.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`ConfigSource::load`].
+    pub fn resolve(&self) -> Result<Vec<ResolvedValue>, String> {
+        let env = Layer::env();
+        let cli = Layer::cli(&self.overrides);
+        let layers = self.apply_layers(&env, &cli)?;
+        validated(layers[3].clone(), &env, &cli, false).map_err(|problems| problems.join("; "))?;
+        let printed = layers.map(|config| config.to_string());
+        let shown: Vec<_> = printed.iter().map(|printed| lines(printed)).collect();
+        let resolved = printed[3]
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .map(|(key, value)| {
+                // Ein Wert stammt aus der obersten Ebene, die ihn setzt oder gegenüber der darunter verändert
+                let changed = |upper: usize| shown[upper - 1].get(key) != shown[upper].get(key);
+                let origin = if cli.sets(key) || changed(3) {
+                    Origin::Cli
+                } else if env.sets(key) || changed(2) {
+                    Origin::Env
+                } else if self.file.is_some() && changed(1) {
+                    Origin::File
+                } else {
+                    Origin::Default
+                };
+                ResolvedValue { key: key.to_string(), value: value.to_string(), origin }
+            })
+            .collect();
+        Ok(resolved)
+    }
+
+    /// Returns the value of `name` from the command line or the environment, without reading the config file.
+    pub fn value(&self, name: &str) -> Option<String> {
+        Layer::cli(&self.overrides).value(name).or_else(|| Layer::env().value(name))
+    }
+
+    /// Returns the configuration after each layer: defaults, file, `env` and `cli`, without validating it.
+    ///
+    /// # Errors
+    /// Returns an error if the config file cannot be read or parsed.
+    fn apply_layers(&self, env: &Layer, cli: &Layer) -> Result<[Config; 4], String> {
+        let defaults = Config::default();
+        let file = match (&self.file, &self.job) {
+            (Some(path), job) => Config::parse_file(Path::new(path), job.as_deref())?,
+            (None, Some(job)) => return Err(format!("Job {} not found, jobs are only read from a config file", job)),
+            (None, None) => defaults.clone(),
+        };
+        let mut from_env = file.clone();
+        from_env.apply(env);
+        let mut from_cli = from_env.clone();
+        from_cli.apply(cli);
+        Ok([defaults, file, from_env, from_cli])
+    }
+}
+
+/// Returns `config` if neither the layers `env` and `cli` nor the config itself have a problem,
+/// not counting missing records and zone if it is only used for `listing`.
+fn validated(config: Config, env: &Layer, cli: &Layer, listing: bool) -> Result<Config, Vec<String>> {
+    let problems: Vec<String> = env
+        .problems()
+        .into_iter()
+        .chain(cli.problems())
+        .chain(config.problems().into_iter().filter(|p| !listing || (p != NO_RECORDS && p != NO_ZONE)))
+        .collect();
+    if problems.is_empty() { Ok(config) } else { Err(problems) }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, &self.job) {
+            (None, _) => f.write_str("environment"),
+            (Some(path), None) => write!(f, "config file {}", path),
+            (Some(path), Some(job)) => write!(f, "job {} of config file {}", job, path),
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::Default => "default",
+            Origin::File => "file",
+            Origin::Env => "env",
+            Origin::Cli => "cli",
+        })
+    }
+}
+
+/// Groups the `KEY: value` lines of a [`Config`] display by key, keeping repeated keys like `RECORD` together.
+fn lines(shown: &str) -> BTreeMap<&str, Vec<&str>> {
+    let mut lines: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (key, value) in shown.lines().filter_map(|line| line.split_once(": ")) {
+        lines.entry(key).or_default().push(value);
+    }
+    lines
 }
 
 impl Config {
@@ -25,20 +1520,996 @@ impl Config {
     /// # Errors
     /// Returns an error if any required environment variable is missing or invalid.
     pub fn from_env() -> Result<Self, String> {
-        let cloudflare_api_token = env::var("CF_API_TOKEN").map_err(|_| "CF_API_TOKEN is missing".to_string())?;
-        let cloudflare_zone_id = env::var("CF_ZONE_ID").map_err(|_| "CF_ZONE_ID is missing".to_string())?;
-        let cloudflare_record_id = env::var("CF_RECORD_ID").map_err(|_| "CF_RECORD_ID is missing".to_string())?;
-        let cloudflare_record_name = env::var("CF_RECORD_NAME").map_err(|_| "CF_RECORD_NAME is missing".to_string())?;
-        let update_interval_secs = env::var("UPDATE_INTERVAL_SECS")
-            .map_err(|_| "UPDATE_INTERVAL_SECS is missing".to_string())?
-            .parse::<u64>()
-            .map_err(|_| "UPDATE_INTERVAL_SECS must be a number".to_string())?;
-        Ok(Config {
-            cloudflare_api_token,
-            cloudflare_zone_id,
-            cloudflare_record_id,
-            cloudflare_record_name,
-            update_interval_secs,
-        })
+        ConfigSource::default().load()
+    }
+
+    /// Loads the configuration from a TOML (`.toml`) or YAML (`.yaml`/`.yml`) file.
+    ///
+    /// Environment variables are applied on top of the file, so every value from the file
+    /// can be overridden by its environment variable.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, or if the resulting configuration is invalid.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        ConfigSource { file: Some(path.as_ref().to_string_lossy().into_owned()), ..ConfigSource::default() }.load()
+    }
+
+    /// Reads a TOML or YAML file without applying environment variables or validating the result. With `job`, the
+    /// settings of that job replace the ones of the top level, see [`JobConfig`].
+    fn parse_file(path: &Path, job: Option<&str>) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let config: Config = parse(path, &content)?;
+        let Some(name) = job else { return Ok(config) };
+        let job = config.jobs.into_iter().find(|job| job.name == name).ok_or_else(|| format!("Job {} not found in {}", name, path.display()))?;
+        if let Some(key) = job.settings.keys().find(|key| TOP_LEVEL_ONLY.contains(&key.as_str())) {
+            return Err(format!("{} can only be set at the top level of {}, not in a job", key, path.display()));
+        }
+        // Die Job-Einstellungen werden über die unveränderte Datei gelegt, damit Defaults nicht als gesetzt gelten
+        let mut settings: Value = parse(path, &content)?;
+        if let Some(top) = settings.as_object_mut() {
+            top.remove("jobs");
+        }
+        merge(&mut settings, Value::Object(job.settings));
+        serde_json::from_value(settings).map_err(|e| format!("Invalid settings of job {} in {}: {}", name, path.display(), e))
+    }
+
+    /// Overrides configuration values with all values of `layer` that are set.
+    ///
+    /// Invalid values are recorded in `layer` and leave the setting unchanged, so all of them can be reported at once.
+    fn apply(&mut self, layer: &Layer) {
+        layer.apply("DNS_PROVIDER", &mut self.provider);
+        layer.apply_secret("CF_API_TOKEN", &mut self.cloudflare_api_token);
+        layer.apply("CF_API_EMAIL", &mut self.cloudflare_api_email);
+        layer.apply_secret("CF_API_KEY", &mut self.cloudflare_api_key);
+        layer.apply("CF_ZONE_ID", &mut self.cloudflare_zone_id);
+        layer.apply("CF_ZONE_NAME", &mut self.cloudflare_zone_name);
+        layer.apply("CF_API_URL", &mut self.cloudflare_api_url);
+        layer.apply_option("CF_TTL", &mut self.cloudflare_ttl);
+        layer.apply_option("CF_PROXIED", &mut self.cloudflare_proxied);
+        layer.apply("CF_COMMENT", &mut self.cloudflare_comment);
+        layer.apply_list("CF_TAGS", &mut self.cloudflare_tags);
+        layer.apply_secret("PORKBUN_API_KEY", &mut self.porkbun.api_key);
+        layer.apply_secret("PORKBUN_SECRET_API_KEY", &mut self.porkbun.secret_api_key);
+        layer.apply("PORKBUN_DOMAIN", &mut self.porkbun.domain);
+        layer.apply_secret("DESEC_TOKEN", &mut self.desec.token);
+        layer.apply("DESEC_DOMAIN", &mut self.desec.domain);
+        layer.apply_secret("DUCKDNS_TOKEN", &mut self.duckdns.token);
+        layer.apply("NOIP_USERNAME", &mut self.noip.username);
+        layer.apply_secret("NOIP_PASSWORD", &mut self.noip.password);
+        layer.apply("DYNDNS2_URL", &mut self.dyndns2.url);
+        layer.apply("DYNDNS2_USERNAME", &mut self.dyndns2.username);
+        layer.apply_secret("DYNDNS2_PASSWORD", &mut self.dyndns2.password);
+        layer.apply("RFC2136_SERVER", &mut self.rfc2136.server);
+        layer.apply("RFC2136_ZONE", &mut self.rfc2136.zone);
+        layer.apply("RFC2136_KEY_NAME", &mut self.rfc2136.key_name);
+        layer.apply_secret("RFC2136_KEY_SECRET", &mut self.rfc2136.key_secret);
+        layer.apply("RFC2136_KEY_ALGORITHM", &mut self.rfc2136.key_algorithm);
+        layer.apply("RFC2136_TTL", &mut self.rfc2136.ttl);
+        layer.apply_secret("LINODE_TOKEN", &mut self.linode.token);
+        layer.apply("LINODE_DOMAIN", &mut self.linode.domain);
+        layer.apply_secret("VULTR_API_KEY", &mut self.vultr.api_key);
+        layer.apply("VULTR_DOMAIN", &mut self.vultr.domain);
+        layer.apply("NAMECHEAP_DOMAIN", &mut self.namecheap.domain);
+        layer.apply_secret("NAMECHEAP_PASSWORD", &mut self.namecheap.password);
+        layer.apply("UPDATE_MODE", &mut self.update_mode);
+        layer.apply("UPDATE_INTERVAL_SECS", &mut self.update_interval_secs);
+        layer.apply("UPDATE_INTERVAL_SECS_V6", &mut self.update_interval_secs_v6);
+        layer.apply("UPDATE_INTERVAL_JITTER_PERCENT", &mut self.update_interval_jitter_percent);
+        layer.apply("STARTUP_SPLAY_SECS", &mut self.startup_splay_secs);
+        layer.apply("STARTUP_DELAY_SECS", &mut self.startup_delay_secs);
+        layer.apply("STARTUP_GRACE_SECS", &mut self.startup_grace_secs);
+        layer.apply("NETWORK_WATCH", &mut self.network_watch);
+        layer.apply("CONFIG_WATCH", &mut self.config_watch);
+        layer.apply("CONTROL_SOCKET", &mut self.control_socket);
+        layer.apply("DAEMONIZE", &mut self.daemonize);
+        layer.apply("PID_FILE", &mut self.pid_file);
+        layer.apply("REREAD_EVERY_N_CYCLES", &mut self.reread_every_n_cycles);
+        layer.apply("UPDATE_CONCURRENCY", &mut self.update_concurrency);
+        layer.apply("MIN_UPDATE_COOLDOWN_SECS", &mut self.min_update_cooldown_secs);
+        layer.apply("FORCE_OVERWRITE", &mut self.force_overwrite);
+        layer.apply("CYCLE_DEADLINE_SECS", &mut self.cycle_deadline_secs);
+        layer.apply("CONNECTIVITY_CHECK", &mut self.connectivity_check);
+        layer.apply("CONNECTIVITY_TARGET", &mut self.connectivity_target);
+        layer.apply_list("QUIET_HOURS", &mut self.quiet_hours);
+        layer.apply("QUIET_HOURS_TZ", &mut self.quiet_hours_tz);
+        layer.apply("QUIET_HOURS_MODE", &mut self.quiet_hours_mode);
+        layer.apply("RECORD_CHECK", &mut self.record_check);
+        layer.apply("REVALIDATE_EVERY_N_CYCLES", &mut self.revalidate_every_n_cycles);
+        layer.apply("RUN_ONCE", &mut self.run_once);
+        layer.apply("EXIT_ON_ERROR", &mut self.exit_on_error);
+        layer.apply("STATE_FILE", &mut self.state_file);
+        layer.apply("HISTORY_FILE", &mut self.history_file);
+        layer.apply("STATUS_FILE", &mut self.status_file);
+        layer.apply("SYNC_FILE", &mut self.sync_file);
+        layer.apply("SYNC_PRUNE", &mut self.sync_prune);
+        layer.apply("RETRY_MAX_FAILURES", &mut self.retry.max_consecutive_failures);
+        layer.apply("RETRY_BACKOFF_BASE_SECS", &mut self.retry.backoff_base_secs);
+        layer.apply("RETRY_BACKOFF_MAX_SECS", &mut self.retry.backoff_max_secs);
+        layer.apply("HTTP_TIMEOUT_SECS", &mut self.http.timeout_secs);
+        layer.apply("HTTP_CONNECT_TIMEOUT_SECS", &mut self.http.connect_timeout_secs);
+        layer.apply("HTTP_USER_AGENT", &mut self.http.user_agent);
+        layer.apply_secret("OUTBOUND_PROXY", &mut self.http.proxy);
+        layer.apply("HTTP_CA_FILE", &mut self.http.ca_file);
+        layer.apply("HTTP_BUILTIN_ROOTS", &mut self.http.builtin_roots);
+        layer.apply("BIND_INTERFACE", &mut self.http.bind_interface);
+        layer.apply_option("BIND_ADDRESS", &mut self.http.bind_address);
+        layer.apply("SERVER_BIND", &mut self.server.bind);
+        layer.apply("HEALTH_MAX_INTERVALS", &mut self.server.health_max_intervals);
+        layer.apply_secret("SERVER_API_TOKEN", &mut self.server.api_token);
+        layer.apply_secret("SERVER_WEBHOOK_TOKEN", &mut self.server.webhook_token);
+        layer.apply_list("IP_SOURCES", &mut self.ip.sources);
+        layer.apply_list("IP_SERVICES", &mut self.ip.services);
+        layer.apply("IP_INTERFACE", &mut self.ip.interface);
+        layer.apply("IP_UPNP_URL", &mut self.ip.upnp_url);
+        layer.apply("IP_UPNP_SERVICE", &mut self.ip.upnp_service);
+        layer.apply("IP_STABLE_CHECKS", &mut self.ip.stable_checks);
+        layer.apply("IP_ALLOW_PRIVATE", &mut self.ip.allow_private);
+        layer.apply("IP_STRATEGY", &mut self.ip.strategy);
+        layer.apply("IP_QUORUM", &mut self.ip.quorum);
+        layer.apply("IP_SOURCE_TIMEOUT_SECS", &mut self.ip.timeout_secs);
+        layer.apply("IP_BREAKER_THRESHOLD", &mut self.ip.breaker_threshold);
+        layer.apply("IP_BREAKER_COOLDOWN_SECS", &mut self.ip.breaker_cooldown_secs);
+        layer.apply_list_secret("NOTIFY_WEBHOOK_URLS", &mut self.notify.webhook_urls);
+        layer.apply_list_secret("NOTIFY_DISCORD_URLS", &mut self.notify.discord_urls);
+        layer.apply_list_secret("NOTIFY_SLACK_URLS", &mut self.notify.slack_urls);
+        layer.apply("NOTIFY_GOTIFY_URL", &mut self.notify.gotify_url);
+        layer.apply_secret("NOTIFY_GOTIFY_TOKEN", &mut self.notify.gotify_token);
+        layer.apply_secret("NOTIFY_PUSHOVER_TOKEN", &mut self.notify.pushover_token);
+        layer.apply_secret("NOTIFY_PUSHOVER_USER", &mut self.notify.pushover_user);
+        layer.apply_secret("HEARTBEAT_URL", &mut self.notify.heartbeat_url);
+        layer.apply("NOTIFY_SMTP_HOST", &mut self.notify.smtp_host);
+        layer.apply_option("NOTIFY_SMTP_PORT", &mut self.notify.smtp_port);
+        layer.apply("NOTIFY_SMTP_TLS", &mut self.notify.smtp_tls);
+        layer.apply("NOTIFY_SMTP_USERNAME", &mut self.notify.smtp_username);
+        layer.apply_secret("NOTIFY_SMTP_PASSWORD", &mut self.notify.smtp_password);
+        layer.apply("NOTIFY_SMTP_FROM", &mut self.notify.smtp_from);
+        layer.apply_list("NOTIFY_SMTP_TO", &mut self.notify.smtp_to);
+        layer.apply("NOTIFY_SMTP_SUBJECT", &mut self.notify.smtp_subject);
+        layer.apply("NOTIFY_SMTP_BODY", &mut self.notify.smtp_body);
+        layer.apply("NOTIFY_FAILURE_THRESHOLD", &mut self.notify.failure_threshold);
+        layer.apply("PRE_UPDATE_CMD", &mut self.hooks.pre_update_cmd);
+        layer.apply("PRE_UPDATE_URL", &mut self.hooks.pre_update_url);
+        layer.apply("ON_CHANGE_CMD", &mut self.hooks.on_change_cmd);
+        layer.apply("ON_FAILURE_CMD", &mut self.hooks.on_failure_cmd);
+        layer.apply("HOOK_TIMEOUT_SECS", &mut self.hooks.timeout_secs);
+        layer.apply("PROPAGATION_CHECK", &mut self.propagation.enabled);
+        layer.apply_list("PROPAGATION_RESOLVERS", &mut self.propagation.resolvers);
+        layer.apply("PROPAGATION_TIMEOUT_SECS", &mut self.propagation.timeout_secs);
+        layer.apply("LOG_TARGET", &mut self.log.target);
+        layer.apply("SYSLOG_ADDRESS", &mut self.log.syslog_address);
+        layer.apply("LOG_FILE", &mut self.log.file);
+        layer.apply("LOG_FILE_MAX_SIZE_MB", &mut self.log.file_max_size_mb);
+        layer.apply("LOG_FILE_ROTATE_EVERY", &mut self.log.file_rotate_every);
+        layer.apply("LOG_FILE_KEEP", &mut self.log.file_keep);
+        layer.apply("CF_RECORD_PATTERN", &mut self.record_pattern);
+        layer.apply("CF_RECORD_TAG", &mut self.record_tag);
+        match (layer.value("CF_RECORDS"), layer.value("CF_RECORD_ID"), layer.value("CF_RECORD_NAME")) {
+            (Some(raw), _, _) => match serde_json::from_str(&raw) {
+                Ok(records) => self.records = records,
+                Err(e) => layer.error(format!("{} must be a JSON list of records: {}", layer.label("CF_RECORDS"), e)),
+            },
+            (None, None, None) => {}
+            (None, id, name) => {
+                self.records = vec![RecordConfig { id: id.unwrap_or_default(), name: name.unwrap_or_default(), ..RecordConfig::default() }];
+            }
+        }
+    }
+
+    /// Points `DNS_PROVIDER` at another zone, given by ID or by name, e.g. to list its records.
+    ///
+    /// # Errors
+    /// Returns an error if the provider has no zones.
+    pub fn set_zone(&mut self, zone: &str) -> Result<(), String> {
+        match self.provider {
+            // Cloudflare-Zonen-IDs enthalten keine Punkte, Domains schon
+            ProviderKind::Cloudflare if zone.contains('.') => {
+                self.cloudflare_zone_id.clear();
+                self.cloudflare_zone_name = zone.to_string();
+            }
+            ProviderKind::Cloudflare => self.cloudflare_zone_id = zone.to_string(),
+            ProviderKind::Porkbun => self.porkbun.domain = zone.to_string(),
+            ProviderKind::Desec => self.desec.domain = zone.to_string(),
+            ProviderKind::Rfc2136 => self.rfc2136.zone = zone.to_string(),
+            ProviderKind::Linode => self.linode.domain = zone.to_string(),
+            ProviderKind::Vultr => self.vultr.domain = zone.to_string(),
+            ProviderKind::Namecheap => self.namecheap.domain = zone.to_string(),
+            ProviderKind::DuckDns | ProviderKind::NoIp | ProviderKind::Dyndns2 => {
+                return Err(format!("DNS_PROVIDER={} has no zones", self.provider));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the provider hosting `record`: its own if set, `DNS_PROVIDER` otherwise.
+    pub fn provider_of(&self, record: &RecordConfig) -> ProviderKind {
+        record.provider.unwrap_or(self.provider)
+    }
+
+    /// Returns the providers hosting at least one record, in the order of their first record.
+    ///
+    /// Without any records this is just `DNS_PROVIDER`, so its settings are still validated and shown. With
+    /// `CF_RECORD_PATTERN` or `CF_RECORD_TAG`, `DNS_PROVIDER` is always included, since the matching records are only
+    /// known at startup.
+    pub fn providers(&self) -> Vec<ProviderKind> {
+        let mut providers = Vec::new();
+        for provider in self.records.iter().map(|r| self.provider_of(r)) {
+            if !providers.contains(&provider) {
+                providers.push(provider);
+            }
+        }
+        let discovers = !self.record_pattern.is_empty() || !self.record_tag.is_empty();
+        if providers.is_empty() || (discovers && !providers.contains(&self.provider)) {
+            providers.push(self.provider);
+        }
+        providers
+    }
+
+    /// Checks all values without contacting any service: required values, ranges and settings depending on each other.
+    ///
+    /// # Returns
+    /// Every problem found, empty if the configuration is valid.
+    ///
+    /// With `jobs`, the top level only provides the settings the jobs inherit, so it needs no records or credentials.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.jobs.is_empty() {
+            for provider in self.providers() {
+                self.provider_problems(provider, &mut problems);
+            }
+            if self.records.is_empty() && self.record_pattern.is_empty() && self.record_tag.is_empty() && self.sync_file.is_empty() {
+                problems.push(NO_RECORDS.to_string());
+            }
+        }
+        let mut names = BTreeSet::new();
+        for job in &self.jobs {
+            if job.name.trim().is_empty() {
+                problems.push("Every job needs a name".to_string());
+            } else if !names.insert(job.name.as_str()) {
+                problems.push(format!("Job {} is defined more than once", job.name));
+            }
+        }
+        if let Some(ttl) = self.cloudflare_ttl && ttl != 1 && !(30..=86400).contains(&ttl) {
+            problems.push("CF_TTL must be 1 (automatic) or between 30 and 86400".to_string());
+        }
+        if !self.record_tag.is_empty() && self.provider != ProviderKind::Cloudflare {
+            problems.push("CF_RECORD_TAG is only supported with DNS_PROVIDER=cloudflare".to_string());
+        }
+        if let Some(tag) = self.cloudflare_tags.iter().find(|tag| tag.starts_with(':')) {
+            problems.push(format!("CF_TAGS entry {} needs a name before the colon", tag));
+        }
+        if !self.sync_file.is_empty() {
+            // Die Zone des DNS_PROVIDER wird auch synchronisiert, wenn er selbst keine Records hostet
+            if !self.providers().contains(&self.provider) {
+                self.provider_problems(self.provider, &mut problems);
+            }
+            if self.provider != ProviderKind::Cloudflare {
+                problems.push("SYNC_FILE is only supported with DNS_PROVIDER=cloudflare".to_string());
+            }
+        }
+        if let Some(pos) = self.records.iter().position(|r| r.id.is_empty() && r.name.is_empty()) {
+            problems.push(format!("Record #{} needs an id or a name", pos + 1));
+        }
+        for (pos, record) in self.records.iter().enumerate() {
+            let provider = self.provider_of(record);
+            if (record.ttl.is_some() || record.proxied.is_some()) && provider != ProviderKind::Cloudflare {
+                problems.push(format!("Record #{}: ttl and proxied are only supported for Cloudflare records", pos + 1));
+            }
+            if let Some(ttl) = record.ttl && ttl != 1 && !(30..=86400).contains(&ttl) {
+                problems.push(format!("Record #{}: ttl must be 1 (automatic) or between 30 and 86400", pos + 1));
+            }
+            if record.record_type == RecordType::Aaaa {
+                if !provider.supports_aaaa() {
+                    problems.push(format!("Record #{}: {} does not support AAAA records", pos + 1, provider));
+                }
+                let sources = if record.ip_sources.is_empty() { &self.ip.sources } else { &record.ip_sources };
+                if let Some(source) = sources.iter().find(|s| !s.supports_ipv6()) {
+                    problems.push(format!("Record #{}: IP source {} can't detect IPv6 addresses", pos + 1, source));
+                }
+            }
+            if record.record_type == RecordType::Txt {
+                if !provider.supports_txt() {
+                    problems.push(format!("Record #{}: {} does not support TXT records", pos + 1, provider));
+                }
+                if record.content.trim().is_empty() {
+                    problems.push(format!("Record #{}: TXT records need a content template", pos + 1));
+                }
+            } else if !record.content.is_empty() {
+                problems.push(format!("Record #{}: content is only used by TXT records", pos + 1));
+            }
+            if let Some(Err(e)) = record.ip_services.iter().map(|s| check_service(s)).find(Result::is_err) {
+                problems.push(format!("Record #{}: ip_services {}", pos + 1, e));
+            }
+            if !record.interface_id.is_empty() || record.prefix_length.is_some() {
+                if record.record_type != RecordType::Aaaa {
+                    problems.push(format!("Record #{}: interface_id and prefix_length are only used by AAAA records", pos + 1));
+                } else if record.interface_id.is_empty() {
+                    problems.push(format!("Record #{}: prefix_length needs an interface_id", pos + 1));
+                }
+                if !record.interface_id.is_empty() && let Err(e) = ip::parse_interface_id(&record.interface_id) {
+                    problems.push(format!("Record #{}: interface_id {}", pos + 1, e));
+                }
+                if let Some(length) = record.prefix_length && !(1..=127).contains(&length) {
+                    problems.push(format!("Record #{}: prefix_length must be between 1 and 127", pos + 1));
+                }
+            }
+        }
+        if self.update_interval_secs == 0 {
+            problems.push("UPDATE_INTERVAL_SECS must be greater than zero".to_string());
+        }
+        if self.update_interval_jitter_percent >= 100 {
+            problems.push("UPDATE_INTERVAL_JITTER_PERCENT must be less than 100".to_string());
+        }
+        if self.reread_every_n_cycles == 0 {
+            problems.push("REREAD_EVERY_N_CYCLES must be greater than zero".to_string());
+        }
+        if self.update_concurrency == 0 {
+            problems.push("UPDATE_CONCURRENCY must be greater than zero".to_string());
+        }
+        if self.cycle_deadline_secs > 0 && self.cycle_deadline_secs < self.http.timeout_secs {
+            problems.push("CYCLE_DEADLINE_SECS must not be shorter than HTTP_TIMEOUT_SECS".to_string());
+        }
+        if self.connectivity_check == ConnectivityCheck::Tcp
+            && !self.connectivity_target.is_empty()
+            && !self.connectivity_target.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        {
+            problems.push(format!("CONNECTIVITY_TARGET must be host:port for CONNECTIVITY_CHECK=tcp: {}", self.connectivity_target));
+        }
+        if !self.quiet_hours.is_empty() && let Err(e) = QuietHours::new(&self.quiet_hours, &self.quiet_hours_tz) {
+            problems.push(format!("QUIET_HOURS: {}", e));
+        }
+        if self.retry.backoff_base_secs == 0 || self.retry.backoff_max_secs < self.retry.backoff_base_secs {
+            problems.push("RETRY_BACKOFF_BASE_SECS must be greater than zero and not exceed RETRY_BACKOFF_MAX_SECS".to_string());
+        }
+        if self.server.health_max_intervals == 0 {
+            problems.push("HEALTH_MAX_INTERVALS must be greater than zero".to_string());
+        }
+        if !self.server.api_token.is_empty() && self.server.bind.is_empty() {
+            problems.push("SERVER_API_TOKEN requires SERVER_BIND".to_string());
+        }
+        if !self.server.webhook_token.is_empty() && self.server.bind.is_empty() {
+            problems.push("SERVER_WEBHOOK_TOKEN requires SERVER_BIND".to_string());
+        }
+        if self.update_mode == UpdateMode::Webhook && self.server.webhook_token.is_empty() {
+            problems.push("UPDATE_MODE=webhook requires SERVER_BIND and SERVER_WEBHOOK_TOKEN".to_string());
+        }
+        if self.http.timeout_secs == 0 || self.http.connect_timeout_secs == 0 {
+            problems.push("HTTP_TIMEOUT_SECS and HTTP_CONNECT_TIMEOUT_SECS must be greater than zero".to_string());
+        }
+        let proxy = self.http.proxy.expose().trim();
+        if !proxy.is_empty()
+            && proxy != "none"
+            && !reqwest::Url::parse(proxy)
+                .is_ok_and(|u| matches!(u.scheme(), "http" | "https" | "socks5" | "socks5h") && u.has_host())
+        {
+            // Die URL kann Zugangsdaten enthalten und wird deshalb nicht ausgegeben
+            problems.push("OUTBOUND_PROXY must be an http://, https://, socks5:// or socks5h:// URL, or none".to_string());
+        }
+        if !self.http.builtin_roots && self.http.ca_file.is_empty() {
+            problems.push("HTTP_BUILTIN_ROOTS=false requires HTTP_CA_FILE, otherwise no HTTPS server is trusted".to_string());
+        }
+        if !self.http.bind_interface.is_empty() && !crate::http::BIND_INTERFACE_SUPPORTED {
+            problems.push("BIND_INTERFACE is only supported on Linux, Android and macOS, use BIND_ADDRESS instead".to_string());
+        }
+        if self.ip.sources.is_empty() {
+            problems.push("IP_SOURCES must contain at least one source".to_string());
+        }
+        if let Some(Err(e)) = self.ip.services.iter().map(|s| check_service(s)).find(Result::is_err) {
+            problems.push(format!("IP_SERVICES {}", e));
+        }
+        if !is_http_url(&self.ip.upnp_url) {
+            problems.push(format!("IP_UPNP_URL is not a valid URL: {}", self.ip.upnp_url));
+        }
+        for (name, urls) in [
+            ("NOTIFY_WEBHOOK_URLS", &self.notify.webhook_urls),
+            ("NOTIFY_DISCORD_URLS", &self.notify.discord_urls),
+            ("NOTIFY_SLACK_URLS", &self.notify.slack_urls),
+        ] {
+            if let Some(url) = urls.iter().find(|u| !is_http_url(u.expose())) {
+                // Webhook-URLs enthalten ihr Token, deshalb nur maskiert ausgeben
+                problems.push(format!("{} contains an invalid URL: {}", name, url));
+            }
+        }
+        if !self.notify.gotify_url.is_empty() && (!is_http_url(&self.notify.gotify_url) || self.notify.gotify_token.is_empty()) {
+            problems.push("NOTIFY_GOTIFY_URL must be a valid URL and requires NOTIFY_GOTIFY_TOKEN".to_string());
+        }
+        if !self.notify.pushover_token.is_empty() && self.notify.pushover_user.is_empty() {
+            problems.push("NOTIFY_PUSHOVER_USER is required when NOTIFY_PUSHOVER_TOKEN is set".to_string());
+        }
+        if !self.notify.heartbeat_url.is_empty() && !is_http_url(self.notify.heartbeat_url.expose()) {
+            problems.push(format!("HEARTBEAT_URL is not a valid URL: {}", self.notify.heartbeat_url));
+        }
+        if !self.notify.smtp_host.is_empty() {
+            if self.notify.smtp_from.is_empty() || self.notify.smtp_to.is_empty() {
+                problems.push("NOTIFY_SMTP_FROM and NOTIFY_SMTP_TO are required when NOTIFY_SMTP_HOST is set".to_string());
+            }
+            if let Some(addr) = std::iter::once(&self.notify.smtp_from)
+                .chain(&self.notify.smtp_to)
+                .find(|addr| addr.parse::<lettre::message::Mailbox>().is_err())
+            {
+                problems.push(format!("Invalid email address in NOTIFY_SMTP_FROM/NOTIFY_SMTP_TO: {}", addr));
+            }
+        }
+        if !self.hooks.pre_update_url.is_empty() && !is_http_url(&self.hooks.pre_update_url) {
+            problems.push(format!("PRE_UPDATE_URL is not a valid URL: {}", self.hooks.pre_update_url));
+        }
+        if self.hooks.timeout_secs == 0 {
+            problems.push("HOOK_TIMEOUT_SECS must be greater than zero".to_string());
+        }
+        if self.propagation.enabled && (self.propagation.resolvers.is_empty() || self.propagation.timeout_secs == 0) {
+            problems.push("PROPAGATION_RESOLVERS must not be empty and PROPAGATION_TIMEOUT_SECS must be greater than zero".to_string());
+        }
+        if self.log.target == LogTarget::Syslog && let Err(e) = self.log.syslog_address.parse::<SyslogAddress>() {
+            problems.push(format!("Invalid SYSLOG_ADDRESS: {}", e));
+        }
+        if self.ip.stable_checks == 0 {
+            problems.push("IP_STABLE_CHECKS must be greater than zero".to_string());
+        }
+        if self.ip.quorum == 0 {
+            problems.push("IP_QUORUM must be greater than zero".to_string());
+        }
+        if self.ip.timeout_secs == 0 {
+            problems.push("IP_SOURCE_TIMEOUT_SECS must be greater than zero".to_string());
+        }
+        problems
+    }
+
+    /// Adds the problems with the settings of `provider` and the records it hosts to `problems`.
+    fn provider_problems(&self, provider: ProviderKind, problems: &mut Vec<String>) {
+        let records: Vec<&RecordConfig> = self.records.iter().filter(|r| self.provider_of(r) == provider).collect();
+        match provider {
+            ProviderKind::Cloudflare => {
+                if self.cloudflare_api_token.is_empty() && (self.cloudflare_api_email.is_empty() || self.cloudflare_api_key.is_empty()) {
+                    problems.push("CF_API_TOKEN (or CF_API_EMAIL and CF_API_KEY) is missing".to_string());
+                }
+                if self.cloudflare_zone_id.is_empty() && self.cloudflare_zone_name.is_empty() {
+                    problems.push(NO_ZONE.to_string());
+                }
+                if !is_http_url(&self.cloudflare_api_url) {
+                    problems.push(format!("CF_API_URL is not a valid URL: {}", self.cloudflare_api_url));
+                }
+            }
+            ProviderKind::Porkbun => {
+                if self.porkbun.api_key.is_empty() || self.porkbun.secret_api_key.is_empty() {
+                    problems.push("PORKBUN_API_KEY and PORKBUN_SECRET_API_KEY are required for DNS_PROVIDER=porkbun".to_string());
+                }
+                if self.porkbun.domain.is_empty() {
+                    problems.push("PORKBUN_DOMAIN is missing".to_string());
+                } else if let Some(record) = records.iter().find(|r| subdomain(&r.name, &self.porkbun.domain).is_none()) {
+                    problems.push(format!("Porkbun records need a name within PORKBUN_DOMAIN, got: {}", record.label()));
+                }
+            }
+            ProviderKind::Desec => {
+                if self.desec.token.is_empty() {
+                    problems.push("DESEC_TOKEN is required for DNS_PROVIDER=desec".to_string());
+                }
+                if self.desec.domain.is_empty() {
+                    problems.push("DESEC_DOMAIN is missing".to_string());
+                } else if let Some(record) = records.iter().find(|r| subdomain(&r.name, &self.desec.domain).is_none()) {
+                    problems.push(format!("deSEC records need a name within DESEC_DOMAIN, got: {}", record.label()));
+                }
+            }
+            ProviderKind::DuckDns => {
+                if self.duckdns.token.is_empty() {
+                    problems.push("DUCKDNS_TOKEN is required for DNS_PROVIDER=duckdns".to_string());
+                }
+            }
+            ProviderKind::NoIp => {
+                if self.noip.username.is_empty() || self.noip.password.is_empty() {
+                    problems.push("NOIP_USERNAME and NOIP_PASSWORD are required for DNS_PROVIDER=noip".to_string());
+                }
+            }
+            ProviderKind::Dyndns2 => {
+                if !is_http_url(&self.dyndns2.url) {
+                    problems.push(format!("DYNDNS2_URL is missing or not a valid URL: {}", self.dyndns2.url));
+                }
+                if self.dyndns2.username.is_empty() || self.dyndns2.password.is_empty() {
+                    problems.push("DYNDNS2_USERNAME and DYNDNS2_PASSWORD are required for DNS_PROVIDER=dyndns2".to_string());
+                }
+            }
+            ProviderKind::Rfc2136 => {
+                let rfc2136 = &self.rfc2136;
+                if rfc2136.server.is_empty() || rfc2136.zone.is_empty() || rfc2136.key_name.is_empty() || rfc2136.key_secret.is_empty() {
+                    problems.push("RFC2136_SERVER, RFC2136_ZONE, RFC2136_KEY_NAME and RFC2136_KEY_SECRET are required for DNS_PROVIDER=rfc2136".to_string());
+                }
+                if !rfc2136.zone.is_empty() && let Some(record) = records.iter().find(|r| subdomain(&r.name, &rfc2136.zone).is_none()) {
+                    problems.push(format!("RFC 2136 records need a name within RFC2136_ZONE, got: {}", record.label()));
+                }
+            }
+            ProviderKind::Linode => {
+                if self.linode.token.is_empty() || self.linode.domain.is_empty() {
+                    problems.push("LINODE_TOKEN and LINODE_DOMAIN are required for DNS_PROVIDER=linode".to_string());
+                }
+            }
+            ProviderKind::Vultr => {
+                if self.vultr.api_key.is_empty() || self.vultr.domain.is_empty() {
+                    problems.push("VULTR_API_KEY and VULTR_DOMAIN are required for DNS_PROVIDER=vultr".to_string());
+                }
+            }
+            ProviderKind::Namecheap => {
+                if self.namecheap.domain.is_empty() || self.namecheap.password.is_empty() {
+                    problems.push("NAMECHEAP_DOMAIN and NAMECHEAP_PASSWORD are required for DNS_PROVIDER=namecheap".to_string());
+                }
+                if !self.namecheap.domain.is_empty()
+                    && let Some(record) = records.iter().find(|r| subdomain(&r.name, &self.namecheap.domain).is_none())
+                {
+                    problems.push(format!("Namecheap records need a name within NAMECHEAP_DOMAIN, got: {}", record.label()));
+                }
+            }
+        }
+        if !provider.uses_record_ids() && let Some(record) = records.iter().find(|r| r.name.is_empty()) {
+            problems.push(format!("{} records need a name, got an ID only: {}", provider, record.id));
+        }
+    }
+
+    /// Writes the settings of `provider` as `KEY: value` lines.
+    fn fmt_provider(&self, f: &mut fmt::Formatter<'_>, provider: ProviderKind) -> fmt::Result {
+        match provider {
+            ProviderKind::Cloudflare => {
+                writeln!(f, "CF_API_TOKEN: {}", self.cloudflare_api_token)?;
+                if self.cloudflare_api_token.is_empty() {
+                    writeln!(f, "CF_API_EMAIL: {}", self.cloudflare_api_email)?;
+                    writeln!(f, "CF_API_KEY: {}", self.cloudflare_api_key)?;
+                }
+                writeln!(f, "CF_ZONE_ID: {}", self.cloudflare_zone_id)?;
+                writeln!(f, "CF_ZONE_NAME: {}", self.cloudflare_zone_name)?;
+                writeln!(f, "CF_API_URL: {}", self.cloudflare_api_url)?;
+                writeln!(f, "CF_TTL: {}", display_option(&self.cloudflare_ttl))?;
+                writeln!(f, "CF_PROXIED: {}", display_option(&self.cloudflare_proxied))?;
+                writeln!(f, "CF_COMMENT: {}", self.cloudflare_comment)?;
+                writeln!(f, "CF_TAGS: {}", self.cloudflare_tags.join(","))?;
+            }
+            ProviderKind::Porkbun => {
+                writeln!(f, "PORKBUN_API_KEY: {}", self.porkbun.api_key)?;
+                writeln!(f, "PORKBUN_SECRET_API_KEY: {}", self.porkbun.secret_api_key)?;
+                writeln!(f, "PORKBUN_DOMAIN: {}", self.porkbun.domain)?;
+            }
+            ProviderKind::Desec => {
+                writeln!(f, "DESEC_TOKEN: {}", self.desec.token)?;
+                writeln!(f, "DESEC_DOMAIN: {}", self.desec.domain)?;
+            }
+            ProviderKind::DuckDns => writeln!(f, "DUCKDNS_TOKEN: {}", self.duckdns.token)?,
+            ProviderKind::NoIp => {
+                writeln!(f, "NOIP_USERNAME: {}", self.noip.username)?;
+                writeln!(f, "NOIP_PASSWORD: {}", self.noip.password)?;
+            }
+            ProviderKind::Dyndns2 => {
+                writeln!(f, "DYNDNS2_URL: {}", self.dyndns2.url)?;
+                writeln!(f, "DYNDNS2_USERNAME: {}", self.dyndns2.username)?;
+                writeln!(f, "DYNDNS2_PASSWORD: {}", self.dyndns2.password)?;
+            }
+            ProviderKind::Rfc2136 => {
+                writeln!(f, "RFC2136_SERVER: {}", self.rfc2136.server)?;
+                writeln!(f, "RFC2136_ZONE: {}", self.rfc2136.zone)?;
+                writeln!(f, "RFC2136_KEY_NAME: {}", self.rfc2136.key_name)?;
+                writeln!(f, "RFC2136_KEY_SECRET: {}", self.rfc2136.key_secret)?;
+                writeln!(f, "RFC2136_KEY_ALGORITHM: {}", self.rfc2136.key_algorithm)?;
+                writeln!(f, "RFC2136_TTL: {}", self.rfc2136.ttl)?;
+            }
+            ProviderKind::Linode => {
+                writeln!(f, "LINODE_TOKEN: {}", self.linode.token)?;
+                writeln!(f, "LINODE_DOMAIN: {}", self.linode.domain)?;
+            }
+            ProviderKind::Vultr => {
+                writeln!(f, "VULTR_API_KEY: {}", self.vultr.api_key)?;
+                writeln!(f, "VULTR_DOMAIN: {}", self.vultr.domain)?;
+            }
+            ProviderKind::Namecheap => {
+                writeln!(f, "NAMECHEAP_DOMAIN: {}", self.namecheap.domain)?;
+                writeln!(f, "NAMECHEAP_PASSWORD: {}", self.namecheap.password)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Config {
+    /// Formats the configuration as one `KEY: value` line per setting.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DNS_PROVIDER: {}", self.provider)?;
+        let providers = self.providers();
+        for &provider in &providers {
+            self.fmt_provider(f, provider)?;
+        }
+        for record in &self.records {
+            let provider = self.provider_of(record);
+            let id = match record.id.as_str() {
+                "" if provider.uses_record_ids() => "<resolved by name>",
+                "" => "<not used>",
+                id => id,
+            };
+            let mut details = vec![format!("ID: {}", id)];
+            if providers.len() > 1 {
+                details.push(format!("provider: {}", provider));
+            }
+            if record.record_type != RecordType::A {
+                details.push(format!("type: {}", record.record_type));
+            }
+            if !record.content.is_empty() {
+                details.push(format!("content: {}", record.content));
+            }
+            if let Some(ttl) = record.ttl {
+                details.push(format!("ttl: {}", ttl));
+            }
+            if let Some(proxied) = record.proxied {
+                details.push(format!("proxied: {}", proxied));
+            }
+            if !record.ip_sources.is_empty() {
+                details.push(format!("IP sources: {}", display_list(&record.ip_sources)));
+            }
+            if !record.ip_services.is_empty() {
+                details.push(format!("IP services: {}", display_list(&record.ip_services)));
+            }
+            if !record.interface_id.is_empty() {
+                details.push(format!("interface ID: {}/{}", record.interface_id, record.prefix_length.unwrap_or(64)));
+            }
+            writeln!(f, "RECORD: {} ({})", record.name, details.join(", "))?;
+        }
+        writeln!(f, "CF_RECORD_PATTERN: {}", self.record_pattern)?;
+        writeln!(f, "CF_RECORD_TAG: {}", self.record_tag)?;
+        writeln!(f, "JOBS: {}", self.jobs.iter().map(|job| job.name.as_str()).collect::<Vec<_>>().join(", "))?;
+        writeln!(f, "UPDATE_MODE: {}", self.update_mode)?;
+        writeln!(f, "UPDATE_INTERVAL_SECS: {}", self.update_interval_secs)?;
+        writeln!(f, "UPDATE_INTERVAL_SECS_V6: {}", self.update_interval_secs_v6)?;
+        writeln!(f, "UPDATE_INTERVAL_JITTER_PERCENT: {}", self.update_interval_jitter_percent)?;
+        writeln!(f, "STARTUP_SPLAY_SECS: {}", self.startup_splay_secs)?;
+        writeln!(f, "STARTUP_DELAY_SECS: {}", self.startup_delay_secs)?;
+        writeln!(f, "STARTUP_GRACE_SECS: {}", self.startup_grace_secs)?;
+        writeln!(f, "NETWORK_WATCH: {}", self.network_watch)?;
+        writeln!(f, "CONFIG_WATCH: {}", self.config_watch)?;
+        writeln!(f, "CONTROL_SOCKET: {}", self.control_socket)?;
+        writeln!(f, "DAEMONIZE: {}", self.daemonize)?;
+        writeln!(f, "PID_FILE: {}", self.pid_file)?;
+        writeln!(f, "REREAD_EVERY_N_CYCLES: {}", self.reread_every_n_cycles)?;
+        writeln!(f, "UPDATE_CONCURRENCY: {}", self.update_concurrency)?;
+        writeln!(f, "MIN_UPDATE_COOLDOWN_SECS: {}", self.min_update_cooldown_secs)?;
+        writeln!(f, "FORCE_OVERWRITE: {}", self.force_overwrite)?;
+        writeln!(f, "CYCLE_DEADLINE_SECS: {}", self.cycle_deadline_secs)?;
+        writeln!(f, "CONNECTIVITY_CHECK: {}", self.connectivity_check)?;
+        writeln!(f, "CONNECTIVITY_TARGET: {}", self.connectivity_target)?;
+        writeln!(f, "QUIET_HOURS: {}", self.quiet_hours.join(","))?;
+        writeln!(f, "QUIET_HOURS_TZ: {}", self.quiet_hours_tz)?;
+        writeln!(f, "QUIET_HOURS_MODE: {}", self.quiet_hours_mode)?;
+        writeln!(f, "RECORD_CHECK: {}", self.record_check)?;
+        writeln!(f, "REVALIDATE_EVERY_N_CYCLES: {}", self.revalidate_every_n_cycles)?;
+        writeln!(f, "RUN_ONCE: {}", self.run_once)?;
+        writeln!(f, "EXIT_ON_ERROR: {}", self.exit_on_error)?;
+        writeln!(f, "STATE_FILE: {}", self.state_file)?;
+        writeln!(f, "HISTORY_FILE: {}", self.history_file)?;
+        writeln!(f, "STATUS_FILE: {}", self.status_file)?;
+        writeln!(f, "SYNC_FILE: {}", self.sync_file)?;
+        writeln!(f, "SYNC_PRUNE: {}", self.sync_prune)?;
+        writeln!(f, "RETRY_MAX_FAILURES: {}", self.retry.max_consecutive_failures)?;
+        writeln!(f, "RETRY_BACKOFF_BASE_SECS: {}", self.retry.backoff_base_secs)?;
+        writeln!(f, "RETRY_BACKOFF_MAX_SECS: {}", self.retry.backoff_max_secs)?;
+        writeln!(f, "HTTP_TIMEOUT_SECS: {}", self.http.timeout_secs)?;
+        writeln!(f, "HTTP_CONNECT_TIMEOUT_SECS: {}", self.http.connect_timeout_secs)?;
+        writeln!(f, "HTTP_USER_AGENT: {}", self.http.user_agent)?;
+        writeln!(f, "OUTBOUND_PROXY: {}", self.http.proxy)?;
+        writeln!(f, "HTTP_CA_FILE: {}", self.http.ca_file)?;
+        writeln!(f, "HTTP_BUILTIN_ROOTS: {}", self.http.builtin_roots)?;
+        writeln!(f, "BIND_INTERFACE: {}", self.http.bind_interface)?;
+        writeln!(f, "BIND_ADDRESS: {}", display_option(&self.http.bind_address))?;
+        writeln!(f, "SERVER_BIND: {}", self.server.bind)?;
+        writeln!(f, "HEALTH_MAX_INTERVALS: {}", self.server.health_max_intervals)?;
+        writeln!(f, "SERVER_API_TOKEN: {}", self.server.api_token)?;
+        writeln!(f, "SERVER_WEBHOOK_TOKEN: {}", self.server.webhook_token)?;
+        writeln!(f, "IP_SOURCES: {}", display_list(&self.ip.sources))?;
+        if self.ip.services.is_empty() {
+            writeln!(f, "IP_SERVICES: <built-in>")?;
+        } else {
+            writeln!(f, "IP_SERVICES: {}", display_list(&self.ip.services))?;
+        }
+        writeln!(f, "IP_INTERFACE: {}", self.ip.interface)?;
+        writeln!(f, "IP_UPNP_URL: {}", self.ip.upnp_url)?;
+        writeln!(f, "IP_UPNP_SERVICE: {}", self.ip.upnp_service)?;
+        writeln!(f, "IP_STABLE_CHECKS: {}", self.ip.stable_checks)?;
+        writeln!(f, "IP_ALLOW_PRIVATE: {}", self.ip.allow_private)?;
+        writeln!(f, "IP_STRATEGY: {}", self.ip.strategy)?;
+        writeln!(f, "IP_QUORUM: {}", self.ip.quorum)?;
+        writeln!(f, "IP_SOURCE_TIMEOUT_SECS: {}", self.ip.timeout_secs)?;
+        writeln!(f, "IP_BREAKER_THRESHOLD: {}", self.ip.breaker_threshold)?;
+        writeln!(f, "IP_BREAKER_COOLDOWN_SECS: {}", self.ip.breaker_cooldown_secs)?;
+        // Webhook-URLs enthalten oft Tokens, daher nur die Anzahl ausgeben
+        writeln!(f, "NOTIFY_WEBHOOK_URLS: <{} configured>", self.notify.webhook_urls.len())?;
+        writeln!(f, "NOTIFY_DISCORD_URLS: <{} configured>", self.notify.discord_urls.len())?;
+        writeln!(f, "NOTIFY_SLACK_URLS: <{} configured>", self.notify.slack_urls.len())?;
+        writeln!(f, "NOTIFY_GOTIFY_URL: {}", self.notify.gotify_url)?;
+        writeln!(f, "NOTIFY_GOTIFY_TOKEN: {}", self.notify.gotify_token)?;
+        writeln!(f, "NOTIFY_PUSHOVER_TOKEN: {}", self.notify.pushover_token)?;
+        writeln!(f, "NOTIFY_PUSHOVER_USER: {}", self.notify.pushover_user)?;
+        writeln!(f, "HEARTBEAT_URL: {}", if self.notify.heartbeat_url.is_empty() { "" } else { "<configured>" })?;
+        writeln!(f, "NOTIFY_SMTP_HOST: {}", self.notify.smtp_host)?;
+        if !self.notify.smtp_host.is_empty() {
+            writeln!(f, "NOTIFY_SMTP_PORT: {}", self.notify.smtp_port.unwrap_or(self.notify.smtp_tls.default_port()))?;
+            writeln!(f, "NOTIFY_SMTP_TLS: {}", self.notify.smtp_tls)?;
+            writeln!(f, "NOTIFY_SMTP_USERNAME: {}", self.notify.smtp_username)?;
+            writeln!(f, "NOTIFY_SMTP_PASSWORD: {}", self.notify.smtp_password)?;
+            writeln!(f, "NOTIFY_SMTP_FROM: {}", self.notify.smtp_from)?;
+            writeln!(f, "NOTIFY_SMTP_TO: {}", display_list(&self.notify.smtp_to))?;
+        }
+        writeln!(f, "NOTIFY_FAILURE_THRESHOLD: {}", self.notify.failure_threshold)?;
+        writeln!(f, "PRE_UPDATE_CMD: {}", self.hooks.pre_update_cmd)?;
+        writeln!(f, "PRE_UPDATE_URL: {}", if self.hooks.pre_update_url.is_empty() { "" } else { "<configured>" })?;
+        writeln!(f, "ON_CHANGE_CMD: {}", self.hooks.on_change_cmd)?;
+        writeln!(f, "ON_FAILURE_CMD: {}", self.hooks.on_failure_cmd)?;
+        writeln!(f, "HOOK_TIMEOUT_SECS: {}", self.hooks.timeout_secs)?;
+        writeln!(f, "PROPAGATION_CHECK: {}", self.propagation.enabled)?;
+        if self.propagation.enabled {
+            writeln!(f, "PROPAGATION_RESOLVERS: {}", display_list(&self.propagation.resolvers))?;
+            writeln!(f, "PROPAGATION_TIMEOUT_SECS: {}", self.propagation.timeout_secs)?;
+        }
+        writeln!(f, "LOG_TARGET: {}", self.log.target)?;
+        if self.log.target == LogTarget::Syslog {
+            writeln!(f, "SYSLOG_ADDRESS: {}", self.log.syslog_address)?;
+        }
+        writeln!(f, "LOG_FILE: {}", self.log.file)?;
+        if !self.log.file.is_empty() {
+            writeln!(f, "LOG_FILE_MAX_SIZE_MB: {}", self.log.file_max_size_mb)?;
+            writeln!(f, "LOG_FILE_ROTATE_EVERY: {}", self.log.file_rotate_every)?;
+            writeln!(f, "LOG_FILE_KEEP: {}", self.log.file_keep)?;
+        }
+        Ok(())
+    }
+}
+
+/// A sensitive configuration value such as an API token.
+///
+/// `Debug` and `Display` only show the first and last 4 characters (or nothing for short values),
+/// so secrets don't end up in logs. Use [`Secret::expose`] to access the actual value.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wraps a sensitive value.
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// Returns the unmasked value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if no value is set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the masked representation used for `Debug` and `Display`.
+    fn masked(&self) -> String {
+        let chars: Vec<char> = self.0.chars().collect();
+        match chars.len() {
+            0 => String::new(),
+            n if n < 12 => "****".to_string(),
+            n => format!("{}****{}", chars[..4].iter().collect::<String>(), chars[n - 4..].iter().collect::<String>()),
+        }
+    }
+}
+
+impl FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret::new(s))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({:?})", self.masked())
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.masked())
+    }
+}
+
+/// A layer of values overriding the config file, named like the environment variables.
+///
+/// Records the names of the values it provided, so `--set` arguments naming no setting can be rejected and
+/// [`ConfigSource::resolve`] can tell which layer a value comes from, and collects all invalid values.
+struct Layer<'a> {
+    /// The `--set` arguments, or `None` for the environment.
+    cli: Option<&'a [(String, String)]>,
+    found: RefCell<BTreeSet<String>>,
+    errors: RefCell<Vec<String>>,
+}
+
+impl<'a> Layer<'a> {
+    /// The environment variables. Empty variables are treated as unset, so defaults like `CF_API_TOKEN=` in the
+    /// Docker image don't clobber values from a config file.
+    fn env() -> Self {
+        Layer { cli: None, found: RefCell::default(), errors: RefCell::default() }
+    }
+
+    /// `KEY=VALUE` arguments from the command line. The last one of a key wins, an empty value is kept.
+    fn cli(values: &'a [(String, String)]) -> Self {
+        Layer { cli: Some(values), found: RefCell::default(), errors: RefCell::default() }
+    }
+
+    /// Returns the value of `name` in this layer, if set.
+    fn value(&self, name: &str) -> Option<String> {
+        let value = match self.cli {
+            Some(values) => values.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.clone()),
+            None => env::var(name).ok().filter(|v| !v.is_empty()),
+        };
+        if value.is_some() {
+            self.found.borrow_mut().insert(name.to_string());
+        }
+        value
+    }
+
+    /// Returns `true` if this layer set `name` or its `_FILE` variant.
+    fn sets(&self, name: &str) -> bool {
+        let found = self.found.borrow();
+        found.contains(name) || found.contains(&format!("{}_FILE", name))
+    }
+
+    /// Returns how `name` is referred to in error messages.
+    fn label(&self, name: &str) -> String {
+        match self.cli {
+            Some(_) => format!("--set {}", name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Records an invalid value.
+    fn error(&self, message: String) {
+        self.errors.borrow_mut().push(message);
+    }
+
+    /// Returns the invalid values found so far, and `--set` arguments naming no setting.
+    fn problems(&self) -> Vec<String> {
+        let found = self.found.borrow();
+        let unknown = self
+            .cli
+            .unwrap_or_default()
+            .iter()
+            .filter(|(key, _)| !found.contains(key))
+            .map(|(key, _)| format!("--set {}: unknown setting", key));
+        self.errors.borrow().iter().cloned().chain(unknown).collect()
+    }
+
+    /// Parses the value `name` into `target` if it is set.
+    fn apply<T: FromStr>(&self, name: &str, target: &mut T) {
+        if let Some(raw) = self.value(name) {
+            match raw.parse() {
+                Ok(value) => *target = value,
+                Err(_) => self.error(format!("{} has an invalid value: {}", self.label(name), raw)),
+            }
+        }
+    }
+
+    /// Parses the value `name` into `target` as `Some(value)` if it is set.
+    fn apply_option<T: FromStr>(&self, name: &str, target: &mut Option<T>) {
+        if let Some(raw) = self.value(name) {
+            match raw.parse() {
+                Ok(value) => *target = Some(value),
+                Err(_) => self.error(format!("{} has an invalid value: {}", self.label(name), raw)),
+            }
+        }
+    }
+
+    /// Parses the comma-separated value `name` into `target` if it is set.
+    fn apply_list<T: FromStr>(&self, name: &str, target: &mut Vec<T>) {
+        if let Some(raw) = self.value(name) {
+            self.parse_list(name, &raw, target);
+        }
+    }
+
+    /// Parses the comma-separated value `name` into `target` like [`Layer::apply_list`], reading it from the file
+    /// named by `<name>_FILE` like [`Layer::apply_secret`], for lists of URLs carrying a token.
+    fn apply_list_secret<T: FromStr>(&self, name: &str, target: &mut Vec<T>) {
+        if let Some(raw) = self.secret_value(name) {
+            self.parse_list(name, &raw, target);
+        }
+    }
+
+    /// Parses the comma-separated `raw` value of `name` into `target`, recording invalid entries.
+    fn parse_list<T: FromStr>(&self, name: &str, raw: &str, target: &mut Vec<T>) {
+        let parsed = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse().map_err(|_| format!("{} has an invalid entry: {}", self.label(name), v)))
+            .collect::<Result<_, _>>();
+        match parsed {
+            Ok(values) => *target = values,
+            Err(e) => self.error(e),
+        }
+    }
+
+    /// Sets the secret `target` from the value `name`, or from the file named by `<name>_FILE`,
+    /// e.g. a Docker or Kubernetes secret mounted at `/run/secrets/cf_api_token`. Whitespace around the file content
+    /// is trimmed. Setting both or naming an unreadable file is an error.
+    fn apply_secret(&self, name: &str, target: &mut Secret) {
+        if let Some(value) = self.secret_value(name) {
+            *target = Secret::new(value);
+        }
+    }
+
+    /// Returns the value `name`, or the trimmed content of the file named by `<name>_FILE`, if one of them is set.
+    /// Setting both or naming an unreadable file is recorded as error.
+    fn secret_value(&self, name: &str) -> Option<String> {
+        let file_var = format!("{}_FILE", name);
+        match (self.value(name), self.value(&file_var)) {
+            (Some(_), Some(_)) => {
+                self.error(format!("Set either {} or {}, not both", self.label(name), self.label(&file_var)));
+                None
+            }
+            (Some(value), None) => Some(value),
+            (None, Some(path)) => match fs::read_to_string(&path) {
+                Ok(value) => Some(value.trim().to_string()),
+                Err(e) => {
+                    self.error(format!("Cannot read {} {}: {}", self.label(&file_var), path, e));
+                    None
+                }
+            },
+            (None, None) => None,
+        }
+    }
+}
+
+/// Returns the part of the record `name` in front of `domain`, e.g. `home` for `home.example.com`
+/// in `example.com` and an empty string for the domain itself, or `None` if `name` is not within `domain`.
+pub(crate) fn subdomain<'a>(name: &'a str, domain: &str) -> Option<&'a str> {
+    let name = name.trim_end_matches('.');
+    let domain = domain.trim_end_matches('.');
+    let (sub, rest) = name.split_at_checked(name.len().checked_sub(domain.len())?)?;
+    if domain.is_empty() || !rest.eq_ignore_ascii_case(domain) {
+        return None;
+    }
+    match sub {
+        "" => Some(""),
+        _ => sub.strip_suffix('.').filter(|s| !s.is_empty()),
+    }
+}
+
+/// Returns `true` if `url` is a valid `http://` or `https://` URL.
+fn is_http_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.has_host())
+}
+
+/// Checks an entry of `IP_SERVICES` or `ip_services`, a URL with an optional response format (see [`ip::parse_service`]).
+fn check_service(entry: &str) -> Result<(), String> {
+    let (url, _) = ip::parse_service(entry).map_err(|e| format!("contains an invalid entry, {}", e))?;
+    if is_http_url(url) { Ok(()) } else { Err(format!("contains an invalid URL: {}", url)) }
+}
+
+/// Reads `content` of the TOML or YAML file at `path`, depending on its extension.
+fn parse<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(content).map_err(|e| format!("Invalid TOML in {}: {}", path.display(), e)),
+        Some("yaml") | Some("yml") => serde_norway::from_str(content).map_err(|e| format!("Invalid YAML in {}: {}", path.display(), e)),
+        _ => Err(format!("Unsupported config file format: {} (expected .toml, .yaml or .yml)", path.display())),
+    }
+}
+
+/// Merges `overrides` into `base`: tables are merged key by key, any other value replaces the one in `base`.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Formats a list for [`Config`]'s `Display` output.
+fn display_list<T: fmt::Display>(values: &[T]) -> String {
+    values.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Formats an optional value for [`Config`]'s `Display` output.
+fn display_option<T: fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "<unchanged>".to_string(),
     }
 }
@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use log::{debug, info, warn};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use crate::control::DaemonControl;
+
+/// Time to wait for further writes before the reload is requested.
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Starts watching the config file at `path`, reloading every job of `control` whenever its content may have changed.
+///
+/// The directory of the file is watched instead of the file itself, so editors and tools replacing the file
+/// (write to a temporary file, then rename) are noticed as well. A burst of writes results in a single notification.
+///
+/// # Errors
+/// Returns an error if the file watcher of the platform cannot be set up.
+pub fn spawn(path: &str, control: Arc<DaemonControl>) -> Result<(), String> {
+    let path = Path::new(path);
+    let file = path.canonicalize().map_err(|e| format!("Cannot watch config file {}: {}", path.display(), e))?;
+    let directory = file.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("/"));
+    let (sender, mut changes) = mpsc::unbounded_channel();
+    let watched = file.clone();
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                if event.paths.iter().any(|p| p.file_name() == watched.file_name()) {
+                    let _ = sender.send(());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Config file watcher error: {}", e),
+        },
+        notify::Config::default(),
+    )
+    .map_err(|e| format!("Cannot watch config file {}: {}", file.display(), e))?;
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Cannot watch config file {}: {}", file.display(), e))?;
+
+    tokio::spawn(async move {
+        // Der Watcher muss so lange leben wie der Task, sonst kommen keine Events mehr
+        let _watcher = watcher;
+        while changes.recv().await.is_some() {
+            // Editoren schreiben oft in mehreren Schritten, erst nach einer Pause neu laden
+            while let Ok(Some(())) = tokio::time::timeout(SETTLE_TIME, changes.recv()).await {}
+            info!("Config file {} changed", file.display());
+            control.reload();
+        }
+        warn!("Stopped watching config file {}", file.display());
+    });
+    Ok(())
+}
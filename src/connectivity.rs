@@ -0,0 +1,58 @@
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::net::{TcpStream, lookup_host};
+use tokio::process::Command;
+use crate::config::ConnectivityCheck;
+
+/// Address connected to by the `tcp` check if `CONNECTIVITY_TARGET` is empty.
+const DEFAULT_TCP_TARGET: &str = "1.1.1.1:443";
+/// Name resolved by the `dns` check if `CONNECTIVITY_TARGET` is empty.
+const DEFAULT_DNS_TARGET: &str = "cloudflare.com";
+/// Host pinged by the `icmp` check if `CONNECTIVITY_TARGET` is empty.
+const DEFAULT_ICMP_TARGET: &str = "1.1.1.1";
+
+/// Checks whether the network is reachable, by connecting to `target` over TCP, resolving it or pinging it,
+/// depending on `check`. An empty `target` probes a well-known host of Cloudflare. Gives up after `timeout`.
+///
+/// # Errors
+/// Returns why the network seems to be down.
+pub async fn probe(check: ConnectivityCheck, target: &str, timeout: Duration) -> Result<(), String> {
+    let probe = async {
+        match check {
+            ConnectivityCheck::None => Ok(()),
+            ConnectivityCheck::Tcp => {
+                let target = if target.is_empty() { DEFAULT_TCP_TARGET } else { target };
+                TcpStream::connect(target).await.map(|_| ()).map_err(|e| format!("cannot connect to {}: {}", target, e))
+            }
+            ConnectivityCheck::Dns => {
+                let target = if target.is_empty() { DEFAULT_DNS_TARGET } else { target };
+                match lookup_host((target, 0)).await.map(|mut addresses| addresses.next()) {
+                    Ok(Some(_)) => Ok(()),
+                    Ok(None) => Err(format!("{} resolves to no address", target)),
+                    Err(e) => Err(format!("cannot resolve {}: {}", target, e)),
+                }
+            }
+            ConnectivityCheck::Icmp => ping(if target.is_empty() { DEFAULT_ICMP_TARGET } else { target }, timeout).await,
+        }
+    };
+    tokio::time::timeout(timeout, probe).await.unwrap_or_else(|_| Err(format!("no answer within {}s", timeout.as_secs())))
+}
+
+/// Sends a single ping to `target` with the system's `ping` command, which unlike a raw socket needs no privileges.
+async fn ping(target: &str, timeout: Duration) -> Result<(), String> {
+    let mut cmd = Command::new("ping");
+    if cfg!(windows) {
+        cmd.args(["-n", "1", "-w", &timeout.as_millis().to_string(), target]);
+    } else {
+        cmd.args(["-c", "1", "-W", &timeout.as_secs().max(1).to_string(), target]);
+    }
+    let status = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(|e| format!("cannot run ping: {}", e))?;
+    if status.success() { Ok(()) } else { Err(format!("{} doesn't answer pings", target)) }
+}
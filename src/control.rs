@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use crate::metrics::unix_now;
+
+pub mod socket;
+
+/// State of the running daemon, shared between the schedulers of its jobs and the control interfaces.
+///
+/// Each scheduler publishes a [`Status`] after every cycle and checks [`DaemonControl::is_paused`] before starting one.
+/// The HTTP control API and the [control socket](socket) read the status of all jobs combined and pause, resume,
+/// trigger or reload all of them.
+pub struct DaemonControl {
+    paused: AtomicBool,
+    /// Names of the jobs, a single empty one if the config has no jobs.
+    jobs: Vec<String>,
+    /// Per job, wakes its scheduler up for an immediate cycle.
+    triggers: Vec<Arc<Notify>>,
+    /// Per job, makes its scheduler reload the configuration before its next cycle.
+    reloads: Vec<Arc<Notify>>,
+    /// Per job the public IP reported by the last webhook, used instead of detecting it in its next cycle.
+    reported_ip: Mutex<Vec<Option<String>>>,
+    /// Per job the status and the records published after its last cycle.
+    status: Mutex<Vec<Status>>,
+    records: Mutex<Vec<Vec<RecordStatus>>>,
+    /// The last [`CYCLE_HISTORY`] update cycles, oldest first.
+    cycles: Mutex<VecDeque<CycleStatus>>,
+}
+
+/// Number of update cycles kept for `/cycles`.
+const CYCLE_HISTORY: usize = 100;
+
+/// Snapshot of the daemon, returned by `/status`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub paused: bool,
+    /// Whether the last connectivity check of a job failed, so cycles are skipped until the network is back.
+    pub offline: bool,
+    /// Most recently detected public IP address, of the job that succeeded last.
+    pub public_ip: Option<String>,
+    /// Unix time of the last successful update cycle of any job, `0` if there was none yet.
+    pub last_success: u64,
+    /// Number of failed update cycles in a row, of the job with the most.
+    pub consecutive_failures: u64,
+    /// Unix time the next update cycle of any job is scheduled for, if one is scheduled.
+    pub next_cycle: Option<u64>,
+}
+
+/// Outcome of a recent update cycle, returned by `/cycles`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleStatus {
+    /// Unix time the cycle finished.
+    pub time: u64,
+    /// Why the cycle failed, `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+/// A managed record, returned by `/records`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordStatus {
+    pub provider: String,
+    pub name: String,
+    pub id: String,
+    /// IP address the record was last confirmed to point to, unknown until the record was read or written.
+    pub ip: Option<String>,
+}
+
+impl DaemonControl {
+    /// Creates the shared state of a daemon running a scheduler for each of its `jobs`, given by name.
+    pub fn new(jobs: Vec<String>) -> Self {
+        let count = jobs.len();
+        DaemonControl {
+            paused: AtomicBool::new(false),
+            jobs,
+            triggers: (0..count).map(|_| Arc::new(Notify::new())).collect(),
+            reloads: (0..count).map(|_| Arc::new(Notify::new())).collect(),
+            reported_ip: Mutex::new(vec![None; count]),
+            status: Mutex::new(vec![Status::default(); count]),
+            records: Mutex::new(vec![Vec::new(); count]),
+            cycles: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns what the scheduler of `job` waits for: the notification for an immediate cycle and the one for a reload.
+    pub fn wakeups(&self, job: usize) -> (Arc<Notify>, Arc<Notify>) {
+        (self.triggers[job].clone(), self.reloads[job].clone())
+    }
+
+    /// Returns the number of jobs.
+    pub fn jobs(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns the index of the job named `name`, if there is one.
+    pub fn job(&self, name: &str) -> Option<usize> {
+        self.jobs.iter().position(|job| !job.is_empty() && job == name)
+    }
+
+    /// Returns `true` if update cycles are paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses update cycles until [`DaemonControl::resume`] is called.
+    ///
+    /// # Returns
+    /// `false` if the daemon was already paused.
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::Relaxed)
+    }
+
+    /// Resumes paused update cycles and starts one right away.
+    ///
+    /// # Returns
+    /// `false` if the daemon was not paused.
+    pub fn resume(&self) -> bool {
+        let resumed = self.paused.swap(false, Ordering::Relaxed);
+        if resumed {
+            self.trigger();
+        }
+        resumed
+    }
+
+    /// Starts an update cycle of every job right away.
+    pub fn trigger(&self) {
+        for trigger in &self.triggers {
+            trigger.notify_one();
+        }
+    }
+
+    /// Starts an update cycle of `job` right away with `ip` as public IP, or with the detected IP if `None`.
+    pub fn report_ip(&self, job: usize, ip: Option<String>) {
+        self.reported_ip.lock().unwrap()[job] = ip;
+        self.triggers[job].notify_one();
+    }
+
+    /// Takes the IP reported by a webhook since the last call for `job`, if any.
+    pub fn take_reported_ip(&self, job: usize) -> Option<String> {
+        self.reported_ip.lock().unwrap()[job].take()
+    }
+
+    /// Reloads the configuration of every job and starts an update cycle with it, like SIGHUP.
+    pub fn reload(&self) {
+        for reload in &self.reloads {
+            reload.notify_one();
+        }
+    }
+
+    /// Replaces the published status of `job` with its state after a cycle, or after one was skipped because the
+    /// network is down. `paused` of `status` is ignored, pausing applies to all jobs.
+    pub fn publish(&self, job: usize, status: Status, records: Vec<RecordStatus>) {
+        self.status.lock().unwrap()[job] = status;
+        self.records.lock().unwrap()[job] = records;
+    }
+
+    /// Remembers the outcome of an update cycle that just finished, `error` if it failed.
+    pub fn cycle_finished(&self, error: Option<String>) {
+        let mut cycles = self.cycles.lock().unwrap();
+        if cycles.len() == CYCLE_HISTORY {
+            cycles.pop_front();
+        }
+        cycles.push_back(CycleStatus { time: unix_now(), error });
+    }
+
+    /// Returns the outcomes of the recent update cycles, oldest first.
+    pub fn cycles(&self) -> Vec<CycleStatus> {
+        self.cycles.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Returns the name and the published status of every job, the name is empty if the config has no jobs.
+    pub fn job_status(&self) -> Vec<(String, Status)> {
+        self.jobs.iter().cloned().zip(self.status.lock().unwrap().iter().cloned()).collect()
+    }
+
+    /// Returns the current status of the daemon, combined from the status of every job.
+    pub fn status(&self) -> Status {
+        let jobs = self.status.lock().unwrap();
+        Status {
+            paused: self.is_paused(),
+            offline: jobs.iter().any(|job| job.offline),
+            public_ip: jobs
+                .iter()
+                .filter(|job| job.public_ip.is_some())
+                .max_by_key(|job| job.last_success)
+                .and_then(|job| job.public_ip.clone()),
+            last_success: jobs.iter().map(|job| job.last_success).max().unwrap_or_default(),
+            consecutive_failures: jobs.iter().map(|job| job.consecutive_failures).max().unwrap_or_default(),
+            next_cycle: jobs.iter().filter_map(|job| job.next_cycle).min(),
+        }
+    }
+
+    /// Returns the managed records of every job as of its last cycle.
+    pub fn records(&self) -> Vec<RecordStatus> {
+        self.records.lock().unwrap().concat()
+    }
+
+    /// Writes the status and the records to `path` as JSON, in the format of [`socket::StatusReply`]. The file is
+    /// replaced atomically, so readers never see a partly written status.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write_status_file(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let reply = socket::StatusReply { status: self.status(), records: self.records() };
+        let json = serde_json::to_string_pretty(&reply).map_err(|e| format!("Failed to serialize status: {}", e))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to write status file {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use log::info;
+use serde::{Deserialize, Serialize};
+use super::{DaemonControl, RecordStatus, Status};
+
+/// Time the daemon may take to answer a command.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A command sent to the daemon over the control socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Return the [`StatusReply`].
+    Status,
+    /// Start an update cycle right away.
+    Trigger,
+    /// Reload the configuration, like SIGHUP.
+    Reload,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(Command::Status),
+            "trigger" => Ok(Command::Trigger),
+            "reload" => Ok(Command::Reload),
+            other => Err(format!("unknown command '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Command::Status => "status",
+            Command::Trigger => "trigger",
+            Command::Reload => "reload",
+        })
+    }
+}
+
+/// Answer of the daemon to [`Command::Status`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusReply {
+    pub status: Status,
+    pub records: Vec<RecordStatus>,
+}
+
+/// Starts accepting commands on a Unix domain socket at `path` in the background.
+///
+/// A socket file left behind by a daemon that is no longer running is replaced. Only the user running the daemon
+/// can connect. Every connection sends one command per line and receives one line per command: JSON for `status`,
+/// `ok: <message>` or `error: <message>` otherwise.
+///
+/// # Errors
+/// Returns an error if the socket cannot be created, or on platforms without Unix domain sockets.
+pub fn spawn(path: &str, control: Arc<DaemonControl>) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        let listener = unix::bind(path).map_err(|e| format!("Cannot create control socket {}: {}", path, e))?;
+        info!("Control socket listening on {}", path);
+        tokio::spawn(unix::serve(listener, control));
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (path, control);
+        Err("Control sockets are only supported on Unix".to_string())
+    }
+}
+
+/// Sends `command` to the daemon listening on the socket at `path`.
+///
+/// # Returns
+/// - `Ok(answer)` with the JSON status or the confirmation message of the daemon.
+/// - `Err` if the daemon cannot be reached or refuses the command.
+pub async fn request(path: &str, command: Command) -> Result<String, String> {
+    #[cfg(unix)]
+    {
+        let answer = tokio::time::timeout(REQUEST_TIMEOUT, unix::request(path, command))
+            .await
+            .map_err(|_| format!("The daemon at {} did not answer within {} seconds", path, REQUEST_TIMEOUT.as_secs()))?
+            .map_err(|e| format!("Cannot reach the daemon at {}: {}", path, e))?;
+        match answer.split_once(": ") {
+            Some(("ok", message)) => Ok(message.to_string()),
+            Some(("error", message)) => Err(format!("The daemon refused '{}': {}", command, message)),
+            _ => Ok(answer),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (path, command);
+        Err("Control sockets are only supported on Unix".to_string())
+    }
+}
+
+/// Executes `line` and returns the answer line without the trailing newline.
+fn answer(line: &str, control: &DaemonControl) -> String {
+    let command = match line.trim().parse() {
+        Ok(command) => command,
+        Err(e) => return format!("error: {}", e),
+    };
+    match command {
+        Command::Status => {
+            let reply = StatusReply { status: control.status(), records: control.records() };
+            serde_json::to_string(&reply).unwrap_or_else(|e| format!("error: {}", e))
+        }
+        Command::Trigger if control.is_paused() => "error: update cycles are paused".to_string(),
+        Command::Trigger => {
+            info!("Update cycle requested via the control socket");
+            control.trigger();
+            "ok: update cycle triggered".to_string()
+        }
+        Command::Reload => {
+            info!("Reload requested via the control socket");
+            control.reload();
+            "ok: reloading the configuration".to_string()
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::sync::Arc;
+    use log::{debug, warn};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use super::{Command, DaemonControl, answer};
+
+    /// Longest command line accepted, so a client can't make the daemon buffer unbounded input.
+    const MAX_LINE: u64 = 256;
+
+    pub(super) fn bind(path: &str) -> io::Result<UnixListener> {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, "file exists and is not a socket"));
+            }
+            // Ein erreichbarer Socket gehört zu einem laufenden Daemon, ein toter bleibt nach einem Absturz zurück
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another daemon is listening on it"));
+            }
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    pub(super) async fn serve(listener: UnixListener, control: Arc<DaemonControl>) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let control = control.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, &control).await {
+                            debug!("Control socket connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    warn!("Stopped accepting control socket connections: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn handle(stream: UnixStream, control: &DaemonControl) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = String::new();
+            if (&mut reader).take(MAX_LINE).read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let mut reply = answer(&line, control);
+            reply.push('\n');
+            writer.write_all(reply.as_bytes()).await?;
+        }
+    }
+
+    pub(super) async fn request(path: &str, command: Command) -> io::Result<String> {
+        let mut stream = UnixStream::connect(path).await?;
+        stream.write_all(format!("{}\n", command).as_bytes()).await?;
+        stream.shutdown().await?;
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).await?;
+        Ok(answer.trim_end().to_string())
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use crondes::Config;
+use crondes::config::LogTarget;
+use log::{info, warn};
+
+/// The locked `PID_FILE` of the running daemon, removed again when dropped.
+pub struct PidFile {
+    file: File,
+    path: String,
+}
+
+/// Prepares the process to run as daemon: takes the `PID_FILE` lock, detaches if `DAEMONIZE` is set and writes the
+/// process ID of the daemon.
+///
+/// Must be called before the async runtime starts its threads, since only the calling thread survives the fork.
+///
+/// # Returns
+/// The locked PID file, to be kept until the daemon exits, or `None` if `PID_FILE` is not set.
+///
+/// # Errors
+/// Returns an error if another instance holds the lock, the PID file cannot be written or the process cannot detach.
+pub fn prepare(cfg: &Config) -> Result<Option<PidFile>, String> {
+    // Die Sperre vor dem Fork nehmen, damit ein zweiter Start noch im Vordergrund mit Fehler endet
+    let pid_file = if cfg.pid_file.is_empty() { None } else { Some(PidFile::lock(&cfg.pid_file)?) };
+    if cfg.daemonize {
+        if cfg.log.target == LogTarget::Stderr && cfg.log.file.is_empty() {
+            warn!("The daemon detaches from stderr, set LOG_FILE or LOG_TARGET to keep its log");
+        }
+        detach()?;
+    }
+    if let Some(pid_file) = &pid_file {
+        pid_file.write_pid()?;
+    }
+    Ok(pid_file)
+}
+
+impl PidFile {
+    /// Opens the PID file at `path` and locks it exclusively.
+    ///
+    /// A file left behind by a daemon that is no longer running is not locked and simply taken over.
+    ///
+    /// # Errors
+    /// Returns an error naming the running instance if the file is locked, or if it cannot be opened.
+    pub fn lock(path: &str) -> Result<PidFile, String> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Cannot open PID_FILE {}: {}", path, e))?;
+        match file.try_lock() {
+            Ok(()) => Ok(PidFile { file, path: path.to_string() }),
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                let pid = pid.trim();
+                Err(format!(
+                    "Another crondes instance is already running (PID {}), PID_FILE {} is locked",
+                    if pid.is_empty() { "unknown" } else { pid },
+                    path
+                ))
+            }
+            Err(TryLockError::Error(e)) => Err(format!("Cannot lock PID_FILE {}: {}", path, e)),
+        }
+    }
+
+    /// Replaces the content of the file with the ID of the current process.
+    fn write_pid(&self) -> Result<(), String> {
+        let mut file = &self.file;
+        file.set_len(0)
+            .and_then(|()| file.rewind())
+            .and_then(|()| writeln!(file, "{}", std::process::id()))
+            .map_err(|e| format!("Cannot write PID_FILE {}: {}", self.path, e))?;
+        info!("Wrote PID {} to {}", std::process::id(), self.path);
+        Ok(())
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Detaches from the terminal: forks twice with a new session in between, so the daemon can never acquire a
+/// controlling terminal again, and points stdin, stdout and stderr to `/dev/null`.
+///
+/// The working directory is kept, so relative paths in the configuration still resolve as at startup.
+#[cfg(unix)]
+fn detach() -> Result<(), String> {
+    use std::io::Error;
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: Vor dem Start der Runtime gibt es nur diesen Thread, fork ist daher unbedenklich
+    unsafe {
+        match libc::fork() {
+            -1 => return Err(format!("Cannot fork the daemon: {}", Error::last_os_error())),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+        if libc::setsid() == -1 {
+            return Err(format!("Cannot start a new session: {}", Error::last_os_error()));
+        }
+        match libc::fork() {
+            -1 => return Err(format!("Cannot fork the daemon: {}", Error::last_os_error())),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+    }
+    let null = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/null")
+        .map_err(|e| format!("Cannot open /dev/null: {}", e))?;
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        // SAFETY: dup2 ersetzt nur den Standard-Deskriptor, null bleibt gültig bis zum Ende der Schleife
+        if unsafe { libc::dup2(null.as_raw_fd(), fd) } == -1 {
+            return Err(format!("Cannot redirect the standard streams: {}", Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn detach() -> Result<(), String> {
+    Err("DAEMONIZE is only supported on Unix, use the Windows service instead".to_string())
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors that can occur while running crondes.
+///
+/// The variants are split by cause so the scheduler can decide whether a failed
+/// update cycle is worth retrying or whether the daemon should exit right away.
+#[derive(Debug, Error)]
+pub enum CrondesError {
+    /// The configuration is missing a value or contains an invalid one.
+    #[error("Config error: {0}")]
+    Config(String),
+    /// The provider rejected the credentials.
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+    /// The provider API reported an error.
+    #[error("API error (HTTP {status}): {message}")]
+    Api {
+        /// HTTP status code of the response.
+        status: u16,
+        /// Provider specific error code, if the response contained one.
+        code: Option<i64>,
+        /// Error message reported by the provider.
+        message: String,
+    },
+    /// The provider API rejected the request because too many requests were sent.
+    #[error("Rate limited by {provider}{}", retry_after.map(|d| format!(", retry after {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited {
+        /// Name of the provider.
+        provider: &'static str,
+        /// How long the provider asked to wait (`Retry-After` header), if it said so.
+        retry_after: Option<Duration>,
+    },
+    /// A DNS server answered a dynamic update or query with an error, or could not be reached.
+    #[error("DNS error: {0}")]
+    Dns(String),
+    /// A request could not be sent or its response could not be read.
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// The public IP address could not be determined.
+    #[error("IP detection failed: {0}")]
+    IpDetection(String),
+    /// The detected IP address is not publicly routable and must not be written to DNS.
+    #[error("Refusing to publish {ip}: the address is {reason}")]
+    NonPublicIp {
+        /// The detected address.
+        ip: String,
+        /// Why the address is not public.
+        reason: &'static str,
+    },
+    /// A notification could not be delivered.
+    #[error("Notification failed: {0}")]
+    Notify(String),
+    /// A user-provided hook command failed.
+    #[error("Hook failed: {0}")]
+    Hook(String),
+    /// An update cycle did not finish within `CYCLE_DEADLINE_SECS` and was aborted.
+    #[error("Update cycle aborted after {}s (CYCLE_DEADLINE_SECS)", .0.as_secs())]
+    Deadline(Duration),
+    /// Some records of an update cycle failed, the reasons have been logged per record.
+    #[error("{failed} of {total} records failed")]
+    Records {
+        failed: usize,
+        total: usize,
+        /// Exit code of the most severe failure of a record, see [`CrondesError::exit_code`].
+        exit_code: u8,
+    },
+}
+
+impl CrondesError {
+    /// Returns `true` if retrying cannot fix the error, so the daemon should exit instead if `EXIT_ON_ERROR` is set.
+    ///
+    /// Config and authentication errors as well as client errors reported by the API (except
+    /// timeouts and rate limits) are fatal. Rate limits, network and DNS server problems, IP detection
+    /// failures, non-public addresses, server errors, aborted cycles and failed records are considered transient.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            CrondesError::Config(_) | CrondesError::Auth(_) => true,
+            CrondesError::Api { status, .. } => (400..500).contains(status) && *status != 408 && *status != 429,
+            CrondesError::RateLimited { .. }
+            | CrondesError::Network(_)
+            | CrondesError::Dns(_)
+            | CrondesError::IpDetection(_)
+            | CrondesError::NonPublicIp { .. }
+            | CrondesError::Notify(_)
+            | CrondesError::Hook(_)
+            | CrondesError::Deadline(_)
+            | CrondesError::Records { .. } => false,
+        }
+    }
+
+    /// Returns the exit code of a failed one-shot update (`update-once` or `RUN_ONCE`), by class of the failure.
+    ///
+    /// `0` and `1` are taken by successful runs without and with a changed record:
+    /// - `2`: the configuration is invalid
+    /// - `3`: the provider rejected the credentials (also HTTP 401 and 403)
+    /// - `4`: a request or DNS server failed, was rate limited or timed out
+    /// - `5`: no usable public IP address was detected
+    /// - `6`: the provider rejected a change, or a hook or notification failed
+    ///
+    /// Failed records exit with the code of their most severe failure, which is the lowest code.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CrondesError::Config(_) => 2,
+            CrondesError::Auth(_) | CrondesError::Api { status: 401 | 403, .. } => 3,
+            CrondesError::Network(_) | CrondesError::Dns(_) | CrondesError::RateLimited { .. } | CrondesError::Deadline(_) => 4,
+            CrondesError::IpDetection(_) | CrondesError::NonPublicIp { .. } => 5,
+            CrondesError::Api { .. } | CrondesError::Hook(_) | CrondesError::Notify(_) => 6,
+            CrondesError::Records { exit_code, .. } => *exit_code,
+        }
+    }
+
+    /// Combines the exit codes of two failures into the one of the more severe failure, where `0` stands for none.
+    pub fn severest(exit_code: u8, other: u8) -> u8 {
+        match (exit_code, other) {
+            (0, code) | (code, 0) => code,
+            (a, b) => a.min(b),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::metrics::now_rfc3339;
+
+/// A single IP change of a record, stored as one JSON line in `HISTORY_FILE`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Time of the change in RFC 3339 format (UTC).
+    pub time: String,
+    /// Name (or ID) of the changed record.
+    pub record: String,
+    /// The record content before the change.
+    pub old_ip: String,
+    /// The record content after the change.
+    pub new_ip: String,
+    /// Description of the change as reported by the provider.
+    pub response: String,
+}
+
+impl HistoryEntry {
+    /// Creates an entry for a change that happened just now.
+    pub fn now(record: &str, old_ip: &str, new_ip: &str, response: &str) -> Self {
+        HistoryEntry {
+            time: now_rfc3339(),
+            record: record.to_string(),
+            old_ip: old_ip.to_string(),
+            new_ip: new_ip.to_string(),
+            response: response.to_string(),
+        }
+    }
+}
+
+/// Appends `entry` to the history file at `path`, creating it if needed.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened or written.
+pub fn append(path: impl AsRef<Path>, entry: &HistoryEntry) -> Result<(), String> {
+    let path = path.as_ref();
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line))
+        .map_err(|e| format!("Failed to write history file {}: {}", path.display(), e))
+}
+
+/// Reads all entries from the history file at `path`, oldest first. A missing file yields no entries.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or contains an invalid line.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<HistoryEntry>, String> {
+    let path = path.as_ref();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read history file {}: {}", path.display(), e)),
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Invalid entry in {} line {}: {}", path.display(), n + 1, e))
+        })
+        .collect()
+}
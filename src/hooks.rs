@@ -0,0 +1,97 @@
+use std::process::Stdio;
+use std::time::Duration;
+use log::{info, warn};
+use tokio::process::Command;
+use crate::config::HooksConfig;
+use crate::error::CrondesError;
+
+/// Hook asked before a record is written, which can veto the update.
+///
+/// Both `PRE_UPDATE_CMD` and `PRE_UPDATE_URL` are checked if configured; the update only goes ahead if both succeed.
+pub struct PreUpdateHook {
+    command: String,
+    url: String,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+impl PreUpdateHook {
+    /// Creates the pre-update hook from the `hooks` section, or `None` if neither a command nor a URL is configured.
+    pub fn from_config(config: &HooksConfig, client: &reqwest::Client) -> Option<Self> {
+        if config.pre_update_cmd.is_empty() && config.pre_update_url.is_empty() {
+            return None;
+        }
+        Some(PreUpdateHook {
+            command: config.pre_update_cmd.clone(),
+            url: config.pre_update_url.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            client: client.clone(),
+        })
+    }
+
+    /// Asks the hook whether `record` may be changed from `old_ip` to `new_ip`.
+    ///
+    /// # Errors
+    /// Returns [`CrondesError::Hook`] if the command exits with a non-zero status, the URL returns a non-2xx
+    /// status, or either of them cannot be run.
+    pub async fn check(&self, record: &str, old_ip: &str, new_ip: &str) -> Result<(), CrondesError> {
+        if !self.command.is_empty() {
+            let env = [("OLD_IP", old_ip), ("NEW_IP", new_ip), ("RECORD_NAME", record), ("STATUS", "pending")];
+            run_command(&self.command, &env, self.timeout).await?;
+        }
+        if !self.url.is_empty() {
+            let resp = self
+                .client
+                .get(&self.url)
+                .timeout(self.timeout)
+                .send()
+                .await
+                .map_err(|e| CrondesError::Hook(format!("PRE_UPDATE_URL request failed: {}", e)))?;
+            if !resp.status().is_success() {
+                return Err(CrondesError::Hook(format!("PRE_UPDATE_URL returned {}", resp.status())));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `command` through the system shell (`sh -c`, or `cmd /C` on Windows) with the extra
+/// environment variables `env`, killing it if it takes longer than `timeout`.
+///
+/// The command's stdout and stderr are captured and logged line by line.
+///
+/// # Errors
+/// Returns an error if the command cannot be started, times out or exits with a non-zero status.
+pub async fn run_command(command: &str, env: &[(&str, &str)], timeout: Duration) -> Result<(), CrondesError> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.envs(env.iter().copied())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = cmd
+        .spawn()
+        .map_err(|e| CrondesError::Hook(format!("Failed to start `{}`: {}", command, e)))?;
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| CrondesError::Hook(format!("`{}` timed out after {}s", command, timeout.as_secs())))?
+        .map_err(|e| CrondesError::Hook(format!("Failed to run `{}`: {}", command, e)))?;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!("[hook] {}", line);
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        warn!("[hook] {}", line);
+    }
+    if !output.status.success() {
+        return Err(CrondesError::Hook(format!("`{}` exited with {}", command, output.status)));
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::time::Duration;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use crate::config::HttpConfig;
+
+/// Default `User-Agent` header sent with every request.
+pub const DEFAULT_USER_AGENT: &str = concat!("crondes/", env!("CARGO_PKG_VERSION"));
+
+/// Whether `BIND_INTERFACE` can be used on this platform.
+pub const BIND_INTERFACE_SUPPORTED: bool = cfg!(any(target_os = "linux", target_os = "android", target_os = "macos"));
+
+/// Destinations that never go through `OUTBOUND_PROXY`: the host itself and the local networks, where e.g. the
+/// UPnP control URL of the router lives.
+const LOCAL_NETWORKS: &str =
+    "localhost,127.0.0.0/8,::1,10.0.0.0/8,172.16.0.0/12,192.168.0.0/16,169.254.0.0/16,fc00::/7,fe80::/10";
+
+/// Builds the HTTP client shared by the DNS provider and the public IP detection.
+///
+/// The client keeps connections alive between update cycles, so repeated requests to the
+/// same host don't pay for a new DNS lookup and TLS handshake every time.
+///
+/// Requests go through `OUTBOUND_PROXY` if set, except for the [`LOCAL_NETWORKS`] and the hosts listed in `NO_PROXY`.
+/// Otherwise reqwest applies `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` itself, unless the proxy is `none`.
+/// The certificates of `HTTP_CA_FILE` are trusted in addition to the built-in roots, or instead of them if
+/// `HTTP_BUILTIN_ROOTS` is `false`. Connections are made from `BIND_INTERFACE` and `BIND_ADDRESS` if set.
+///
+/// # Errors
+/// Returns an error if the client cannot be constructed (e.g. the TLS backend fails to initialize, the proxy URL
+/// is invalid or the CA file cannot be read).
+pub fn build_client(config: &HttpConfig) -> Result<Client, String> {
+    let mut builder = Client::builder()
+        .user_agent(&config.user_agent)
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .tcp_keepalive(Duration::from_secs(60))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tls_built_in_root_certs(config.builtin_roots);
+    match config.proxy.expose().trim() {
+        "" => {}
+        "none" => builder = builder.no_proxy(),
+        url => {
+            let no_proxy = std::env::var("NO_PROXY").or_else(|_| std::env::var("no_proxy")).unwrap_or_default();
+            let proxy = Proxy::all(url).map_err(|e| format!("Invalid OUTBOUND_PROXY: {}", e))?;
+            builder = builder.proxy(proxy.no_proxy(NoProxy::from_string(&format!("{},{}", LOCAL_NETWORKS, no_proxy))));
+        }
+    }
+    if let Some(address) = config.bind_address {
+        builder = builder.local_address(address);
+    }
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+    if !config.bind_interface.is_empty() {
+        builder = builder.interface(&config.bind_interface);
+    }
+    if !config.ca_file.is_empty() {
+        for certificate in read_certificates(&config.ca_file)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Reads all certificates of the PEM file at `path`.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or contains no valid certificate.
+fn read_certificates(path: &str) -> Result<Vec<Certificate>, String> {
+    let pem = fs::read(path).map_err(|e| format!("Failed to read HTTP_CA_FILE {}: {}", path, e))?;
+    let certificates = Certificate::from_pem_bundle(&pem).map_err(|e| format!("Invalid certificate in {}: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("HTTP_CA_FILE {} contains no PEM certificate", path));
+    }
+    Ok(certificates)
+}
@@ -1,36 +1,425 @@
-use std::error::Error;
-use std::net::IpAddr;
-
-/// List of external services to fetch the public IP address from.
-const IP_SERVICES: [&str; 5] = [
-    "https://api.ipify.org",
-    "https://ifconfig.me/ip",
-    "https://checkip.amazonaws.com",
-    "https://ipecho.net/plain",
-    "https://ident.me",
-];
-
-/// Attempts to fetch the current public IPv4 address from multiple external services.
-///
-/// The function iterates through a list of known IP services and returns the first valid IPv4 address found.
-/// Each response is strictly validated to ensure it is a valid IP address.
+mod dns;
+mod http;
+mod interface;
+mod stun;
+mod trace;
+mod upnp;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use log::{info, warn};
+use crate::config::{Config, IpSourceKind, IpStrategy};
+use crate::error::CrondesError;
+use crate::metrics::METRICS;
+pub use dns::DnsSource;
+pub use http::{HttpSource, ResponseFormat, parse_service};
+pub use interface::InterfaceSource;
+pub use stun::StunSource;
+pub use trace::TraceSource;
+pub use upnp::UpnpSource;
+
+/// A way of finding out the public IP address of this host.
+#[async_trait]
+pub trait IpSource: Send + Sync {
+    /// Returns a label identifying the source in logs and metrics, e.g. the URL or server queried.
+    fn name(&self) -> &str;
+
+    /// Asks the source for the public IP address.
+    ///
+    /// # Errors
+    /// Returns an error if the source cannot be reached or does not return a valid address.
+    async fn fetch(&self) -> Result<IpAddr, CrondesError>;
+}
+
+/// The address family of a public IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    /// Returns `true` if `ip` belongs to this family.
+    pub fn contains(self, ip: &IpAddr) -> bool {
+        match self {
+            IpFamily::V4 => ip.is_ipv4(),
+            IpFamily::V6 => ip.is_ipv6(),
+        }
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpFamily::V4 => "IPv4",
+            IpFamily::V6 => "IPv6",
+        })
+    }
+}
+
+/// Detects the public IP address of one family by querying a list of [`IpSource`]s.
 ///
-/// # Errors
-/// Returns an error if no valid public IP address could be determined from any of the services.
-pub async fn fetch_public_ip() -> Result<String, Box<dyn Error>> {
-    for &url in IP_SERVICES.iter() {
-        let resp = reqwest::get(url).await;
-        if let Ok(r) = resp {
-            let text = r.text().await;
-            if let Ok(ip) = text {
-                let ip = ip.trim();
-                if let Ok(parsed) = ip.parse::<IpAddr>() {
-                    if parsed.is_ipv4() {
+/// A source failing `breaker_threshold` times in a row is skipped for `breaker_cooldown`, then probed with a single
+/// query again (circuit breaker).
+pub struct IpDetector {
+    sources: Vec<Box<dyn IpSource>>,
+    strategy: IpStrategy,
+    quorum: usize,
+    timeout: Duration,
+    family: IpFamily,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    health: Mutex<Vec<SourceHealth>>,
+}
+
+/// What an [`IpDetector`] has learned about one of its sources.
+#[derive(Debug, Clone, Copy, Default)]
+struct SourceHealth {
+    /// Failed queries in a row.
+    failures: u32,
+    /// Set once the source failed `breaker_threshold` times in a row; it is skipped until then.
+    open_until: Option<Instant>,
+    /// Smoothed response time of the successful queries.
+    latency: Option<Duration>,
+}
+
+impl IpDetector {
+    /// Creates a new [`IpDetector`] querying the given sources with `strategy` for an IPv4 address.
+    /// `quorum` is only used by [`IpStrategy::Consensus`]. Each source gets at most `timeout` to answer.
+    /// A source is skipped for 5 minutes after 3 failures in a row.
+    pub fn new(sources: Vec<Box<dyn IpSource>>, strategy: IpStrategy, quorum: usize, timeout: Duration) -> Self {
+        if strategy == IpStrategy::Consensus && quorum > sources.len() {
+            warn!("IP_QUORUM is {} but only {} IP sources are configured, no address can ever be confirmed", quorum, sources.len());
+        }
+        let health = Mutex::new(vec![SourceHealth::default(); sources.len()]);
+        IpDetector {
+            sources,
+            strategy,
+            quorum,
+            timeout,
+            family: IpFamily::V4,
+            breaker_threshold: 3,
+            breaker_cooldown: Duration::from_secs(300),
+            health,
+        }
+    }
+
+    /// Creates the sources listed in `IP_SOURCES`, using `client` for all HTTP based sources.
+    pub fn from_config(config: &Config, client: &reqwest::Client) -> Self {
+        IpDetector::for_sources(config, client, &config.ip.sources, &config.ip.services, IpFamily::V4)
+    }
+
+    /// Creates the given kinds of sources detecting an address of `family`, with `services` for the `http` source
+    /// (the built-in list if empty) and the remaining settings from `config`.
+    ///
+    /// For IPv6 the built-in sources query endpoints that are only reachable over IPv6. Kinds that can't detect IPv6
+    /// addresses (see [`IpSourceKind::supports_ipv6`]) are skipped.
+    pub fn for_sources(
+        config: &Config,
+        client: &reqwest::Client,
+        kinds: &[IpSourceKind],
+        services: &[String],
+        family: IpFamily,
+    ) -> Self {
+        let timeout = Duration::from_secs(config.ip.timeout_secs);
+        let mut sources: Vec<Box<dyn IpSource>> = Vec::new();
+        for kind in kinds {
+            if family == IpFamily::V6 && !kind.supports_ipv6() {
+                warn!("IP source {} can't detect IPv6 addresses, skipping it", kind);
+                continue;
+            }
+            match kind {
+                IpSourceKind::Trace => {
+                    let urls = if family == IpFamily::V6 { &trace::TRACE_URLS_V6[..] } else { &trace::TRACE_URLS[..] };
+                    for &url in urls {
+                        sources.push(Box::new(TraceSource::new(url, client.clone())));
+                    }
+                }
+                IpSourceKind::Http if services.is_empty() => {
+                    let urls = if family == IpFamily::V6 { &http::IP_SERVICES_V6[..] } else { &http::IP_SERVICES[..] };
+                    for &url in urls {
+                        sources.push(Box::new(HttpSource::new(url, client.clone())));
+                    }
+                }
+                IpSourceKind::Http => {
+                    for entry in services {
+                        match HttpSource::from_entry(entry, client.clone()) {
+                            Ok(source) => sources.push(Box::new(source)),
+                            Err(e) => warn!("Skipping IP service: {}", e),
+                        }
+                    }
+                }
+                IpSourceKind::Dns if family == IpFamily::V6 => {
+                    for &server in dns::OPENDNS_RESOLVERS_V6.iter() {
+                        sources.push(Box::new(DnsSource::new(IpAddr::V6(server), timeout)));
+                    }
+                }
+                IpSourceKind::Dns => {
+                    for &server in dns::OPENDNS_RESOLVERS.iter() {
+                        sources.push(Box::new(DnsSource::new(IpAddr::V4(server), timeout)));
+                    }
+                }
+                IpSourceKind::Stun => {
+                    for &server in stun::STUN_SERVERS.iter() {
+                        sources.push(Box::new(StunSource::new(server, timeout)));
+                    }
+                }
+                IpSourceKind::Upnp => {
+                    sources.push(Box::new(UpnpSource::new(config.ip.upnp_url.as_str(), config.ip.upnp_service.as_str(), client.clone())));
+                }
+                IpSourceKind::Interface => {
+                    let interface = Some(config.ip.interface.clone()).filter(|i| !i.is_empty());
+                    sources.push(Box::new(InterfaceSource::new(interface, family)));
+                }
+            }
+        }
+        IpDetector {
+            family,
+            breaker_threshold: config.ip.breaker_threshold,
+            breaker_cooldown: Duration::from_secs(config.ip.breaker_cooldown_secs),
+            ..IpDetector::new(sources, config.ip.strategy, config.ip.quorum, timeout)
+        }
+    }
+
+    /// Returns the address family this detector looks for.
+    pub fn family(&self) -> IpFamily {
+        self.family
+    }
+
+    /// Attempts to determine the current public address of the detector's family.
+    ///
+    /// With [`IpStrategy::Sequential`] the sources are queried in order, with [`IpStrategy::Fastest`] ordered by their
+    /// recent response times, with [`IpStrategy::Race`] all at once. Either way the first valid address is returned
+    /// and pending queries are cancelled. With [`IpStrategy::Consensus`] all sources are queried at once until `quorum`
+    /// of them agree. Sources that fail, time out or return something else are logged and counted in the metrics;
+    /// sources skipped by the circuit breaker aren't queried at all.
+    ///
+    /// # Errors
+    /// Returns an error if no valid public IP address could be determined from any of the sources.
+    pub async fn detect(&self) -> Result<String, CrondesError> {
+        match self.strategy {
+            IpStrategy::Consensus => return self.consensus().await,
+            IpStrategy::Sequential | IpStrategy::Fastest => {
+                for index in self.candidates(1) {
+                    if let Some(ip) = self.query(index).await {
+                        return Ok(ip.to_string());
+                    }
+                }
+            }
+            IpStrategy::Race => {
+                let mut pending: FuturesUnordered<_> = self.candidates(1).into_iter().map(|i| self.query(i)).collect();
+                while let Some(result) = pending.next().await {
+                    if let Some(ip) = result {
                         return Ok(ip.to_string());
                     }
                 }
             }
         }
+        Err(CrondesError::IpDetection(format!("No valid public {} address could be determined", self.family)))
     }
-    Err("No valid public IP address could be determined".into())
+
+    /// Queries all sources at once and returns the first address reported by `quorum` sources.
+    ///
+    /// Disagreeing answers are logged, since they hint at a misbehaving source.
+    ///
+    /// # Errors
+    /// Returns an error listing all answers if no address reached the quorum.
+    async fn consensus(&self) -> Result<String, CrondesError> {
+        let mut votes: BTreeMap<IpAddr, usize> = BTreeMap::new();
+        let mut pending: FuturesUnordered<_> = self.candidates(self.quorum).into_iter().map(|i| self.query(i)).collect();
+        while let Some(result) = pending.next().await {
+            let Some(ip) = result else { continue };
+            let count = votes.entry(ip).or_insert(0);
+            *count += 1;
+            if *count >= self.quorum {
+                if votes.len() > 1 {
+                    warn!("IP sources disagree: {}. Accepting {}", format_votes(&votes), ip);
+                }
+                return Ok(ip.to_string());
+            }
+        }
+        let answers = if votes.is_empty() { "no valid answers".to_string() } else { format_votes(&votes) };
+        Err(CrondesError::IpDetection(format!("No IP address was confirmed by {} sources ({})", self.quorum, answers)))
+    }
+
+    /// Returns the indices of the sources to query, in order.
+    ///
+    /// Sources skipped by the circuit breaker are left out until their cooldown has passed, unless fewer than `needed`
+    /// sources would remain. With [`IpStrategy::Fastest`] the sources are sorted by their smoothed response time,
+    /// untried ones first so every source gets measured.
+    fn candidates(&self, needed: usize) -> Vec<usize> {
+        let health = self.health.lock().unwrap();
+        let now = Instant::now();
+        let mut candidates: Vec<usize> =
+            (0..self.sources.len()).filter(|&i| health[i].open_until.is_none_or(|until| until <= now)).collect();
+        if candidates.len() < needed {
+            candidates = (0..self.sources.len()).collect();
+        }
+        if self.strategy == IpStrategy::Fastest {
+            candidates.sort_by_key(|&i| health[i].latency.unwrap_or_default());
+        }
+        candidates
+    }
+
+    /// Queries the source at `index`, giving up after the per-source timeout.
+    ///
+    /// Returns `None` if the source failed or did not return an address of the detector's family; the reason is logged.
+    async fn query(&self, index: usize) -> Option<IpAddr> {
+        let source = self.sources[index].as_ref();
+        let started = Instant::now();
+        let result = match tokio::time::timeout(self.timeout, source.fetch()).await {
+            Ok(result) => result,
+            Err(_) => Err(CrondesError::IpDetection(format!("no answer within {}s", self.timeout.as_secs()))),
+        };
+        match result {
+            Ok(ip) if self.family.contains(&ip) => {
+                self.record(index, Some(started.elapsed()));
+                return Some(ip);
+            }
+            Ok(ip) => warn!("IP source {} returned {}, which is not an {} address", source.name(), ip, self.family),
+            Err(e) => warn!("IP source {} failed: {}", source.name(), e),
+        }
+        METRICS.ip_service_failed(source.name());
+        self.record(index, None);
+        None
+    }
+
+    /// Records the outcome of a query of the source at `index`: its response time, or `None` if it failed.
+    fn record(&self, index: usize, latency: Option<Duration>) {
+        let mut health = self.health.lock().unwrap();
+        let source = &mut health[index];
+        let name = self.sources[index].name();
+        match latency {
+            Some(latency) => {
+                if source.open_until.take().is_some() {
+                    info!("IP source {} answers again", name);
+                }
+                source.failures = 0;
+                // Gleitender Mittelwert, damit ein einzelner langsamer Request die Reihenfolge nicht umwirft
+                source.latency = Some(source.latency.map_or(latency, |average| average.mul_f64(0.7) + latency.mul_f64(0.3)));
+            }
+            None => {
+                source.failures += 1;
+                if self.breaker_threshold > 0 && source.failures >= self.breaker_threshold {
+                    source.open_until = Some(Instant::now() + self.breaker_cooldown);
+                    warn!(
+                        "IP source {} failed {} times in a row, skipping it for {}s",
+                        name,
+                        source.failures,
+                        self.breaker_cooldown.as_secs()
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Formats the answers collected in consensus mode, e.g. `1.2.3.4 (2x), 5.6.7.8 (1x)`.
+fn format_votes(votes: &BTreeMap<IpAddr, usize>) -> String {
+    votes.iter().map(|(ip, n)| format!("{} ({}x)", ip, n)).collect::<Vec<_>>().join(", ")
+}
+
+/// Returns `true` if `ip` is a globally routable address (see [`non_public_reason`] and [`non_public_reason_v6`]).
+pub(crate) fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => non_public_reason(ip).is_none(),
+        IpAddr::V6(ip) => non_public_reason_v6(ip).is_none(),
+    }
+}
+
+/// Classifies `ip` and returns why it is not a globally routable address, or `None` if it is.
+///
+/// Covers private (RFC 1918), shared (CGNAT, RFC 6598), loopback, link-local, documentation,
+/// benchmarking, multicast and other reserved ranges.
+pub fn non_public_reason(ip: &Ipv4Addr) -> Option<&'static str> {
+    let [a, b, c, _] = ip.octets();
+    if a == 100 && (64..128).contains(&b) {
+        Some("in the shared address space 100.64.0.0/10 used for carrier-grade NAT (CGNAT)")
+    } else if ip.is_private() {
+        Some("a private address (RFC 1918)")
+    } else if ip.is_loopback() {
+        Some("a loopback address")
+    } else if ip.is_link_local() {
+        Some("a link-local address")
+    } else if ip.is_documentation() || (a == 198 && (18..20).contains(&b)) {
+        Some("reserved for documentation or benchmarking")
+    } else if ip.is_multicast() {
+        Some("a multicast address")
+    } else if a == 0 || a >= 240 || (a == 192 && b == 0 && c == 0) {
+        Some("in a reserved range")
+    } else {
+        None
+    }
+}
+
+/// Classifies the IPv6 address `ip` like [`non_public_reason`]: only global unicast addresses (`2000::/3`) are public.
+///
+/// Covers unique local (RFC 4193), link-local, loopback, documentation, multicast and IPv4-mapped addresses.
+pub fn non_public_reason_v6(ip: &Ipv6Addr) -> Option<&'static str> {
+    let [a, b, ..] = ip.segments();
+    if ip.is_loopback() || ip.is_unspecified() {
+        Some("a loopback or unspecified address")
+    } else if ip.is_unique_local() {
+        Some("a unique local address (RFC 4193)")
+    } else if ip.is_unicast_link_local() {
+        Some("a link-local address")
+    } else if (a == 0x2001 && b == 0x0db8) || (a == 0x3fff && b < 0x1000) {
+        Some("reserved for documentation")
+    } else if ip.is_multicast() {
+        Some("a multicast address")
+    } else if ip.to_ipv4_mapped().is_some() {
+        Some("an IPv4-mapped address")
+    } else if a & 0xe000 != 0x2000 {
+        Some("outside the global unicast range 2000::/3")
+    } else {
+        None
+    }
+}
+
+/// Refuses addresses that are not globally routable, since publishing them in a public record is always wrong.
+///
+/// # Errors
+/// Returns [`CrondesError::NonPublicIp`] with an explanation if `ip` is not a public IPv4 or IPv6 address.
+pub fn check_public(ip: &str) -> Result<(), CrondesError> {
+    let reason = match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(parsed)) => non_public_reason(&parsed),
+        Ok(IpAddr::V6(parsed)) => non_public_reason_v6(&parsed),
+        Err(_) => Some("not a valid IP address"),
+    };
+    match reason {
+        Some(reason) => Err(CrondesError::NonPublicIp { ip: ip.to_string(), reason }),
+        None => Ok(()),
+    }
+}
+
+/// Parses the interface identifier of a record: an IPv6 address whose bits after the prefix are used, e.g.
+/// `::1234:56ff:fe78:9abc`, or a MAC address like `02:11:22:33:44:55`, turned into its modified EUI-64 identifier.
+///
+/// # Errors
+/// Returns a description of the problem if `value` is neither.
+pub fn parse_interface_id(value: &str) -> Result<Ipv6Addr, String> {
+    let value = value.trim();
+    if let Ok(id) = value.parse() {
+        return Ok(id);
+    }
+    let mac: Vec<u8> = value.split([':', '-']).filter_map(|octet| u8::from_str_radix(octet, 16).ok()).collect();
+    if mac.len() != 6 || value.split([':', '-']).count() != 6 {
+        return Err(format!("{} is neither an IPv6 suffix like ::1234 nor a MAC address", value));
+    }
+    // Modified EUI-64 (RFC 4291): U/L-Bit umdrehen und ff:fe in die Mitte
+    let mut octets = [0; 16];
+    octets[8..].copy_from_slice(&[mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]);
+    Ok(Ipv6Addr::from(octets))
+}
+
+/// Combines the first `prefix_length` bits of `prefix` with the remaining bits of `interface_id`.
+pub fn with_interface_id(prefix: Ipv6Addr, prefix_length: u8, interface_id: Ipv6Addr) -> Ipv6Addr {
+    let mask = u128::MAX.checked_shl(128 - u32::from(prefix_length.min(128))).unwrap_or(0);
+    Ipv6Addr::from((u128::from(prefix) & mask) | (u128::from(interface_id) & !mask))
 }
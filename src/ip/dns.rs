@@ -0,0 +1,60 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use async_trait::async_trait;
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use crate::error::CrondesError;
+use super::IpSource;
+
+/// The OpenDNS resolvers answering `myip.opendns.com` with the address of the client.
+pub(super) const OPENDNS_RESOLVERS: [Ipv4Addr; 2] = [
+    Ipv4Addr::new(208, 67, 222, 222),
+    Ipv4Addr::new(208, 67, 220, 220),
+];
+
+/// The IPv6 addresses of the OpenDNS resolvers, answering `myip.opendns.com` with the IPv6 address of the client.
+pub(super) const OPENDNS_RESOLVERS_V6: [Ipv6Addr; 2] = [
+    Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35),
+    Ipv6Addr::new(0x2620, 0x119, 0x53, 0, 0, 0, 0, 0x53),
+];
+
+/// The special name that OpenDNS resolves to the address of the client.
+const MYIP_NAME: &str = "myip.opendns.com.";
+
+/// Detects the public IP address with a DNS query for `myip.opendns.com` at a specific resolver.
+///
+/// Works on networks that block the HTTP echo services, as long as outgoing DNS is allowed. A resolver reached over
+/// IPv6 is asked for the AAAA record, so it returns the IPv6 address of the client.
+pub struct DnsSource {
+    name: String,
+    server: IpAddr,
+    resolver: TokioResolver,
+}
+
+impl DnsSource {
+    /// Creates a new [`DnsSource`] querying `server` on port 53, giving up after `timeout`.
+    pub fn new(server: IpAddr, timeout: Duration) -> Self {
+        let config = ResolverConfig::from_parts(None, vec![], NameServerConfigGroup::from_ips_clear(&[server], 53, true));
+        let mut builder = TokioResolver::builder_with_config(config, TokioConnectionProvider::default());
+        builder.options_mut().timeout = timeout;
+        builder.options_mut().cache_size = 0;
+        DnsSource { name: format!("dns://{}", server), server, resolver: builder.build() }
+    }
+}
+
+#[async_trait]
+impl IpSource for DnsSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        let failed = |e| CrondesError::IpDetection(format!("DNS lookup failed: {}", e));
+        let ip = match self.server {
+            IpAddr::V4(_) => self.resolver.ipv4_lookup(MYIP_NAME).await.map_err(failed)?.iter().next().map(|a| IpAddr::V4(a.0)),
+            IpAddr::V6(_) => self.resolver.ipv6_lookup(MYIP_NAME).await.map_err(failed)?.iter().next().map(|a| IpAddr::V6(a.0)),
+        };
+        ip.ok_or_else(|| CrondesError::IpDetection("DNS response contains no address".to_string()))
+    }
+}
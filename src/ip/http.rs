@@ -0,0 +1,109 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use regex::Regex;
+use crate::error::CrondesError;
+use super::IpSource;
+
+/// Built-in list of external services to fetch the public IP address from, used unless `IP_SERVICES` is set.
+pub(super) const IP_SERVICES: [&str; 5] = [
+    "https://api.ipify.org",
+    "https://ifconfig.me/ip",
+    "https://checkip.amazonaws.com",
+    "https://ipecho.net/plain",
+    "https://ident.me",
+];
+
+/// Built-in services only reachable over IPv6, used for AAAA records unless `IP_SERVICES` is set.
+pub(super) const IP_SERVICES_V6: [&str; 3] = [
+    "https://api6.ipify.org",
+    "https://v6.ident.me",
+    "https://ipv6.icanhazip.com",
+];
+
+/// How the address is read from the response of an [`HttpSource`].
+#[derive(Debug, Clone)]
+pub enum ResponseFormat {
+    /// The trimmed body is the address.
+    Text,
+    /// The body is JSON and the address is the string at this JSON pointer, e.g. `/ip`.
+    Json(String),
+    /// The address is the first capture group of this regex, or the whole match if it has none.
+    Regex(Regex),
+}
+
+/// An HTTP echo service that returns the caller's IP address, as plain text unless another [`ResponseFormat`] is set.
+pub struct HttpSource {
+    url: String,
+    format: ResponseFormat,
+    client: reqwest::Client,
+}
+
+impl HttpSource {
+    /// Creates a new [`HttpSource`] for `url` answering in plain text, using `client` for all requests.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        HttpSource { url: url.into(), format: ResponseFormat::Text, client }
+    }
+
+    /// Creates a new [`HttpSource`] from an `IP_SERVICES` entry (see [`parse_service`]).
+    ///
+    /// # Errors
+    /// Returns an error if the response format of the entry is invalid.
+    pub fn from_entry(entry: &str, client: reqwest::Client) -> Result<Self, String> {
+        let (url, format) = parse_service(entry)?;
+        Ok(HttpSource { url: url.to_string(), format, client })
+    }
+
+    /// Reads the address from the response `body` as configured by the [`ResponseFormat`].
+    fn extract(&self, body: &str) -> Option<String> {
+        match &self.format {
+            ResponseFormat::Text => Some(body.trim().to_string()),
+            ResponseFormat::Json(pointer) => {
+                let json: serde_json::Value = serde_json::from_str(body).ok()?;
+                json.pointer(pointer)?.as_str().map(|ip| ip.trim().to_string())
+            }
+            ResponseFormat::Regex(regex) => {
+                let captures = regex.captures(body)?;
+                captures.get(1).or_else(|| captures.get(0)).map(|ip| ip.as_str().trim().to_string())
+            }
+        }
+    }
+}
+
+/// Splits an `IP_SERVICES` entry into the URL and the [`ResponseFormat`]: the URL, optionally followed by a space and
+/// `json:<pointer>` (e.g. `https://api.ipify.org?format=json json:/ip`) or `regex:<pattern>`.
+///
+/// # Errors
+/// Returns an error if the format is unknown, the JSON pointer doesn't start with `/` or the regex is invalid.
+pub fn parse_service(entry: &str) -> Result<(&str, ResponseFormat), String> {
+    let entry = entry.trim();
+    let Some((url, format)) = entry.split_once(char::is_whitespace) else {
+        return Ok((entry, ResponseFormat::Text));
+    };
+    let format = format.trim_start();
+    let format = if let Some(pointer) = format.strip_prefix("json:") {
+        if !pointer.starts_with('/') {
+            return Err(format!("the JSON pointer of {} must start with '/'", url));
+        }
+        ResponseFormat::Json(pointer.to_string())
+    } else if let Some(pattern) = format.strip_prefix("regex:") {
+        ResponseFormat::Regex(Regex::new(pattern).map_err(|e| format!("invalid regex for {}: {}", url, e))?)
+    } else {
+        return Err(format!("unknown response format for {}: {} (expected json:<pointer> or regex:<pattern>)", url, format));
+    };
+    Ok((url, format))
+}
+
+#[async_trait]
+impl IpSource for HttpSource {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    /// Fetches the URL and strictly parses the address extracted from the response body.
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        let text = self.client.get(&self.url).send().await?.error_for_status()?.text().await?;
+        self.extract(&text)
+            .and_then(|ip| ip.parse().ok())
+            .ok_or_else(|| CrondesError::IpDetection(format!("invalid response: {:.64}", text.trim())))
+    }
+}
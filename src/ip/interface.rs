@@ -0,0 +1,49 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::CrondesError;
+use super::{IpFamily, IpSource, is_public};
+
+/// Reads the public IP address directly from the local network interfaces, without any external lookup.
+///
+/// Only useful on hosts with a public address on one of their interfaces, e.g. routers
+/// or servers without NAT. Private, shared (CGNAT) and other reserved addresses are skipped.
+pub struct InterfaceSource {
+    name: String,
+    interface: Option<String>,
+    family: IpFamily,
+}
+
+impl InterfaceSource {
+    /// Creates a new [`InterfaceSource`] reading an address of `family` from the named interface (e.g. `ppp0`),
+    /// or from all interfaces if `interface` is `None`.
+    pub fn new(interface: Option<String>, family: IpFamily) -> Self {
+        let name = match &interface {
+            Some(interface) => format!("interface:{}", interface),
+            None => "interface".to_string(),
+        };
+        InterfaceSource { name, interface, family }
+    }
+}
+
+#[async_trait]
+impl IpSource for InterfaceSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        let interfaces = if_addrs::get_if_addrs()
+            .map_err(|e| CrondesError::IpDetection(format!("Failed to list network interfaces: {}", e)))?;
+        let mut candidates = interfaces
+            .into_iter()
+            .filter(|iface| self.interface.as_ref().is_none_or(|name| &iface.name == name))
+            .peekable();
+        if let Some(name) = &self.interface && candidates.peek().is_none() {
+            return Err(CrondesError::IpDetection(format!("Network interface {} not found or has no address", name)));
+        }
+        candidates
+            .map(|iface| iface.ip())
+            .find(|ip| self.family.contains(ip) && is_public(ip))
+            .ok_or_else(|| CrondesError::IpDetection(format!("{} has no public {} address", self.name, self.family)))
+    }
+}
@@ -0,0 +1,126 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use async_trait::async_trait;
+use rand::RngExt;
+use tokio::net::UdpSocket;
+use crate::error::CrondesError;
+use super::IpSource;
+
+/// Public STUN servers to ask for the mapped address.
+pub(super) const STUN_SERVERS: [&str; 2] = [
+    "stun.cloudflare.com:3478",
+    "stun.l.google.com:19302",
+];
+
+/// The fixed magic cookie of every STUN message (RFC 5389).
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Detects the public IP address with a STUN binding request (RFC 5389) over UDP.
+///
+/// The server answers with the address it saw the request coming from, which is the
+/// public address of the NAT gateway in front of this host.
+pub struct StunSource {
+    name: String,
+    server: String,
+    timeout: Duration,
+}
+
+impl StunSource {
+    /// Creates a new [`StunSource`] for `server` (`host:port`), giving up after `timeout`.
+    pub fn new(server: impl Into<String>, timeout: Duration) -> Self {
+        let server = server.into();
+        StunSource { name: format!("stun://{}", server), server, timeout }
+    }
+
+    /// Sends a binding request and waits for the matching response.
+    async fn binding(&self) -> Result<IpAddr, CrondesError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(io_error)?;
+        socket.connect(&self.server).await.map_err(io_error)?;
+
+        let transaction_id: [u8; 12] = rand::rng().random();
+        let mut request = Vec::with_capacity(20);
+        request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&transaction_id);
+        socket.send(&request).await.map_err(io_error)?;
+
+        let mut buf = [0u8; 512];
+        loop {
+            let len = socket.recv(&mut buf).await.map_err(io_error)?;
+            // Antworten auf andere Transaktionen ignorieren
+            if len >= 20 && buf[8..20] == transaction_id {
+                return parse_response(&buf[..len]);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl IpSource for StunSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        tokio::time::timeout(self.timeout, self.binding())
+            .await
+            .map_err(|_| CrondesError::IpDetection("STUN request timed out".to_string()))?
+    }
+}
+
+/// Extracts the mapped address from a STUN binding response.
+///
+/// Prefers `XOR-MAPPED-ADDRESS` and falls back to the legacy `MAPPED-ADDRESS` attribute.
+fn parse_response(msg: &[u8]) -> Result<IpAddr, CrondesError> {
+    let invalid = |reason: &str| CrondesError::IpDetection(format!("invalid STUN response: {}", reason));
+    if u16::from_be_bytes([msg[0], msg[1]]) != BINDING_SUCCESS {
+        return Err(invalid("not a binding success response"));
+    }
+    let body_len = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+    let body = msg.get(20..20 + body_len).ok_or_else(|| invalid("truncated message"))?;
+
+    let mut mapped = None;
+    let mut pos = 0;
+    while pos + 4 <= body.len() {
+        let attr_type = u16::from_be_bytes([body[pos], body[pos + 1]]);
+        let attr_len = u16::from_be_bytes([body[pos + 2], body[pos + 3]]) as usize;
+        let value = body.get(pos + 4..pos + 4 + attr_len).ok_or_else(|| invalid("truncated attribute"))?;
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => return decode_address(value, Some(&msg[4..20])).ok_or_else(|| invalid("bad XOR-MAPPED-ADDRESS")),
+            ATTR_MAPPED_ADDRESS => mapped = decode_address(value, None),
+            _ => {}
+        }
+        // Attribute sind auf 4 Byte aufgefüllt
+        pos += 4 + attr_len.div_ceil(4) * 4;
+    }
+    mapped.ok_or_else(|| invalid("no mapped address"))
+}
+
+/// Decodes a (XOR-)MAPPED-ADDRESS attribute value. `xor_key` is the magic cookie followed by
+/// the transaction ID for XOR-MAPPED-ADDRESS, and `None` for the plain attribute.
+fn decode_address(value: &[u8], xor_key: Option<&[u8]>) -> Option<IpAddr> {
+    let family = *value.get(1)?;
+    let len = match family {
+        0x01 => 4,
+        0x02 => 16,
+        _ => return None,
+    };
+    let mut addr = value.get(4..4 + len)?.to_vec();
+    if let Some(key) = xor_key {
+        addr.iter_mut().zip(key).for_each(|(byte, k)| *byte ^= k);
+    }
+    Some(match family {
+        0x01 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(addr.as_slice()).ok()?)),
+        _ => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(addr.as_slice()).ok()?)),
+    })
+}
+
+/// Converts a socket error into an IP detection error.
+fn io_error(e: std::io::Error) -> CrondesError {
+    CrondesError::IpDetection(format!("STUN request failed: {}", e))
+}
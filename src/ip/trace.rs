@@ -0,0 +1,51 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::CrondesError;
+use super::IpSource;
+
+/// Cloudflare's trace endpoints, reachable by IP so they work even if DNS is broken.
+pub(super) const TRACE_URLS: [&str; 3] = [
+    "https://1.1.1.1/cdn-cgi/trace",
+    "https://1.0.0.1/cdn-cgi/trace",
+    "https://cloudflare.com/cdn-cgi/trace",
+];
+
+/// The same endpoints reachable over IPv6 only, used to detect the public IPv6 address.
+pub(super) const TRACE_URLS_V6: [&str; 2] = [
+    "https://[2606:4700:4700::1111]/cdn-cgi/trace",
+    "https://[2606:4700:4700::1001]/cdn-cgi/trace",
+];
+
+/// Detects the public IP address via Cloudflare's `/cdn-cgi/trace` endpoint.
+///
+/// The endpoint returns `key=value` lines describing the request, including the client address
+/// in the `ip=` line. Since crondes talks to Cloudflare anyway, no third-party service is involved.
+pub struct TraceSource {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl TraceSource {
+    /// Creates a new [`TraceSource`] for `url` using `client` for all requests.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        TraceSource { url: url.into(), client }
+    }
+}
+
+#[async_trait]
+impl IpSource for TraceSource {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        let text = self.client.get(&self.url).send().await?.error_for_status()?.text().await?;
+        let ip = text
+            .lines()
+            .find_map(|line| line.strip_prefix("ip="))
+            .ok_or_else(|| CrondesError::IpDetection("trace response contains no ip= line".to_string()))?;
+        ip.trim()
+            .parse()
+            .map_err(|_| CrondesError::IpDetection(format!("invalid ip= line in trace response: {:.64}", ip)))
+    }
+}
@@ -0,0 +1,62 @@
+use std::net::IpAddr;
+use async_trait::async_trait;
+use crate::error::CrondesError;
+use super::IpSource;
+
+/// Detects the public IP address by asking the local router via UPnP IGD / TR-064.
+///
+/// Sends a `GetExternalIPAddress` SOAP request to the router's WAN connection service,
+/// so IP detection stays inside the LAN. The defaults match the IGD service of a Fritz!Box.
+pub struct UpnpSource {
+    control_url: String,
+    service: String,
+    client: reqwest::Client,
+}
+
+impl UpnpSource {
+    /// Creates a new [`UpnpSource`] posting to `control_url` for the service type `service`,
+    /// e.g. `urn:schemas-upnp-org:service:WANIPConnection:1`.
+    pub fn new(control_url: impl Into<String>, service: impl Into<String>, client: reqwest::Client) -> Self {
+        UpnpSource { control_url: control_url.into(), service: service.into(), client }
+    }
+}
+
+#[async_trait]
+impl IpSource for UpnpSource {
+    fn name(&self) -> &str {
+        &self.control_url
+    }
+
+    async fn fetch(&self) -> Result<IpAddr, CrondesError> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">"#,
+                r#"<s:Body><u:GetExternalIPAddress xmlns:u="{}"/></s:Body></s:Envelope>"#,
+            ),
+            self.service
+        );
+        let text = self.client
+            .post(&self.control_url)
+            .header("Content-Type", r#"text/xml; charset="utf-8""#)
+            .header("SOAPAction", format!("\"{}#GetExternalIPAddress\"", self.service))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let ip = xml_element(&text, "NewExternalIPAddress")
+            .ok_or_else(|| CrondesError::IpDetection("UPnP response contains no NewExternalIPAddress".to_string()))?;
+        ip.parse()
+            .map_err(|_| CrondesError::IpDetection(format!("router reported an invalid address: {:.64}", ip)))
+    }
+}
+
+/// Returns the trimmed text content of the first `<tag>` element, ignoring namespace prefixes.
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find('<')?;
+    Some(xml[start..end].trim())
+}
@@ -0,0 +1,48 @@
+//! Keeps DNS records in sync with the public IP address of the host.
+//!
+//! The crate powers the `crondes` binary but can also be embedded into other programs:
+//!
+//! ```no_run
+//! use crondes::{Config, CrondesError, Updater};
+//!
+//! # async fn example() -> Result<(), CrondesError> {
+//! let config = Config::from_env().map_err(CrondesError::Config)?;
+//! let mut updater = Updater::new(config)?;
+//!
+//! // Either run a single update cycle ...
+//! let report = updater.update_once().await?;
+//! println!("Updated {} of {} records", report.updated, report.total);
+//!
+//! // ... or keep the records in sync until SIGINT/SIGTERM.
+//! updater.run().await
+//! # }
+//! ```
+
+pub mod config;
+mod configwatch;
+pub mod connectivity;
+pub mod control;
+pub mod error;
+pub mod history;
+pub mod hooks;
+pub mod http;
+pub mod ip;
+pub mod logging;
+pub mod metrics;
+pub mod nameserver;
+mod netwatch;
+pub mod notify;
+pub mod propagation;
+pub mod provider;
+pub mod quiethours;
+mod retry;
+mod server;
+mod signals;
+mod systemd;
+pub mod state;
+pub mod updater;
+pub mod zonesync;
+
+pub use config::Config;
+pub use error::CrondesError;
+pub use updater::{CycleReport, Updater};
@@ -0,0 +1,416 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use log::{Level, Log, Metadata, Record};
+use crate::config::{LogConfig, LogTarget};
+use crate::metrics::unix_now;
+
+/// Name the messages are tagged with in syslog and the journal.
+const IDENTIFIER: &str = "crondes";
+
+/// Syslog facility `daemon`.
+const FACILITY_DAEMON: u8 = 3;
+
+/// How long connecting to and writing to a TCP syslog collector may block logging.
+const SYSLOG_TCP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Minimum time between two attempts to reconnect to a TCP syslog collector.
+const SYSLOG_RECONNECT_EVERY: Duration = Duration::from_secs(30);
+
+/// Socket of the native journald protocol.
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Installs the global logger, writing to stderr until [`configure`] selects another backend.
+///
+/// The log level is read from `RUST_LOG` and applies to every backend.
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter();
+    let logger = LOGGER.get_or_init(|| Logger { stderr, backend: RwLock::new(Backend::Stderr), file: RwLock::new(None) });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Switches the log output to the backend selected in `config` and opens `LOG_FILE`, if set.
+/// Does nothing if [`init`] wasn't called.
+///
+/// # Errors
+/// Returns an error if `SYSLOG_ADDRESS` is invalid, the syslog or journald socket or the log file cannot be opened.
+pub fn configure(config: &LogConfig) -> Result<(), String> {
+    let backend = match config.target {
+        LogTarget::Stderr => Backend::Stderr,
+        LogTarget::Syslog => Backend::Syslog(Syslog::connect(config.syslog_address.parse()?)?),
+        #[cfg(unix)]
+        LogTarget::Journald => Backend::Journald(Journald::connect()?),
+        #[cfg(not(unix))]
+        LogTarget::Journald => return Err("LOG_TARGET=journald is only supported on Linux".to_string()),
+    };
+    let file = match config.file.as_str() {
+        "" => None,
+        path => Some(LogFile::open(path, config)?),
+    };
+    if let Some(logger) = LOGGER.get() {
+        *logger.backend.write().unwrap_or_else(|e| e.into_inner()) = backend;
+        *logger.file.write().unwrap_or_else(|e| e.into_inner()) = file;
+    }
+    Ok(())
+}
+
+/// Where syslog messages are sent to, parsed from `SYSLOG_ADDRESS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyslogAddress {
+    /// A local datagram socket like `/dev/log`, given as `unix:/dev/log`.
+    Unix(PathBuf),
+    /// A remote collector reached via UDP, given as `udp://host:port`.
+    Udp(String),
+    /// A remote collector reached via TCP with octet-counting framing (RFC 6587), given as `tcp://host:port`.
+    Tcp(String),
+}
+
+impl FromStr for SyslogAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix("unix://").or_else(|| s.strip_prefix("unix:")) {
+            return if path.is_empty() { Err("missing socket path".to_string()) } else { Ok(SyslogAddress::Unix(PathBuf::from(path))) };
+        }
+        let (scheme, address) = s.split_once("://").ok_or_else(|| format!("expected unix:, udp:// or tcp://, got {}", s))?;
+        match address.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {}
+            _ => return Err(format!("expected host:port, got {}", address)),
+        }
+        match scheme {
+            "udp" => Ok(SyslogAddress::Udp(address.to_string())),
+            "tcp" => Ok(SyslogAddress::Tcp(address.to_string())),
+            other => Err(format!("unknown scheme {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for SyslogAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyslogAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+            SyslogAddress::Udp(address) => write!(f, "udp://{}", address),
+            SyslogAddress::Tcp(address) => write!(f, "tcp://{}", address),
+        }
+    }
+}
+
+/// The global logger, dispatching to the backend selected by `LOG_TARGET`.
+struct Logger {
+    /// Writes to stderr and decides which records are logged at all, based on `RUST_LOG`.
+    stderr: env_logger::Logger,
+    backend: RwLock<Backend>,
+    /// `LOG_FILE`, written in addition to the backend.
+    file: RwLock<Option<LogFile>>,
+}
+
+enum Backend {
+    Stderr,
+    Syslog(Syslog),
+    #[cfg(unix)]
+    Journald(Journald),
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.stderr.matches(record) {
+            return;
+        }
+        let result = match &*self.backend.read().unwrap_or_else(|e| e.into_inner()) {
+            Backend::Stderr => {
+                self.stderr.log(record);
+                Ok(())
+            }
+            Backend::Syslog(syslog) => syslog.send(record),
+            #[cfg(unix)]
+            Backend::Journald(journald) => journald.send(record),
+        };
+        if let Some(file) = &*self.file.read().unwrap_or_else(|e| e.into_inner()) {
+            // Ein volles Dateisystem darf den Daemon nicht stören, die Zeile erreicht trotzdem das Backend
+            let _ = file.write(record);
+        }
+        // Eine nicht erreichbare Senke darf keine Meldungen verschlucken
+        if result.is_err() {
+            self.stderr.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Maps a log level to a syslog severity, which journald uses as priority as well.
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Sends RFC 5424 messages to a syslog daemon or collector.
+struct Syslog {
+    transport: Transport,
+    hostname: String,
+}
+
+enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    /// After a write failed, the connection is re-established at most every [`SYSLOG_RECONNECT_EVERY`]; messages
+    /// in between go to stderr.
+    Tcp { address: String, connection: Mutex<TcpConnection> },
+}
+
+struct TcpConnection {
+    stream: Option<TcpStream>,
+    /// When the last reconnect was attempted.
+    attempted: Option<Instant>,
+}
+
+impl Syslog {
+    fn connect(address: SyslogAddress) -> Result<Self, String> {
+        let error = |e: io::Error| format!("Cannot connect to syslog at {}: {}", address, e);
+        let transport = match &address {
+            #[cfg(unix)]
+            SyslogAddress::Unix(path) => {
+                let socket = UnixDatagram::unbound().map_err(error)?;
+                socket.connect(path).map_err(error)?;
+                Transport::Unix(socket)
+            }
+            #[cfg(not(unix))]
+            SyslogAddress::Unix(_) => return Err("unix syslog sockets are only supported on Unix".to_string()),
+            SyslogAddress::Udp(remote) => {
+                let remote = remote
+                    .to_socket_addrs()
+                    .map_err(error)?
+                    .next()
+                    .ok_or_else(|| format!("Cannot connect to syslog at {}: the host has no address", address))?;
+                // Lokal an die Adressfamilie des Ziels binden, sonst sind IPv6-Collectors unerreichbar
+                let local: SocketAddr = match remote {
+                    SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                    SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+                };
+                let socket = UdpSocket::bind(local).map_err(error)?;
+                socket.connect(remote).map_err(error)?;
+                Transport::Udp(socket)
+            }
+            SyslogAddress::Tcp(remote) => {
+                let stream = connect_tcp(remote).map_err(error)?;
+                let connection = TcpConnection { stream: Some(stream), attempted: None };
+                Transport::Tcp { address: remote.clone(), connection: Mutex::new(connection) }
+            }
+        };
+        let hostname = fs::read_to_string("/proc/sys/kernel/hostname")
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "-".to_string());
+        Ok(Syslog { transport, hostname })
+    }
+
+    fn send(&self, record: &Record<'_>) -> io::Result<()> {
+        let message = format!(
+            "<{}>1 {:.6} {} {} {} - - {}",
+            FACILITY_DAEMON * 8 + severity(record.level()),
+            jiff::Timestamp::now(),
+            self.hostname,
+            IDENTIFIER,
+            process::id(),
+            record.args()
+        );
+        match &self.transport {
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Tcp { address, connection } => {
+                let frame = format!("{} {}", message.len(), message);
+                let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(stream) = connection.stream.as_mut()
+                    && stream.write_all(frame.as_bytes()).is_ok()
+                {
+                    return Ok(());
+                }
+                connection.stream = None;
+                // Ein nicht erreichbarer Collector darf nicht jede Meldung um einen Verbindungsversuch verzögern
+                if connection.attempted.is_some_and(|attempted| attempted.elapsed() < SYSLOG_RECONNECT_EVERY) {
+                    return Err(io::Error::new(io::ErrorKind::NotConnected, "syslog collector is not connected"));
+                }
+                connection.attempted = Some(Instant::now());
+                let mut stream = connect_tcp(address)?;
+                stream.write_all(frame.as_bytes())?;
+                connection.stream = Some(stream);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Connects to the TCP syslog collector at `address`, trying each of its addresses for at most
+/// [`SYSLOG_TCP_TIMEOUT`]. Writes to the connection time out after the same time.
+fn connect_tcp(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "the host has no address");
+    for remote in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&remote, SYSLOG_TCP_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(SYSLOG_TCP_TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Appends log lines to `LOG_FILE`, rotating it by size and time.
+///
+/// Rotated files are renamed to `<file>.1` (newest) up to `<file>.<LOG_FILE_KEEP>`; older ones are overwritten.
+struct LogFile {
+    path: PathBuf,
+    /// Size in bytes after which the file is rotated, `0` for no limit.
+    max_size: u64,
+    /// Length of a rotation period in seconds.
+    period: Option<u64>,
+    keep: usize,
+    current: Mutex<OpenLogFile>,
+}
+
+struct OpenLogFile {
+    file: File,
+    size: u64,
+    /// Index of the rotation period the file was started in.
+    period: u64,
+}
+
+impl LogFile {
+    fn open(path: &str, config: &LogConfig) -> Result<Self, String> {
+        let path = PathBuf::from(path);
+        let (file, size, modified) = open_append(&path).map_err(|e| format!("Cannot open log file {}: {}", path.display(), e))?;
+        let period = config.file_rotate_every.secs();
+        Ok(LogFile {
+            max_size: config.file_max_size_mb.saturating_mul(1024 * 1024),
+            keep: config.file_keep,
+            current: Mutex::new(OpenLogFile { file, size, period: period.map_or(0, |secs| modified / secs) }),
+            period,
+            path,
+        })
+    }
+
+    fn write(&self, record: &Record<'_>) -> io::Result<()> {
+        let line = format!("[{:.0} {:<5} {}] {}\n", jiff::Timestamp::now(), record.level(), record.target(), record.args());
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let period = self.period.map_or(0, |secs| unix_now() / secs);
+        let full = self.max_size > 0 && current.size + line.len() as u64 > self.max_size;
+        if current.size > 0 && (full || period != current.period) {
+            self.rotate(&mut current)?;
+        }
+        current.period = period;
+        current.file.write_all(line.as_bytes())?;
+        current.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts the rotated files by one and starts a new, empty file.
+    fn rotate(&self, current: &mut OpenLogFile) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                // Lücken in der Nummerierung, z.B. nach manuellem Löschen, sind kein Fehler
+                let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        let (file, size, _) = open_append(&self.path)?;
+        current.file = file;
+        current.size = size;
+        Ok(())
+    }
+
+    /// Path of the `n`-th rotated file.
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+}
+
+/// Opens `path` for appending and returns it with its size and modification time (Unix seconds).
+fn open_append(path: &Path) -> io::Result<(File, u64, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or_else(unix_now, |since| since.as_secs());
+    Ok((file, metadata.len(), modified))
+}
+
+/// Sends structured entries via the native journald protocol.
+#[cfg(unix)]
+struct Journald {
+    socket: UnixDatagram,
+}
+
+#[cfg(unix)]
+impl Journald {
+    fn connect() -> Result<Self, String> {
+        let error = |e: io::Error| format!("Cannot connect to journald at {}: {}", JOURNALD_SOCKET, e);
+        let socket = UnixDatagram::unbound().map_err(error)?;
+        socket.connect(JOURNALD_SOCKET).map_err(error)?;
+        Ok(Journald { socket })
+    }
+
+    fn send(&self, record: &Record<'_>) -> io::Result<()> {
+        let mut entry = Vec::new();
+        field(&mut entry, "PRIORITY", &severity(record.level()).to_string());
+        field(&mut entry, "MESSAGE", &record.args().to_string());
+        field(&mut entry, "SYSLOG_IDENTIFIER", IDENTIFIER);
+        field(&mut entry, "SYSLOG_PID", &process::id().to_string());
+        field(&mut entry, "TARGET", record.target());
+        if let Some(file) = record.file() {
+            field(&mut entry, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            field(&mut entry, "CODE_LINE", &line.to_string());
+        }
+        self.socket.send(&entry).map(|_| ())
+    }
+}
+
+/// Appends a field to a journal entry; values with line breaks need the length-prefixed binary form.
+#[cfg(unix)]
+fn field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
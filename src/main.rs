@@ -1,113 +1,813 @@
-mod config;
-mod cloudflare;
-mod ip;
+mod cli;
+mod daemon;
+mod service;
 
-use std::error::Error;
-use cloudflare::Cloudflare;
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use clap::Parser;
+use cli::{Cli, Command, CtlCommand, Output, RecordCommand, ServiceCommand};
+use crondes::config::{ConfigSource, RecordConfig, RecordType};
+use crondes::control::socket::{self, StatusReply};
+use crondes::ip::{self, IpDetector};
+use crondes::provider::{DnsProvider, NewRecord, RecordInfo};
+use crondes::zonesync::{self, Change};
+use crondes::{Config, CrondesError, CycleReport, Updater, history, http, logging, provider};
 use log::{info, error};
-use std::sync::Arc;
-use tokio::sync::Notify;
-use std::time::Duration;
+use serde::Serialize;
 
 
-/// Checks all required credentials and IDs (API token, zone ID, record ID).
-/// If the record ID is invalid, logs all available records and returns an error.
-pub async fn check_all_info(cf: &Cloudflare) -> Result<(), Box<dyn Error>> {
-    if !cf.api_token_right().await? {
-        return Err("API token is invalid".into());
+/// Initializes the config from the given source, switches to the configured log backend and logs the values.
+pub fn init_and_log_config(source: &ConfigSource) -> Result<Config, CrondesError> {
+    if let Some(path) = &source.file {
+        info!("Loading config file {}", path);
     }
-    if !cf.zone_id_right().await? {
-        return Err("Zone ID is invalid".into());
+    let cfg = source.load().map_err(CrondesError::Config)?;
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    info!("Loaded config:");
+    for line in cfg.to_string().lines() {
+        info!("  {}", line);
+    }
+    Ok(cfg)
+}
+
+/// Creates the updaters for `cfg` loaded from `source`: one per job of the config file, each with the job's config
+/// loaded and logged, or a single one if there are no jobs or `source` already selects one.
+pub fn updaters(cfg: Config, source: ConfigSource) -> Result<Vec<Updater>, CrondesError> {
+    if cfg.jobs.is_empty() || source.job.is_some() {
+        return Ok(vec![Updater::new(cfg)?.reloadable(source)]);
+    }
+    cfg.jobs
+        .iter()
+        .map(|job| {
+            let source = source.job(&job.name);
+            info!("Job {}:", job.name);
+            init_and_log_config(&source).and_then(Updater::new).map(|updater| updater.reloadable(source))
+        })
+        .collect()
+}
+
+fn main() -> ExitCode {
+    logging::init();
+    info!("Logger initialized");
+    let cli = Cli::parse();
+    let output = cli.output;
+    let source = ConfigSource { file: cli.config, overrides: cli.overrides, job: cli.job };
+
+    // Windows-Dienste werden vom Service Control Manager gestartet und verwaltet
+    if cli.service {
+        return finish(service::run(source));
+    }
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Service { command: ServiceCommand::Install } => finish(service::install(&source)),
+        Command::Service { command: ServiceCommand::Uninstall } => finish(service::uninstall()),
+        Command::Service { command: ServiceCommand::Start } => finish(service::control(true)),
+        Command::Service { command: ServiceCommand::Stop } => finish(service::control(false)),
+        Command::SystemdUnit { watchdog_secs } => finish(systemd_unit(&source, watchdog_secs)),
+        // Steuerbefehle gehen an den laufenden Daemon und brauchen keine vollständige Config
+        Command::Ctl { socket, command } => finish(report(output, block_on(ctl(&source, socket, command, output)))),
+        // Verify meldet alle Probleme der Config auf einmal, statt beim ersten abzubrechen, und wie Apply und einmalige
+        // Updates die Art des Fehlers über den Exit-Code
+        Command::Verify => finish_code(block_on(verify(&source, output)).map(|()| ExitCode::SUCCESS)),
+        // Zonen und Records lassen sich auch ohne konfigurierte Records auflisten
+        Command::Zones => finish(report(output, block_on(zones(&source, output)))),
+        Command::Records { zone } => finish(report(output, block_on(records(&source, zone.as_deref(), output)))),
+        Command::Record { zone, command } => finish(block_on(record(&source, zone.as_deref(), command))),
+        // Plan und Apply vergleichen die Zone mit SYNC_FILE, ohne den Daemon zu starten
+        Command::Plan => finish(block_on(plan(&source))),
+        Command::Apply { auto_approve } => finish_code(block_on(apply(&source, auto_approve)).map(|()| ExitCode::SUCCESS)),
+        Command::ShowConfig { resolved } => finish(show_config(&source, resolved)),
+        Command::History { limit, record } => {
+            finish(init_and_log_config(&source).and_then(|cfg| history(&cfg, limit, record.as_deref())))
+        }
+        Command::UpdateOnce => finish_code(
+            init_and_log_config(&source)
+                .inspect_err(|e| print_update_failure(output, e))
+                .and_then(|cfg| block_on(update_once(cfg, source, output))),
+        ),
+        Command::Run => run(source, output),
+    }
+}
+
+/// Logs the error of a command that failed.
+///
+/// # Returns
+/// `0` on success, `1` on failure.
+fn finish<E: std::fmt::Display>(result: Result<(), E>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the error of a failed command as [`ErrorResult`] with `--output json`, so scripts always get a document.
+///
+/// # Returns
+/// `result` unchanged.
+fn report<T>(output: Output, result: Result<T, CrondesError>) -> Result<T, CrondesError> {
+    if output == Output::Json
+        && let Err(e) = &result
+    {
+        let _ = print_json(&ErrorResult { ok: false, error: e.to_string() });
+    }
+    result
+}
+
+/// Logs the error of a command that tells the class of its failures through the exit code, like `update-once`.
+///
+/// # Returns
+/// The exit code of the command, or the [exit code](CrondesError::exit_code) telling the class of the failure.
+fn finish_code(result: Result<ExitCode, CrondesError>) -> ExitCode {
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::from(e.exit_code())
+        }
     }
-    if !cf.record_id_right().await? {
-        error!("Record ID is invalid. Listing all available records:");
-        let records = cf.list_records().await?;
-        for rec in records {
-            error!("ID: {} | Name: {} | Type: {} | Content: {}", rec.id, rec.name, rec.record_type, rec.content);
+}
+
+/// Builds the async runtime, started only after the process was daemonized.
+fn runtime() -> Result<tokio::runtime::Runtime, CrondesError> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| CrondesError::Config(format!("Cannot start the async runtime: {}", e)))
+}
+
+/// Runs `future` to completion on a new async runtime.
+fn block_on<T>(future: impl Future<Output = Result<T, CrondesError>>) -> Result<T, CrondesError> {
+    runtime()?.block_on(future)
+}
+
+/// Runs the update daemon, or a single update cycle like `update-once` if `RUN_ONCE` is set.
+fn run(source: ConfigSource, output: Output) -> ExitCode {
+    let cfg = match init_and_log_config(&source) {
+        Ok(cfg) => cfg,
+        // Auch ohne gültige Konfiguration soll RUN_ONCE den Exit-Code und das JSON von update-once liefern
+        Err(e) if run_once(&source) => {
+            print_update_failure(output, &e);
+            return finish_code(Err(e));
         }
-        return Err("Record ID is invalid".into());
+        Err(e) => return finish(Err(e)),
+    };
+    if cfg.run_once {
+        return finish_code(block_on(update_once(cfg, source, output)));
+    }
+    // PID-Datei sperren und ggf. in den Hintergrund wechseln, bevor die Runtime Threads startet
+    let _pid_file = match daemon::prepare(&cfg) {
+        Ok(pid_file) => pid_file,
+        Err(e) => return finish(Err(e)),
+    };
+    finish(block_on(async { Updater::run_all(updaters(cfg, source)?).await }))
+}
+
+/// Tells whether `RUN_ONCE` is set for a configuration that failed to load or to set up logging.
+///
+/// Reads the command line and the environment first, then the config file if it can still be loaded.
+fn run_once(source: &ConfigSource) -> bool {
+    source
+        .value("RUN_ONCE")
+        .and_then(|value| value.parse().ok())
+        .or_else(|| source.load().ok().map(|cfg| cfg.run_once))
+        .unwrap_or(false)
+}
+
+/// Prints the loaded configuration, with `resolved` one line per value with the layer it comes from.
+fn show_config(source: &ConfigSource, resolved: bool) -> Result<(), CrondesError> {
+    let cfg = init_and_log_config(source)?;
+    if !resolved {
+        print!("{}", cfg);
+        return Ok(());
+    }
+    for value in source.resolve().map_err(CrondesError::Config)? {
+        println!("{}: {} ({})", value.key, value.value, value.origin);
     }
     Ok(())
 }
 
-/// Initializes the config from environment variables and logs the values.
-pub fn init_and_log_config() -> Result<config::Config, Box<dyn Error>> {
-    let cfg = config::Config::from_env()?;
-    info!("Loaded config:");
-    info!("  CF_API_TOKEN: {}", &cfg.cloudflare_api_token);
-    info!("  CF_ZONE_ID: {}", &cfg.cloudflare_zone_id);
-    info!("  CF_RECORD_ID: {}", &cfg.cloudflare_record_id);
-    info!("  CF_RECORD_NAME: {}", &cfg.cloudflare_record_name);
-    info!("  UPDATE_INTERVAL_SECS: {}", cfg.update_interval_secs);
-    Ok(cfg)
+/// Result of `zones`, `records` and `ctl` printed with `--output json` if they failed.
+#[derive(Serialize)]
+struct ErrorResult {
+    ok: bool,
+    error: String,
 }
 
-#[tokio::main]
-async fn main() {
-    env_logger::init();
-    info!("Logger initialized");
+/// Result of `update-once` printed with `--output json`.
+#[derive(Serialize)]
+struct UpdateResult<'a> {
+    ok: bool,
+    public_ip: Option<&'a str>,
+    updated: usize,
+    total: usize,
+    error: Option<String>,
+}
 
-    // 1. Config laden
-    let cfg = match init_and_log_config() {
+/// Result of `verify` printed with `--output json`.
+#[derive(Default, Serialize)]
+struct VerifyResult {
+    ok: bool,
+    config_problems: Vec<String>,
+    providers: Vec<ProviderResult>,
+}
+
+/// Credential check of a provider and the checks of its records, empty if the credentials were rejected.
+#[derive(Serialize)]
+struct ProviderResult {
+    /// Name of the job the provider belongs to, `None` without jobs.
+    job: Option<String>,
+    provider: String,
+    error: Option<String>,
+    records: Vec<RecordResult>,
+}
+
+/// Check of a single configured record.
+#[derive(Serialize)]
+struct RecordResult {
+    record: String,
+    id: String,
+    error: Option<String>,
+}
+
+/// Performs a single update cycle for all records configured in `cfg`, loaded from `source`, job by job.
+///
+/// Prints whether any record was updated, so the outcome is visible when driven by cron or systemd timers.
+///
+/// # Returns
+/// Exit code `1` if a record was updated, `0` if all records already pointed to the public IP.
+///
+/// # Errors
+/// Returns the most severe error of the failed jobs, whose [exit code](CrondesError::exit_code) tells the class of the
+/// failure; the others are logged. The remaining jobs are still updated and their results printed with `--output json`.
+async fn update_once(cfg: Config, source: ConfigSource, output: Output) -> Result<ExitCode, CrondesError> {
+    let mut updaters = updaters(cfg, source).inspect_err(|e| print_update_failure(output, e))?;
+    let mut reports = Vec::new();
+    let mut failure: Option<CrondesError> = None;
+    for updater in &mut updaters {
+        match updater.update_once().await {
+            Ok(report) => reports.push(report),
+            // Der Exit-Code soll den schwersten Fehler aller Jobs melden, nicht den ersten
+            Err(e) => match failure.take() {
+                Some(first) if first.exit_code() <= e.exit_code() => {
+                    error!("{}", e);
+                    failure = Some(first);
+                }
+                Some(first) => {
+                    error!("{}", first);
+                    failure = Some(e);
+                }
+                None => failure = Some(e),
+            },
+        }
+    }
+    // Die Jobs können verschiedene öffentliche IPs erkennen, z.B. für IPv4 und IPv6
+    let mut public_ips: Vec<&str> = Vec::new();
+    for report in &reports {
+        if !public_ips.contains(&report.public_ip.as_str()) {
+            public_ips.push(&report.public_ip);
+        }
+    }
+    let report = CycleReport {
+        public_ip: public_ips.join(", "),
+        updated: reports.iter().map(|report| report.updated).sum(),
+        total: reports.iter().map(|report| report.total).sum(),
+    };
+    if let Some(e) = failure {
+        if output == Output::Json {
+            let result = UpdateResult {
+                ok: false,
+                public_ip: (!reports.is_empty()).then_some(report.public_ip.as_str()),
+                updated: report.updated,
+                total: report.total,
+                error: Some(e.to_string()),
+            };
+            print_json(&result)?;
+        }
+        return Err(e);
+    }
+    info!("Update completed successfully.");
+    if output == Output::Json {
+        let result = UpdateResult {
+            ok: true,
+            public_ip: Some(&report.public_ip),
+            updated: report.updated,
+            total: report.total,
+            error: None,
+        };
+        print_json(&result)?;
+    } else if report.updated > 0 {
+        println!("Updated {} of {} records to {}", report.updated, report.total, report.public_ip);
+    } else {
+        println!("No update needed, all {} records already point to {}", report.total, report.public_ip);
+    }
+    for updater in &mut updaters {
+        updater.wait_for_propagation().await;
+    }
+    Ok(if report.updated > 0 { ExitCode::from(1) } else { ExitCode::SUCCESS })
+}
+
+/// Prints the result of a failed `update-once` with `--output json`.
+fn print_update_failure(output: Output, error: &CrondesError) {
+    if output == Output::Json {
+        let _ = print_json(&UpdateResult { ok: false, public_ip: None, updated: 0, total: 0, error: Some(error.to_string()) });
+    }
+}
+
+/// Checks the whole configuration offline, then the credentials and every configured record, and prints the result
+/// of each check.
+///
+/// All configuration problems are printed at once. The online checks need a valid configuration and are skipped
+/// otherwise; a provider with invalid credentials fails all of its records. With jobs, the providers and records of
+/// every job are checked.
+async fn verify(source: &ConfigSource, output: Output) -> Result<(), CrondesError> {
+    let mut result = VerifyResult::default();
+    let outcome = check_online(source, &mut result).await;
+    result.ok = outcome.is_ok();
+    if output == Output::Json {
+        print_json(&result)?;
+    } else {
+        print_verify(&result);
+    }
+    outcome
+}
+
+/// Runs the checks of [`verify`] and collects their results in `result`.
+async fn check_online(source: &ConfigSource, result: &mut VerifyResult) -> Result<(), CrondesError> {
+    let cfg = match source.check() {
         Ok(cfg) => cfg,
-        Err(e) => {
-            error!("Config error: {}", e);
-            return;
+        Err(problems) => {
+            result.config_problems = problems.iter().map(|p| p.to_string()).collect();
+            return Err(CrondesError::Config(format!("{} configuration problem(s), skipped the online checks", problems.len())));
         }
     };
-    // 2. Cloudflare-Objekt erstellen
-    let cf = Cloudflare::new(cfg);
-
-    // 3. Scheduler starten
-    let shutdown = Arc::new(Notify::new());
-    let shutdown_signal = shutdown.clone();
-    let interval = Duration::from_secs(cf.config.update_interval_secs);
-
-    tokio::spawn(async move {
-        let mut run_count = 0;
-        loop {
-            run_count += 1;
-            info!("--- Update loop iteration #{} ---", run_count);
-            info!("Starting update cycle...");
-            if let Err(e) = update(&cf).await {
-                error!("Update failed: {}. Shutting down scheduler.", e);
-                shutdown_signal.notify_waiters();
-                break;
-            } else {
-                info!("Update completed successfully.");
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    // Mit Jobs werden die Provider und Records jedes Jobs geprüft, die oberste Ebene hat keine eigenen
+    let mut updaters = Vec::new();
+    if cfg.jobs.is_empty() {
+        updaters.push((None, Updater::new(cfg)?));
+    } else {
+        for job in &cfg.jobs {
+            let cfg = source.job(&job.name).load().map_err(CrondesError::Config)?;
+            updaters.push((Some(job.name.clone()), Updater::new(cfg)?));
+        }
+    }
+    let mut failed = 0;
+    let mut exit_code = 0;
+    let mut total = 0;
+    // Eine gescheiterte Prüfung der Zugangsdaten ist auch ohne Records ein Fehler
+    let mut credentials_failed = None;
+    for (job, updater) in &updaters {
+        for (provider, records) in updater.providers() {
+            total += records.len();
+            let mut checked = ProviderResult { job: job.clone(), provider: provider.name().to_string(), error: None, records: Vec::new() };
+            if let Err(e) = provider.check_credentials().await {
+                checked.error = Some(e.to_string());
+                result.providers.push(checked);
+                failed += records.len();
+                exit_code = CrondesError::severest(exit_code, e.exit_code());
+                credentials_failed.get_or_insert(e);
+                continue;
             }
-            info!("Waiting {} seconds until next iteration...", interval.as_secs());
-            tokio::select! {
-                _ = tokio::time::sleep(interval) => {},
-                _ = shutdown_signal.notified() => break,
+            for mut record in records.iter().cloned() {
+                if record.id.is_empty() && provider.uses_record_ids() {
+                    match provider.find_record_id(&record.name, record.record_type).await {
+                        Ok(id) => record.id = id,
+                        Err(e) => {
+                            let error = Some(e.to_string());
+                            checked.records.push(RecordResult { record: record.label().to_string(), id: String::new(), error });
+                            failed += 1;
+                            exit_code = CrondesError::severest(exit_code, e.exit_code());
+                            continue;
+                        }
+                    }
+                }
+                let error = match provider.check_record(&record).await {
+                    Ok(()) => None,
+                    Err(e) => {
+                        failed += 1;
+                        exit_code = CrondesError::severest(exit_code, e.exit_code());
+                        Some(e.to_string())
+                    }
+                };
+                checked.records.push(RecordResult { record: record.label().to_string(), id: record.id, error });
             }
+            result.providers.push(checked);
         }
-    });
-
-    // Warten auf Shutdown (z.B. durch Fehler oder externes Signal)
-    shutdown.notified().await;
-    info!("Scheduler stopped. Exiting.");
-}
-
-/// Führt einen vollständigen Update-Zyklus durch: check_all_info und ggf. IP-Update.
-async fn update(cf: &Cloudflare) -> Result<(), Box<dyn Error>> {
-    info!("Checking Cloudflare credentials and IDs...");
-    check_all_info(cf).await?;
-    let current_dns_ip = cf.current_ip().await?;
-    info!("Current DNS IP: {}", current_dns_ip);
-    let public_ip = crate::ip::fetch_public_ip().await?;
-    info!("Public IP: {}", public_ip);
-    if current_dns_ip != public_ip {
-        info!("Updating DNS record: {} → {}", current_dns_ip, public_ip);
-        match cf.update_ip(&public_ip).await {
-            Ok(response_body) => info!("DNS record updated successfully. Response: {}", response_body),
-            Err(e) => {
-                error!("Error updating DNS record: {}", e);
-                return Err(e);
+    }
+    if failed > 0 {
+        return Err(CrondesError::Records { failed, total, exit_code });
+    }
+    credentials_failed.map_or(Ok(()), Err)
+}
+
+/// Prints the results of [`verify`] as one line per check.
+fn print_verify(result: &VerifyResult) {
+    if result.config_problems.is_empty() {
+        println!("Config: OK");
+    }
+    for problem in &result.config_problems {
+        println!("Config: FAILED - {}", problem);
+    }
+    for provider in &result.providers {
+        let name = match &provider.job {
+            Some(job) => format!("[{}] {}", job, provider.provider),
+            None => provider.provider.clone(),
+        };
+        match &provider.error {
+            Some(e) => println!("{} credentials: FAILED - {}", name, e),
+            None => println!("{} credentials: OK", name),
+        }
+        for record in &provider.records {
+            let label = if record.id.is_empty() { record.record.clone() } else { format!("{} ({})", record.record, record.id) };
+            match &record.error {
+                Some(e) => println!("{}: FAILED - {}", label, e),
+                None => println!("{}: OK", label),
             }
         }
+    }
+}
+
+/// Refuses commands working with the provider and zone of a single job if the config file has jobs and `--job` doesn't
+/// select one of them, since the top level of the file has no records of its own.
+fn require_job(cfg: &Config) -> Result<(), CrondesError> {
+    if cfg.jobs.is_empty() {
+        return Ok(());
+    }
+    let jobs = cfg.jobs.iter().map(|job| job.name.as_str()).collect::<Vec<_>>().join(", ");
+    Err(CrondesError::Config(format!("The config file has the jobs {}, select one of them with --job", jobs)))
+}
+
+/// Prints the zones accessible with the credentials of `DNS_PROVIDER` as table or JSON.
+async fn zones(source: &ConfigSource, output: Output) -> Result<(), CrondesError> {
+    let cfg = source.load_for_listing(None).map_err(CrondesError::Config)?;
+    require_job(&cfg)?;
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    let client = http::build_client(&cfg.http)
+        .map_err(|e| CrondesError::Config(format!("Failed to create HTTP client: {}", e)))?;
+    let zones = provider::from_config(cfg.provider, &cfg, client)?.list_zones().await?;
+    if output == Output::Json {
+        print_json(&zones)?;
     } else {
-        info!("No update needed. Public IP unchanged: {}", public_ip);
+        print_table(&["ID", "NAME"], zones.into_iter().map(|z| vec![z.id, z.name]).collect());
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Prints all DNS records visible to the provider as table or JSON.
+///
+/// With several providers, those addressing records by name only are skipped since they can't list records.
+async fn records(source: &ConfigSource, zone: Option<&str>, output: Output) -> Result<(), CrondesError> {
+    let mut cfg = source.load_for_listing(zone).map_err(CrondesError::Config)?;
+    require_job(&cfg)?;
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    if zone.is_some() {
+        // Nur DNS_PROVIDER kennt die angegebene Zone
+        cfg.records.retain(|r| r.provider.is_none_or(|p| p == cfg.provider));
+    }
+    let updater = Updater::new(cfg)?;
+    let multiple = updater.providers().count() > 1;
+    let mut rows = Vec::new();
+    let mut listed = Vec::new();
+    for (provider, _) in updater.providers().filter(|(p, _)| !multiple || p.uses_record_ids()) {
+        for rec in provider.list_records().await? {
+            if output == Output::Json {
+                let mut value = serde_json::to_value(&rec).map_err(|e| CrondesError::Config(e.to_string()))?;
+                value["provider"] = provider.name().into();
+                listed.push(value);
+            } else if multiple {
+                rows.push(vec![provider.name().to_string(), rec.id, rec.name, rec.record_type, rec.content]);
+            } else {
+                rows.push(vec![rec.id, rec.name, rec.record_type, rec.content]);
+            }
+        }
+    }
+    if output == Output::Json {
+        print_json(&listed)?;
+    } else if multiple {
+        print_table(&["PROVIDER", "ID", "NAME", "TYPE", "CONTENT"], rows);
+    } else {
+        print_table(&["ID", "NAME", "TYPE", "CONTENT"], rows);
+    }
+    Ok(())
+}
+
+/// Creates, deletes or changes a single record of `DNS_PROVIDER` in its configured zone or in `zone`.
+async fn record(source: &ConfigSource, zone: Option<&str>, command: RecordCommand) -> Result<(), CrondesError> {
+    let cfg = source.load_for_listing(zone).map_err(CrondesError::Config)?;
+    require_job(&cfg)?;
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    let client = http::build_client(&cfg.http)
+        .map_err(|e| CrondesError::Config(format!("Failed to create HTTP client: {}", e)))?;
+    let provider = provider::from_config(cfg.provider, &cfg, client)?;
+    match command {
+        RecordCommand::Create { name, content, record_type, ttl, proxied } => {
+            let record = NewRecord { name, record_type, content, ttl, proxied: proxied.then_some(true) };
+            let created = provider.create_record(&record).await?;
+            println!("Created {} {} with content {} (ID {})", created.record_type, created.name, created.content, created.id);
+        }
+        RecordCommand::Delete { record, record_type } => {
+            let record = find_record(provider.as_ref(), &record, record_type).await?;
+            provider.delete_record(&record).await?;
+            println!("Deleted {} ({})", record.label(), record.id);
+        }
+        RecordCommand::Set { record, content, record_type } => {
+            let record = find_record(provider.as_ref(), &record, record_type).await?;
+            println!("{}", provider.set_content(&record, &content).await?);
+        }
+    }
+    Ok(())
+}
+
+/// Looks up the record with the ID or name `record` at `provider`.
+///
+/// Providers addressing records by name only take the name as given, with `record_type` or `A` as type. Otherwise
+/// `record_type` narrows down records of the same name, and the type of the listed record is kept.
+///
+/// # Errors
+/// Returns an error if no record or several records match.
+async fn find_record(provider: &dyn DnsProvider, record: &str, record_type: Option<RecordType>) -> Result<RecordConfig, CrondesError> {
+    if !provider.uses_record_ids() {
+        let record_type = record_type.unwrap_or_default();
+        return Ok(RecordConfig { id: String::new(), name: record.to_string(), record_type, ..RecordConfig::default() });
+    }
+    let records = provider.list_records().await?;
+    if let Some(found) = records.iter().find(|r| r.id == record) {
+        return Ok(found_record(found.clone()));
+    }
+    let mut matches: Vec<_> = records
+        .into_iter()
+        .filter(|r| r.name.eq_ignore_ascii_case(record))
+        .filter(|r| record_type.is_none_or(|t| r.record_type.parse() == Ok(t)))
+        .collect();
+    match matches.len() {
+        0 => Err(CrondesError::Config(format!("No record with ID or name {} found", record))),
+        1 => Ok(found_record(matches.remove(0))),
+        n => Err(CrondesError::Config(format!("{} records named {} found, pass the record ID or --type instead", n, record))),
+    }
+}
+
+/// Turns a listed record into the [`RecordConfig`] the provider methods take, keeping its ID, name and type.
+fn found_record(found: RecordInfo) -> RecordConfig {
+    // Andere Typen wie CNAME werden nur über die ID angesprochen, der Typ spielt dort keine Rolle
+    let record_type = found.record_type.parse().unwrap_or_default();
+    RecordConfig { id: found.id, name: found.name, record_type, ..RecordConfig::default() }
+}
+
+/// Compares the zone of `DNS_PROVIDER` with `SYNC_FILE`.
+///
+/// The public IP is only detected if a record in the file asks for it.
+///
+/// # Returns
+/// The provider together with the changes needed to make the zone match the file.
+async fn sync_plan(source: &ConfigSource) -> Result<(Box<dyn DnsProvider>, Vec<Change>), CrondesError> {
+    let cfg = source.load().map_err(CrondesError::Config)?;
+    require_job(&cfg)?;
+    logging::configure(&cfg.log).map_err(CrondesError::Config)?;
+    if cfg.sync_file.is_empty() {
+        return Err(CrondesError::Config("SYNC_FILE is not set".to_string()));
+    }
+    let desired = zonesync::read(&cfg.sync_file).map_err(CrondesError::Config)?;
+    let client = http::build_client(&cfg.http)
+        .map_err(|e| CrondesError::Config(format!("Failed to create HTTP client: {}", e)))?;
+    let public_ip = if desired.iter().any(|r| r.content == zonesync::PUBLIC_IP) {
+        let public_ip = IpDetector::from_config(&cfg, &client).detect().await?;
+        if !cfg.ip.allow_private {
+            ip::check_public(&public_ip)?;
+        }
+        public_ip
+    } else {
+        String::new()
+    };
+    let provider = provider::from_config(cfg.provider, &cfg, client)?;
+    let existing = provider.list_records().await?;
+    let changes = zonesync::plan(&desired, &existing, &public_ip, cfg.sync_prune);
+    Ok((provider, changes))
+}
+
+/// Prints the changes needed to make the zone match `SYNC_FILE`.
+async fn plan(source: &ConfigSource) -> Result<(), CrondesError> {
+    let (_, changes) = sync_plan(source).await?;
+    print_plan(&changes);
+    Ok(())
+}
+
+/// Prints the changes needed to make the zone match `SYNC_FILE` and carries them out, after asking for confirmation
+/// unless `auto_approve` is set.
+///
+/// # Errors
+/// Returns an error if the changes can't be planned, confirmation is needed but stdin is no terminal, or a change fails.
+async fn apply(source: &ConfigSource, auto_approve: bool) -> Result<(), CrondesError> {
+    let (provider, changes) = sync_plan(source).await?;
+    print_plan(&changes);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    if !auto_approve && !confirm("Apply these changes?")? {
+        println!("Apply cancelled, nothing was changed.");
+        return Ok(());
+    }
+    let mut failed = 0;
+    let mut exit_code = 0;
+    for change in &changes {
+        match zonesync::apply(provider.as_ref(), change).await {
+            Ok(()) => println!("Done: {}", change),
+            Err(e) => {
+                println!("Failed: {} - {}", change, e);
+                failed += 1;
+                exit_code = CrondesError::severest(exit_code, e.exit_code());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(CrondesError::Records { failed, total: changes.len(), exit_code });
+    }
+    Ok(())
+}
+
+/// Prints `changes` like a diff, `+` for records to create, `~` to update and `-` to delete, colored if stdout is a
+/// terminal and `NO_COLOR` is not set.
+fn print_plan(changes: &[Change]) {
+    if changes.is_empty() {
+        println!("No changes, the zone matches SYNC_FILE.");
+        return;
+    }
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut counts = [0; 3];
+    for change in changes {
+        let (index, sign, code) = match change {
+            Change::Create(_) => (0, '+', "32"),
+            Change::Update { .. } => (1, '~', "33"),
+            Change::Delete(_) => (2, '-', "31"),
+        };
+        counts[index] += 1;
+        if color {
+            println!("\x1b[{}m{} {}\x1b[0m", code, sign, change);
+        } else {
+            println!("{} {}", sign, change);
+        }
+    }
+    println!("Plan: {} to create, {} to update, {} to delete.", counts[0], counts[1], counts[2]);
+}
+
+/// Asks `question` on the terminal.
+///
+/// # Returns
+/// `true` if the answer is `y` or `yes`.
+///
+/// # Errors
+/// Returns an error if stdin is no terminal or cannot be read.
+fn confirm(question: &str) -> Result<bool, CrondesError> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(CrondesError::Config("Not asking for confirmation without a terminal, pass --auto-approve".to_string()));
+    }
+    print!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).map_err(|e| CrondesError::Config(format!("Cannot read the answer: {}", e)))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Prints `value` as pretty-printed JSON.
+fn print_json<T: Serialize>(value: &T) -> Result<(), CrondesError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| CrondesError::Config(e.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Prints `rows` as table with left-aligned columns under `headers`.
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+/// Prints the last `limit` IP changes from the history file, optionally only those of `record`.
+fn history(cfg: &Config, limit: usize, record: Option<&str>) -> Result<(), CrondesError> {
+    if cfg.history_file.is_empty() {
+        return Err(CrondesError::Config("HISTORY_FILE is not set".to_string()));
+    }
+    let entries = history::read(&cfg.history_file).map_err(CrondesError::Config)?;
+    let entries: Vec<_> = entries
+        .into_iter()
+        .filter(|e| record.is_none_or(|r| e.record.eq_ignore_ascii_case(r)))
+        .collect();
+    if entries.is_empty() {
+        println!("No IP changes recorded yet.");
+    }
+    for entry in &entries[entries.len().saturating_sub(limit)..] {
+        println!("{} | {} | {} → {} | {}", entry.time, entry.record, entry.old_ip, entry.new_ip, entry.response);
+    }
+    Ok(())
+}
+
+/// Sends `command` to the daemon listening on `socket`, or on the control socket of the configuration.
+///
+/// With `--output json`, `status` prints the reply of the daemon as is and other commands its answer as `message`.
+async fn ctl(source: &ConfigSource, socket: Option<String>, command: CtlCommand, output: Output) -> Result<(), CrondesError> {
+    let socket = match socket.or_else(|| source.value("CONTROL_SOCKET")) {
+        Some(socket) => socket,
+        None => source.load().map_err(CrondesError::Config)?.control_socket,
+    };
+    if socket.is_empty() {
+        return Err(CrondesError::Config("No control socket configured, set CONTROL_SOCKET or pass --socket".to_string()));
+    }
+    let command = match command {
+        CtlCommand::Status => socket::Command::Status,
+        CtlCommand::Trigger => socket::Command::Trigger,
+        CtlCommand::Reload => socket::Command::Reload,
+    };
+    let answer = socket::request(&socket, command).await.map_err(CrondesError::Config)?;
+    if command != socket::Command::Status {
+        if output == Output::Json {
+            print_json(&serde_json::json!({ "message": answer }))?;
+        } else {
+            println!("{}", answer);
+        }
+        return Ok(());
+    }
+    let reply: StatusReply = serde_json::from_str(&answer)
+        .map_err(|e| CrondesError::Config(format!("Invalid status from the daemon: {}", e)))?;
+    if output == Output::Json {
+        return print_json(&reply);
+    }
+    let status = reply.status;
+    println!("Paused: {}", if status.paused { "yes" } else { "no" });
+    println!("Offline: {}", if status.offline { "yes" } else { "no" });
+    println!("Public IP: {}", status.public_ip.as_deref().unwrap_or("unknown"));
+    println!("Last success: {}", if status.last_success == 0 { "never".to_string() } else { timestamp(status.last_success) });
+    println!("Next cycle: {}", status.next_cycle.map_or_else(|| "not scheduled".to_string(), timestamp));
+    println!("Consecutive failures: {}", status.consecutive_failures);
+    for record in &reply.records {
+        let name = if record.name.is_empty() { &record.id } else { &record.name };
+        println!("{} | {} | {}", record.provider, name, record.ip.as_deref().unwrap_or("unknown"));
+    }
+    Ok(())
+}
+
+/// Formats a Unix time in RFC 3339 format (UTC).
+fn timestamp(secs: u64) -> String {
+    jiff::Timestamp::from_second(secs as i64).map_or_else(|_| secs.to_string(), |t| t.to_string())
+}
+
+/// Prints a unit file running this binary as systemd service, with the config file of `source` if there is one.
+///
+/// `Type=notify` makes systemd wait for the first successful update cycle, and `WatchdogSec` restarts a daemon
+/// that stopped pinging the watchdog.
+fn systemd_unit(source: &ConfigSource, watchdog_secs: u64) -> Result<(), CrondesError> {
+    let exe = std::env::current_exe().map_err(|e| CrondesError::Config(format!("Cannot determine the path of crondes: {}", e)))?;
+    let mut exec = exe.display().to_string();
+    if let Some(path) = &source.file {
+        let path = std::fs::canonicalize(path).map_err(|e| CrondesError::Config(format!("Config file {}: {}", path, e)))?;
+        exec.push_str(&format!(" --config {}", path.display()));
+    }
+    for (key, value) in &source.overrides {
+        // systemd trennt Argumente an Leerzeichen, außer in Anführungszeichen
+        if value.contains(char::is_whitespace) {
+            exec.push_str(&format!(" --set \"{}={}\"", key, value));
+        } else {
+            exec.push_str(&format!(" --set {}={}", key, value));
+        }
+    }
+    if let Some(job) = &source.job {
+        exec.push_str(&format!(" --job {}", job));
+    }
+    exec.push_str(" run");
+    print!(
+        "[Unit]
+Description=crondes dynamic DNS updater
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=notify
+ExecStart={exec}
+ExecReload=/bin/kill -HUP $MAINPID
+EnvironmentFile=-/etc/crondes/crondes.env
+Restart=on-failure
+RestartSec=30
+WatchdogSec={watchdog_secs}
+NoNewPrivileges=yes
+ProtectSystem=strict
+ProtectHome=yes
+PrivateTmp=yes
+# Writable locations for STATE_FILE, HISTORY_FILE and LOG_FILE: /var/lib/crondes and /var/log/crondes
+StateDirectory=crondes
+LogsDirectory=crondes
+
+[Install]
+WantedBy=multi-user.target
+"
+    );
+    Ok(())
+}
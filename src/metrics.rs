@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Process wide metrics, exposed in the Prometheus text format on `/metrics`.
+pub static METRICS: Metrics = Metrics::new();
+
+/// Counters and gauges describing the update loop.
+pub struct Metrics {
+    cycles: AtomicU64,
+    failed_cycles: AtomicU64,
+    record_updates: AtomicU64,
+    record_update_failures: AtomicU64,
+    rate_limited: AtomicU64,
+    /// Per job: the Unix time of its last successful update cycle and its most recently detected public IP.
+    jobs: Mutex<BTreeMap<String, (u64, Option<String>)>>,
+    ip_service_failures: Mutex<BTreeMap<String, u64>>,
+    /// Per DNS provider: whether its last check succeeded and how often it failed.
+    providers: Mutex<BTreeMap<String, (bool, u64)>>,
+}
+
+impl Metrics {
+    /// Creates an empty set of metrics.
+    pub const fn new() -> Self {
+        Metrics {
+            cycles: AtomicU64::new(0),
+            failed_cycles: AtomicU64::new(0),
+            record_updates: AtomicU64::new(0),
+            record_update_failures: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            jobs: Mutex::new(BTreeMap::new()),
+            ip_service_failures: Mutex::new(BTreeMap::new()),
+            providers: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records the outcome of a finished update cycle of `job`, empty for the unnamed job.
+    pub fn cycle_finished(&self, job: &str, success: bool) {
+        self.cycles.fetch_add(1, Ordering::Relaxed);
+        if success {
+            self.jobs.lock().unwrap().entry(job.to_string()).or_default().0 = unix_now();
+        } else {
+            self.failed_cycles.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the Unix time of the last successful update cycle of `job`, or `0` if there was none yet.
+    pub fn last_success(&self, job: &str) -> u64 {
+        self.jobs.lock().unwrap().get(job).map_or(0, |(last_success, _)| *last_success)
+    }
+
+    /// Records a DNS record that was successfully written.
+    pub fn record_updated(&self) {
+        self.record_updates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a DNS record that could not be brought up to date.
+    pub fn record_update_failed(&self) {
+        self.record_update_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a request the provider API rejected with a rate limit.
+    pub fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Stores the most recently detected public IP address of `job`.
+    pub fn set_public_ip(&self, job: &str, ip: &str) {
+        self.jobs.lock().unwrap().entry(job.to_string()).or_default().1 = Some(ip.to_string());
+    }
+
+    /// Returns the most recently detected public IP address of `job`, if any.
+    pub fn public_ip(&self, job: &str) -> Option<String> {
+        self.jobs.lock().unwrap().get(job).and_then(|(_, ip)| ip.clone())
+    }
+
+    /// Records a failed lookup at the given public IP service.
+    pub fn ip_service_failed(&self, service: &str) {
+        *self.ip_service_failures.lock().unwrap().entry(service.to_string()).or_insert(0) += 1;
+    }
+
+    /// Records whether all records of the given DNS provider could be brought up to date.
+    pub fn provider_checked(&self, provider: &str, success: bool) {
+        let mut providers = self.providers.lock().unwrap();
+        let (up, failures) = providers.entry(provider.to_string()).or_insert((false, 0));
+        *up = success;
+        if !success {
+            *failures += 1;
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        counter(&mut out, "crondes_update_cycles_total", "Number of finished update cycles.", self.cycles.load(Ordering::Relaxed));
+        counter(&mut out, "crondes_update_cycles_failed_total", "Number of failed update cycles.", self.failed_cycles.load(Ordering::Relaxed));
+        counter(&mut out, "crondes_record_updates_total", "Number of successful DNS record writes.", self.record_updates.load(Ordering::Relaxed));
+        counter(&mut out, "crondes_record_update_failures_total", "Number of records that failed to update.", self.record_update_failures.load(Ordering::Relaxed));
+        counter(&mut out, "crondes_rate_limited_requests_total", "Number of provider API requests rejected with a rate limit.", self.rate_limited.load(Ordering::Relaxed));
+        // Jeder Job hat seine eigenen Zyklen und IP-Quellen, das Label ist beim unbenannten Job leer
+        let jobs = self.jobs.lock().unwrap();
+        let _ = writeln!(out, "# HELP crondes_last_success_timestamp_seconds Unix time of the last successful update cycle per job.");
+        let _ = writeln!(out, "# TYPE crondes_last_success_timestamp_seconds gauge");
+        for (job, (last_success, _)) in jobs.iter() {
+            let _ = writeln!(out, "crondes_last_success_timestamp_seconds{{job=\"{}\"}} {}", escape(job), last_success);
+        }
+        let _ = writeln!(out, "# HELP crondes_public_ip_info Most recently detected public IP address per job.");
+        let _ = writeln!(out, "# TYPE crondes_public_ip_info gauge");
+        for (job, (_, ip)) in jobs.iter() {
+            if let Some(ip) = ip {
+                let _ = writeln!(out, "crondes_public_ip_info{{job=\"{}\",ip=\"{}\"}} 1", escape(job), escape(ip));
+            }
+        }
+        let failures = self.ip_service_failures.lock().unwrap();
+        let _ = writeln!(out, "# HELP crondes_ip_service_failures_total Number of failed lookups per public IP service.");
+        let _ = writeln!(out, "# TYPE crondes_ip_service_failures_total counter");
+        for (service, count) in failures.iter() {
+            let _ = writeln!(out, "crondes_ip_service_failures_total{{service=\"{}\"}} {}", escape(service), count);
+        }
+        let providers = self.providers.lock().unwrap();
+        let _ = writeln!(out, "# HELP crondes_provider_up Whether the last update cycle of a DNS provider succeeded.");
+        let _ = writeln!(out, "# TYPE crondes_provider_up gauge");
+        for (provider, (up, _)) in providers.iter() {
+            let _ = writeln!(out, "crondes_provider_up{{provider=\"{}\"}} {}", escape(provider), u8::from(*up));
+        }
+        let _ = writeln!(out, "# HELP crondes_provider_failures_total Number of failed update cycles per DNS provider.");
+        let _ = writeln!(out, "# TYPE crondes_provider_failures_total counter");
+        for (provider, (_, failures)) in providers.iter() {
+            let _ = writeln!(out, "crondes_provider_failures_total{{provider=\"{}\"}} {}", escape(provider), failures);
+        }
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Appends a counter with its `HELP` and `TYPE` lines.
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escapes a label value for the Prometheus text format.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Returns the current Unix time in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Returns the current time in RFC 3339 format (UTC), e.g. `2024-05-01T12:00:00Z`.
+pub fn now_rfc3339() -> String {
+    jiff::Timestamp::from_second(unix_now() as i64).unwrap_or(jiff::Timestamp::UNIX_EPOCH).to_string()
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use log::{debug, warn};
+use crate::error::CrondesError;
+
+/// Time a nameserver may take to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads records directly from their authoritative nameservers, used for `RECORD_CHECK=dns`.
+///
+/// The nameservers of a record are looked up once through the system resolver and queried without recursion
+/// and caching afterwards, so an answer always reflects the content of the zone and not a cached copy.
+pub struct AuthoritativeLookup {
+    system: TokioResolver,
+    /// Resolvers pointing to the authoritative nameservers, per record name.
+    authoritative: Mutex<HashMap<String, TokioResolver>>,
+}
+
+impl AuthoritativeLookup {
+    /// Creates a lookup discovering nameservers through the system resolver, or through Google Public DNS
+    /// if the system configuration cannot be read.
+    pub fn new() -> Self {
+        let system = TokioResolver::builder_tokio()
+            .unwrap_or_else(|e| {
+                warn!("Could not read the system DNS configuration, using Google Public DNS: {}", e);
+                TokioResolver::builder_with_config(ResolverConfig::google(), TokioConnectionProvider::default())
+            })
+            .build();
+        AuthoritativeLookup { system, authoritative: Mutex::new(HashMap::new()) }
+    }
+
+    /// Queries the authoritative nameservers of `name` for its A record.
+    ///
+    /// # Returns
+    /// - `Ok(ip)` with the first address of the record.
+    /// - `Err` if the nameservers cannot be found or don't return an address for `name`.
+    pub async fn current_ip(&self, name: &str) -> Result<String, CrondesError> {
+        let fqdn = format!("{}.", name.trim_end_matches('.'));
+        let resolver = self.resolver(&fqdn).await?;
+        let lookup = resolver
+            .ipv4_lookup(fqdn.as_str())
+            .await
+            .map_err(|e| CrondesError::Dns(format!("Authoritative lookup of {} failed: {}", name, e)))?;
+        lookup
+            .iter()
+            .next()
+            .map(|a| a.0.to_string())
+            .ok_or_else(|| CrondesError::Dns(format!("{} has no A record", name)))
+    }
+
+    /// Returns a resolver querying the authoritative nameservers of `fqdn`, looking them up on the first call.
+    async fn resolver(&self, fqdn: &str) -> Result<TokioResolver, CrondesError> {
+        if let Some(resolver) = self.authoritative.lock().unwrap().get(fqdn) {
+            return Ok(resolver.clone());
+        }
+        let servers = self.nameservers(fqdn).await?;
+        let group = NameServerConfigGroup::from_ips_clear(&servers, 53, true);
+        let mut builder = TokioResolver::builder_with_config(
+            ResolverConfig::from_parts(None, vec![], group),
+            TokioConnectionProvider::default(),
+        );
+        builder.options_mut().timeout = QUERY_TIMEOUT;
+        builder.options_mut().recursion_desired = false;
+        builder.options_mut().cache_size = 0;
+        let resolver = builder.build();
+        self.authoritative.lock().unwrap().insert(fqdn.to_string(), resolver.clone());
+        Ok(resolver)
+    }
+
+    /// Finds the addresses of the nameservers of the zone containing `fqdn`.
+    ///
+    /// Walks up the name one label at a time until a name with NS records is found, which is the zone apex.
+    async fn nameservers(&self, fqdn: &str) -> Result<Vec<IpAddr>, CrondesError> {
+        let mut zone = fqdn;
+        let hosts = loop {
+            match self.system.ns_lookup(zone).await {
+                Ok(lookup) if lookup.iter().next().is_some() => break lookup.iter().map(|ns| ns.0.to_string()).collect::<Vec<_>>(),
+                _ => match zone.split_once('.') {
+                    Some((_, parent)) if parent.trim_end_matches('.').contains('.') => zone = parent,
+                    _ => return Err(CrondesError::Dns(format!("No nameservers found for {}", fqdn.trim_end_matches('.')))),
+                },
+            }
+        };
+        let mut servers = Vec::new();
+        for host in &hosts {
+            match self.system.lookup_ip(host.as_str()).await {
+                Ok(lookup) => servers.extend(lookup.iter()),
+                Err(e) => debug!("Could not resolve nameserver {}: {}", host, e),
+            }
+        }
+        if servers.is_empty() {
+            return Err(CrondesError::Dns(format!("Nameservers of {} ({}) could not be resolved", zone, hosts.join(", "))));
+        }
+        debug!("Nameservers of {}: {} ({:?})", fqdn, hosts.join(", "), servers);
+        Ok(servers)
+    }
+}
+
+impl Default for AuthoritativeLookup {
+    fn default() -> Self {
+        AuthoritativeLookup::new()
+    }
+}
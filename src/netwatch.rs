@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use crate::control::DaemonControl;
+
+/// Starts watching for network changes that may have changed the public IP, triggering an update cycle of every job of
+/// `control` for each of them.
+///
+/// On Linux the kernel reports them via rtnetlink: IPv4 addresses added to or removed from the interface of the
+/// default route, and the default route itself changing (e.g. after a PPPoE reconnect). Bursts of events are
+/// combined into a single notification.
+///
+/// # Errors
+/// Returns an error if the netlink socket cannot be opened, or on other platforms, where only polling is available.
+pub fn spawn(control: Arc<DaemonControl>) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        let socket = linux::open().map_err(|e| format!("Cannot subscribe to network changes: {}", e))?;
+        tokio::spawn(linux::watch(socket, control));
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = control;
+        Err("Network change events are only supported on Linux".to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs;
+    use std::io;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use log::{debug, info, warn};
+    use netlink_sys::protocols::NETLINK_ROUTE;
+    use netlink_sys::{AsyncSocket, AsyncSocketExt, SocketAddr, TokioSocket};
+    use crate::control::DaemonControl;
+
+    const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+    const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+    const RTM_NEWADDR: u16 = 20;
+    const RTM_DELADDR: u16 = 21;
+    const RTM_NEWROUTE: u16 = 24;
+    const RTM_DELROUTE: u16 = 25;
+    const AF_INET: u8 = 2;
+    const RT_SCOPE_HOST: u8 = 254;
+    const RT_TABLE_MAIN: u8 = 254;
+    /// Size of `struct nlmsghdr`.
+    const HEADER_LEN: usize = 16;
+
+    /// Time to wait for further events before the update is triggered.
+    const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+    pub(super) fn open() -> io::Result<TokioSocket> {
+        let mut socket = TokioSocket::new(NETLINK_ROUTE)?;
+        socket.socket_mut().bind(&SocketAddr::new(0, RTMGRP_IPV4_IFADDR | RTMGRP_IPV4_ROUTE))?;
+        Ok(socket)
+    }
+
+    pub(super) async fn watch(socket: TokioSocket, control: Arc<DaemonControl>) {
+        loop {
+            let change = match socket.recv_from_full().await {
+                Ok((messages, _)) => relevant_change(&messages),
+                Err(e) => {
+                    warn!("Stopped watching for network changes: {}", e);
+                    return;
+                }
+            };
+            let Some(change) = change else { continue };
+            // Eine Einwahl erzeugt mehrere Events kurz nacheinander, die zusammen nur ein Update auslösen
+            while let Ok(Ok(_)) = tokio::time::timeout(SETTLE_TIME, socket.recv_from_full()).await {}
+            info!("Network change detected ({}), starting an update cycle", change);
+            control.trigger();
+        }
+    }
+
+    /// Describes the first change in a batch of netlink messages that may have changed the public IP.
+    fn relevant_change(mut messages: &[u8]) -> Option<String> {
+        let default_interface = default_route_interface();
+        while messages.len() >= HEADER_LEN {
+            let len = u32::from_ne_bytes(messages[0..4].try_into().ok()?) as usize;
+            let kind = u16::from_ne_bytes(messages[4..6].try_into().ok()?);
+            if len < HEADER_LEN || len > messages.len() {
+                return None;
+            }
+            let payload = &messages[HEADER_LEN..len];
+            debug!("Netlink message type {} ({} bytes)", kind, len);
+            match kind {
+                // struct ifaddrmsg: family, prefixlen, flags, scope, index
+                RTM_NEWADDR | RTM_DELADDR if payload.len() >= 8 && payload[0] == AF_INET && payload[3] != RT_SCOPE_HOST => {
+                    let index = u32::from_ne_bytes(payload[4..8].try_into().ok()?);
+                    if default_interface.as_ref().is_none_or(|(default, _)| *default == index) {
+                        let name = default_interface.map_or_else(|| format!("interface #{}", index), |(_, name)| name);
+                        let action = if kind == RTM_NEWADDR { "added to" } else { "removed from" };
+                        return Some(format!("address {} {}", action, name));
+                    }
+                }
+                // struct rtmsg: family, dst_len, src_len, tos, table, ...
+                RTM_NEWROUTE | RTM_DELROUTE if payload.len() >= 5 && payload[0] == AF_INET && payload[1] == 0 && payload[4] == RT_TABLE_MAIN => {
+                    return Some(if kind == RTM_NEWROUTE { "default route added" } else { "default route removed" }.to_string());
+                }
+                _ => {}
+            }
+            // Nachrichten sind auf 4 Bytes ausgerichtet
+            messages = &messages[len.next_multiple_of(4).min(messages.len())..];
+        }
+        None
+    }
+
+    /// Returns the index and name of the interface carrying the IPv4 default route, if there is one.
+    fn default_route_interface() -> Option<(u32, String)> {
+        let routes = fs::read_to_string("/proc/net/route").ok()?;
+        let name = routes.lines().skip(1).find_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            (columns.len() > 7 && columns[1] == "00000000" && columns[7] == "00000000").then(|| columns[0].to_string())
+        })?;
+        let index = fs::read_to_string(format!("/sys/class/net/{}/ifindex", name)).ok()?.trim().parse().ok()?;
+        Some((index, name))
+    }
+}
@@ -0,0 +1,219 @@
+mod command;
+mod discord;
+mod gotify;
+mod heartbeat;
+mod pushover;
+mod slack;
+mod smtp;
+mod webhook;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::time::Duration;
+use log::{error, info, warn};
+use serde::Serialize;
+use crate::config::Config;
+use crate::error::CrondesError;
+use crate::metrics::now_rfc3339;
+pub use command::CommandHook;
+pub use discord::Discord;
+pub use gotify::Gotify;
+pub use heartbeat::Heartbeat;
+pub use pushover::Pushover;
+pub use slack::Slack;
+pub use smtp::Smtp;
+pub use webhook::Webhook;
+
+/// Something that happened in the update loop and is worth telling the user about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A record was changed to a new IP address.
+    IpChanged { record: String, old_ip: String, new_ip: String },
+    /// A component of the update cycle, e.g. `IPv4 detection` or a provider, has failed `failures` cycles in a row
+    /// since `since`, reaching `NOTIFY_FAILURE_THRESHOLD`.
+    UpdateFailed { component: String, since: String, failures: u32, error: String },
+    /// A component whose failures were notified succeeded again after `failures` failed cycles in a row.
+    Recovered { component: String, failures: u32 },
+    /// Public resolvers didn't return the new IP of an updated record in time.
+    PropagationFailed { record: String, new_ip: String, error: String },
+    /// A record was changed outside of crondes from `expected` to `found` and is not updated to `new_ip`, because
+    /// `FORCE_OVERWRITE` is disabled.
+    Conflict { record: String, expected: String, found: String, new_ip: String },
+}
+
+/// An [`Event`] together with the time it happened, as sent to the notifiers.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    #[serde(flatten)]
+    pub event: Event,
+    /// Time of the event in RFC 3339 format (UTC).
+    pub timestamp: String,
+}
+
+impl Notification {
+    /// Creates a notification for an event that happened just now.
+    pub fn new(event: Event) -> Self {
+        Notification { event, timestamp: now_rfc3339() }
+    }
+
+    /// Returns a short title for the event, e.g. for a message subject.
+    pub fn title(&self) -> &'static str {
+        match self.event {
+            Event::IpChanged { .. } => "IP address changed",
+            Event::UpdateFailed { .. } => "DNS update failed",
+            Event::Recovered { .. } => "DNS updates recovered",
+            Event::PropagationFailed { .. } => "DNS propagation failed",
+            Event::Conflict { .. } => "DNS record changed outside of crondes",
+        }
+    }
+
+    /// Returns `true` if the event reports a problem, so notifiers can raise its priority.
+    pub fn is_failure(&self) -> bool {
+        matches!(self.event, Event::UpdateFailed { .. } | Event::PropagationFailed { .. } | Event::Conflict { .. })
+    }
+
+    /// Returns a one-line human readable description of the event.
+    pub fn message(&self) -> String {
+        match &self.event {
+            Event::IpChanged { record, old_ip, new_ip } => format!("{} changed from {} to {}", record, old_ip, new_ip),
+            Event::UpdateFailed { component, since, failures, error } => {
+                format!("Updates failing since {}: {} failed {} cycles in a row: {}", since, component, failures, error)
+            }
+            Event::Recovered { component, failures } => format!("{} succeeded again after {} failed cycles", component, failures),
+            Event::PropagationFailed { record, new_ip, error } => format!("{} does not resolve to {}: {}", record, new_ip, error),
+            Event::Conflict { record, expected, found, new_ip } => {
+                format!("{} was changed from {} to {} outside of crondes and is not updated to {}", record, expected, found, new_ip)
+            }
+        }
+    }
+
+    /// Fills in a message template. Supported placeholders are `{title}`, `{message}`, `{timestamp}`,
+    /// `{record}`, `{old_ip}`, `{new_ip}` and `{error}`; placeholders not set by the event are left empty.
+    /// For a conflict, `{old_ip}` is the content found in the record.
+    pub fn render(&self, template: &str) -> String {
+        let (record, old_ip, new_ip, error) = match &self.event {
+            Event::IpChanged { record, old_ip, new_ip } => (record.as_str(), old_ip.as_str(), new_ip.as_str(), ""),
+            Event::UpdateFailed { error, .. } => ("", "", "", error.as_str()),
+            Event::Recovered { .. } => ("", "", "", ""),
+            Event::PropagationFailed { record, new_ip, error } => (record.as_str(), "", new_ip.as_str(), error.as_str()),
+            Event::Conflict { record, found, new_ip, .. } => (record.as_str(), found.as_str(), new_ip.as_str(), ""),
+        };
+        template
+            .replace("{title}", self.title())
+            .replace("{message}", &self.message())
+            .replace("{timestamp}", &self.timestamp)
+            .replace("{record}", record)
+            .replace("{old_ip}", old_ip)
+            .replace("{new_ip}", new_ip)
+            .replace("{error}", error)
+    }
+
+    /// Returns the event details not already part of [`Notification::message`] as `(name, value)` pairs
+    /// for notifiers with structured messages.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match &self.event {
+            Event::IpChanged { record, old_ip, new_ip } => vec![
+                ("Record", record.clone()),
+                ("Old IP", old_ip.clone()),
+                ("New IP", new_ip.clone()),
+            ],
+            Event::PropagationFailed { record, new_ip, .. } => vec![("Record", record.clone()), ("New IP", new_ip.clone())],
+            Event::UpdateFailed { component, since, .. } => vec![("Component", component.clone()), ("Failing since", since.clone())],
+            Event::Recovered { component, .. } => vec![("Component", component.clone())],
+            Event::Conflict { record, expected, found, new_ip } => vec![
+                ("Record", record.clone()),
+                ("Last written", expected.clone()),
+                ("Found", found.clone()),
+                ("New IP", new_ip.clone()),
+            ],
+        }
+    }
+}
+
+/// A channel notifications are delivered to.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Returns a label identifying the notifier in logs.
+    fn name(&self) -> &str;
+
+    /// Delivers a single notification.
+    ///
+    /// # Errors
+    /// Returns an error if the notification could not be delivered.
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError>;
+}
+
+/// All configured notifiers. Events are delivered to every notifier at once.
+pub struct Notifiers {
+    notifiers: Vec<Box<dyn Notifier>>,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl Notifiers {
+    /// Creates a new [`Notifiers`] delivering to the given notifiers and pinging `heartbeat` after every cycle.
+    pub fn new(notifiers: Vec<Box<dyn Notifier>>, heartbeat: Option<Heartbeat>) -> Self {
+        Notifiers { notifiers, heartbeat }
+    }
+
+    /// Creates the notifiers described by the `notify` section, using `client` for all requests.
+    pub fn from_config(config: &Config, client: &reqwest::Client) -> Self {
+        let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+        for url in &config.notify.webhook_urls {
+            notifiers.push(Box::new(Webhook::new(url.expose(), client.clone())));
+        }
+        for url in &config.notify.discord_urls {
+            notifiers.push(Box::new(Discord::new(url.expose(), client.clone())));
+        }
+        for url in &config.notify.slack_urls {
+            notifiers.push(Box::new(Slack::new(url.expose(), client.clone())));
+        }
+        if !config.notify.gotify_url.is_empty() {
+            notifiers.push(Box::new(Gotify::new(&config.notify.gotify_url, config.notify.gotify_token.clone(), client.clone())));
+        }
+        if !config.notify.pushover_token.is_empty() {
+            notifiers.push(Box::new(Pushover::new(
+                config.notify.pushover_token.clone(),
+                config.notify.pushover_user.clone(),
+                client.clone(),
+            )));
+        }
+        if !config.hooks.on_change_cmd.is_empty() || !config.hooks.on_failure_cmd.is_empty() {
+            notifiers.push(Box::new(CommandHook::new(&config.hooks)));
+        }
+        if !config.notify.smtp_host.is_empty() {
+            match Smtp::new(&config.notify, Duration::from_secs(config.http.timeout_secs)) {
+                Ok(smtp) => notifiers.push(Box::new(smtp)),
+                Err(e) => error!("Email notifications disabled: {}", e),
+            }
+        }
+        let heartbeat = reqwest::Url::parse(config.notify.heartbeat_url.expose())
+            .ok()
+            .map(|url| Heartbeat::new(url, client.clone()));
+        Notifiers::new(notifiers, heartbeat)
+    }
+
+    /// Pings the heartbeat URL, if configured, with the outcome of an update cycle.
+    pub async fn heartbeat(&self, success: bool, message: &str) {
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.ping(success, message).await;
+        }
+    }
+
+    /// Delivers `event` to all notifiers.
+    ///
+    /// Delivery failures are logged but not returned, a broken notifier must never stop DNS updates.
+    pub async fn send(&self, event: Event) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        let notification = Notification::new(event);
+        info!("Sending notification: {}", notification.message());
+        join_all(self.notifiers.iter().map(|notifier| async {
+            if let Err(e) = notifier.notify(&notification).await {
+                warn!("Notifier {} failed: {}", notifier.name(), e);
+            }
+        }))
+        .await;
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use crate::config::HooksConfig;
+use crate::error::CrondesError;
+use crate::hooks::run_command;
+use super::{Event, Notification, Notifier};
+
+/// Runs user-provided shell commands on IP changes (`ON_CHANGE_CMD`) and failures (`ON_FAILURE_CMD`).
+///
+/// The commands get the event details as the environment variables `OLD_IP`, `NEW_IP`,
+/// `RECORD_NAME`, `STATUS` (`changed`, `failed` or `propagation_failed`) and `ERROR`.
+pub struct CommandHook {
+    on_change: String,
+    on_failure: String,
+    timeout: Duration,
+}
+
+impl CommandHook {
+    /// Creates a new [`CommandHook`] from the `hooks` section.
+    pub fn new(config: &HooksConfig) -> Self {
+        CommandHook {
+            on_change: config.on_change_cmd.clone(),
+            on_failure: config.on_failure_cmd.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for CommandHook {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let (command, env) = match &notification.event {
+            Event::IpChanged { record, old_ip, new_ip } => (
+                &self.on_change,
+                vec![("OLD_IP", old_ip.as_str()), ("NEW_IP", new_ip.as_str()), ("RECORD_NAME", record.as_str()), ("STATUS", "changed")],
+            ),
+            Event::UpdateFailed { component, error, .. } => {
+                (&self.on_failure, vec![("STATUS", "failed"), ("COMPONENT", component.as_str()), ("ERROR", error.as_str())])
+            }
+            Event::PropagationFailed { record, new_ip, error } => (
+                &self.on_failure,
+                vec![("NEW_IP", new_ip.as_str()), ("RECORD_NAME", record.as_str()), ("STATUS", "propagation_failed"), ("ERROR", error.as_str())],
+            ),
+            Event::Conflict { record, found, new_ip, .. } => (
+                &self.on_failure,
+                vec![("OLD_IP", found.as_str()), ("NEW_IP", new_ip.as_str()), ("RECORD_NAME", record.as_str()), ("STATUS", "conflict")],
+            ),
+            Event::Recovered { .. } => return Ok(()),
+        };
+        if command.is_empty() {
+            return Ok(());
+        }
+        run_command(command, &env, self.timeout).await
+    }
+}
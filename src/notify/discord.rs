@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use serde_json::json;
+use crate::error::CrondesError;
+use super::{Event, Notification, Notifier};
+
+/// Posts notifications to a Discord channel webhook as an embed.
+pub struct Discord {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Discord {
+    /// Creates a new [`Discord`] notifier for the webhook `url` using `client`.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Discord { url: url.into(), client }
+    }
+}
+
+#[async_trait]
+impl Notifier for Discord {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let color = match notification.event {
+            Event::IpChanged { .. } => 0x3498db,
+            Event::UpdateFailed { .. } => 0xe74c3c,
+            Event::Recovered { .. } => 0x2ecc71,
+            Event::PropagationFailed { .. } => 0xe67e22,
+            Event::Conflict { .. } => 0xf39c12,
+        };
+        let fields: Vec<_> = notification
+            .fields()
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": value, "inline": true }))
+            .collect();
+        let payload = json!({
+            "username": "crondes",
+            "embeds": [{
+                "title": notification.title(),
+                "description": notification.message(),
+                "color": color,
+                "fields": fields,
+                "timestamp": notification.timestamp,
+            }],
+        });
+        // Die URL enthält das Token des Webhooks und darf nicht mit dem Fehler geloggt werden
+        self.client
+            .post(&self.url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest::Error::without_url)?;
+        Ok(())
+    }
+}
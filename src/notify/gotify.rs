@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use serde_json::json;
+use crate::config::Secret;
+use crate::error::CrondesError;
+use super::{Notification, Notifier};
+
+/// Sends notifications to a Gotify server.
+pub struct Gotify {
+    url: String,
+    token: Secret,
+    client: reqwest::Client,
+}
+
+impl Gotify {
+    /// Creates a new [`Gotify`] notifier for the server at `url` using the application `token`.
+    pub fn new(url: &str, token: Secret, client: reqwest::Client) -> Self {
+        Gotify { url: format!("{}/message", url.trim_end_matches('/')), token, client }
+    }
+}
+
+#[async_trait]
+impl Notifier for Gotify {
+    fn name(&self) -> &str {
+        "gotify"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let payload = json!({
+            "title": notification.title(),
+            "message": notification.message(),
+            "priority": if notification.is_failure() { 8 } else { 5 },
+        });
+        self.client
+            .post(&self.url)
+            .header("X-Gotify-Key", self.token.expose())
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use log::warn;
+use reqwest::Url;
+
+/// Dead man's switch: pings a monitoring URL after every update cycle.
+///
+/// Supports healthchecks.io style URLs (success pings the URL, failure pings `<url>/fail`,
+/// both with the message as body) and Uptime Kuma push URLs (`/api/push/<token>`,
+/// with `status=up|down` and `msg` as query parameters).
+pub struct Heartbeat {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl Heartbeat {
+    /// Creates a new [`Heartbeat`] pinging `url` using `client`.
+    pub fn new(url: Url, client: reqwest::Client) -> Self {
+        Heartbeat { url, client }
+    }
+
+    /// Reports the outcome of an update cycle. Failures to reach the monitor are only logged.
+    pub async fn ping(&self, success: bool, message: &str) {
+        let request = if self.url.path().contains("/api/push/") {
+            let mut url = self.url.clone();
+            let query: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(key, _)| key != "status" && key != "msg")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query)
+                .append_pair("status", if success { "up" } else { "down" })
+                .append_pair("msg", message);
+            self.client.get(url)
+        } else if success {
+            self.client.post(self.url.clone()).body(message.to_string())
+        } else {
+            let url = format!("{}/fail", self.url.as_str().trim_end_matches('/'));
+            self.client.post(url).body(message.to_string())
+        };
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => {}
+            Err(e) => warn!("Heartbeat ping failed: {}", e),
+        }
+    }
+}
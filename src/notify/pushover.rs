@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use serde_json::json;
+use crate::config::Secret;
+use crate::error::CrondesError;
+use super::{Notification, Notifier};
+
+const PUSHOVER_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// Sends notifications via Pushover.
+pub struct Pushover {
+    token: Secret,
+    user: Secret,
+    client: reqwest::Client,
+}
+
+impl Pushover {
+    /// Creates a new [`Pushover`] notifier with the application `token` and the `user` (or group) key.
+    pub fn new(token: Secret, user: Secret, client: reqwest::Client) -> Self {
+        Pushover { token, user, client }
+    }
+}
+
+#[async_trait]
+impl Notifier for Pushover {
+    fn name(&self) -> &str {
+        "pushover"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let payload = json!({
+            "token": self.token.expose(),
+            "user": self.user.expose(),
+            "title": notification.title(),
+            "message": notification.message(),
+            "priority": if notification.is_failure() { 1 } else { 0 },
+        });
+        self.client.post(PUSHOVER_URL).json(&payload).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use serde_json::json;
+use crate::error::CrondesError;
+use super::{Notification, Notifier};
+
+/// Posts notifications to a Slack incoming webhook using Block Kit.
+pub struct Slack {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Slack {
+    /// Creates a new [`Slack`] notifier for the incoming webhook `url` using `client`.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Slack { url: url.into(), client }
+    }
+}
+
+#[async_trait]
+impl Notifier for Slack {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let fields: Vec<_> = notification
+            .fields()
+            .into_iter()
+            .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) }))
+            .collect();
+        let mut blocks = vec![
+            json!({ "type": "header", "text": { "type": "plain_text", "text": notification.title() } }),
+            json!({ "type": "section", "text": { "type": "plain_text", "text": notification.message() } }),
+        ];
+        if !fields.is_empty() {
+            blocks.push(json!({ "type": "section", "fields": fields }));
+        }
+        blocks.push(json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": notification.timestamp }] }));
+        // `text` ist der Fallback für Push-Benachrichtigungen
+        let payload = json!({
+            "text": format!("{}: {}", notification.title(), notification.message()),
+            "blocks": blocks,
+        });
+        // Die URL enthält das Token des Webhooks und darf nicht mit dem Fehler geloggt werden
+        self.client
+            .post(&self.url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest::Error::without_url)?;
+        Ok(())
+    }
+}
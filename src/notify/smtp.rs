@@ -0,0 +1,76 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use crate::config::{NotifyConfig, SmtpTls};
+use crate::error::CrondesError;
+use super::{Notification, Notifier};
+
+/// Sends notifications as plain text emails via SMTP.
+pub struct Smtp {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject: String,
+    body: String,
+}
+
+impl Smtp {
+    /// Creates a new [`Smtp`] notifier from the `smtp_*` settings of the `notify` section.
+    ///
+    /// # Errors
+    /// Returns an error if an address is invalid or the TLS settings cannot be set up for the host.
+    pub fn new(config: &NotifyConfig, timeout: Duration) -> Result<Self, CrondesError> {
+        let smtp_error = |e: lettre::transport::smtp::Error| CrondesError::Config(format!("Invalid SMTP settings: {}", e));
+        let mut builder = match config.smtp_tls {
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host).map_err(smtp_error)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host).map_err(smtp_error)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+        };
+        builder = builder.port(config.smtp_port.unwrap_or(config.smtp_tls.default_port())).timeout(Some(timeout));
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                config.smtp_username.clone(),
+                config.smtp_password.expose().to_string(),
+            ));
+        }
+        let parse = |addr: &str| {
+            addr.parse::<Mailbox>()
+                .map_err(|e| CrondesError::Config(format!("Invalid email address {}: {}", addr, e)))
+        };
+        Ok(Smtp {
+            transport: builder.build(),
+            from: parse(&config.smtp_from)?,
+            to: config.smtp_to.iter().map(|addr| parse(addr)).collect::<Result<_, _>>()?,
+            subject: config.smtp_subject.clone(),
+            body: config.smtp_body.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Smtp {
+    fn name(&self) -> &str {
+        "smtp"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(notification.render(&self.subject))
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .body(notification.render(&self.body))
+            .map_err(|e| CrondesError::Config(format!("Failed to build email: {}", e)))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| CrondesError::Notify(format!("Failed to send email: {}", e)))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use crate::error::CrondesError;
+use super::{Notification, Notifier};
+
+/// Generic HTTP webhook: POSTs every notification as JSON to a URL.
+///
+/// The payload contains the `event` type (`ip_changed`, `update_failed` or `recovered`),
+/// the event specific fields (`record`, `old_ip`, `new_ip`, `error`, `failures`) and a `timestamp`.
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    /// Creates a new [`Webhook`] posting to `url` using `client`.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Webhook { url: url.into(), client }
+    }
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), CrondesError> {
+        // Die URL enthält das Token des Webhooks und darf nicht mit dem Fehler geloggt werden
+        self.client
+            .post(&self.url)
+            .json(notification)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest::Error::without_url)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use futures::future::join_all;
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use log::{debug, info};
+use crate::config::PropagationConfig;
+
+/// Time between two rounds of queries.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time a single resolver may take to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Checks that an updated record is served with its new IP by public resolvers.
+///
+/// Every resolver is queried directly without caching, so the check sees what clients of that
+/// resolver get, including cached old answers until their TTL expires.
+pub struct PropagationCheck {
+    resolvers: Vec<(IpAddr, TokioResolver)>,
+    timeout: Duration,
+}
+
+impl PropagationCheck {
+    /// Creates the check from the `propagation` section, `None` if it is disabled.
+    pub fn from_config(config: &PropagationConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let resolvers = config
+            .resolvers
+            .iter()
+            .map(|&server| {
+                let group = NameServerConfigGroup::from_ips_clear(&[server], 53, true);
+                let mut builder = TokioResolver::builder_with_config(
+                    ResolverConfig::from_parts(None, vec![], group),
+                    TokioConnectionProvider::default(),
+                );
+                builder.options_mut().timeout = QUERY_TIMEOUT;
+                builder.options_mut().attempts = 1;
+                builder.options_mut().cache_size = 0;
+                (server, builder.build())
+            })
+            .collect();
+        Some(PropagationCheck { resolvers, timeout: Duration::from_secs(config.timeout_secs) })
+    }
+
+    /// Queries all resolvers until each of them returns `ip` for `name`, asking for A records for an IPv4 address and
+    /// for AAAA records for an IPv6 address.
+    ///
+    /// # Returns
+    /// - `Ok(elapsed)` with the time it took until all resolvers returned `ip`.
+    /// - `Err` naming the resolvers that still answered differently when the timeout passed.
+    pub async fn wait(&self, name: &str, ip: IpAddr) -> Result<Duration, String> {
+        let start = Instant::now();
+        let fqdn = format!("{}.", name.trim_end_matches('.'));
+        let fqdn = fqdn.as_str();
+        let mut pending: Vec<&(IpAddr, TokioResolver)> = self.resolvers.iter().collect();
+        loop {
+            let answers = join_all(pending.iter().map(|&(server, client)| async move {
+                let addresses: Result<Vec<IpAddr>, _> = match ip {
+                    IpAddr::V4(_) => client.ipv4_lookup(fqdn).await.map(|lookup| lookup.iter().map(|a| IpAddr::V4(a.0)).collect()),
+                    IpAddr::V6(_) => client.ipv6_lookup(fqdn).await.map(|lookup| lookup.iter().map(|a| IpAddr::V6(a.0)).collect()),
+                };
+                match addresses {
+                    Ok(addresses) if addresses.contains(&ip) => None,
+                    Ok(addresses) => Some(format!("{}: {}", server, addresses.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "))),
+                    Err(e) => Some(format!("{}: {}", server, e)),
+                }
+            }))
+            .await;
+            let mut still_pending = Vec::new();
+            let mut mismatches = Vec::new();
+            for (resolver, answer) in pending.into_iter().zip(answers) {
+                if let Some(answer) = answer {
+                    debug!("[{}] {}", name, answer);
+                    mismatches.push(answer);
+                    still_pending.push(resolver);
+                }
+            }
+            pending = still_pending;
+            if pending.is_empty() {
+                let elapsed = start.elapsed();
+                info!("[{}] Public resolvers return {} after {} seconds", name, ip, elapsed.as_secs());
+                return Ok(elapsed);
+            }
+            if start.elapsed() + POLL_INTERVAL > self.timeout {
+                return Err(format!("not propagated after {} seconds ({})", self.timeout.as_secs(), mismatches.join("; ")));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
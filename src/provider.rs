@@ -0,0 +1,253 @@
+pub mod cloudflare;
+pub mod desec;
+pub mod duckdns;
+pub mod dyndns2;
+pub mod linode;
+pub mod namecheap;
+pub mod porkbun;
+pub mod rfc2136;
+pub mod vultr;
+
+use async_trait::async_trait;
+use hickory_resolver::TokioResolver;
+use serde::Serialize;
+use crate::config::{Config, ProviderKind, RecordConfig, RecordType};
+use crate::error::CrondesError;
+use cloudflare::Cloudflare;
+use desec::Desec;
+use duckdns::DuckDns;
+use dyndns2::Dyndns2;
+use linode::Linode;
+use namecheap::Namecheap;
+use porkbun::Porkbun;
+use rfc2136::Rfc2136;
+use vultr::Vultr;
+
+/// Creates the DNS provider of the given kind from its settings in `config`, using `client` for all requests.
+///
+/// # Errors
+/// Returns an error if the provider settings cannot be used, e.g. an invalid TSIG key.
+pub fn from_config(kind: ProviderKind, config: &Config, client: reqwest::Client) -> Result<Box<dyn DnsProvider>, CrondesError> {
+    Ok(match kind {
+        ProviderKind::Cloudflare => Box::new(Cloudflare::new(config.clone(), client)),
+        ProviderKind::Porkbun => Box::new(Porkbun::new(config.porkbun.clone(), client)),
+        ProviderKind::Desec => Box::new(Desec::new(config.desec.clone(), client)),
+        ProviderKind::DuckDns => Box::new(DuckDns::new(config.duckdns.clone(), client)),
+        ProviderKind::NoIp => Box::new(Dyndns2::no_ip(&config.noip, client)),
+        ProviderKind::Dyndns2 => Box::new(Dyndns2::new(&config.dyndns2, client)),
+        ProviderKind::Rfc2136 => Box::new(Rfc2136::new(config.rfc2136.clone()).map_err(CrondesError::Config)?),
+        ProviderKind::Linode => Box::new(Linode::new(config.linode.clone(), client)),
+        ProviderKind::Vultr => Box::new(Vultr::new(config.vultr.clone(), client)),
+        ProviderKind::Namecheap => Box::new(Namecheap::new(config.namecheap.clone(), client)),
+    })
+}
+
+/// Common interface for DNS backends that crondes can keep in sync with the public IP.
+///
+/// The scheduler only talks to providers through this trait, so adding a new backend
+/// means adding a new implementation here without touching the update loop.
+#[async_trait]
+pub trait DnsProvider: Send + Sync {
+    /// Human readable name of the provider, used in log output.
+    fn name(&self) -> &'static str;
+
+    /// Whether records are addressed by ID.
+    ///
+    /// Providers returning `false` address records by name only, so names are not resolved
+    /// to IDs at startup and [`DnsProvider::list_records`] is not needed for updates.
+    fn uses_record_ids(&self) -> bool {
+        true
+    }
+
+    /// Checks that the configured credentials are valid.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the provider is ready to be used.
+    /// - `Err` describing the first problem found otherwise.
+    async fn check_credentials(&self) -> Result<(), CrondesError>;
+
+    /// Checks that the given record exists and is accessible.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the record can be read and written.
+    /// - `Err` if the record is invalid or the request fails.
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError>;
+
+    /// Reads the IP address currently stored in the given DNS record.
+    ///
+    /// # Returns
+    /// - `Ok(ip)` with the current IP as a string if successful.
+    /// - `Err` if the request fails or the IP cannot be found.
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError>;
+
+    /// Writes a new IP address into the given DNS record.
+    ///
+    /// # Returns
+    /// - `Ok(response)` with a short description of the provider response if the update was successful.
+    /// - `Err` if the update failed.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError>;
+
+    /// Replaces only the content of the given DNS record, as `crondes record set` does. Unlike
+    /// [`DnsProvider::update_ip`], nothing configured for automatic updates like TTL, comment or tags is written.
+    ///
+    /// The default writes through [`DnsProvider::update_ip`], for providers whose updates only change the content.
+    ///
+    /// # Returns
+    /// - `Ok(response)` with a short description of the provider response if the update was successful.
+    /// - `Err` if the update failed.
+    async fn set_content(&self, record: &RecordConfig, content: &str) -> Result<String, CrondesError> {
+        self.update_ip(record, content).await
+    }
+
+    /// Whether [`DnsProvider::update_batch`] writes several records in a single request.
+    ///
+    /// Providers returning `false` get their records written one by one with [`DnsProvider::update_ip`].
+    fn supports_batch(&self) -> bool {
+        false
+    }
+
+    /// Writes `new_ip` into all `records` in a single atomic request. Only used if [`DnsProvider::supports_batch`]
+    /// returns `true`.
+    ///
+    /// # Returns
+    /// - `Ok(responses)` with a short description of the provider response per record, in the order of `records`.
+    /// - `Err` if the request failed, in which case none of the records was changed.
+    async fn update_batch(&self, _records: &[RecordConfig], _new_ip: &str) -> Result<Vec<String>, CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support batch updates", self.name())))
+    }
+
+    /// Whether [`DnsProvider::current_ip`] already answers from DNS instead of a provider API,
+    /// so `RECORD_CHECK=dns` doesn't save any requests.
+    fn reads_from_dns(&self) -> bool {
+        false
+    }
+
+    /// Whether the record is served through a proxy, so resolvers return the proxy's addresses instead of its content.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the record is proxied.
+    /// - `Err` if the request fails.
+    async fn is_proxied(&self, _record: &RecordConfig) -> Result<bool, CrondesError> {
+        Ok(false)
+    }
+
+    /// Lists all DNS records visible to the provider.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<RecordInfo>)` with all records if successful.
+    /// - `Err` if the request fails or the response is invalid.
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError>;
+
+    /// Lists the zones (domains) accessible with the configured credentials.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ZoneInfo>)` with all zones if successful.
+    /// - `Err` if the request fails or the provider can't list zones.
+    async fn list_zones(&self) -> Result<Vec<ZoneInfo>, CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support listing zones", self.name())))
+    }
+
+    /// Creates a new DNS record.
+    ///
+    /// # Returns
+    /// - `Ok(RecordInfo)` with the created record if successful.
+    /// - `Err` if the request fails or the provider can't create records.
+    async fn create_record(&self, _record: &NewRecord) -> Result<RecordInfo, CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support creating records", self.name())))
+    }
+
+    /// Replaces name, type, content, TTL and proxy status of the given DNS record with those of `desired`.
+    ///
+    /// # Returns
+    /// - `Ok(RecordInfo)` with the changed record if successful.
+    /// - `Err` if the request fails or the provider can't change records this way.
+    async fn update_record(&self, _record: &RecordConfig, _desired: &NewRecord) -> Result<RecordInfo, CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support changing records", self.name())))
+    }
+
+    /// Deletes the given DNS record.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the record was deleted.
+    /// - `Err` if the request fails or the provider can't delete records.
+    async fn delete_record(&self, _record: &RecordConfig) -> Result<(), CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support deleting records", self.name())))
+    }
+
+    /// Looks up the ID of the record with the given name and type via [`DnsProvider::list_records`].
+    ///
+    /// # Returns
+    /// - `Ok(id)` with the record ID if exactly one matching record exists.
+    /// - `Err` if no or several records match, or the request fails.
+    async fn find_record_id(&self, name: &str, record_type: RecordType) -> Result<String, CrondesError> {
+        let record_type = record_type.to_string();
+        let mut matches: Vec<RecordInfo> = self.list_records().await?
+            .into_iter()
+            .filter(|r| r.record_type == record_type && r.name.eq_ignore_ascii_case(name))
+            .collect();
+        match matches.len() {
+            0 => Err(CrondesError::Config(format!("No {} record named {} found", record_type, name))),
+            1 => Ok(matches.remove(0).id),
+            n => Err(CrondesError::Config(format!("{} {} records named {} found, configure the record ID instead", n, record_type, name))),
+        }
+    }
+}
+
+/// Resolves the first IPv4 address of `name` via the system resolver.
+///
+/// Used as the current record content by providers that can only push updates but not read records. A name that
+/// doesn't exist or has no A record yet, like a freshly registered host, is reported as an empty address, so it is
+/// pushed by the next update.
+///
+/// # Errors
+/// Returns [`CrondesError::Dns`] if the resolver fails or can't be reached.
+pub(crate) async fn lookup_ipv4(name: &str) -> Result<String, CrondesError> {
+    let resolver = TokioResolver::builder_tokio()
+        .map_err(|e| CrondesError::Dns(format!("Cannot read the system DNS configuration: {}", e)))?
+        .build();
+    let fqdn = format!("{}.", name.trim_end_matches('.'));
+    match resolver.ipv4_lookup(fqdn.as_str()).await {
+        Ok(lookup) => Ok(lookup.iter().next().map(|a| a.0.to_string()).unwrap_or_default()),
+        Err(e) if e.is_no_records_found() => Ok(String::new()),
+        Err(e) => Err(CrondesError::Dns(format!("DNS lookup of {} failed: {}", name, e))),
+    }
+}
+
+/// Simple struct to hold DNS record info.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    /// TTL in seconds, if the provider reports it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    /// Whether the record is served through a proxy, for providers that support proxying.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    /// Tags like `managed-by:crondes`, for providers that support tagging records.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// A zone (domain) hosted at a provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneInfo {
+    /// ID of the zone, the name for providers addressing zones by name.
+    pub id: String,
+    pub name: String,
+}
+
+/// A DNS record to create with [`DnsProvider::create_record`].
+#[derive(Debug, Clone)]
+pub struct NewRecord {
+    pub name: String,
+    pub record_type: String,
+    pub content: String,
+    /// TTL in seconds, the provider's default if `None`.
+    pub ttl: Option<u32>,
+    /// Whether the record is served through a proxy, the provider's default if `None`.
+    pub proxied: Option<bool>,
+}
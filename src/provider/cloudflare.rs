@@ -0,0 +1,479 @@
+mod api;
+
+use std::time::Duration;
+use async_trait::async_trait;
+use futures::future::join_all;
+use log::{error, info};
+use serde::de::DeserializeOwned;
+use tokio::sync::OnceCell;
+use crate::config::{Config, RecordConfig};
+use crate::error::CrondesError;
+use crate::metrics::{METRICS, now_rfc3339};
+use super::{DnsProvider, NewRecord, RecordInfo, ZoneInfo};
+use api::{ApiMessage, BatchBody, BatchResult, ContentPatch, DnsRecord, DnsRecordBody, Envelope, RecordPatch, TokenStatus, Zone, ZoneDetails};
+
+/// Permissions the API token needs on the zone, with the names shown in the Cloudflare dashboard.
+const REQUIRED_PERMISSIONS: [(&str, &str); 2] = [("#dns_records:read", "Zone.DNS:Read"), ("#dns_records:edit", "Zone.DNS:Edit")];
+
+/// Struct for interacting with the Cloudflare API for DNS record management.
+///
+/// This struct wraps a [`Config`] object and provides methods to check credentials,
+/// validate zone and record IDs, fetch the current DNS record IP, and update the record.
+/// All requests go through a single shared [`reqwest::Client`] so connections are reused.
+/// If only `CF_ZONE_NAME` is configured, the zone ID is looked up on first use and cached.
+pub struct Cloudflare {
+    /// The configuration containing API token, zone ID, records, and update interval.
+    pub config: Config,
+    client: reqwest::Client,
+    zone_id: OnceCell<String>,
+}
+
+impl Cloudflare {
+    /// Creates a new [`Cloudflare`] instance from the given [`Config`] using `client` for all requests.
+    pub fn new(config: Config, client: reqwest::Client) -> Self {
+        Cloudflare { config, client, zone_id: OnceCell::new() }
+    }
+
+    /// Checks if the API token is valid and active by making a test request to the Cloudflare API.
+    ///
+    /// With a Global API Key, the key is checked by reading the user instead, since it has no token status.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the token is valid.
+    /// - `Ok(false)` if the token is invalid, disabled or expired.
+    /// - `Err` if the request fails.
+    pub async fn api_token_right(&self) -> Result<bool, CrondesError> {
+        if self.uses_global_key() {
+            return self.accessible(&self.url("/user")).await;
+        }
+        if self.config.cloudflare_api_token.expose().trim().is_empty() {
+            return Ok(false);
+        }
+        let request = self.client.get(self.url("/user/tokens/verify"));
+        match self.call::<TokenStatus>(request).await {
+            Ok(token) if token.status == "active" => Ok(true),
+            Ok(token) => {
+                error!("API token is {}", token.status);
+                Ok(false)
+            }
+            Err(CrondesError::Auth(_)) => Ok(false),
+            Err(e @ CrondesError::Api { .. }) if e.is_fatal() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the permissions from [`REQUIRED_PERMISSIONS`] the API token lacks on the zone.
+    ///
+    /// # Returns
+    /// - `Ok(missing)` with the dashboard names of the missing permissions, empty if the token has all of them
+    ///   or the API doesn't report the token's permissions.
+    /// - `Err` if the request fails.
+    pub async fn missing_permissions(&self) -> Result<Vec<&'static str>, CrondesError> {
+        let url = self.url(&format!("/zones/{}", self.zone_id().await?));
+        let zone: ZoneDetails = self.call(self.client.get(&url)).await?;
+        let Some(granted) = zone.permissions else {
+            return Ok(Vec::new());
+        };
+        Ok(REQUIRED_PERMISSIONS
+            .iter()
+            .filter(|(key, _)| !granted.iter().any(|g| g == key))
+            .map(|(_, name)| *name)
+            .collect())
+    }
+
+    /// Checks if the zone ID is valid and accessible with the current API token.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the zone ID is valid and accessible.
+    /// - `Ok(false)` if not, or if no zone with the configured name exists.
+    /// - `Err` if the request fails.
+    pub async fn zone_id_right(&self) -> Result<bool, CrondesError> {
+        let zone_id = match self.zone_id().await {
+            Ok(zone_id) => zone_id,
+            Err(CrondesError::Config(e)) => {
+                error!("{}", e);
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+        let url = self.url(&format!("/zones/{}", zone_id));
+        self.accessible(&url).await
+    }
+
+    /// Returns the configured zone ID, or looks up the ID of the zone named `CF_ZONE_NAME` on first use.
+    ///
+    /// # Returns
+    /// - `Ok(id)` with the zone ID.
+    /// - `Err(CrondesError::Config)` if neither is configured or no zone with that name is accessible.
+    /// - `Err` if the request fails.
+    pub async fn zone_id(&self) -> Result<&str, CrondesError> {
+        if !self.config.cloudflare_zone_id.trim().is_empty() {
+            return Ok(&self.config.cloudflare_zone_id);
+        }
+        let name = self.config.cloudflare_zone_name.trim();
+        if name.is_empty() {
+            return Err(CrondesError::Config("CF_ZONE_ID or CF_ZONE_NAME is missing".to_string()));
+        }
+        self.zone_id
+            .get_or_try_init(|| async {
+                let request = self.client.get(self.url("/zones")).query(&[("name", name)]);
+                let zones: Vec<Zone> = self.call(request).await?;
+                let zone = zones
+                    .into_iter()
+                    .find(|z| z.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| CrondesError::Config(format!("Zone {} not found or not accessible with the API token", name)))?;
+                info!("Resolved Cloudflare zone {} to ID {}", zone.name, zone.id);
+                Ok(zone.id)
+            })
+            .await
+            .map(String::as_str)
+    }
+
+    /// Checks if the record ID is valid and accessible with the current API token and zone ID.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the record ID is valid and accessible.
+    /// - `Ok(false)` if not.
+    /// - `Err` if the request fails.
+    pub async fn record_id_right(&self, record: &RecordConfig) -> Result<bool, CrondesError> {
+        if record.id.trim().is_empty() {
+            return Ok(false);
+        }
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        self.accessible(&url).await
+    }
+
+    /// Renders `CF_COMMENT` for an update of the record `name` from `old_ip` to `new_ip`.
+    ///
+    /// # Returns
+    /// The comment to write, or `None` if `CF_COMMENT` is empty and the record keeps its comment.
+    fn comment(&self, name: &str, old_ip: &str, new_ip: &str) -> Option<String> {
+        let template = self.config.cloudflare_comment.trim();
+        if template.is_empty() {
+            return None;
+        }
+        Some(
+            template
+                .replace("{timestamp}", &now_rfc3339())
+                .replace("{old_ip}", old_ip)
+                .replace("{new_ip}", new_ip)
+                .replace("{record}", name),
+        )
+    }
+
+    /// Adds the tags of `CF_TAGS` to the `existing` tags of a record, replacing existing tags of the same name.
+    ///
+    /// # Returns
+    /// The tags to write, or `None` if `CF_TAGS` is empty and the record keeps its tags.
+    fn tags(&self, existing: &[String]) -> Option<Vec<String>> {
+        if self.config.cloudflare_tags.is_empty() {
+            return None;
+        }
+        let tag_name = |tag: &str| tag.split(':').next().unwrap_or_default().to_string();
+        let mut tags: Vec<String> = existing
+            .iter()
+            .filter(|tag| !self.config.cloudflare_tags.iter().any(|own| tag_name(own) == tag_name(tag)))
+            .cloned()
+            .collect();
+        tags.extend(self.config.cloudflare_tags.iter().cloned());
+        Some(tags)
+    }
+
+    /// Returns the URL of the API endpoint `path` below `CF_API_URL`.
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.cloudflare_api_url.trim_end_matches('/'), path)
+    }
+
+    /// Returns `true` if requests are authenticated with the Global API Key because no API token is configured.
+    fn uses_global_key(&self) -> bool {
+        self.config.cloudflare_api_token.is_empty() && !self.config.cloudflare_api_key.is_empty()
+    }
+
+    /// Adds the configured credentials to `request`: the API token if set, the email and Global API Key otherwise.
+    fn authenticate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.uses_global_key() {
+            request
+                .header("X-Auth-Email", &self.config.cloudflare_api_email)
+                .header("X-Auth-Key", self.config.cloudflare_api_key.expose())
+        } else {
+            request.bearer_auth(self.config.cloudflare_api_token.expose())
+        }
+    }
+
+    /// Checks if a GET request on `url` succeeds.
+    ///
+    /// Client errors (invalid token, unknown or inaccessible resource) count as "not accessible",
+    /// while network problems and server errors are returned as errors so they can be retried.
+    async fn accessible(&self, url: &str) -> Result<bool, CrondesError> {
+        match self.call::<serde_json::Value>(self.client.get(url)).await {
+            Ok(_) => Ok(true),
+            Err(CrondesError::Auth(_)) => Ok(false),
+            Err(e @ CrondesError::Api { .. }) if e.is_fatal() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends an authenticated request and unwraps the Cloudflare response envelope.
+    ///
+    /// # Returns
+    /// - `Ok(result)` with the deserialized `result` of the response if the API reports success.
+    /// - `Err(CrondesError::Auth)` if the API rejects the credentials (HTTP 401/403).
+    /// - `Err(CrondesError::RateLimited)` with the `Retry-After` delay if too many requests were sent (HTTP 429).
+    /// - `Err(CrondesError::Api)` with the API error messages if the API reports any other failure.
+    /// - `Err(CrondesError::Network)` if the request fails.
+    async fn call<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, CrondesError> {
+        let resp = self.authenticate(request).send().await?;
+        let status = resp.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            METRICS.rate_limited();
+            let retry_after = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(CrondesError::RateLimited { provider: "Cloudflare", retry_after });
+        }
+        let text = resp.text().await?;
+        let envelope: Envelope<T> = serde_json::from_str(&text).map_err(|e| CrondesError::Api {
+            status: status.as_u16(),
+            code: None,
+            message: format!("Invalid Cloudflare API response: {}", e),
+        })?;
+        if !status.is_success() || !envelope.success {
+            let message = ApiMessage::join(&envelope.errors);
+            if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                return Err(CrondesError::Auth(message));
+            }
+            return Err(CrondesError::Api {
+                status: status.as_u16(),
+                code: envelope.errors.first().map(|e| e.code),
+                message,
+            });
+        }
+        for message in &envelope.messages {
+            info!("Cloudflare: [{}] {}", message.code, message.message);
+        }
+        envelope.result.ok_or_else(|| CrondesError::Api {
+            status: status.as_u16(),
+            code: None,
+            message: "Cloudflare API response contains no result".to_string(),
+        })
+    }
+
+    /// Fetches all pages of the list at `path`, requesting `per_page` items at a time.
+    ///
+    /// # Returns
+    /// - `Ok(items)` with the items of all pages.
+    /// - `Err` if a request fails, see [`Cloudflare::call`].
+    async fn pages<T: DeserializeOwned>(&self, path: &str, per_page: usize) -> Result<Vec<T>, CrondesError> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let request = self.client.get(self.url(path)).query(&[("page", page), ("per_page", per_page)]);
+            let result: Vec<T> = self.call(request).await?;
+            let last = result.len() < per_page;
+            items.extend(result);
+            if last {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Fetches the full DNS record for the given record.
+    ///
+    /// # Returns
+    /// - `Ok(record)` with the record as returned by the API if successful.
+    /// - `Err` if the request fails or the API reports an error.
+    async fn fetch_record(&self, record: &RecordConfig) -> Result<DnsRecord, CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        self.call(self.client.get(&url)).await
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Cloudflare {
+    fn name(&self) -> &'static str {
+        "Cloudflare"
+    }
+
+    /// Checks the API token, the zone ID and the token's permissions on the zone in that order.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        if !self.api_token_right().await? {
+            let credentials = if self.uses_global_key() { "API email or Global API Key" } else { "API token" };
+            return Err(CrondesError::Auth(format!("{} is invalid", credentials)));
+        }
+        if !self.zone_id_right().await? {
+            return Err(CrondesError::Config("Zone ID is invalid".to_string()));
+        }
+        let missing = self.missing_permissions().await?;
+        if !missing.is_empty() {
+            return Err(CrondesError::Auth(format!(
+                "API token is valid but lacks the {} permission on the zone",
+                missing.join(" and ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the record ID. If it is invalid, logs all available records of the zone to help fixing the config.
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        if !self.record_id_right(record).await? {
+            error!("Record ID {} ({}) is invalid. Listing all available records:", record.id, record.name);
+            let records = self.list_records().await?;
+            for rec in records {
+                error!("ID: {} | Name: {} | Type: {} | Content: {}", rec.id, rec.name, rec.record_type, rec.content);
+            }
+            return Err(CrondesError::Config(format!("Record ID {} is invalid", record.id)));
+        }
+        Ok(())
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        Ok(self.fetch_record(record).await?.content)
+    }
+
+    async fn is_proxied(&self, record: &RecordConfig) -> Result<bool, CrondesError> {
+        // CF_PROXIED wird bei jedem Update geschrieben und gilt damit spätestens nach dem nächsten Update
+        if let Some(proxied) = record.proxied.or(self.config.cloudflare_proxied) {
+            return Ok(proxied);
+        }
+        Ok(self.fetch_record(record).await?.proxied.unwrap_or(false))
+    }
+
+    /// Replaces the record content. TTL and proxy status are set from the record's own `ttl` and `proxied`, or from
+    /// `CF_TTL` and `CF_PROXIED` if configured, the comment from `CF_COMMENT` and the tags of `CF_TAGS` are added;
+    /// everything else is taken from the existing record so it is not silently rewritten.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let existing = self.fetch_record(record).await?;
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        let comment = self.comment(&existing.name, &existing.content, new_ip);
+        let body = DnsRecordBody {
+            record_type: &existing.record_type,
+            name: &existing.name,
+            content: new_ip,
+            ttl: record.ttl.or(self.config.cloudflare_ttl).unwrap_or(existing.ttl),
+            proxied: record.proxied.or(self.config.cloudflare_proxied).or(existing.proxied),
+            comment: comment.as_deref().or(existing.comment.as_deref()),
+            // Ein PUT ersetzt den ganzen Record, ohne CF_TAGS müssen die bisherigen Tags mitgeschickt werden
+            tags: Some(self.tags(&existing.tags).unwrap_or_else(|| existing.tags.clone())),
+        };
+        let updated: DnsRecord = self.call(self.client.put(&url).json(&body)).await?;
+        Ok(format!("{} {} is now {}", updated.record_type, updated.name, updated.content))
+    }
+
+    /// Patches only the content, so TTL, proxy status, comment and tags stay as they are.
+    async fn set_content(&self, record: &RecordConfig, content: &str) -> Result<String, CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        let updated: DnsRecord = self.call(self.client.patch(&url).json(&ContentPatch { content })).await?;
+        Ok(format!("{} {} is now {}", updated.record_type, updated.name, updated.content))
+    }
+
+    fn supports_batch(&self) -> bool {
+        true
+    }
+
+    /// Patches all records through the batch endpoint, which applies all changes or none. TTL, proxy status, comment
+    /// and tags are set per record like in [`DnsProvider::update_ip`]. If `CF_COMMENT` names the old IP or `CF_TAGS`
+    /// is set, the records are read first, since a patch replaces all tags.
+    async fn update_batch(&self, records: &[RecordConfig], new_ip: &str) -> Result<Vec<String>, CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records/batch", self.zone_id().await?));
+        let existing = if self.config.cloudflare_comment.contains("{old_ip}") || !self.config.cloudflare_tags.is_empty() {
+            join_all(records.iter().map(|record| self.fetch_record(record)))
+                .await
+                .into_iter()
+                .map(|existing| existing.map(Some))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![None; records.len()]
+        };
+        let patches = records
+            .iter()
+            .zip(&existing)
+            .map(|(record, existing)| RecordPatch {
+                id: &record.id,
+                content: new_ip,
+                ttl: record.ttl.or(self.config.cloudflare_ttl),
+                proxied: record.proxied.or(self.config.cloudflare_proxied),
+                comment: self.comment(&record.name, existing.as_ref().map_or("", |e| e.content.as_str()), new_ip),
+                tags: self.tags(existing.as_ref().map_or(&[], |e| e.tags.as_slice())),
+            })
+            .collect();
+        let result: BatchResult = self.call(self.client.post(&url).json(&BatchBody { patches })).await?;
+        if result.patches.len() != records.len() {
+            return Err(CrondesError::Api {
+                status: 200,
+                code: None,
+                message: format!("Batch update returned {} of {} records", result.patches.len(), records.len()),
+            });
+        }
+        Ok(result
+            .patches
+            .into_iter()
+            .map(|updated| format!("{} {} is now {}", updated.record_type, updated.name, updated.content))
+            .collect())
+    }
+
+    /// Creates the record with TTL and proxy status from `CF_TTL` and `CF_PROXIED` unless given,
+    /// falling back to automatic TTL and Cloudflare's proxy default, and with the tags of `CF_TAGS`.
+    async fn create_record(&self, record: &NewRecord) -> Result<RecordInfo, CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records", self.zone_id().await?));
+        let body = DnsRecordBody {
+            record_type: &record.record_type,
+            name: &record.name,
+            content: &record.content,
+            ttl: record.ttl.or(self.config.cloudflare_ttl).unwrap_or(1),
+            proxied: record.proxied.or(self.config.cloudflare_proxied),
+            comment: None,
+            tags: self.tags(&[]),
+        };
+        let created: DnsRecord = self.call(self.client.post(&url).json(&body)).await?;
+        Ok(record_info(created))
+    }
+
+    /// Patches the record, so its comment and tags are kept.
+    async fn update_record(&self, record: &RecordConfig, desired: &NewRecord) -> Result<RecordInfo, CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        let body = DnsRecordBody {
+            record_type: &desired.record_type,
+            name: &desired.name,
+            content: &desired.content,
+            ttl: desired.ttl.or(self.config.cloudflare_ttl).unwrap_or(1),
+            proxied: desired.proxied.or(self.config.cloudflare_proxied),
+            comment: None,
+            tags: None,
+        };
+        let updated: DnsRecord = self.call(self.client.patch(&url).json(&body)).await?;
+        Ok(record_info(updated))
+    }
+
+    async fn delete_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        let url = self.url(&format!("/zones/{}/dns_records/{}", self.zone_id().await?, record.id));
+        self.call::<serde_json::Value>(self.client.delete(&url)).await?;
+        Ok(())
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        let path = format!("/zones/{}/dns_records", self.zone_id().await?);
+        let records: Vec<DnsRecord> = self.pages(&path, 1000).await?;
+        Ok(records.into_iter().map(record_info).collect())
+    }
+
+    async fn list_zones(&self) -> Result<Vec<ZoneInfo>, CrondesError> {
+        let zones: Vec<Zone> = self.pages("/zones", 50).await?;
+        Ok(zones.into_iter().map(|zone| ZoneInfo { id: zone.id, name: zone.name }).collect())
+    }
+}
+
+/// Converts a record as returned by the API into the provider independent [`RecordInfo`].
+fn record_info(record: DnsRecord) -> RecordInfo {
+    RecordInfo {
+        id: record.id,
+        name: record.name,
+        record_type: record.record_type,
+        content: record.content,
+        ttl: Some(record.ttl),
+        proxied: record.proxied,
+        tags: record.tags,
+    }
+}
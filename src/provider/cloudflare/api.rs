@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+/// Response envelope wrapping every Cloudflare API v4 response.
+#[derive(Debug, Deserialize)]
+pub struct Envelope<T> {
+    pub success: bool,
+    #[serde(default)]
+    pub errors: Vec<ApiMessage>,
+    #[serde(default)]
+    pub messages: Vec<ApiMessage>,
+    pub result: Option<T>,
+}
+
+/// An error or informational message returned by the Cloudflare API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiMessage {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Result of the token verify endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenStatus {
+    /// `active`, `disabled` or `expired`.
+    pub status: String,
+}
+
+/// Zone details, only the permissions the API token has on the zone are needed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneDetails {
+    /// Permission keys like `#dns_records:edit`. Missing if the API doesn't report them.
+    #[serde(default)]
+    pub permissions: Option<Vec<String>>,
+}
+
+/// A zone as returned by the Cloudflare zones list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+}
+
+/// A DNS record as returned by the Cloudflare API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DnsRecord {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: u32,
+    #[serde(default)]
+    pub proxied: Option<bool>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Request body for creating or overwriting a DNS record.
+#[derive(Debug, Serialize)]
+pub struct DnsRecordBody<'a> {
+    #[serde(rename = "type")]
+    pub record_type: &'a str,
+    pub name: &'a str,
+    pub content: &'a str,
+    pub ttl: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Request body for changing only the content of a DNS record.
+#[derive(Debug, Serialize)]
+pub struct ContentPatch<'a> {
+    pub content: &'a str,
+}
+
+/// Request body of the batch endpoint; only patches are sent, the other operations are left empty.
+#[derive(Debug, Serialize)]
+pub struct BatchBody<'a> {
+    pub patches: Vec<RecordPatch<'a>>,
+}
+
+/// A change of a single record in a batch, only the set fields are written.
+#[derive(Debug, Serialize)]
+pub struct RecordPatch<'a> {
+    pub id: &'a str,
+    pub content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Result of the batch endpoint, with the changed records in the order of the request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchResult {
+    #[serde(default)]
+    pub patches: Vec<DnsRecord>,
+}
+
+impl ApiMessage {
+    /// Formats a list of API messages as `[code] message; [code] message`.
+    pub fn join(messages: &[ApiMessage]) -> String {
+        messages
+            .iter()
+            .map(|m| format!("[{}] {}", m.code, m.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
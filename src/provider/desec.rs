@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::config::{self, DesecConfig, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, ZoneInfo};
+
+const API_BASE: &str = "https://desec.io/api/v1";
+
+/// DNS provider for domains hosted at deSEC.
+///
+/// Records are managed through deSEC's RRset API: the A or AAAA RRset of a record name is read and
+/// replaced as a whole, so an RRset with several addresses is reduced to the public IP.
+pub struct Desec {
+    config: DesecConfig,
+    client: reqwest::Client,
+}
+
+/// An RRset as returned by the deSEC API.
+#[derive(Deserialize)]
+struct RrSet {
+    /// Fully qualified name with a trailing dot, e.g. `home.example.com.`.
+    name: String,
+    subname: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    records: Vec<String>,
+}
+
+/// A domain as returned by the deSEC API.
+#[derive(Deserialize)]
+struct Domain {
+    name: String,
+}
+
+/// Request body replacing the records of an RRset.
+#[derive(Serialize)]
+struct RrSetPatch<'a> {
+    records: [&'a str; 1],
+}
+
+/// Error body returned by the deSEC API.
+#[derive(Deserialize)]
+struct ErrorDetail {
+    detail: String,
+}
+
+impl Desec {
+    /// Creates a new [`Desec`] provider using `client` for all requests.
+    pub fn new(config: DesecConfig, client: reqwest::Client) -> Self {
+        Desec { config, client }
+    }
+
+    /// Sends an authenticated request and deserializes the response.
+    ///
+    /// # Returns
+    /// - `Ok(result)` with the deserialized response body if the request succeeds.
+    /// - `Err(CrondesError::Auth)` if the token is rejected (HTTP 401/403).
+    /// - `Err(CrondesError::Api)` with deSEC's error detail for any other failure.
+    /// - `Err(CrondesError::Network)` if the request fails.
+    async fn call<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, CrondesError> {
+        let resp = request
+            .header(reqwest::header::AUTHORIZATION, format!("Token {}", self.config.token.expose()))
+            .send()
+            .await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<ErrorDetail>(&text).map_or(text, |e| e.detail);
+            if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                return Err(CrondesError::Auth(message));
+            }
+            return Err(CrondesError::Api { status: status.as_u16(), code: None, message });
+        }
+        serde_json::from_str(&text).map_err(|e| CrondesError::Api {
+            status: status.as_u16(),
+            code: None,
+            message: format!("Invalid deSEC API response: {}", e),
+        })
+    }
+
+    /// Returns the URL of the RRset of `record` (A or AAAA). deSEC addresses the zone apex with the subname `@`.
+    fn rrset_url(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        match config::subdomain(&record.name, &self.config.domain) {
+            Some(sub) => {
+                let sub = if sub.is_empty() { "@" } else { sub };
+                Ok(format!("{}/domains/{}/rrsets/{}/{}/", API_BASE, self.config.domain, sub, record.record_type))
+            }
+            None => Err(CrondesError::Config(format!("Record {} is not within {}", record.label(), self.config.domain))),
+        }
+    }
+
+    /// Fetches the RRset of `record`.
+    ///
+    /// # Returns
+    /// - `Ok(rrset)` if the RRset exists.
+    /// - `Err(CrondesError::Api)` with status 404 if it doesn't, or any other request error.
+    async fn fetch_rrset(&self, record: &RecordConfig) -> Result<RrSet, CrondesError> {
+        let url = self.rrset_url(record)?;
+        self.call(self.client.get(&url)).await
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Desec {
+    fn name(&self) -> &'static str {
+        "deSEC"
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    /// Checks the token and that the domain belongs to the account.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        let url = format!("{}/domains/{}/", API_BASE, self.config.domain);
+        match self.call::<serde_json::Value>(self.client.get(&url)).await {
+            Err(CrondesError::Api { status: 404, .. }) => {
+                Err(CrondesError::Config(format!("Domain {} not found in the deSEC account", self.config.domain)))
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        match self.fetch_rrset(record).await {
+            Err(CrondesError::Api { status: 404, .. }) => {
+                Err(CrondesError::Config(format!("No {} RRset named {} found at deSEC", record.record_type, record.label())))
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        let rrset = self.fetch_rrset(record).await?;
+        rrset
+            .records
+            .into_iter()
+            .next()
+            .ok_or_else(|| CrondesError::Config(format!("{} RRset {} contains no records", record.record_type, record.label())))
+    }
+
+    /// Replaces all addresses of the RRset with `new_ip`, keeping its TTL.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let url = self.rrset_url(record)?;
+        let updated: RrSet = self.call(self.client.patch(&url).json(&RrSetPatch { records: [new_ip] })).await?;
+        Ok(format!("{} {} is now {}", updated.record_type, updated.name.trim_end_matches('.'), updated.records.join(", ")))
+    }
+
+    /// Lists all RRsets of the domain, one entry per RRset. The subname (`@` for the apex) is used as ID.
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        let url = format!("{}/domains/{}/rrsets/", API_BASE, self.config.domain);
+        let rrsets: Vec<RrSet> = self.call(self.client.get(&url)).await?;
+        Ok(rrsets
+            .into_iter()
+            .map(|rrset| RecordInfo {
+                id: if rrset.subname.is_empty() { "@".to_string() } else { rrset.subname },
+                name: rrset.name.trim_end_matches('.').to_string(),
+                record_type: rrset.record_type,
+                content: rrset.records.join(", "),
+                ttl: None,
+                proxied: None,
+                tags: Vec::new(),
+            })
+            .collect())
+    }
+
+    async fn list_zones(&self) -> Result<Vec<ZoneInfo>, CrondesError> {
+        let domains: Vec<Domain> = self.call(self.client.get(format!("{}/domains/", API_BASE))).await?;
+        Ok(domains.into_iter().map(|d| ZoneInfo { id: d.name.clone(), name: d.name }).collect())
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use crate::config::{DuckDnsConfig, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, lookup_ipv4};
+
+const UPDATE_URL: &str = "https://www.duckdns.org/update";
+
+/// DNS provider for DuckDNS subdomains.
+///
+/// DuckDNS only offers an update URL, so records are addressed by name and their current
+/// address is read via DNS until crondes has pushed one itself.
+pub struct DuckDns {
+    config: DuckDnsConfig,
+    client: reqwest::Client,
+    /// Addresses pushed in this run per record name, so DNS caching can't cause repeated updates.
+    pushed: Mutex<HashMap<String, String>>,
+}
+
+impl DuckDns {
+    /// Creates a new [`DuckDns`] provider using `client` for all requests.
+    pub fn new(config: DuckDnsConfig, client: reqwest::Client) -> Self {
+        DuckDns { config, client, pushed: Mutex::new(HashMap::new()) }
+    }
+}
+
+/// Returns the DuckDNS subdomain of a record name, e.g. `home` for `home.duckdns.org`.
+fn subdomain(name: &str) -> &str {
+    let name = name.trim_end_matches('.');
+    name.strip_suffix(".duckdns.org").unwrap_or(name)
+}
+
+/// Returns the fully qualified name of a record, e.g. `home.duckdns.org` for `home`.
+fn hostname(name: &str) -> String {
+    format!("{}.duckdns.org", subdomain(name))
+}
+
+#[async_trait]
+impl DnsProvider for DuckDns {
+    fn name(&self) -> &'static str {
+        "DuckDNS"
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    fn reads_from_dns(&self) -> bool {
+        true
+    }
+
+    /// DuckDNS has no way to check a token without updating a record, so this always succeeds.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        Ok(())
+    }
+
+    async fn check_record(&self, _record: &RecordConfig) -> Result<(), CrondesError> {
+        Ok(())
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        if let Some(ip) = self.pushed.lock().unwrap_or_else(|e| e.into_inner()).get(&record.name) {
+            return Ok(ip.clone());
+        }
+        lookup_ipv4(&hostname(&record.name)).await
+    }
+
+    /// Calls the update URL. DuckDNS answers `OK` or `KO` without further details; `KO` means an
+    /// invalid token or a subdomain not belonging to the account.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let resp = self
+            .client
+            .get(UPDATE_URL)
+            .query(&[("domains", subdomain(&record.name)), ("token", self.config.token.expose()), ("ip", new_ip)])
+            .send()
+            .await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        match text.trim() {
+            "OK" if status.is_success() => {
+                self.pushed.lock().unwrap_or_else(|e| e.into_inner()).insert(record.name.clone(), new_ip.to_string());
+                Ok(format!("{} is now {}", hostname(&record.name), new_ip))
+            }
+            "KO" => Err(CrondesError::Auth(format!("DuckDNS rejected the update of {}, check the token and subdomain", record.label()))),
+            other => Err(CrondesError::Api { status: status.as_u16(), code: None, message: format!("Unexpected DuckDNS response: {}", other) }),
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        Err(CrondesError::Config("DuckDNS does not support listing records".to_string()))
+    }
+}
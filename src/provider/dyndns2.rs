@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use crate::config::{Dyndns2Config, NoIpConfig, RecordConfig, Secret};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, lookup_ipv4};
+
+const NOIP_UPDATE_URL: &str = "https://dynupdate.no-ip.com/nic/update";
+
+/// DNS provider for services speaking the dyndns2 update protocol
+/// (`/nic/update?hostname=...&myip=...` with basic auth), e.g. No-IP, Dynu or self-hosted endpoints.
+///
+/// Records are addressed by hostname and their current address is read via DNS until crondes
+/// has pushed one itself. Most services block clients that keep sending unchanged addresses,
+/// so the update URL is only called when the address actually differs.
+pub struct Dyndns2 {
+    name: &'static str,
+    url: String,
+    username: String,
+    password: Secret,
+    client: reqwest::Client,
+    /// Addresses pushed in this run per hostname, so DNS caching can't cause repeated updates.
+    pushed: Mutex<HashMap<String, String>>,
+}
+
+impl Dyndns2 {
+    /// Creates a new [`Dyndns2`] provider for the update URL of `config`, using `client` for all requests.
+    pub fn new(config: &Dyndns2Config, client: reqwest::Client) -> Self {
+        Dyndns2::with_url("dyndns2", &config.url, &config.username, &config.password, client)
+    }
+
+    /// Creates a new [`Dyndns2`] provider for No-IP, using `client` for all requests.
+    pub fn no_ip(config: &NoIpConfig, client: reqwest::Client) -> Self {
+        Dyndns2::with_url("No-IP", NOIP_UPDATE_URL, &config.username, &config.password, client)
+    }
+
+    fn with_url(name: &'static str, url: &str, username: &str, password: &Secret, client: reqwest::Client) -> Self {
+        Dyndns2 {
+            name,
+            url: url.to_string(),
+            username: username.to_string(),
+            password: password.clone(),
+            client,
+            pushed: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Dyndns2 {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    fn reads_from_dns(&self) -> bool {
+        true
+    }
+
+    /// The protocol has no way to check credentials without updating a hostname, so this always succeeds.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        Ok(())
+    }
+
+    async fn check_record(&self, _record: &RecordConfig) -> Result<(), CrondesError> {
+        Ok(())
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        if let Some(ip) = self.pushed.lock().unwrap_or_else(|e| e.into_inner()).get(&record.name) {
+            return Ok(ip.clone());
+        }
+        lookup_ipv4(&record.name).await
+    }
+
+    /// Calls the update URL and maps the dyndns2 return codes to errors.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let resp = self
+            .client
+            .get(&self.url)
+            .basic_auth(&self.username, Some(self.password.expose()))
+            .query(&[("hostname", record.name.as_str()), ("myip", new_ip)])
+            .send()
+            .await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        let text = text.trim();
+        let code = text.split_whitespace().next().unwrap_or_default();
+        match code {
+            "good" | "nochg" => {
+                self.pushed.lock().unwrap_or_else(|e| e.into_inner()).insert(record.name.clone(), new_ip.to_string());
+                Ok(text.to_string())
+            }
+            "badauth" => Err(CrondesError::Auth(format!("{} rejected the username or password", self.name))),
+            "nohost" | "notfqdn" | "numhost" => {
+                Err(CrondesError::Config(format!("Hostname {} is unknown or invalid at {}: {}", record.name, self.name, text)))
+            }
+            "badagent" | "!donator" | "abuse" => {
+                Err(CrondesError::Config(format!("{} refused the update of {}: {}", self.name, record.name, text)))
+            }
+            "911" | "dnserr" => Err(CrondesError::Api { status: 503, code: None, message: format!("{} server error: {}", self.name, text) }),
+            _ if status == reqwest::StatusCode::UNAUTHORIZED => {
+                Err(CrondesError::Auth(format!("{} rejected the username or password", self.name)))
+            }
+            _ => Err(CrondesError::Api {
+                status: status.as_u16(),
+                code: None,
+                message: format!("Unexpected {} response: {}", self.name, text),
+            }),
+        }
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        Err(CrondesError::Config(format!("{} does not support listing records", self.name)))
+    }
+}
@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use crate::config::{LinodeConfig, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, ZoneInfo};
+
+const API_BASE: &str = "https://api.linode.com/v4";
+
+/// DNS provider for domains managed by the Linode Domains API.
+///
+/// Records are addressed by their numeric ID within the domain. The domain ID is looked up by
+/// name on first use and cached.
+pub struct Linode {
+    config: LinodeConfig,
+    client: reqwest::Client,
+    domain_id: OnceCell<u64>,
+}
+
+/// A page of a paginated list response.
+#[derive(Deserialize)]
+struct Page<T> {
+    data: Vec<T>,
+    page: u32,
+    pages: u32,
+}
+
+#[derive(Deserialize)]
+struct Domain {
+    id: u64,
+    domain: String,
+}
+
+/// A domain record as returned by the Linode API. `name` is relative to the domain, empty for the apex.
+#[derive(Deserialize)]
+struct DomainRecord {
+    id: u64,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    target: String,
+}
+
+#[derive(Serialize)]
+struct RecordUpdate<'a> {
+    target: &'a str,
+}
+
+/// Error body returned by the Linode API.
+#[derive(Deserialize)]
+struct Errors {
+    errors: Vec<ErrorReason>,
+}
+
+#[derive(Deserialize)]
+struct ErrorReason {
+    reason: String,
+}
+
+impl Linode {
+    /// Creates a new [`Linode`] provider using `client` for all requests.
+    pub fn new(config: LinodeConfig, client: reqwest::Client) -> Self {
+        Linode { config, client, domain_id: OnceCell::new() }
+    }
+
+    /// Sends an authenticated request and deserializes the response.
+    ///
+    /// # Returns
+    /// - `Ok(result)` with the deserialized response body if the request succeeds.
+    /// - `Err(CrondesError::Auth)` if the token is rejected (HTTP 401/403).
+    /// - `Err(CrondesError::Api)` with the reasons reported by Linode for any other failure.
+    /// - `Err(CrondesError::Network)` if the request fails.
+    async fn call<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, CrondesError> {
+        let resp = request.bearer_auth(self.config.token.expose()).send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let message = match serde_json::from_str::<Errors>(&text) {
+                Ok(errors) => errors.errors.into_iter().map(|e| e.reason).collect::<Vec<_>>().join("; "),
+                Err(_) => text,
+            };
+            if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                return Err(CrondesError::Auth(message));
+            }
+            return Err(CrondesError::Api { status: status.as_u16(), code: None, message });
+        }
+        serde_json::from_str(&text).map_err(|e| CrondesError::Api {
+            status: status.as_u16(),
+            code: None,
+            message: format!("Invalid Linode API response: {}", e),
+        })
+    }
+
+    /// Fetches all pages of a list endpoint.
+    async fn list<T: DeserializeOwned>(&self, url: &str) -> Result<Vec<T>, CrondesError> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let request = self.client.get(url).query(&[("page", page), ("page_size", 500)]);
+            let result: Page<T> = self.call(request).await?;
+            items.extend(result.data);
+            if result.page >= result.pages {
+                return Ok(items);
+            }
+            page += 1;
+        }
+    }
+
+    /// Returns the ID of the configured domain, looking it up on first use.
+    async fn domain_id(&self) -> Result<u64, CrondesError> {
+        self.domain_id
+            .get_or_try_init(|| async {
+                let domains: Vec<Domain> = self.list(&format!("{}/domains", API_BASE)).await?;
+                domains
+                    .into_iter()
+                    .find(|d| d.domain.eq_ignore_ascii_case(&self.config.domain))
+                    .map(|d| d.id)
+                    .ok_or_else(|| CrondesError::Config(format!("Domain {} not found in the Linode account", self.config.domain)))
+            })
+            .await
+            .copied()
+    }
+
+    /// Returns the API URL of `record`.
+    async fn record_url(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        Ok(format!("{}/domains/{}/records/{}", API_BASE, self.domain_id().await?, record.id))
+    }
+
+    /// Returns the fully qualified name of a record whose name is relative to the domain.
+    fn full_name(&self, name: &str) -> String {
+        if name.is_empty() { self.config.domain.clone() } else { format!("{}.{}", name, self.config.domain) }
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Linode {
+    fn name(&self) -> &'static str {
+        "Linode"
+    }
+
+    /// Checks the token by looking up the domain.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        self.domain_id().await.map(|_| ())
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        let url = self.record_url(record).await?;
+        match self.call::<DomainRecord>(self.client.get(&url)).await {
+            Err(CrondesError::Api { status: 404, .. }) => Err(CrondesError::Config(format!("Record ID {} is invalid", record.id))),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        let url = self.record_url(record).await?;
+        let current: DomainRecord = self.call(self.client.get(&url)).await?;
+        Ok(current.target)
+    }
+
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let url = self.record_url(record).await?;
+        let updated: DomainRecord = self.call(self.client.put(&url).json(&RecordUpdate { target: new_ip })).await?;
+        Ok(format!("{} {} is now {}", updated.record_type, self.full_name(&updated.name), updated.target))
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        let url = format!("{}/domains/{}/records", API_BASE, self.domain_id().await?);
+        let records: Vec<DomainRecord> = self.list(&url).await?;
+        Ok(records
+            .into_iter()
+            .map(|rec| RecordInfo {
+                id: rec.id.to_string(),
+                name: self.full_name(&rec.name),
+                record_type: rec.record_type,
+                content: rec.target,
+                ttl: None,
+                proxied: None,
+                tags: Vec::new(),
+            })
+            .collect())
+    }
+
+    async fn list_zones(&self) -> Result<Vec<ZoneInfo>, CrondesError> {
+        let domains: Vec<Domain> = self.list(&format!("{}/domains", API_BASE)).await?;
+        Ok(domains.into_iter().map(|d| ZoneInfo { id: d.id.to_string(), name: d.domain }).collect())
+    }
+}
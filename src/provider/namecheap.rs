@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use crate::config::{self, NamecheapConfig, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, lookup_ipv4};
+
+const UPDATE_URL: &str = "https://dynamicdns.park-your-domain.com/update";
+
+/// DNS provider for Namecheap's dynamic DNS endpoint.
+///
+/// Dynamic DNS has to be enabled for the domain in the Namecheap dashboard, which shows the DDNS
+/// password. Records are addressed by name and their current address is read via DNS until
+/// crondes has pushed one itself.
+pub struct Namecheap {
+    config: NamecheapConfig,
+    client: reqwest::Client,
+    /// Addresses pushed in this run per record name, so DNS caching can't cause repeated updates.
+    pushed: Mutex<HashMap<String, String>>,
+}
+
+impl Namecheap {
+    /// Creates a new [`Namecheap`] provider using `client` for all requests.
+    pub fn new(config: NamecheapConfig, client: reqwest::Client) -> Self {
+        Namecheap { config, client, pushed: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns the host part of `record` as expected by Namecheap, `@` for the domain itself.
+    fn host<'a>(&self, record: &'a RecordConfig) -> Result<&'a str, CrondesError> {
+        match config::subdomain(&record.name, &self.config.domain) {
+            Some("") => Ok("@"),
+            Some(host) => Ok(host),
+            None => Err(CrondesError::Config(format!("Record {} is not within {}", record.label(), self.config.domain))),
+        }
+    }
+}
+
+/// Returns the trimmed text content of the first `<tag>` element.
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find('<')?;
+    Some(xml[start..end].trim())
+}
+
+/// Checks the XML response of an update for the errors counted in `ErrCount` and listed as `Err1`, `Err2`, ...
+///
+/// # Errors
+/// Returns [`CrondesError::Auth`] for a wrong password or unknown domain, and [`CrondesError::Api`] with all listed
+/// errors for any other failure or a non-2xx `status`.
+fn check_response(status: reqwest::StatusCode, xml: &str) -> Result<(), CrondesError> {
+    let errors: usize = xml_element(xml, "ErrCount").and_then(|count| count.parse().ok()).unwrap_or(0);
+    if status.is_success() && errors == 0 {
+        return Ok(());
+    }
+    let message = (1..=errors.max(1))
+        .filter_map(|i| xml_element(xml, &format!("Err{}", i)))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let message = if message.is_empty() { format!("HTTP {}", status) } else { message };
+    // Namecheap meldet falsche Passwörter und unbekannte Domains nur als Fehlertext, nicht per HTTP-Status
+    if message.contains("Passwords do not match") || message.contains("Domain name not found") {
+        return Err(CrondesError::Auth(message));
+    }
+    Err(CrondesError::Api { status: status.as_u16(), code: None, message: format!("Namecheap update failed: {}", message) })
+}
+
+#[async_trait]
+impl DnsProvider for Namecheap {
+    fn name(&self) -> &'static str {
+        "Namecheap"
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    fn reads_from_dns(&self) -> bool {
+        true
+    }
+
+    /// The endpoint has no way to check the password without updating a record, so this always succeeds.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        Ok(())
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        self.host(record).map(|_| ())
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        if let Some(ip) = self.pushed.lock().unwrap_or_else(|e| e.into_inner()).get(&record.name) {
+            return Ok(ip.clone());
+        }
+        lookup_ipv4(&record.name).await
+    }
+
+    /// Calls the update URL and reports the errors listed in the XML response.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let resp = self
+            .client
+            .get(UPDATE_URL)
+            .query(&[
+                ("host", self.host(record)?),
+                ("domain", self.config.domain.as_str()),
+                ("password", self.config.password.expose()),
+                ("ip", new_ip),
+            ])
+            .send()
+            .await?;
+        let status = resp.status();
+        let xml = resp.text().await?;
+        check_response(status, &xml)?;
+        self.pushed.lock().unwrap_or_else(|e| e.into_inner()).insert(record.name.clone(), new_ip.to_string());
+        let ip = xml_element(&xml, "IP").unwrap_or(new_ip);
+        Ok(format!("{} is now {}", record.name, ip))
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        Err(CrondesError::Config("Namecheap dynamic DNS does not support listing records".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    /// A response of the update endpoint listing `errors`.
+    fn response(errors: &[&str]) -> String {
+        let listed: String = errors.iter().enumerate().map(|(i, e)| format!("<Err{0}>{1}</Err{0}>", i + 1, e)).collect();
+        format!(
+            "<?xml version=\"1.0\"?><interface-response><Command>SETDNSHOST</Command><IP>203.0.113.7</IP>\
+             <ErrCount>{}</ErrCount><errors>{}</errors><Done>true</Done></interface-response>",
+            errors.len(),
+            listed
+        )
+    }
+
+    #[test]
+    fn successful_update() {
+        assert!(check_response(StatusCode::OK, &response(&[])).is_ok());
+    }
+
+    #[test]
+    fn wrong_password_is_an_auth_error() {
+        let err = check_response(StatusCode::OK, &response(&["Passwords do not match"])).unwrap_err();
+        assert!(matches!(err, CrondesError::Auth(ref message) if message == "Passwords do not match"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn all_errors_are_reported() {
+        let err = check_response(StatusCode::OK, &response(&["Invalid IP", "Host not found"])).unwrap_err();
+        assert!(
+            matches!(err, CrondesError::Api { ref message, .. } if message == "Namecheap update failed: Invalid IP; Host not found"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn http_errors_without_error_list() {
+        let err = check_response(StatusCode::BAD_GATEWAY, "").unwrap_err();
+        assert!(matches!(err, CrondesError::Api { status: 502, .. }), "unexpected error: {}", err);
+    }
+}
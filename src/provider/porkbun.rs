@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::config::{self, PorkbunConfig, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo};
+
+const API_BASE: &str = "https://api.porkbun.com/api/json/v3";
+
+/// DNS provider for domains registered at Porkbun.
+///
+/// Porkbun's API only uses POST requests with the API keys in the JSON body.
+/// Records are retrieved and edited by name and type.
+pub struct Porkbun {
+    config: PorkbunConfig,
+    client: reqwest::Client,
+}
+
+/// Body sent with every request, extended by request specific fields.
+#[derive(Serialize)]
+struct Auth<'a, T: Serialize> {
+    apikey: &'a str,
+    secretapikey: &'a str,
+    #[serde(flatten)]
+    body: T,
+}
+
+/// Fields of an `editByNameType` request.
+#[derive(Serialize)]
+struct EditBody<'a> {
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<&'a str>,
+}
+
+/// Status every Porkbun response carries, `SUCCESS` or `ERROR` with a message.
+#[derive(Deserialize)]
+struct Status {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Records {
+    records: Vec<PorkbunRecord>,
+}
+
+/// A DNS record as returned by the Porkbun API. All values are strings, including the TTL.
+#[derive(Deserialize)]
+struct PorkbunRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    #[serde(default)]
+    ttl: Option<String>,
+}
+
+impl Porkbun {
+    /// Creates a new [`Porkbun`] provider using `client` for all requests.
+    pub fn new(config: PorkbunConfig, client: reqwest::Client) -> Self {
+        Porkbun { config, client }
+    }
+
+    /// Sends an authenticated POST request to `path` and checks the response status.
+    ///
+    /// # Returns
+    /// - `Ok(result)` with the deserialized response if Porkbun reports `SUCCESS`.
+    /// - `Err(CrondesError::Auth)` if the API keys are rejected (HTTP 401/403).
+    /// - `Err(CrondesError::Api)` with Porkbun's message for any other failure.
+    /// - `Err(CrondesError::Network)` if the request fails.
+    async fn call<T: DeserializeOwned>(&self, path: &str, body: impl Serialize) -> Result<T, CrondesError> {
+        let auth = Auth { apikey: self.config.api_key.expose(), secretapikey: self.config.secret_api_key.expose(), body };
+        let resp = self.client.post(format!("{}{}", API_BASE, path)).json(&auth).send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        let invalid = |e: serde_json::Error| CrondesError::Api {
+            status: status.as_u16(),
+            code: None,
+            message: format!("Invalid Porkbun API response: {}", e),
+        };
+        let result: Status = serde_json::from_str(&text).map_err(invalid)?;
+        if !status.is_success() || result.status != "SUCCESS" {
+            let message = result.message.unwrap_or(result.status);
+            if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                return Err(CrondesError::Auth(message));
+            }
+            return Err(CrondesError::Api { status: status.as_u16(), code: None, message });
+        }
+        serde_json::from_str(&text).map_err(invalid)
+    }
+
+    /// Returns the path suffix `/<domain>/<type>[/<subdomain>]` addressing the A or AAAA records of `record`.
+    fn by_name_type(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        match config::subdomain(&record.name, &self.config.domain) {
+            Some("") => Ok(format!("/{}/{}", self.config.domain, record.record_type)),
+            Some(sub) => Ok(format!("/{}/{}/{}", self.config.domain, record.record_type, sub)),
+            None => Err(CrondesError::Config(format!("Record {} is not within {}", record.label(), self.config.domain))),
+        }
+    }
+
+    /// Fetches the record with the name and type of `record`.
+    ///
+    /// # Returns
+    /// - `Ok(record)` with the first matching record.
+    /// - `Err(CrondesError::Config)` if no record with that name and type exists.
+    async fn fetch_record(&self, record: &RecordConfig) -> Result<PorkbunRecord, CrondesError> {
+        let path = format!("/dns/retrieveByNameType{}", self.by_name_type(record)?);
+        let records: Records = self.call(&path, serde_json::Map::new()).await?;
+        records
+            .records
+            .into_iter()
+            .next()
+            .ok_or_else(|| CrondesError::Config(format!("No {} record named {} found at Porkbun", record.record_type, record.label())))
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Porkbun {
+    fn name(&self) -> &'static str {
+        "Porkbun"
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    /// Checks the API keys via the `ping` endpoint.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        let _: Status = self.call("/ping", serde_json::Map::new()).await?;
+        Ok(())
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        self.fetch_record(record).await.map(|_| ())
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        Ok(self.fetch_record(record).await?.content)
+    }
+
+    /// Replaces the content of all records with the name and type of `record`, keeping their TTL.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let existing = self.fetch_record(record).await?;
+        let path = format!("/dns/editByNameType{}", self.by_name_type(record)?);
+        let body = EditBody { content: new_ip, ttl: existing.ttl.as_deref() };
+        let _: Status = self.call(&path, body).await?;
+        Ok(format!("{} {} is now {}", record.record_type, existing.name, new_ip))
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        let path = format!("/dns/retrieve/{}", self.config.domain);
+        let records: Records = self.call(&path, serde_json::Map::new()).await?;
+        Ok(records
+            .records
+            .into_iter()
+            .map(|rec| RecordInfo {
+                ttl: rec.ttl.as_deref().and_then(|ttl| ttl.parse().ok()),
+                id: rec.id,
+                name: rec.name,
+                record_type: rec.record_type,
+                content: rec.content,
+                proxied: None,
+                tags: Vec::new(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,221 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use rand::RngExt;
+use hickory_proto::dnssec::rdata::tsig::TsigAlgorithm;
+use hickory_proto::dnssec::tsig::TSigner;
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode, UpdateMessage};
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_proto::runtime::{TokioRuntimeProvider, TokioTime};
+use hickory_proto::udp::UdpClientStream;
+use hickory_proto::xfer::{DnsExchange, DnsHandle, DnsRequest, DnsRequestOptions, FirstAnswer};
+use crate::config::{Rfc2136Config, RecordConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo};
+
+/// Time the DNS server may take to answer a single message.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Allowed difference between the local clock and the server's clock for TSIG signatures.
+const FUDGE_SECS: u16 = 300;
+
+/// DNS provider sending RFC 2136 dynamic updates signed with a TSIG key to an authoritative
+/// server under the user's control, e.g. BIND, Knot or PowerDNS.
+///
+/// All messages are sent via UDP to the configured server. Updates are signed and their responses
+/// verified; the queries reading the current record are plain queries to the same server.
+pub struct Rfc2136 {
+    config: Rfc2136Config,
+    zone: Name,
+    signer: TSigner,
+}
+
+impl Rfc2136 {
+    /// Creates a new [`Rfc2136`] provider.
+    ///
+    /// # Errors
+    /// Returns an error if the zone name, key name, key secret or key algorithm is invalid.
+    pub fn new(config: Rfc2136Config) -> Result<Self, String> {
+        let mut zone = Name::from_ascii(&config.zone).map_err(|e| format!("Invalid RFC2136_ZONE: {}", e))?;
+        zone.set_fqdn(true);
+        let signer = signer(&config)?;
+        Ok(Rfc2136 { config, zone, signer })
+    }
+
+    /// Returns the fully qualified name of `record`.
+    fn record_name(&self, record: &RecordConfig) -> Result<Name, CrondesError> {
+        let mut name = Name::from_ascii(&record.name)
+            .map_err(|e| CrondesError::Config(format!("Invalid record name {}: {}", record.name, e)))?;
+        name.set_fqdn(true);
+        if !self.zone.zone_of(&name) {
+            return Err(CrondesError::Config(format!("Record {} is not within {}", record.name, self.zone)));
+        }
+        Ok(name)
+    }
+
+    /// Sends `message` to the configured server and returns the response. Updates are signed and
+    /// their responses verified.
+    ///
+    /// # Returns
+    /// - `Ok(response)` if the server answered with `NOERROR` (or `NXDOMAIN` for queries).
+    /// - `Err(CrondesError::Auth)` if the key is rejected or the server refuses the request.
+    /// - `Err(CrondesError::Config)` if the zone is not served by the server.
+    /// - `Err(CrondesError::Dns)` with the DNS response code for any other failure or if the server can't be reached.
+    async fn exchange(&self, message: Message) -> Result<Message, CrondesError> {
+        let server = self.server().await?;
+        let stream = UdpClientStream::builder(server, TokioRuntimeProvider::new())
+            .with_timeout(Some(TIMEOUT))
+            .with_signer(Some(Arc::new(self.signer.clone())))
+            .build();
+        let (client, background) = DnsExchange::connect::<_, _, TokioTime>(stream)
+            .await
+            .map_err(|e| self.network_error(e))?;
+        let background = tokio::spawn(background);
+        let response = client
+            .send(DnsRequest::new(message, DnsRequestOptions::default()))
+            .first_answer()
+            .await;
+        background.abort();
+        // Server mit falschem Key antworten unsigniert, das meldet hickory nur als Verifikationsfehler
+        let response = response
+            .map_err(|e| match e.to_string() {
+                msg if msg.contains("tsig") => {
+                    CrondesError::Auth(format!("{} sent no valid TSIG signature, check the key: {}", self.config.server, msg))
+                }
+                _ => self.network_error(e),
+            })?
+            .into_message();
+        match response.response_code() {
+            ResponseCode::NoError | ResponseCode::NXDomain => Ok(response),
+            code @ (ResponseCode::NotAuth | ResponseCode::Refused | ResponseCode::BADSIG | ResponseCode::BADKEY | ResponseCode::BADTIME) => {
+                Err(CrondesError::Auth(format!("{} rejected the request: {}", self.config.server, code)))
+            }
+            ResponseCode::NotZone => Err(CrondesError::Config(format!("{} is not authoritative for {}", self.config.server, self.zone))),
+            code => Err(CrondesError::Dns(format!("{} answered {}", self.config.server, code))),
+        }
+    }
+
+    /// Resolves the configured server address, using port 53 if none is given.
+    async fn server(&self) -> Result<SocketAddr, CrondesError> {
+        let server = &self.config.server;
+        if let Ok(addr) = server.parse::<SocketAddr>() {
+            return Ok(addr);
+        }
+        if let Ok(ip) = server.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, 53));
+        }
+        let target = if server.contains(':') { server.clone() } else { format!("{}:53", server) };
+        tokio::net::lookup_host(target)
+            .await
+            .map_err(|e| CrondesError::Config(format!("Cannot resolve RFC2136_SERVER {}: {}", server, e)))?
+            .next()
+            .ok_or_else(|| CrondesError::Config(format!("RFC2136_SERVER {} has no address", server)))
+    }
+
+    fn network_error(&self, e: impl std::fmt::Display) -> CrondesError {
+        CrondesError::Dns(format!("Request to {} failed: {}", self.config.server, e))
+    }
+
+    /// Builds a non-recursive query for `name` and `record_type`.
+    fn query(name: Name, record_type: RecordType) -> Message {
+        let mut message = Message::new();
+        message
+            .set_id(rand::rng().random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false)
+            .add_query(Query::query(name, record_type));
+        message
+    }
+
+    /// Builds an empty update message for the zone.
+    fn update(&self) -> Message {
+        let mut message = Message::new();
+        message
+            .set_id(rand::rng().random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Update)
+            .set_recursion_desired(false);
+        message.add_zone(Query::query(self.zone.clone(), RecordType::SOA));
+        message
+    }
+}
+
+/// Creates the TSIG signer from the configured key.
+fn signer(config: &Rfc2136Config) -> Result<TSigner, String> {
+    let key = data_encoding::BASE64
+        .decode(config.key_secret.expose().trim().as_bytes())
+        .map_err(|e| format!("RFC2136_KEY_SECRET is not valid base64: {}", e))?;
+    let algorithm = match config.key_algorithm.to_ascii_lowercase().as_str() {
+        "hmac-sha256" => TsigAlgorithm::HmacSha256,
+        "hmac-sha384" => TsigAlgorithm::HmacSha384,
+        "hmac-sha512" => TsigAlgorithm::HmacSha512,
+        other => return Err(format!("Unsupported RFC2136_KEY_ALGORITHM: {}", other)),
+    };
+    let name = Name::from_ascii(&config.key_name).map_err(|e| format!("Invalid RFC2136_KEY_NAME: {}", e))?;
+    TSigner::new(key, algorithm, name, FUDGE_SECS).map_err(|e| format!("Invalid TSIG key: {}", e))
+}
+
+#[async_trait]
+impl DnsProvider for Rfc2136 {
+    fn name(&self) -> &'static str {
+        "RFC 2136"
+    }
+
+    fn uses_record_ids(&self) -> bool {
+        false
+    }
+
+    fn reads_from_dns(&self) -> bool {
+        true
+    }
+
+    /// Sends a signed update without changes, only requiring the zone apex to exist. It fails if the
+    /// key is unknown or not allowed to update the zone, or if the server isn't authoritative for it.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        let mut message = self.update();
+        let mut apex = Record::update0(self.zone.clone(), 0, RecordType::ANY);
+        apex.set_dns_class(DNSClass::ANY);
+        message.add_pre_requisite(apex);
+        self.exchange(message).await.map(|_| ())
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        self.record_name(record).map(|_| ())
+    }
+
+    /// Reads the A record from the server. A missing record is reported as an empty address,
+    /// so it is created by the next update.
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        let response = self.exchange(Self::query(self.record_name(record)?, RecordType::A)).await?;
+        Ok(response
+            .answers()
+            .iter()
+            .find_map(|answer| match answer.data() {
+                RData::A(ip) => Some(ip.to_string()),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Replaces the A RRset of the record with `new_ip` in a single update message.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        let name = self.record_name(record)?;
+        let ip: A = new_ip
+            .parse()
+            .map_err(|_| CrondesError::Config(format!("RFC 2136 updates need an IPv4 address, got {}", new_ip)))?;
+        let mut message = self.update();
+        let mut delete = Record::update0(name.clone(), 0, RecordType::A);
+        delete.set_dns_class(DNSClass::ANY);
+        message.add_update(delete);
+        message.add_update(Record::from_rdata(name.clone(), self.config.ttl, RData::A(ip)));
+        self.exchange(message).await?;
+        Ok(format!("A {} is now {}", record.name, new_ip))
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        Err(CrondesError::Config("RFC 2136 does not support listing records".to_string()))
+    }
+}
@@ -0,0 +1,204 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::config::{RecordConfig, VultrConfig};
+use crate::error::CrondesError;
+use super::{DnsProvider, RecordInfo, ZoneInfo};
+
+const API_BASE: &str = "https://api.vultr.com/v2";
+
+/// DNS provider for domains managed by Vultr DNS.
+///
+/// Records are addressed by their ID within the domain.
+pub struct Vultr {
+    config: VultrConfig,
+    client: reqwest::Client,
+}
+
+/// A DNS record as returned by the Vultr API. `name` is relative to the domain, empty for the apex.
+#[derive(Deserialize)]
+struct VultrRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct RecordResponse {
+    record: VultrRecord,
+}
+
+#[derive(Deserialize)]
+struct RecordList {
+    records: Vec<VultrRecord>,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct DomainList {
+    domains: Vec<VultrDomain>,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct VultrDomain {
+    domain: String,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    links: Links,
+}
+
+#[derive(Deserialize)]
+struct Links {
+    #[serde(default)]
+    next: String,
+}
+
+#[derive(Serialize)]
+struct RecordUpdate<'a> {
+    data: &'a str,
+}
+
+/// Error body returned by the Vultr API.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl Vultr {
+    /// Creates a new [`Vultr`] provider using `client` for all requests.
+    pub fn new(config: VultrConfig, client: reqwest::Client) -> Self {
+        Vultr { config, client }
+    }
+
+    /// Sends an authenticated request and returns the response body.
+    ///
+    /// # Returns
+    /// - `Ok(body)` if the request succeeds. The body is empty for `204 No Content`.
+    /// - `Err(CrondesError::Auth)` if the API key is rejected (HTTP 401/403).
+    /// - `Err(CrondesError::Api)` with the error reported by Vultr for any other failure.
+    /// - `Err(CrondesError::Network)` if the request fails.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<String, CrondesError> {
+        let resp = request.bearer_auth(self.config.api_key.expose()).send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<ErrorBody>(&text).map_or(text, |e| e.error);
+            if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                return Err(CrondesError::Auth(message));
+            }
+            return Err(CrondesError::Api { status: status.as_u16(), code: None, message });
+        }
+        Ok(text)
+    }
+
+    /// Sends an authenticated request and deserializes the response, see [`Vultr::send`].
+    async fn call<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, CrondesError> {
+        let text = self.send(request).await?;
+        serde_json::from_str(&text).map_err(|e| CrondesError::Api {
+            status: 200,
+            code: None,
+            message: format!("Invalid Vultr API response: {}", e),
+        })
+    }
+
+    /// Returns the API URL of `record`.
+    fn record_url(&self, record: &RecordConfig) -> String {
+        format!("{}/domains/{}/records/{}", API_BASE, self.config.domain, record.id)
+    }
+
+    /// Fetches the record with the ID of `record`.
+    async fn fetch_record(&self, record: &RecordConfig) -> Result<VultrRecord, CrondesError> {
+        let response: RecordResponse = self.call(self.client.get(self.record_url(record))).await?;
+        Ok(response.record)
+    }
+
+    /// Returns the fully qualified name of a record whose name is relative to the domain.
+    fn full_name(&self, name: &str) -> String {
+        if name.is_empty() { self.config.domain.clone() } else { format!("{}.{}", name, self.config.domain) }
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Vultr {
+    fn name(&self) -> &'static str {
+        "Vultr"
+    }
+
+    /// Checks the API key and that the domain belongs to the account.
+    async fn check_credentials(&self) -> Result<(), CrondesError> {
+        let url = format!("{}/domains/{}", API_BASE, self.config.domain);
+        match self.send(self.client.get(&url)).await {
+            Err(CrondesError::Api { status: 404, .. }) => {
+                Err(CrondesError::Config(format!("Domain {} not found in the Vultr account", self.config.domain)))
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn check_record(&self, record: &RecordConfig) -> Result<(), CrondesError> {
+        match self.fetch_record(record).await {
+            Err(CrondesError::Api { status: 404, .. }) => Err(CrondesError::Config(format!("Record ID {} is invalid", record.id))),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn current_ip(&self, record: &RecordConfig) -> Result<String, CrondesError> {
+        Ok(self.fetch_record(record).await?.data)
+    }
+
+    /// Updates the record data. Vultr answers `204 No Content`, so the record is read back for the response.
+    async fn update_ip(&self, record: &RecordConfig, new_ip: &str) -> Result<String, CrondesError> {
+        self.send(self.client.patch(self.record_url(record)).json(&RecordUpdate { data: new_ip })).await?;
+        let updated = self.fetch_record(record).await?;
+        Ok(format!("{} {} is now {}", updated.record_type, self.full_name(&updated.name), updated.data))
+    }
+
+    async fn list_records(&self) -> Result<Vec<RecordInfo>, CrondesError> {
+        let url = format!("{}/domains/{}/records", API_BASE, self.config.domain);
+        let mut records = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut request = self.client.get(&url).query(&[("per_page", "500")]);
+            if !cursor.is_empty() {
+                request = request.query(&[("cursor", &cursor)]);
+            }
+            let page: RecordList = self.call(request).await?;
+            records.extend(page.records.into_iter().map(|rec| RecordInfo {
+                id: rec.id,
+                name: self.full_name(&rec.name),
+                record_type: rec.record_type,
+                content: rec.data,
+                ttl: None,
+                proxied: None,
+                tags: Vec::new(),
+            }));
+            if page.meta.links.next.is_empty() {
+                return Ok(records);
+            }
+            cursor = page.meta.links.next;
+        }
+    }
+
+    async fn list_zones(&self) -> Result<Vec<ZoneInfo>, CrondesError> {
+        let url = format!("{}/domains", API_BASE);
+        let mut zones = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut request = self.client.get(&url).query(&[("per_page", "500")]);
+            if !cursor.is_empty() {
+                request = request.query(&[("cursor", &cursor)]);
+            }
+            let page: DomainList = self.call(request).await?;
+            zones.extend(page.domains.into_iter().map(|d| ZoneInfo { id: d.domain.clone(), name: d.domain }));
+            if page.meta.links.next.is_empty() {
+                return Ok(zones);
+            }
+            cursor = page.meta.links.next;
+        }
+    }
+}
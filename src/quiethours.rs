@@ -0,0 +1,193 @@
+//! Time windows during which the daemon doesn't change DNS records, configured with `QUIET_HOURS`.
+//!
+//! A window is written as `[DAYS ]HH:MM-HH:MM`, e.g. `Mon-Fri 08:00-18:00`, `Sat 10:00-14:00` or `22:00-06:00`.
+//! Without days it applies to every day. A window ending before it starts runs past midnight and belongs to the day it
+//! starts on, `24:00` ends at midnight.
+
+use jiff::Timestamp;
+use jiff::tz::TimeZone;
+
+/// Names of the weekdays, starting with Monday like [`jiff::civil::Weekday::to_monday_zero_offset`].
+const WEEKDAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// The configured quiet windows together with the time zone their times are given in.
+#[derive(Debug, Clone)]
+pub struct QuietHours {
+    windows: Vec<Window>,
+    tz: TimeZone,
+}
+
+/// A single window of `QUIET_HOURS`.
+#[derive(Debug, Clone)]
+struct Window {
+    /// The window as configured, for log messages.
+    spec: String,
+    /// Days the window starts on, Monday first.
+    days: [bool; 7],
+    /// Start and end in minutes since midnight.
+    start: u16,
+    end: u16,
+}
+
+impl QuietHours {
+    /// Parses the `windows` of `QUIET_HOURS`, whose times are local times of the time zone `tz`.
+    ///
+    /// `tz` is an IANA name like `Europe/Berlin` looked up in the system's time zone database, `UTC`, or a POSIX
+    /// rule like `CET-1CEST,M3.5.0,M10.5.0/3` for systems without one. Empty uses the time zone of the system.
+    ///
+    /// # Errors
+    /// Returns an error describing the first invalid window, or the unknown time zone.
+    pub fn new(windows: &[String], tz: &str) -> Result<Self, String> {
+        let windows = windows.iter().map(|spec| parse_window(spec)).collect::<Result<_, _>>()?;
+        Ok(QuietHours { windows, tz: time_zone(tz)? })
+    }
+
+    /// Returns the window `now` falls into, as configured, or `None` outside of all windows.
+    pub fn active(&self, now: Timestamp) -> Option<&str> {
+        let local = now.to_zoned(self.tz.clone());
+        let weekday = local.weekday().to_monday_zero_offset() as usize;
+        let minute = local.hour() as u16 * 60 + local.minute() as u16;
+        self.windows.iter().find(|w| w.contains(weekday, minute)).map(|w| w.spec.as_str())
+    }
+}
+
+impl Window {
+    /// Returns `true` if the window covers `minute` on the day `weekday`, counted from Monday.
+    fn contains(&self, weekday: usize, minute: u16) -> bool {
+        if self.start < self.end {
+            self.days[weekday] && (self.start..self.end).contains(&minute)
+        } else {
+            // Über Mitternacht: der Rest des Starttags und der Anfang des Folgetags
+            (self.days[weekday] && minute >= self.start) || (self.days[(weekday + 6) % 7] && minute < self.end)
+        }
+    }
+}
+
+/// Parses a window like `Mon-Fri 08:00-18:00`.
+fn parse_window(spec: &str) -> Result<Window, String> {
+    let spec = spec.trim();
+    let invalid = |reason: &str| format!("invalid quiet window '{}': {}", spec, reason);
+    let (days, times) = match spec.rsplit_once(char::is_whitespace) {
+        Some((days, times)) => (parse_days(days.trim()).map_err(|e| invalid(&e))?, times),
+        None => ([true; 7], spec),
+    };
+    let (start, end) = times.split_once('-').ok_or_else(|| invalid("expected HH:MM-HH:MM"))?;
+    let start = parse_minute(start).ok_or_else(|| invalid("invalid start time"))?;
+    let end = parse_minute(end).ok_or_else(|| invalid("invalid end time"))?;
+    if start == end || start == 24 * 60 {
+        return Err(invalid("the window is empty"));
+    }
+    Ok(Window { spec: spec.to_string(), days, start, end: end % (24 * 60) })
+}
+
+/// Parses a day like `Sat` or a range of days like `Mon-Fri` or `Fri-Mon`.
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    let (first, last) = days.split_once('-').unwrap_or((days, days));
+    let (first, last) = (weekday(first)?, weekday(last)?);
+    let mut selected = [false; 7];
+    let mut day = first;
+    loop {
+        selected[day] = true;
+        if day == last {
+            return Ok(selected);
+        }
+        day = (day + 1) % 7;
+    }
+}
+
+/// Returns the index of the weekday named `name` in full or by its first three letters, counted from Monday.
+fn weekday(name: &str) -> Result<usize, String> {
+    let name = name.trim().to_ascii_lowercase();
+    WEEKDAYS
+        .iter()
+        .position(|day| name.len() >= 3 && day.starts_with(&name))
+        .ok_or_else(|| format!("unknown day '{}'", name))
+}
+
+/// Parses `HH:MM` into minutes since midnight, allowing `24:00`.
+fn parse_minute(time: &str) -> Option<u16> {
+    let (hour, minute) = time.trim().split_once(':')?;
+    let (hour, minute): (u16, u16) = (hour.parse().ok()?, minute.parse().ok()?);
+    match (hour, minute) {
+        (24, 0) => Some(24 * 60),
+        (0..24, 0..60) => Some(hour * 60 + minute),
+        _ => None,
+    }
+}
+
+/// Looks up the time zone `name`, see [`QuietHours::new`].
+fn time_zone(name: &str) -> Result<TimeZone, String> {
+    match name.trim() {
+        "" => Ok(TimeZone::system()),
+        name if name.eq_ignore_ascii_case("utc") => Ok(TimeZone::UTC),
+        name => TimeZone::get(name)
+            .or_else(|e| if name.contains(|c: char| c.is_ascii_digit()) { TimeZone::posix(name) } else { Err(e) })
+            .map_err(|e| format!("unknown time zone '{}': {}", name, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses an RFC 3339 time like `2024-06-07T23:30:00Z`. The 7th of June 2024 is a Friday.
+    fn at(time: &str) -> Timestamp {
+        time.parse().unwrap()
+    }
+
+    fn quiet(windows: &[&str], tz: &str) -> QuietHours {
+        QuietHours::new(&windows.iter().map(|w| w.to_string()).collect::<Vec<_>>(), tz).unwrap()
+    }
+
+    #[test]
+    fn window_past_midnight_belongs_to_its_start_day() {
+        let quiet = quiet(&["Fri 22:00-06:00"], "UTC");
+        assert_eq!(quiet.active(at("2024-06-07T23:30:00Z")), Some("Fri 22:00-06:00"));
+        // Der Samstagmorgen gehört noch zum Fenster vom Freitag
+        assert!(quiet.active(at("2024-06-08T05:59:00Z")).is_some());
+        assert!(quiet.active(at("2024-06-08T06:00:00Z")).is_none());
+        assert!(quiet.active(at("2024-06-08T23:00:00Z")).is_none());
+        // Der Freitagmorgen gehört zum Donnerstag, für den kein Fenster gilt
+        assert!(quiet.active(at("2024-06-07T01:00:00Z")).is_none());
+        assert!(quiet.active(at("2024-06-07T21:59:00Z")).is_none());
+    }
+
+    #[test]
+    fn day_ranges_wrap_around_the_week() {
+        let quiet = quiet(&["Fri-Mon 10:00-12:00"], "UTC");
+        for day in ["2024-06-07", "2024-06-08", "2024-06-09", "2024-06-10"] {
+            assert!(quiet.active(at(&format!("{}T11:00:00Z", day))).is_some(), "{}", day);
+        }
+        for day in ["2024-06-11", "2024-06-12", "2024-06-13"] {
+            assert!(quiet.active(at(&format!("{}T11:00:00Z", day))).is_none(), "{}", day);
+        }
+        assert!(quiet.active(at("2024-06-09T12:00:00Z")).is_none());
+    }
+
+    #[test]
+    fn window_can_end_at_midnight() {
+        let quiet = quiet(&["Fri 20:00-24:00"], "UTC");
+        assert!(quiet.active(at("2024-06-07T23:59:00Z")).is_some());
+        assert!(quiet.active(at("2024-06-08T00:00:00Z")).is_none());
+        assert!(quiet.active(at("2024-06-07T19:59:00Z")).is_none());
+    }
+
+    #[test]
+    fn empty_and_invalid_windows_are_rejected() {
+        for window in ["08:00-08:00", "24:00-06:00", "08:00-24:30", "Mon 8-9", "Someday 08:00-09:00"] {
+            assert!(QuietHours::new(&[window.to_string()], "UTC").is_err(), "{}", window);
+        }
+        // Ein ganzer Tag ist nicht leer
+        assert!(quiet(&["00:00-24:00"], "UTC").active(at("2024-06-07T12:00:00Z")).is_some());
+    }
+
+    #[test]
+    fn posix_rule_is_used_without_time_zone_database() {
+        let quiet = quiet(&["22:00-23:00"], "CET-1CEST,M3.5.0,M10.5.0/3");
+        // 22:30 Sommerzeit und 22:30 Winterzeit
+        assert!(quiet.active(at("2024-06-07T20:30:00Z")).is_some());
+        assert!(quiet.active(at("2024-01-05T21:30:00Z")).is_some());
+        assert!(quiet.active(at("2024-01-05T20:30:00Z")).is_none());
+        assert!(QuietHours::new(&[], "Nowhere/Atlantis").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+use rand::RngExt;
+
+/// Exponential backoff with jitter for retrying failed update cycles.
+///
+/// Each consecutive failure doubles the delay, starting at `base` and capped at `max`.
+/// The actual delay is randomized between 50% and 100% of that value so several
+/// instances failing at the same time don't retry in lockstep.
+#[derive(Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    /// Creates a new [`Backoff`] with the given initial and maximum delay.
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Backoff { base, max, failures: 0 }
+    }
+
+    /// Returns the number of consecutive failures recorded since the last reset.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Records a failure and returns the delay to wait before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+        let factor = 2u32.saturating_pow(self.failures - 1);
+        let delay = self.base.saturating_mul(factor).min(self.max);
+        delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+
+    /// Resets the failure counter after a successful attempt.
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// Randomly shortens or lengthens `interval` by up to `percent` percent.
+pub fn jitter(interval: Duration, percent: u64) -> Duration {
+    if percent == 0 {
+        return interval;
+    }
+    let spread = percent as f64 / 100.0;
+    interval.mul_f64(rand::rng().random_range(1.0 - spread..=1.0 + spread))
+}
+
+/// Returns a random delay between zero and `max`.
+pub fn splay(max: Duration) -> Duration {
+    max.mul_f64(rand::rng().random_range(0.0..=1.0))
+}
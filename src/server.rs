@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{error, info, warn};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use crate::config::{Secret, ServerConfig};
+use crate::control::DaemonControl;
+use crate::error::CrondesError;
+use crate::metrics::{METRICS, unix_now};
+
+/// Shared state of the HTTP handlers.
+#[derive(Clone)]
+struct AppState {
+    /// Per job, the maximum age of its last successful update cycle for `/healthz` to report healthy, `None` if its
+    /// cycles only run on demand.
+    health_windows: Arc<Vec<Option<Duration>>>,
+    /// Unix time the server was started, used as reference before the first successful cycle.
+    started: u64,
+    /// Bearer token required by the control API.
+    api_token: Secret,
+    /// Token required by `/webhook`.
+    webhook_token: Secret,
+    control: Arc<DaemonControl>,
+}
+
+/// Starts the embedded HTTP server on the address configured in `config` in the background.
+///
+/// Endpoints:
+/// - `/metrics`: Prometheus metrics of the update loop.
+/// - `/healthz`: `200 OK` if the last update cycle of every job succeeded within its window in `health_windows`,
+///   `503 Service Unavailable` otherwise. Jobs without a window are not checked.
+///
+/// If `webhook_token` is set, `GET /webhook` and `POST /webhook` start an update cycle, optionally with the public IP
+/// reported in the request (see [`webhook`]). `/webhook/{job}` does the same for a single job of the config file.
+///
+/// If `api_token` is set, the control API is served as well and requires it as bearer token:
+/// - `GET /status`: State of the daemon as JSON (see [`crate::control::Status`]).
+/// - `GET /records`: The managed records and the IP they were last confirmed to point to, as JSON.
+/// - `GET /cycles`: Outcomes of the recent update cycles, as JSON (see [`crate::control::CycleStatus`]).
+/// - `POST /trigger`: Starts an update cycle right away.
+/// - `POST /pause`, `POST /resume`: Suspends update cycles until resumed, resuming starts a cycle right away.
+///
+/// Along with the control API, `GET /` serves a dashboard page using it; the page itself asks for the token.
+///
+/// # Errors
+/// Returns an error if the address cannot be bound.
+pub async fn spawn(config: &ServerConfig, health_windows: Vec<Option<Duration>>, control: Arc<DaemonControl>) -> Result<(), CrondesError> {
+    let bind = &config.bind;
+    let listener = TcpListener::bind(bind)
+        .await
+        .map_err(|e| CrondesError::Config(format!("Failed to bind HTTP server to {}: {}", bind, e)))?;
+    let state = AppState {
+        health_windows: Arc::new(health_windows),
+        started: unix_now(),
+        api_token: config.api_token.clone(),
+        webhook_token: config.webhook_token.clone(),
+        control,
+    };
+    let mut app = Router::new()
+        .route("/metrics", get(metrics))
+        .route("/healthz", get(healthz));
+    let mut extras = Vec::new();
+    if !config.webhook_token.is_empty() {
+        app = app.route("/webhook", get(webhook).post(webhook)).route("/webhook/{job}", get(job_webhook).post(job_webhook));
+        extras.push("webhook");
+    }
+    if !config.api_token.is_empty() {
+        let api = Router::new()
+            .route("/status", get(status))
+            .route("/records", get(records))
+            .route("/cycles", get(cycles))
+            .route("/trigger", post(trigger))
+            .route("/pause", post(pause))
+            .route("/resume", post(resume))
+            .route_layer(middleware::from_fn_with_state(state.clone(), authenticate));
+        app = app.merge(api).route("/", get(dashboard));
+        extras.push("control API");
+        extras.push("dashboard");
+    }
+    let app = app.with_state(state);
+    if extras.is_empty() {
+        info!("HTTP server listening on {}", bind);
+    } else {
+        info!("HTTP server listening on {} ({} enabled)", bind, extras.join(", "));
+    }
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("HTTP server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Handler for `/metrics`.
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render())
+}
+
+/// Handler for `/healthz`.
+///
+/// Before the first successful cycle the server start time is used as reference,
+/// so the daemon is not reported unhealthy right after startup.
+async fn healthz(State(state): State<AppState>) -> impl IntoResponse {
+    let now = unix_now();
+    let overdue = state
+        .control
+        .job_status()
+        .into_iter()
+        .zip(state.health_windows.iter())
+        .filter_map(|((job, status), window)| {
+            let age = now.saturating_sub(status.last_success.max(state.started));
+            window.filter(|window| age > window.as_secs()).map(|_| (job, age))
+        })
+        .max_by_key(|(_, age)| *age);
+    match overdue {
+        None => (StatusCode::OK, "ok\n".to_string()),
+        Some((job, age)) if job.is_empty() => {
+            (StatusCode::SERVICE_UNAVAILABLE, format!("no successful update cycle for {} seconds\n", age))
+        }
+        Some((job, age)) => {
+            (StatusCode::SERVICE_UNAVAILABLE, format!("no successful update cycle of job {} for {} seconds\n", job, age))
+        }
+    }
+}
+
+/// Rejects control API requests without the configured bearer token.
+async fn authenticate(State(state): State<AppState>, headers: HeaderMap, request: Request, next: Next) -> Response {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), state.api_token.expose().as_bytes()) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], "unauthorized\n").into_response(),
+    }
+}
+
+/// Compares two byte strings in time independent of where they differ, so the token can't be guessed byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Handler for `GET /status`.
+async fn status(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.control.status())
+}
+
+/// Handler for `GET /records`.
+async fn records(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.control.records())
+}
+
+/// Handler for `GET /cycles`.
+async fn cycles(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.control.cycles())
+}
+
+/// Handler for `GET /`, the dashboard. It holds no data, the page loads everything from the control API.
+async fn dashboard() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], include_str!("../assets/dashboard.html"))
+}
+
+/// Handler for `POST /trigger`.
+///
+/// Refused while paused, since the cycle would be skipped anyway.
+async fn trigger(State(state): State<AppState>) -> impl IntoResponse {
+    if state.control.is_paused() {
+        return (StatusCode::CONFLICT, "update cycles are paused\n");
+    }
+    info!("Update cycle requested via the control API");
+    state.control.trigger();
+    (StatusCode::ACCEPTED, "update cycle triggered\n")
+}
+
+/// Handler for `POST /pause`.
+async fn pause(State(state): State<AppState>) -> impl IntoResponse {
+    if state.control.pause() {
+        info!("Update cycles paused via the control API");
+        "paused\n"
+    } else {
+        "already paused\n"
+    }
+}
+
+/// Handler for `POST /resume`.
+async fn resume(State(state): State<AppState>) -> impl IntoResponse {
+    if state.control.resume() {
+        info!("Update cycles resumed via the control API");
+        "resumed\n"
+    } else {
+        "not paused\n"
+    }
+}
+
+/// Handler for `GET /webhook` and `POST /webhook`.
+///
+/// The token is accepted as bearer token or as `token` query parameter, since routers and AWS SNS can't set headers.
+/// The public IP is taken from the `ip` or `myip` query parameter, otherwise from the body: a plain address, a JSON
+/// object with an `ip` field, or an AWS SNS notification carrying one of those. Without an IP the update cycle detects it
+/// as usual. SNS subscription confirmations are logged so the URL can be opened by hand.
+///
+/// With several jobs, a cycle of every job is started. An IP can only be reported to a single job, see [`job_webhook`],
+/// since the jobs usually manage the records of different networks.
+async fn webhook(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    handle_webhook(state, None, params, headers, body)
+}
+
+/// Handler for `GET /webhook/{job}` and `POST /webhook/{job}`, which start an update cycle of the job only, like
+/// [`webhook`].
+async fn job_webhook(
+    State(state): State<AppState>,
+    Path(job): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    handle_webhook(state, Some(job), params, headers, body)
+}
+
+/// Handles a webhook of all jobs or of the job named `job`, see [`webhook`].
+fn handle_webhook(state: AppState, job: Option<String>, params: HashMap<String, String>, headers: HeaderMap, body: String) -> Response {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(params.get("token").map(String::as_str));
+    if !token.is_some_and(|token| constant_time_eq(token.as_bytes(), state.webhook_token.expose().as_bytes())) {
+        return (StatusCode::UNAUTHORIZED, "unauthorized\n").into_response();
+    }
+    let body = match headers.get("x-amz-sns-message-type").and_then(|value| value.to_str().ok()) {
+        Some("SubscriptionConfirmation") => {
+            let url = serde_json::from_str::<Value>(&body).ok().and_then(|v| v["SubscribeURL"].as_str().map(str::to_string));
+            // Die URL wird bewusst nicht selbst aufgerufen, der Server soll keine Anfragen an beliebige Adressen schicken
+            warn!("AWS SNS subscription received, confirm it by opening {}", url.as_deref().unwrap_or("the SubscribeURL"));
+            return (StatusCode::OK, "subscription confirmation logged\n").into_response();
+        }
+        Some("Notification") => {
+            serde_json::from_str::<Value>(&body).ok().and_then(|v| v["Message"].as_str().map(str::to_string)).unwrap_or_default()
+        }
+        _ => body,
+    };
+    let reported = match params.get("ip").or(params.get("myip")) {
+        Some(ip) => parse_ip(ip),
+        None => reported_ip(&body),
+    };
+    let ip = match reported {
+        Ok(ip) => ip,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{}\n", e)).into_response(),
+    };
+    let index = match &job {
+        Some(name) => match state.control.job(name) {
+            Some(index) => Some(index),
+            None => return (StatusCode::NOT_FOUND, format!("unknown job '{}'\n", name)).into_response(),
+        },
+        None if state.control.jobs() == 1 => Some(0),
+        // Eine IP gehört zum Netz eines Jobs, sie darf nicht in die Records aller Jobs geschrieben werden
+        None if ip.is_some() => {
+            return (StatusCode::BAD_REQUEST, "several jobs are configured, report the IP to /webhook/{job}\n").into_response();
+        }
+        None => None,
+    };
+    if state.control.is_paused() {
+        return (StatusCode::CONFLICT, "update cycles are paused\n").into_response();
+    }
+    let message = match &ip {
+        Some(ip) => format!("update to {} triggered\n", ip),
+        None => "update cycle triggered\n".to_string(),
+    };
+    info!(
+        "Webhook received{}{}",
+        job.map(|job| format!(" for job {}", job)).unwrap_or_default(),
+        ip.as_ref().map(|ip| format!(", reported IP {}", ip)).unwrap_or_default()
+    );
+    match index {
+        Some(index) => state.control.report_ip(index, ip),
+        None => state.control.trigger(),
+    }
+    (StatusCode::ACCEPTED, message).into_response()
+}
+
+/// Extracts the public IP from a webhook body.
+///
+/// # Returns
+/// - `Ok(Some(ip))` if the body is an IPv4 address or a JSON object with an `ip` field.
+/// - `Ok(None)` if the body is empty or a JSON object without `ip`, so the webhook only triggers a cycle.
+/// - `Err` if the body contains something else.
+fn reported_ip(body: &str) -> Result<Option<String>, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Ok(None);
+    }
+    if body.starts_with('{') {
+        let value: Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {}", e))?;
+        return match &value["ip"] {
+            Value::Null => Ok(None),
+            Value::String(ip) => parse_ip(ip),
+            other => Err(format!("invalid ip field: {}", other)),
+        };
+    }
+    parse_ip(body)
+}
+
+/// Validates a reported IP.
+fn parse_ip(ip: &str) -> Result<Option<String>, String> {
+    ip.trim().parse::<Ipv4Addr>().map(|ip| Some(ip.to_string())).map_err(|_| format!("'{}' is not an IPv4 address", ip.trim()))
+}
@@ -0,0 +1,231 @@
+use crondes::config::ConfigSource;
+
+/// Name the service is registered under in the Windows service control manager.
+#[cfg_attr(not(windows), allow(dead_code))]
+const SERVICE_NAME: &str = "crondes";
+
+/// Runs the update daemon as Windows service, called by the service control manager through `crondes --service`.
+///
+/// Blocks until the service is stopped. The service reports `StartPending` while the configuration is loaded,
+/// `Running` while the daemon runs and `Stopped` with a non-zero exit code if it gave up.
+///
+/// # Errors
+/// Returns an error if the process was not started by the service control manager, or on other platforms.
+pub fn run(source: ConfigSource) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        windows::run(source)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = source;
+        Err("--service is only supported on Windows".to_string())
+    }
+}
+
+/// Registers crondes as automatically started Windows service running `crondes --service` with the config file,
+/// `--set` overrides and `--job` of `source`.
+///
+/// # Errors
+/// Returns an error if the service cannot be created, e.g. without administrator rights, or on other platforms.
+pub fn install(source: &ConfigSource) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        windows::install(source).map_err(|e| format!("Cannot install the {} service: {}", SERVICE_NAME, e))
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = source;
+        Err("Windows services are only supported on Windows".to_string())
+    }
+}
+
+/// Stops and removes the Windows service.
+///
+/// # Errors
+/// Returns an error if the service doesn't exist or cannot be removed, or on other platforms.
+pub fn uninstall() -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        windows::uninstall().map_err(|e| format!("Cannot uninstall the {} service: {}", SERVICE_NAME, e))
+    }
+    #[cfg(not(windows))]
+    {
+        Err("Windows services are only supported on Windows".to_string())
+    }
+}
+
+/// Asks the service control manager to start (`true`) or stop (`false`) the Windows service.
+///
+/// # Errors
+/// Returns an error if the service doesn't exist or the request is refused, or on other platforms.
+pub fn control(start: bool) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        windows::control(start).map_err(|e| format!("Cannot control the {} service: {}", SERVICE_NAME, e))
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = start;
+        Err("Windows services are only supported on Windows".to_string())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::OsString;
+    use std::sync::{Arc, OnceLock};
+    use std::time::Duration;
+    use crondes::config::ConfigSource;
+    use crondes::{CrondesError, Updater};
+    use log::{error, info};
+    use tokio::sync::Notify;
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+        ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+    use windows_service::{define_windows_service, service_dispatcher};
+    use super::SERVICE_NAME;
+
+    /// Time the service control manager waits for a pending start or stop before it considers the service hung.
+    const PENDING_HINT: Duration = Duration::from_secs(60);
+
+    /// Config source of the service, handed from `run` to the service main function, which can't take arguments.
+    static SOURCE: OnceLock<ConfigSource> = OnceLock::new();
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    pub(super) fn run(source: ConfigSource) -> Result<(), String> {
+        let _ = SOURCE.set(source);
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+            .map_err(|e| format!("Cannot connect to the service control manager, --service is only for use by it: {}", e))
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(e) = run_service() {
+            error!("Windows service failed: {}", e);
+        }
+    }
+
+    fn run_service() -> Result<(), windows_service::Error> {
+        let stop = Arc::new(Notify::new());
+        let stop_request = stop.clone();
+        let status = service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                // notify_one speichert die Anfrage, falls der Daemon gerade noch startet
+                stop_request.notify_one();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+        set_state(&status, ServiceState::StartPending, 0)?;
+
+        let result = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime.block_on(async {
+                let source = SOURCE.get().cloned().unwrap_or_default();
+                let cfg = crate::init_and_log_config(&source)?;
+                let updaters = crate::updaters(cfg, source)?;
+                set_state(&status, ServiceState::Running, 0).map_err(|e| CrondesError::Config(e.to_string()))?;
+                info!("Running as Windows service {}", SERVICE_NAME);
+                Updater::run_all_until(updaters, async {
+                    stop.notified().await;
+                    info!("Stop requested by the service control manager");
+                    let _ = set_state(&status, ServiceState::StopPending, 0);
+                })
+                .await
+            }),
+            Err(e) => Err(CrondesError::Config(format!("Cannot start the async runtime: {}", e))),
+        };
+        let exit_code = match result {
+            Ok(()) => 0,
+            Err(e) => {
+                error!("{}", e);
+                1
+            }
+        };
+        set_state(&status, ServiceState::Stopped, exit_code)
+    }
+
+    /// Reports `state` to the service control manager, with a service specific `exit_code` once stopped.
+    fn set_state(status: &ServiceStatusHandle, state: ServiceState, exit_code: u32) -> Result<(), windows_service::Error> {
+        let controls_accepted = match state {
+            ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            _ => ServiceControlAccept::empty(),
+        };
+        let wait_hint = match state {
+            ServiceState::StartPending | ServiceState::StopPending => PENDING_HINT,
+            _ => Duration::default(),
+        };
+        status.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: if exit_code == 0 { ServiceExitCode::Win32(0) } else { ServiceExitCode::ServiceSpecific(exit_code) },
+            checkpoint: 0,
+            wait_hint,
+            process_id: None,
+        })
+    }
+
+    pub(super) fn install(source: &ConfigSource) -> Result<(), windows_service::Error> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?;
+        let mut launch_arguments = vec![OsString::from("--service")];
+        if let Some(path) = &source.file {
+            // Der Dienst startet in System32, deshalb muss der Pfad absolut sein
+            let path = std::fs::canonicalize(path).map_err(windows_service::Error::Winapi)?;
+            launch_arguments.push(OsString::from("--config"));
+            launch_arguments.push(path.into_os_string());
+        }
+        for (key, value) in &source.overrides {
+            launch_arguments.push(OsString::from("--set"));
+            launch_arguments.push(OsString::from(format!("{}={}", key, value)));
+        }
+        if let Some(job) = &source.job {
+            launch_arguments.push(OsString::from("--job"));
+            launch_arguments.push(OsString::from(job));
+        }
+        let info = ServiceInfo {
+            name: OsString::from(SERVICE_NAME),
+            display_name: OsString::from("crondes dynamic DNS updater"),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: std::env::current_exe().map_err(windows_service::Error::Winapi)?,
+            launch_arguments,
+            dependencies: vec![],
+            account_name: None,
+            account_password: None,
+        };
+        let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+        service.set_description("Keeps DNS records in sync with the public IP address")?;
+        println!("Installed service {}, start it with `crondes service start`", SERVICE_NAME);
+        Ok(())
+    }
+
+    pub(super) fn uninstall() -> Result<(), windows_service::Error> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+        let service = manager.open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)?;
+        if service.query_status()?.current_state != ServiceState::Stopped {
+            service.stop()?;
+        }
+        service.delete()?;
+        println!("Removed service {}", SERVICE_NAME);
+        Ok(())
+    }
+
+    pub(super) fn control(start: bool) -> Result<(), windows_service::Error> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+        let service = manager.open_service(SERVICE_NAME, ServiceAccess::START | ServiceAccess::STOP)?;
+        if start {
+            service.start::<&str>(&[])?;
+            println!("Started service {}", SERVICE_NAME);
+        } else {
+            service.stop()?;
+            println!("Stop of service {} requested", SERVICE_NAME);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+/// Waits until the process receives a shutdown request.
+///
+/// On Unix this is SIGINT (Ctrl-C) or SIGTERM (e.g. `docker stop`), elsewhere only Ctrl-C.
+///
+/// # Returns
+/// The name of the received signal, for log output.
+#[cfg(unix)]
+pub async fn shutdown_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sigterm = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    let mut sigint = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+    tokio::select! {
+        _ = sigterm.recv() => "SIGTERM",
+        _ = sigint.recv() => "SIGINT",
+    }
+}
+
+/// Waits until the process receives a shutdown request.
+///
+/// On Unix this is SIGINT (Ctrl-C) or SIGTERM (e.g. `docker stop`), elsewhere only Ctrl-C.
+///
+/// # Returns
+/// The name of the received signal, for log output.
+#[cfg(not(unix))]
+pub async fn shutdown_signal() -> &'static str {
+    tokio::signal::ctrl_c().await.expect("Failed to install Ctrl-C handler");
+    "Ctrl-C"
+}
+
+/// A request sent to the running daemon by a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// SIGHUP: load the configuration again.
+    Reload,
+    /// SIGUSR1: start an update cycle right away.
+    Trigger,
+}
+
+/// Receives the signals controlling the running daemon. Never yields anything on platforms without Unix signals.
+pub struct ControlSignals {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    user1: tokio::signal::unix::Signal,
+}
+
+impl ControlSignals {
+    /// Installs the handlers for SIGHUP and SIGUSR1, replacing their default action of terminating the process.
+    #[cfg(unix)]
+    pub fn new() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+        ControlSignals {
+            hangup: signal(SignalKind::hangup()).expect("Failed to install SIGHUP handler"),
+            user1: signal(SignalKind::user_defined1()).expect("Failed to install SIGUSR1 handler"),
+        }
+    }
+
+    /// Installs the handlers for SIGHUP and SIGUSR1, replacing their default action of terminating the process.
+    #[cfg(not(unix))]
+    pub fn new() -> Self {
+        ControlSignals {}
+    }
+
+    /// Waits for the next control signal.
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> Control {
+        tokio::select! {
+            _ = self.hangup.recv() => Control::Reload,
+            _ = self.user1.recv() => Control::Trigger,
+        }
+    }
+
+    /// Waits for the next control signal.
+    #[cfg(not(unix))]
+    pub async fn recv(&mut self) -> Control {
+        std::future::pending().await
+    }
+}
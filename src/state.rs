@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::ip::IpFamily;
+
+/// Persistent state remembered across restarts, stored as JSON in `STATE_FILE`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// The public IPv4 address all A and TXT records were last confirmed to point to.
+    pub ip: Option<String>,
+    /// Unix time at which the records were last changed to a new IPv4 address.
+    pub changed_at: Option<u64>,
+    /// The public IPv6 address all AAAA records were last confirmed to point to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<String>,
+    /// Unix time at which the records were last changed to a new IPv6 address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6_changed_at: Option<u64>,
+    /// Unix time of the last update cycle that left all records of an address family in sync.
+    pub updated_at: Option<u64>,
+}
+
+impl State {
+    /// Loads the state from `path`. A missing file yields an empty state.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(State::default()),
+            Err(e) => return Err(format!("Failed to read state file {}: {}", path.display(), e)),
+        };
+        serde_json::from_str(&content).map_err(|e| format!("Invalid state file {}: {}", path.display(), e))
+    }
+
+    /// Writes the state to `path` atomically: the JSON is written to a temporary file next to it,
+    /// which then replaces the old file, so a crash never leaves a half-written state behind.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize state: {}", e))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to write state file {}: {}", path.display(), e))
+    }
+
+    /// Returns the address the records of `family` were last confirmed to point to.
+    pub fn ip(&self, family: IpFamily) -> Option<&String> {
+        match family {
+            IpFamily::V4 => self.ip.as_ref(),
+            IpFamily::V6 => self.ipv6.as_ref(),
+        }
+    }
+
+    /// Records that all records of `family` point to `ip` as of `now`, updating the change time of the family if the
+    /// IP is new. The other family keeps its address, so a changed IPv6 prefix doesn't touch the IPv4 state.
+    pub fn in_sync(&mut self, family: IpFamily, ip: &str, now: u64) {
+        let (current, changed_at) = match family {
+            IpFamily::V4 => (&mut self.ip, &mut self.changed_at),
+            IpFamily::V6 => (&mut self.ipv6, &mut self.ipv6_changed_at),
+        };
+        if current.as_deref() != Some(ip) {
+            *current = Some(ip.to_string());
+            *changed_at = Some(now);
+        }
+        self.updated_at = Some(now);
+    }
+
+    /// Describes when the IP of `family` last changed, e.g. `3 days ago`.
+    pub fn last_change(&self, family: IpFamily, now: u64) -> Option<String> {
+        let changed_at = match family {
+            IpFamily::V4 => self.changed_at,
+            IpFamily::V6 => self.ipv6_changed_at,
+        };
+        changed_at.map(|at| format_age(now.saturating_sub(at)))
+    }
+}
+
+/// Formats a duration in seconds as a rough human readable age.
+fn format_age(secs: u64) -> String {
+    let (value, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{} ago", value, unit, if value == 1 { "" } else { "s" })
+}
@@ -0,0 +1,47 @@
+use std::env;
+use std::time::Duration;
+use log::debug;
+
+/// Sends `state` to the service manager, e.g. `READY=1` or `STATUS=...`.
+///
+/// Does nothing unless the daemon was started by systemd with `Type=notify` (or `NotifyAccess` set), which is
+/// announced through `NOTIFY_SOCKET`. Errors are only logged, since the daemon works without a service manager as well.
+pub fn notify(state: &str) {
+    let Ok(socket) = env::var("NOTIFY_SOCKET") else { return };
+    if let Err(e) = send(&socket, state) {
+        debug!("Could not notify systemd at {}: {}", socket, e);
+    }
+}
+
+/// Returns how often `WATCHDOG=1` has to be sent, which is half the `WatchdogSec` of the unit so a ping is never late.
+///
+/// # Returns
+/// `None` if the watchdog is disabled or meant for another process.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") && pid.parse() != Ok(std::process::id()) {
+        return None;
+    }
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+#[cfg(unix)]
+fn send(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    let datagram = UnixDatagram::unbound()?;
+    // Ein führendes @ steht für einen Socket im abstrakten Namensraum
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        datagram.send_to_addr(state.as_bytes(), &address)?;
+        return Ok(());
+    }
+    datagram.send_to(state.as_bytes(), socket)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &str, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "systemd is only available on Unix"))
+}
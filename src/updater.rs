@@ -0,0 +1,1777 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use futures::future::join_all;
+use log::{error, info, warn};
+use tokio::sync::{Notify, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{Interval, MissedTickBehavior};
+use crate::config::{Config, ConfigSource, IpSourceKind, QuietHoursMode, RecordCheck, RecordConfig, RecordType, UpdateMode};
+use crate::control::{DaemonControl, RecordStatus, Status};
+use crate::error::CrondesError;
+use crate::history::{self, HistoryEntry};
+use crate::hooks::PreUpdateHook;
+use crate::metrics::{METRICS, now_rfc3339, unix_now};
+use crate::nameserver::AuthoritativeLookup;
+use crate::notify::{Event, Notifiers};
+use crate::propagation::PropagationCheck;
+use crate::quiethours::QuietHours;
+use crate::provider::{self, DnsProvider, RecordInfo};
+use crate::retry::{self, Backoff};
+use crate::state::State;
+use crate::ip::{self, IpDetector, IpFamily};
+use crate::signals::{Control, ControlSignals};
+use crate::{configwatch, connectivity, control, http, logging, netwatch, server, signals, systemd, zonesync};
+
+/// Keeps the configured DNS records in sync with the public IP address.
+///
+/// An [`Updater`] owns the DNS providers and the sources used to detect the public IP address. It can either run a single
+/// update cycle ([`Updater::update_once`]) or run as a daemon ([`Updater::run`]).
+pub struct Updater {
+    config: Config,
+    providers: Vec<ProviderGroup>,
+    /// How the public IPs of the records are detected. The first lookup finds the IPv4 address with `IP_SOURCES`,
+    /// which is also used for the state file, the metrics and `SYNC_FILE`.
+    lookups: Vec<IpLookup>,
+    /// Limits the records read and written at the same time to `UPDATE_CONCURRENCY`.
+    permits: Semaphore,
+    state: State,
+    notifiers: Arc<Notifiers>,
+    /// Waits for updated records to show up at public resolvers, if `PROPAGATION_CHECK` is set.
+    propagation: Option<Arc<PropagationCheck>>,
+    /// Reads records from their nameservers if `RECORD_CHECK=dns` is set.
+    authoritative: Option<AuthoritativeLookup>,
+    /// Propagation checks still running in the background.
+    propagating: Mutex<JoinSet<()>>,
+    pre_update: Option<PreUpdateHook>,
+    /// Failure streaks of the components of the update cycle that are failing, by component.
+    streaks: BTreeMap<String, Streak>,
+    /// Records changed outside of crondes, by record, with the content the conflict was notified for.
+    conflicts: BTreeMap<String, String>,
+    /// Number of update cycles that reached the providers, used for `REVALIDATE_EVERY_N_CYCLES`.
+    cycles: u64,
+    /// Where the configuration is loaded from again on SIGHUP, if reloading is enabled.
+    source: Option<ConfigSource>,
+    /// Public IP reported by a webhook, used instead of detecting it until a cycle succeeds with it.
+    reported_ip: Option<String>,
+    /// The provider whose zone is reconciled with `SYNC_FILE`, if it is set.
+    sync: Option<Box<dyn DnsProvider>>,
+    /// Time windows in which records are not written, if `QUIET_HOURS` is set.
+    quiet: Option<QuietHours>,
+    /// Most recently detected public IP address of this job, published to the control interfaces.
+    public_ip: Option<String>,
+    /// Unix time of the last successful update cycle of this job, `0` if there was none yet.
+    last_success: u64,
+}
+
+/// Events ending the wait of the scheduler for the next update cycle before the interval has passed.
+struct Wakeups {
+    /// Stops the scheduler.
+    stop: Arc<Notify>,
+    /// Starts a cycle right away, e.g. after a network change.
+    trigger: Arc<Notify>,
+    /// Reloads the configuration and starts a cycle with it, e.g. after the config file changed.
+    reload: Arc<Notify>,
+    /// SIGHUP and SIGUSR1.
+    signals: ControlSignals,
+    /// Ticks whenever the systemd watchdog has to be pinged, if it is enabled.
+    watchdog: Option<Interval>,
+}
+
+/// A DNS provider together with the records it hosts and its own retry state, so a failing
+/// provider neither blocks nor delays the records of the others.
+struct ProviderGroup {
+    provider: Box<dyn DnsProvider>,
+    records: Vec<RecordConfig>,
+    /// Per record the index of the [`IpLookup`] detecting its address.
+    lookups: Vec<usize>,
+    /// Glob of `CF_RECORD_PATTERN`, only set for `DNS_PROVIDER`; the matching A records are added when resolving.
+    pattern: Option<String>,
+    /// Tag of `CF_RECORD_TAG`, only set for `DNS_PROVIDER`; the A records carrying it are added when resolving.
+    tag: Option<String>,
+    resolved: bool,
+    /// Whether the credentials have been checked and no authentication error occurred since.
+    verified: bool,
+    /// Per record the IP it was last confirmed to point to, so unchanged records don't have to be read every cycle.
+    confirmed: Vec<Option<String>>,
+    /// Per record whether it is read from its authoritative nameservers instead of the provider API.
+    via_dns: Vec<bool>,
+    /// Per record when it was last written, for `MIN_UPDATE_COOLDOWN_SECS`.
+    written: Vec<Option<Instant>>,
+    /// Per record the content crondes last wrote or found, for `FORCE_OVERWRITE`.
+    expected: Vec<Option<String>>,
+    backoff: Backoff,
+    /// When a failed provider is due again; it is skipped by cycles before that.
+    retry_at: Option<Instant>,
+    /// Exit code of the failure the provider is backing off from, for the records it skips.
+    exit_code: u8,
+}
+
+/// Exit code of records whose public IP couldn't be detected, like [`CrondesError::IpDetection`].
+const IP_DETECTION_FAILED: u8 = 5;
+
+/// Component of the update cycle that failed for a reason not attributed to the IP detection or a provider.
+const UPDATE_CYCLE: &str = "update cycle";
+
+/// Unix time of 2025-01-01. During `STARTUP_GRACE_SECS` an earlier system time means the clock is not set yet.
+const CLOCK_SET_AFTER: u64 = 1_735_689_600;
+
+/// Consecutive failures of one component of the update cycle, e.g. the IPv4 detection or a provider.
+struct Streak {
+    failures: u32,
+    /// Time of the first failure of the streak in RFC 3339 format.
+    since: String,
+}
+
+/// Detects the public IP for all records with the same address family, IP sources and IP services.
+struct IpLookup {
+    family: IpFamily,
+    sources: Vec<IpSourceKind>,
+    services: Vec<String>,
+    detector: IpDetector,
+    /// The last detected address and in how many consecutive cycles it was seen.
+    observed: Option<(String, u32)>,
+}
+
+/// The address an [`IpLookup`] detected in the current update cycle.
+struct PublicIp {
+    address: String,
+    /// In how many consecutive cycles the address has been detected.
+    seen: u32,
+    /// Whether the address has been detected often enough to be written, see `IP_STABLE_CHECKS`.
+    stable: bool,
+}
+
+/// Record counts of a single provider in an update cycle.
+#[derive(Default)]
+struct GroupReport {
+    updated: usize,
+    pending: usize,
+    failed: usize,
+    /// Exit code of the most severe failure of a record, `0` if none failed.
+    exit_code: u8,
+    /// Whether the credentials are still known to be valid after the cycle.
+    verified: bool,
+    /// Per record the IP it is known to point to after the cycle.
+    confirmed: Vec<Option<String>>,
+    /// Indexes of the records written in the cycle.
+    written: Vec<usize>,
+    /// Per record found in sync or written in the cycle its index and content.
+    expected: Vec<(usize, String)>,
+    /// Per record changed outside of crondes its index, its content and the public IP it wasn't updated to.
+    conflicts: Vec<(usize, String, String)>,
+}
+
+/// Record counts of reconciling the zone with `SYNC_FILE` in an update cycle.
+struct SyncReport {
+    /// Number of records listed in the file.
+    total: usize,
+    updated: usize,
+    failed: usize,
+    /// Exit code of the most severe failure, `0` if none failed.
+    exit_code: u8,
+}
+
+/// Outcome of bringing a single record up to date.
+enum RecordOutcome {
+    /// The record already pointed to the public IP.
+    Unchanged,
+    /// The record was written.
+    Updated,
+    /// The record differs, but is not written yet, e.g. because the public IP is not stable yet.
+    Pending,
+    /// The record differs, but the pre-update hook vetoed the update.
+    Vetoed,
+    /// The record was changed outside of crondes to the given content and is not overwritten, see `FORCE_OVERWRITE`.
+    Conflict(String),
+    /// The record has to be changed from the given content and is left to a batch update.
+    Due(String),
+}
+
+impl GroupReport {
+    /// Logs and counts a record whose update failed with `error`.
+    fn fail(&mut self, record: &RecordConfig, error: &CrondesError) {
+        // Nach Authentifizierungsfehlern die Credentials im nächsten Zyklus erneut prüfen
+        if matches!(error, CrondesError::Auth(_)) {
+            self.verified = false;
+        }
+        error!("[{}] Record update failed: {}", record.label(), error);
+        METRICS.record_update_failed();
+        self.failed += 1;
+        self.exit_code = CrondesError::severest(self.exit_code, error.exit_code());
+    }
+}
+
+impl IpLookup {
+    /// Returns the index of the lookup in `lookups` detecting the address of `record`, adding it if there is none yet.
+    fn index_for(lookups: &mut Vec<IpLookup>, record: &RecordConfig, config: &Config, client: &reqwest::Client) -> usize {
+        let family = record.record_type.family();
+        let sources = if record.ip_sources.is_empty() { &config.ip.sources } else { &record.ip_sources };
+        let services = if record.ip_services.is_empty() { &config.ip.services } else { &record.ip_services };
+        if let Some(i) = lookups.iter().position(|l| l.same(family, sources, services)) {
+            return i;
+        }
+        lookups.push(IpLookup {
+            family,
+            sources: sources.clone(),
+            services: services.clone(),
+            detector: IpDetector::for_sources(config, client, sources, services, family),
+            observed: None,
+        });
+        lookups.len() - 1
+    }
+
+    /// Returns `true` if the lookup detects addresses of `family` with the given sources and services.
+    fn same(&self, family: IpFamily, sources: &[IpSourceKind], services: &[String]) -> bool {
+        self.family == family && self.sources == sources && self.services == services
+    }
+}
+
+/// Summary of a successful update cycle.
+#[derive(Debug, Clone)]
+pub struct CycleReport {
+    /// The detected public IP address.
+    pub public_ip: String,
+    /// Number of records that were written in this cycle.
+    pub updated: usize,
+    /// Number of records checked in this cycle.
+    pub total: usize,
+}
+
+impl Updater {
+    /// Creates a new [`Updater`] with the HTTP client and DNS providers described by `config`.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP client or one of the providers cannot be created from the configuration.
+    pub fn new(config: Config) -> Result<Self, CrondesError> {
+        let client = http::build_client(&config.http)
+            .map_err(|e| CrondesError::Config(format!("Failed to create HTTP client: {}", e)))?;
+        let mut providers = Vec::new();
+        for kind in config.providers() {
+            let records = config.records.iter().filter(|r| config.provider_of(r) == kind).cloned().collect();
+            providers.push((provider::from_config(kind, &config, client.clone())?, records));
+        }
+        let sync = if config.sync_file.is_empty() {
+            None
+        } else {
+            // Eine fehlerhafte Datei soll schon beim Start auffallen und nicht erst im ersten Zyklus
+            zonesync::read(&config.sync_file).map_err(CrondesError::Config)?;
+            Some(provider::from_config(config.provider, &config, client.clone())?)
+        };
+        let quiet = if config.quiet_hours.is_empty() {
+            None
+        } else {
+            Some(QuietHours::new(&config.quiet_hours, &config.quiet_hours_tz).map_err(|e| CrondesError::Config(format!("QUIET_HOURS: {}", e)))?)
+        };
+        let pattern_group = config.providers().iter().position(|&kind| kind == config.provider);
+        let mut updater = Updater::with_providers(config, providers, client);
+        updater.sync = sync;
+        updater.quiet = quiet;
+        if let Some(i) = pattern_group {
+            let group = &mut updater.providers[i];
+            group.pattern = Some(updater.config.record_pattern.clone()).filter(|pattern| !pattern.is_empty());
+            group.tag = Some(updater.config.record_tag.clone()).filter(|tag| !tag.is_empty());
+        }
+        Ok(updater)
+    }
+
+    /// Creates a new [`Updater`] using a custom DNS provider for all records. `client` is used for HTTP based IP sources.
+    pub fn with_provider(config: Config, provider: Box<dyn DnsProvider>, client: reqwest::Client) -> Self {
+        let records = config.records.clone();
+        Updater::with_providers(config, vec![(provider, records)], client)
+    }
+
+    /// Creates a new [`Updater`] pushing the public IP to every provider with the records it hosts.
+    pub fn with_providers(config: Config, providers: Vec<(Box<dyn DnsProvider>, Vec<RecordConfig>)>, client: reqwest::Client) -> Self {
+        let state = match &config.state_file {
+            path if path.is_empty() => State::default(),
+            path => State::load(path).unwrap_or_else(|e| {
+                warn!("{}. Starting with an empty state.", e);
+                State::default()
+            }),
+        };
+        let mut lookups = vec![IpLookup {
+            family: IpFamily::V4,
+            sources: config.ip.sources.clone(),
+            services: config.ip.services.clone(),
+            detector: IpDetector::from_config(&config, &client),
+            observed: None,
+        }];
+        // Der State einer Adressfamilie wird nur gespeichert, wenn alle ihre Records synchron sind
+        let mut groups = Vec::new();
+        for (provider, records) in providers {
+            let record_lookups: Vec<usize> =
+                records.iter().map(|record| IpLookup::index_for(&mut lookups, record, &config, &client)).collect();
+            groups.push(ProviderGroup {
+                provider,
+                confirmed: record_lookups.iter().map(|&i| state.ip(lookups[i].family).cloned()).collect(),
+                via_dns: vec![false; records.len()],
+                written: vec![None; records.len()],
+                expected: records
+                    .iter()
+                    .zip(&record_lookups)
+                    .map(|(record, &i)| state.ip(lookups[i].family).map(|ip| desired_content(record, ip)))
+                    .collect(),
+                lookups: record_lookups,
+                records,
+                pattern: None,
+                tag: None,
+                resolved: false,
+                verified: false,
+                backoff: Backoff::new(
+                    Duration::from_secs(config.retry.backoff_base_secs),
+                    Duration::from_secs(config.retry.backoff_max_secs),
+                ),
+                retry_at: None,
+                exit_code: 0,
+            });
+        }
+        for family in [IpFamily::V4, IpFamily::V6] {
+            if let Some(last_ip) = state.ip(family) {
+                let last_change = state.last_change(family, unix_now()).unwrap_or_else(|| "unknown".to_string());
+                info!("Last known {} from state file: {} (last change {})", family, last_ip, last_change);
+            }
+        }
+        let notifiers = Arc::new(Notifiers::from_config(&config, &client));
+        let propagation = PropagationCheck::from_config(&config.propagation).map(Arc::new);
+        let authoritative = (config.record_check == RecordCheck::Dns).then(AuthoritativeLookup::new);
+        let pre_update = PreUpdateHook::from_config(&config.hooks, &client);
+        let permits = Semaphore::new(config.update_concurrency.max(1));
+        Updater {
+            config,
+            providers: groups,
+            lookups,
+            permits,
+            state,
+            notifiers,
+            propagation,
+            authoritative,
+            propagating: Mutex::new(JoinSet::new()),
+            pre_update,
+            streaks: BTreeMap::new(),
+            conflicts: BTreeMap::new(),
+            cycles: 0,
+            source: None,
+            reported_ip: None,
+            sync: None,
+            quiet: None,
+            public_ip: None,
+            last_success: 0,
+        }
+    }
+
+    /// Enables reloading the configuration from `source` when the daemon receives SIGHUP.
+    pub fn reloadable(mut self, source: ConfigSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Returns the configuration of this updater.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the DNS providers used by this updater, each with the records it hosts.
+    ///
+    /// Record IDs are filled in once they have been resolved.
+    pub fn providers(&self) -> impl Iterator<Item = (&dyn DnsProvider, &[RecordConfig])> {
+        self.providers.iter().map(|group| (group.provider.as_ref(), group.records.as_slice()))
+    }
+
+    /// Waits until all propagation checks started by previous cycles have finished.
+    ///
+    /// Used by one-shot runs, which would otherwise exit before the resolvers were checked.
+    pub async fn wait_for_propagation(&mut self) {
+        let propagating = self.propagating.get_mut().unwrap_or_else(|e| e.into_inner());
+        while propagating.join_next().await.is_some() {}
+    }
+
+    /// Performs a single update cycle for all configured records.
+    ///
+    /// Records configured by name only are resolved on the first call.
+    ///
+    /// # Errors
+    /// Returns an error if the cycle failed; see [`CrondesError::is_fatal`] to decide whether to retry.
+    pub async fn update_once(&mut self) -> Result<CycleReport, CrondesError> {
+        self.cycle(None).await
+    }
+
+    /// Runs the update daemon until a shutdown signal (SIGINT/SIGTERM) is received or, with `EXIT_ON_ERROR`, until
+    /// too many update cycles in a row have failed.
+    ///
+    /// SIGUSR1 starts an update cycle right away, SIGHUP reloads the configuration if the updater is
+    /// [reloadable](Updater::reloadable).
+    ///
+    /// # Errors
+    /// Returns the error that made the daemon give up.
+    pub async fn run(self) -> Result<(), CrondesError> {
+        self.run_until(async {
+            let signal = signals::shutdown_signal().await;
+            info!("Received {}", signal);
+        })
+        .await
+    }
+
+    /// Runs the update daemon until the `shutdown` future completes or, with `EXIT_ON_ERROR`, until too many update
+    /// cycles in a row have failed.
+    ///
+    /// Failed cycles are retried with exponential backoff instead of waiting for the regular interval. With
+    /// `EXIT_ON_ERROR`, fatal errors (see [`CrondesError::is_fatal`]) stop the daemon right away. On shutdown the
+    /// in-flight cycle is finished before this function returns.
+    ///
+    /// # Errors
+    /// Returns the error that made the daemon give up.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> Result<(), CrondesError> {
+        Updater::run_all_until(vec![self], shutdown).await
+    }
+
+    /// Runs the updaters of several jobs as one daemon until a shutdown signal (SIGINT/SIGTERM) is received, see
+    /// [`Updater::run_all_until`].
+    ///
+    /// # Errors
+    /// Returns the error that made a job give up.
+    pub async fn run_all(updaters: Vec<Updater>) -> Result<(), CrondesError> {
+        Updater::run_all_until(updaters, async {
+            let signal = signals::shutdown_signal().await;
+            info!("Received {}", signal);
+        })
+        .await
+    }
+
+    /// Runs the updaters of several jobs as one daemon like [`Updater::run_until`], each with its own scheduler, until
+    /// the `shutdown` future completes or all of them gave up.
+    ///
+    /// The HTTP server, the control socket and the watches of the network and the config file are started once, with
+    /// the settings of the first updater, and serve all jobs: triggering, pausing and reloading applies to every job,
+    /// and the published status combines them. If a job gives up, it is logged and the others keep running.
+    ///
+    /// # Errors
+    /// Returns the error that made the first job give up, once the daemon stops.
+    pub async fn run_all_until(updaters: Vec<Updater>, shutdown: impl Future<Output = ()>) -> Result<(), CrondesError> {
+        let Some(first) = updaters.first() else { return Ok(()) };
+        let config = first.config.clone();
+        let interval = Duration::from_secs(config.update_interval_secs);
+        let jobs: Vec<String> = updaters.iter().map(|updater| updater.job().to_string()).collect();
+        let control = Arc::new(DaemonControl::new(jobs.clone()));
+
+        // HTTP-Server für Metriken, Health-Check und Steuerung starten, falls konfiguriert
+        if !config.server.bind.is_empty() {
+            // Das längste mögliche Intervall jedes Jobs zählen, damit der Jitter den Health-Check nicht kippt.
+            // Ohne Webhook läuft kein Zyklus, deshalb gibt es für Webhook-Jobs kein Alter, ab dem der Daemon ungesund wäre
+            let health_windows = updaters
+                .iter()
+                .map(|updater| {
+                    let interval = Duration::from_secs(updater.config.update_interval_secs);
+                    let longest = interval.mul_f64(1.0 + updater.config.update_interval_jitter_percent as f64 / 100.0);
+                    (updater.config.update_mode == UpdateMode::Poll).then(|| longest * config.server.health_max_intervals)
+                })
+                .collect();
+            server::spawn(&config.server, health_windows, control.clone()).await?;
+        }
+
+        let socket = config.control_socket.clone();
+        if !socket.is_empty() {
+            control::socket::spawn(&socket, control.clone()).map_err(CrondesError::Config)?;
+        }
+
+        // Netzwerkänderungen lösen einen Zyklus vor Ablauf des Intervalls aus
+        if config.network_watch && let Err(e) = netwatch::spawn(control.clone()) {
+            warn!("{}. Falling back to polling every {} seconds.", e, interval.as_secs());
+        }
+
+        // Änderungen an der Config-Datei werden ab dem nächsten Zyklus übernommen
+        if config.config_watch
+            && let Some(path) = first.source.as_ref().and_then(|s| s.file.clone())
+            && let Err(e) = configwatch::spawn(&path, control.clone())
+        {
+            warn!("{}. Reload it with SIGHUP instead.", e);
+        }
+
+        // Einen Scheduler pro Job starten
+        let mut schedulers = JoinSet::new();
+        let mut stops = Vec::new();
+        for (job, updater) in updaters.into_iter().enumerate() {
+            let (trigger, reload) = control.wakeups(job);
+            let stop = Arc::new(Notify::new());
+            stops.push(stop.clone());
+            let watchdog = systemd::watchdog_interval().map(|period| {
+                let mut watchdog = tokio::time::interval(period);
+                watchdog.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                watchdog
+            });
+            let wakeups = Wakeups { stop, trigger, reload, signals: ControlSignals::new(), watchdog };
+            let control = control.clone();
+            schedulers.spawn(async move { (job, updater.schedule(job, wakeups, control).await) });
+        }
+
+        // Warten auf Shutdown: durch externes Signal oder weil alle Jobs aufgegeben haben
+        let mut result = Ok(());
+        let mut shutdown = std::pin::pin!(shutdown);
+        loop {
+            tokio::select! {
+                stopped = schedulers.join_next() => {
+                    let Some(stopped) = stopped else { break };
+                    let (job, stopped) = stopped.expect("Scheduler task panicked");
+                    // Ein aufgebender Job hält die übrigen nicht an
+                    if let Err(e) = &stopped && !schedulers.is_empty() {
+                        error!("Job {} gave up: {}. The other jobs keep running.", jobs[job], e);
+                    }
+                    if result.is_ok() {
+                        result = stopped;
+                    }
+                }
+                _ = &mut shutdown => {
+                    info!("Shutdown requested, finishing current update cycle...");
+                    systemd::notify("STOPPING=1");
+                    break;
+                }
+            }
+        }
+        // notify_one speichert eine Erlaubnis, ein Scheduler mitten im Zyklus hält also vor dem nächsten Warten an
+        for stop in &stops {
+            stop.notify_one();
+        }
+        while let Some(stopped) = schedulers.join_next().await {
+            let (_, stopped) = stopped.expect("Scheduler task panicked");
+            if result.is_ok() {
+                result = stopped;
+            }
+        }
+        if !socket.is_empty() {
+            let _ = std::fs::remove_file(&socket);
+        }
+        info!("Scheduler stopped. Exiting.");
+        result
+    }
+
+    /// The scheduler loop: runs update cycles until `stop` is notified or the daemon gives up.
+    ///
+    /// Between cycles it waits for whatever comes first: the timer or one of the `wakeups`. With `UPDATE_MODE=webhook`
+    /// only failed cycles are retried on a timer, otherwise cycles run when a webhook arrives. While `control` is paused,
+    /// cycles are skipped, and so are they while the `CONNECTIVITY_CHECK` fails, without counting as failed. During
+    /// `STARTUP_GRACE_SECS`, cycles wait for the system clock to be set and failures are retried without counting as
+    /// failed either. After each cycle the state of the daemon is published to `control` as the state of `job`.
+    async fn schedule(mut self, job: usize, mut wakeups: Wakeups, control: Arc<DaemonControl>) -> Result<(), CrondesError> {
+        let mut backoff = Backoff::new(
+            Duration::from_secs(self.config.retry.backoff_base_secs),
+            Duration::from_secs(self.config.retry.backoff_max_secs),
+        );
+        let splay = Duration::from_secs(self.config.startup_splay_secs);
+        let startup_delay = Duration::from_secs(self.config.startup_delay_secs);
+        // systemd erfährt erst nach dem ersten erfolgreichen Zyklus, dass der Dienst bereit ist
+        let mut ready = false;
+        if self.config.update_mode == UpdateMode::Webhook {
+            info!("Waiting for the first webhook...");
+            systemd::notify("READY=1\nSTATUS=Waiting for the first webhook");
+            ready = true;
+            if !self.wait(None, &mut wakeups).await {
+                return Ok(());
+            }
+        } else if !splay.is_zero() || !startup_delay.is_zero() {
+            let delay = startup_delay + retry::splay(splay);
+            info!("Waiting {} seconds before the first update cycle (STARTUP_DELAY_SECS, STARTUP_SPLAY_SECS)...", delay.as_secs());
+            if !self.wait(Some(delay), &mut wakeups).await {
+                return Ok(());
+            }
+        }
+        // Fehler beim Booten zählen nicht gegen RETRY_MAX_FAILURES und haben ihren eigenen Backoff
+        let grace_until = Instant::now() + Duration::from_secs(self.config.startup_grace_secs);
+        let mut grace_backoff = Backoff::new(
+            Duration::from_secs(self.config.retry.backoff_base_secs),
+            Duration::from_secs(self.config.retry.backoff_max_secs),
+        );
+        let mut run_count = 0;
+        // Mit UPDATE_INTERVAL_SECS_V6 prüft ein Zyklus nur die fällige Adressfamilie, None steht für beide
+        let mut timers = FamilyTimers::new();
+        let mut family = None;
+        let mut offline = false;
+        loop {
+            let max_failures = self.config.retry.max_consecutive_failures;
+            let interval = match self.config.update_mode {
+                UpdateMode::Poll => Some(Duration::from_secs(self.config.update_interval_secs)),
+                UpdateMode::Webhook => None,
+            };
+            let interval_v6 = interval.and_then(|_| self.separate_v6_interval());
+            if control.is_paused() {
+                // Pausiert wird nur das Aktualisieren, Signale und Reloads werden weiter verarbeitet
+                info!("Update cycles are paused, waiting to be resumed...");
+                systemd::notify("STATUS=Paused");
+                self.publish(&control, job, backoff.failures().into(), None, false);
+                if !self.wait(interval, &mut wakeups).await {
+                    return Ok(());
+                }
+                continue;
+            }
+            if self.config.quiet_hours_mode == QuietHoursMode::Skip
+                && let Some(window) = self.quiet_window()
+            {
+                info!("Quiet hours ({}), skipping the update cycle", window);
+                systemd::notify("STATUS=Quiet hours, updates are suspended");
+                self.publish(&control, job, backoff.failures().into(), interval.map(|delay| unix_now() + delay.as_secs()), false);
+                if !self.wait(interval, &mut wakeups).await {
+                    return Ok(());
+                }
+                continue;
+            }
+            if let Err(reason) = self.probe_connectivity().await {
+                // Ohne Netz zählt der Zyklus nicht als fehlgeschlagen, damit das Fehlerbudget nicht aufgebraucht wird
+                if !offline {
+                    warn!("Network is down ({}), skipping update cycles until it is back", reason);
+                    offline = true;
+                }
+                systemd::notify("STATUS=Offline, waiting for the network");
+                self.publish(&control, job, backoff.failures().into(), interval.map(|delay| unix_now() + delay.as_secs()), true);
+                if !self.wait(interval, &mut wakeups).await {
+                    return Ok(());
+                }
+                continue;
+            }
+            if offline {
+                info!("Network is back, resuming update cycles");
+                offline = false;
+            }
+            let in_grace = Instant::now() < grace_until;
+            if in_grace && unix_now() < CLOCK_SET_AFTER {
+                let delay = grace_backoff.next_delay();
+                warn!("System clock reads {}, waiting {} seconds for it to be set (STARTUP_GRACE_SECS)", now_rfc3339(), delay.as_secs());
+                systemd::notify("STATUS=Waiting for the system clock to be set");
+                if !self.wait(Some(delay), &mut wakeups).await {
+                    return Ok(());
+                }
+                continue;
+            }
+            if let Some(ip) = control.take_reported_ip(job) {
+                self.reported_ip = Some(ip);
+            }
+            run_count += 1;
+            systemd::notify("WATCHDOG=1");
+            match self.source.as_ref().and_then(|source| source.job.as_deref()) {
+                Some(name) => info!("--- Update loop iteration #{} of job {} ---", run_count, name),
+                None => info!("--- Update loop iteration #{} ---", run_count),
+            }
+            match family {
+                Some(family) => info!("Starting update cycle for the {} records...", family),
+                None => info!("Starting update cycle..."),
+            }
+            let result = self.cycle(family).await;
+            control.cycle_finished(result.as_ref().err().map(|e| e.to_string()));
+            let ran = family.take();
+            let delay = match result {
+                Ok(report) => {
+                    info!("Update completed successfully.");
+                    let status = format!("STATUS=Public IP {}, {} of {} records updated", report.public_ip, report.updated, report.total);
+                    if ready {
+                        systemd::notify(&status);
+                    } else {
+                        systemd::notify(&format!("READY=1\n{}", status));
+                        ready = true;
+                    }
+                    backoff.reset();
+                    let jitter = self.config.update_interval_jitter_percent;
+                    match (interval, interval_v6) {
+                        (Some(interval), Some(interval_v6)) => {
+                            let (next, delay) = timers.next(ran, retry::jitter(interval, jitter), retry::jitter(interval_v6, jitter));
+                            family = next;
+                            Some(delay)
+                        }
+                        (interval, _) => interval.map(|interval| retry::jitter(interval, jitter)),
+                    }
+                }
+                Err(e) if in_grace => {
+                    let delay = grace_backoff.next_delay();
+                    warn!("Update failed during the startup grace period: {}. Retrying in {} seconds.", e, delay.as_secs());
+                    systemd::notify(&format!("STATUS=Starting, update failed: {}", e.to_string().replace('\n', " ")));
+                    Some(delay)
+                }
+                Err(e) if e.is_fatal() && self.config.exit_on_error => {
+                    error!("Update failed with a fatal error: {}. Shutting down scheduler.", e);
+                    return Err(e);
+                }
+                Err(e) => {
+                    let delay = backoff.next_delay();
+                    if self.config.exit_on_error && max_failures > 0 && backoff.failures() >= max_failures {
+                        error!("Update failed {} times in a row: {}. Shutting down scheduler.", backoff.failures(), e);
+                        return Err(e);
+                    }
+                    if e.is_fatal() {
+                        // Ohne EXIT_ON_ERROR bleibt der Daemon am Leben, bis Config oder Zugangsdaten korrigiert sind
+                        error!(
+                            "Update failed ({} in a row): {}. Retrying with backoff, this won't succeed until the configuration is fixed.",
+                            backoff.failures(), e
+                        );
+                    } else {
+                        error!("Update failed ({} in a row): {}. Retrying with backoff.", backoff.failures(), e);
+                    }
+                    systemd::notify(&format!("STATUS=Update failed ({} in a row): {}", backoff.failures(), e.to_string().replace('\n', " ")));
+                    Some(match e {
+                        // Bei Rate-Limits mindestens so lange warten, wie der Provider verlangt
+                        CrondesError::RateLimited { retry_after, .. } => delay.max(retry_after.unwrap_or_default()),
+                        // Fehlgeschlagene Provider haben ihren eigenen Backoff, der nächste davon bestimmt die Wartezeit
+                        _ => self.next_retry().unwrap_or(delay),
+                    })
+                }
+            };
+            match delay {
+                Some(delay) => info!("Waiting {} seconds until next iteration...", delay.as_secs()),
+                None => info!("Waiting for the next webhook..."),
+            }
+            self.publish(&control, job, backoff.failures().into(), delay.map(|delay| unix_now() + delay.as_secs()), false);
+            let deadline = delay.map(|delay| Instant::now() + delay);
+            if !self.wait(delay, &mut wakeups).await {
+                return Ok(());
+            }
+            // Netzwerkänderungen, Signale und Reloads prüfen alle Records
+            if deadline.is_some_and(|deadline| Instant::now() < deadline) {
+                family = None;
+            }
+        }
+    }
+
+    /// Checks that the network is up with `CONNECTIVITY_CHECK`, giving up after `HTTP_CONNECT_TIMEOUT_SECS`.
+    ///
+    /// # Errors
+    /// Returns why the network seems to be down.
+    async fn probe_connectivity(&self) -> Result<(), String> {
+        let timeout = Duration::from_secs(self.config.http.connect_timeout_secs);
+        connectivity::probe(self.config.connectivity_check, &self.config.connectivity_target, timeout).await
+    }
+
+    /// Returns the window of `QUIET_HOURS` the current time falls into, if any.
+    fn quiet_window(&self) -> Option<&str> {
+        self.quiet.as_ref()?.active(jiff::Timestamp::now())
+    }
+
+    /// Returns the interval of `UPDATE_INTERVAL_SECS_V6` if it is set and a record holds an IPv6 address, so IPv4 and
+    /// IPv6 records run on their own timers.
+    fn separate_v6_interval(&self) -> Option<Duration> {
+        let seconds = self.config.update_interval_secs_v6;
+        let v6_used = (0..self.lookups.len()).any(|i| self.lookups[i].family == IpFamily::V6 && self.lookup_used(i));
+        (seconds > 0 && v6_used).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Returns the name of the job this updater runs, empty for the unnamed job of a config without jobs.
+    fn job(&self) -> &str {
+        self.source.as_ref().and_then(|source| source.job.as_deref()).unwrap_or_default()
+    }
+
+    /// Publishes the state of `job` to `control` and writes the state of the daemon to `STATUS_FILE`, if set.
+    fn publish(&self, control: &DaemonControl, job: usize, consecutive_failures: u64, next_cycle: Option<u64>, offline: bool) {
+        let status = Status {
+            offline,
+            public_ip: self.public_ip.clone(),
+            last_success: self.last_success,
+            consecutive_failures,
+            next_cycle,
+            ..Status::default()
+        };
+        control.publish(job, status, self.record_status());
+        if !self.config.status_file.is_empty()
+            && let Err(e) = control.write_status_file(&self.config.status_file)
+        {
+            warn!("{}", e);
+        }
+    }
+
+    /// Returns the managed records and the IP they were last confirmed to point to, for the control API.
+    fn record_status(&self) -> Vec<RecordStatus> {
+        self.providers
+            .iter()
+            .flat_map(|group| {
+                group.records.iter().zip(&group.confirmed).map(|(record, ip)| RecordStatus {
+                    provider: group.provider.name().to_string(),
+                    name: record.name.clone(),
+                    id: record.id.clone(),
+                    ip: ip.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Waits `delay` before the next cycle, or less if one of the `wakeups` arrives first. Without a delay only the
+    /// `wakeups` end the wait. The systemd watchdog is pinged while waiting.
+    ///
+    /// # Returns
+    /// `false` if `stop` was notified and the scheduler should exit instead.
+    async fn wait(&mut self, delay: Option<Duration>, wakeups: &mut Wakeups) -> bool {
+        let timer = async {
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(timer);
+        loop {
+            tokio::select! {
+                _ = &mut timer => return true,
+                _ = wakeups.trigger.notified() => return true,
+                _ = tick(&mut wakeups.watchdog) => systemd::notify("WATCHDOG=1"),
+                _ = wakeups.reload.notified() => {
+                    info!("Reloading the configuration");
+                    self.reload().await;
+                    return true;
+                }
+                control = wakeups.signals.recv() => {
+                    match control {
+                        Control::Trigger => info!("Received SIGUSR1, starting an update cycle"),
+                        Control::Reload => {
+                            info!("Received SIGHUP, reloading the configuration");
+                            self.reload().await;
+                        }
+                    }
+                    return true;
+                }
+                _ = wakeups.stop.notified() => return false,
+            }
+        }
+    }
+
+    /// Loads the configuration again and switches to it, keeping the state of the running daemon.
+    ///
+    /// Providers are created from scratch, so their credentials are checked again in the next cycle. Records that were
+    /// already managed keep their confirmed IP. If the new configuration is invalid or its records cannot be resolved,
+    /// the current one stays in use. Changes of `SERVER_BIND`, `NETWORK_WATCH`, `CONFIG_WATCH` and `CONTROL_SOCKET` only
+    /// take effect after a restart.
+    async fn reload(&mut self) {
+        let Some(source) = self.source.clone() else {
+            warn!("This updater was not created from a config source, ignoring the reload request");
+            return;
+        };
+        let mut reloaded = match source.load().map_err(CrondesError::Config).and_then(Updater::new) {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                error!("Reloading the configuration from {} failed, keeping the current one: {}", source, e);
+                return;
+            }
+        };
+        if let Err(e) = reloaded.resolve_records().await {
+            error!("Reloading the configuration from {} failed, keeping the current one: {}", source, e);
+            return;
+        }
+        if let Err(e) = logging::configure(&reloaded.config.log) {
+            warn!("Keeping the current log output: {}", e);
+        }
+        if reloaded.config.server.bind != self.config.server.bind
+            || reloaded.config.network_watch != self.config.network_watch
+            || reloaded.config.config_watch != self.config.config_watch
+            || reloaded.config.control_socket != self.config.control_socket
+            || reloaded.config.pid_file != self.config.pid_file
+        {
+            warn!("Changes of SERVER_BIND, NETWORK_WATCH, CONFIG_WATCH, CONTROL_SOCKET and PID_FILE take effect after a restart");
+        }
+        for group in &mut reloaded.providers {
+            let name = group.provider.name();
+            for (i, record) in group.records.iter().enumerate() {
+                // Neue Records gelten als unbekannt und werden im nächsten Zyklus gelesen
+                let old = self.providers.iter().filter(|old| old.provider.name() == name).find_map(|old| {
+                    old.records.iter().position(|old| old.label() == record.label()).map(|j| (old, j))
+                });
+                group.confirmed[i] = old.and_then(|(old, j)| old.confirmed[j].clone());
+                group.written[i] = old.and_then(|(old, j)| old.written[j]);
+                // Ohne bekannten alten Inhalt bleibt der aus dem State-File
+                if let Some(expected) = old.and_then(|(old, j)| old.expected[j].clone()) {
+                    group.expected[i] = Some(expected);
+                }
+            }
+        }
+        for lookup in &mut reloaded.lookups {
+            lookup.observed = self
+                .lookups
+                .iter_mut()
+                .find(|old| old.same(lookup.family, &lookup.sources, &lookup.services))
+                .and_then(|old| old.observed.take());
+        }
+        reloaded.reported_ip = self.reported_ip.take();
+        reloaded.streaks = std::mem::take(&mut self.streaks);
+        reloaded.conflicts = std::mem::take(&mut self.conflicts);
+        reloaded.cycles = self.cycles;
+        reloaded.public_ip = self.public_ip.take();
+        reloaded.last_success = self.last_success;
+        reloaded.propagating = std::mem::take(&mut self.propagating);
+        reloaded.source = Some(source);
+        *self = reloaded;
+        info!("Reloaded config:");
+        for line in self.config.to_string().lines() {
+            info!("  {}", line);
+        }
+    }
+
+    /// Returns the time until the next failed provider is due again, if any provider is backing off.
+    fn next_retry(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.providers.iter().filter_map(|group| group.retry_at).min().map(|at| at.saturating_duration_since(now))
+    }
+
+    /// Resolves the IDs of all records that are configured by name only and adds the A records matching
+    /// `CF_RECORD_PATTERN` or carrying `CF_RECORD_TAG`, unless they are configured already.
+    ///
+    /// With `RECORD_CHECK=dns` it also decides which records can be read from their nameservers: records without
+    /// a name, AAAA records, records of providers already reading from DNS and records proxied by Cloudflare keep using
+    /// the API.
+    /// The results are cached, so the lookup only happens once per provider.
+    async fn resolve_records(&mut self) -> Result<(), CrondesError> {
+        let last_ip = self.state.ip.clone();
+        for group in self.providers.iter_mut().filter(|g| !g.resolved) {
+            if group.provider.uses_record_ids() {
+                for record in group.records.iter_mut().filter(|r| r.id.is_empty()) {
+                    record.id = group.provider.find_record_id(&record.name, record.record_type).await?;
+                    info!("[{}] Resolved record ID: {}", record.name, record.id);
+                }
+            }
+            if group.pattern.is_some() || group.tag.is_some() {
+                if !group.provider.uses_record_ids() {
+                    return Err(CrondesError::Config(format!(
+                        "CF_RECORD_PATTERN and CF_RECORD_TAG need a provider that can list records, {} can't",
+                        group.provider.name()
+                    )));
+                }
+                let listed: Vec<_> = group.provider.list_records().await?.into_iter().filter(|r| r.record_type == "A").collect();
+                if let Some(pattern) = group.pattern.clone() {
+                    let matching: Vec<_> = listed.iter().filter(|r| glob_matches(&pattern, &r.name)).collect();
+                    if matching.is_empty() {
+                        warn!("No A record matches CF_RECORD_PATTERN {}", pattern);
+                    }
+                    for rec in matching {
+                        if adopt(group, rec, &last_ip) {
+                            info!("[{}] Matches CF_RECORD_PATTERN {} (ID: {})", rec.name, pattern, rec.id);
+                        }
+                    }
+                }
+                if let Some(tag) = group.tag.clone() {
+                    let tagged: Vec<_> = listed.iter().filter(|r| r.tags.contains(&tag)).collect();
+                    if tagged.is_empty() {
+                        warn!("No A record carries CF_RECORD_TAG {}", tag);
+                    }
+                    for rec in tagged {
+                        if adopt(group, rec, &last_ip) {
+                            info!("[{}] Carries CF_RECORD_TAG {} (ID: {})", rec.name, tag, rec.id);
+                        }
+                    }
+                }
+            }
+            if self.authoritative.is_some() && !group.provider.reads_from_dns() {
+                for (record, via_dns) in group.records.iter().zip(group.via_dns.iter_mut()) {
+                    if record.name.is_empty() {
+                        warn!("[{}] Record has no name, reading it from the {} API", record.label(), group.provider.name());
+                    } else if record.record_type != RecordType::A {
+                        info!("[{}] Record is {}, reading it from the {} API", record.label(), record.record_type, group.provider.name());
+                    } else if group.provider.is_proxied(record).await? {
+                        info!("[{}] Record is proxied, reading it from the {} API", record.label(), group.provider.name());
+                    } else {
+                        *via_dns = true;
+                    }
+                }
+            }
+            group.resolved = true;
+        }
+        Ok(())
+    }
+
+    /// Führt einen vollständigen Update-Zyklus durch: Credentials prüfen und alle Records aktualisieren.
+    ///
+    /// A failing record does not abort the cycle; all records are processed and the cycle
+    /// only reports an error afterwards if at least one of them failed.
+    ///
+    /// Pings the heartbeat URL after every cycle. A cycle running longer than `CYCLE_DEADLINE_SECS` is aborted and
+    /// fails with [`CrondesError::Deadline`]. Failures are counted per component, see [`Updater::component_failed`].
+    async fn cycle(&mut self, family: Option<IpFamily>) -> Result<CycleReport, CrondesError> {
+        let result = match self.config.cycle_deadline_secs {
+            0 => self.update_records(family).await,
+            secs => {
+                let deadline = Duration::from_secs(secs);
+                // Abgebrochene Records gelten als nicht bestätigt und werden im nächsten Zyklus erneut geprüft
+                tokio::time::timeout(deadline, self.update_records(family))
+                    .await
+                    .unwrap_or(Err(CrondesError::Deadline(deadline)))
+            }
+        };
+        if result.is_ok() {
+            self.reported_ip = None;
+            self.last_success = unix_now();
+        }
+        METRICS.cycle_finished(self.job(), result.is_ok());
+        let message = match &result {
+            Ok(report) => format!("Public IP {}, {} of {} records updated", report.public_ip, report.updated, report.total),
+            Err(e) => e.to_string(),
+        };
+        self.notifiers.heartbeat(result.is_ok(), &message).await;
+        match &result {
+            Ok(_) => self.component_succeeded(UPDATE_CYCLE).await,
+            // Diese Fehler hat update_records schon der IP-Ermittlung oder dem Provider zugeordnet
+            Err(CrondesError::Records { .. } | CrondesError::IpDetection(_) | CrondesError::NonPublicIp { .. }) => {}
+            Err(e) => self.component_failed(UPDATE_CYCLE, &e.to_string()).await,
+        }
+        result
+    }
+
+    /// Counts a failed cycle of `component` and notifies once it failed `NOTIFY_FAILURE_THRESHOLD` cycles in a row.
+    async fn component_failed(&mut self, component: &str, error: &str) {
+        let streak = self.streaks.entry(component.to_string()).or_insert_with(|| Streak { failures: 0, since: now_rfc3339() });
+        streak.failures += 1;
+        if streak.failures == self.config.notify.failure_threshold {
+            let event = Event::UpdateFailed {
+                component: component.to_string(),
+                since: streak.since.clone(),
+                failures: streak.failures,
+                error: error.to_string(),
+            };
+            self.notifiers.send(event).await;
+        }
+    }
+
+    /// Ends the failure streak of `component`, notifying that it recovered if its failures were notified.
+    async fn component_succeeded(&mut self, component: &str) {
+        let threshold = self.config.notify.failure_threshold;
+        if let Some(streak) = self.streaks.remove(component)
+            && threshold > 0
+            && streak.failures >= threshold
+        {
+            self.notifiers.send(Event::Recovered { component: component.to_string(), failures: streak.failures }).await;
+        }
+    }
+
+    /// Detects the public IPs and updates the records of every provider that is not backing off, after resolving the
+    /// records configured by name only.
+    ///
+    /// A provider whose credential check fails with a fatal error (see [`CrondesError::is_fatal`]) or that rate-limits
+    /// the requests aborts the cycle; any other failure only counts its records as failed and makes the provider back off on its own.
+    /// Likewise a public IP that can't be detected only fails the records using it, unless no IP was detected at all.
+    /// With `family`, only the records holding an address of that family are checked.
+    async fn update_records(&mut self, family: Option<IpFamily>) -> Result<CycleReport, CrondesError> {
+        // Records ohne ID werden im ersten Zyklus aufgelöst, damit ein Fehlschlag wie jeder andere wiederholt wird
+        self.resolve_records().await?;
+        let checked: Vec<bool> = self.lookups.iter().map(|lookup| family.is_none_or(|family| lookup.family == family)).collect();
+        let used: Vec<bool> = (0..self.lookups.len()).map(|i| checked[i] && self.lookup_used(i)).collect();
+        let several = used.iter().filter(|&&used| used).count() > 1;
+        let mut detected = Vec::new();
+        let mut detection_error = None;
+        let mut detection_failures: Vec<(IpFamily, String)> = Vec::new();
+        for (i, &used) in used.iter().enumerate() {
+            if !used {
+                detected.push(None);
+                continue;
+            }
+            match self.detect(i).await {
+                Ok(public_ip) => detected.push(Some(public_ip)),
+                Err(e) => {
+                    if several {
+                        error!("{}", e);
+                    }
+                    detection_failures.push((self.lookups[i].family, e.to_string()));
+                    detection_error.get_or_insert(e);
+                    detected.push(None);
+                }
+            }
+        }
+        for family in [IpFamily::V4, IpFamily::V6] {
+            let component = format!("{} detection", family);
+            match detection_failures.iter().find(|(failed, _)| *failed == family) {
+                Some((_, error)) => self.component_failed(&component, error).await,
+                None if (0..self.lookups.len()).any(|i| used[i] && self.lookups[i].family == family) => {
+                    self.component_succeeded(&component).await;
+                }
+                None => {}
+            }
+        }
+        if detected.iter().all(Option::is_none) {
+            return Err(detection_error.unwrap_or_else(|| CrondesError::IpDetection("No IP source is configured".to_string())));
+        }
+        let mut total = self.providers.iter().flat_map(|g| &g.lookups).filter(|&&lookup| checked[lookup]).count();
+        self.cycles += 1;
+        let every = self.config.revalidate_every_n_cycles;
+        let revalidate = every > 0 && self.cycles.is_multiple_of(every);
+        let reread = self.cycles.is_multiple_of(self.config.reread_every_n_cycles);
+        let mut failed = 0;
+        let mut exit_code = 0;
+        let mut updated = 0;
+        let mut pending = 0;
+        let mut backing_off = Vec::new();
+        let mut provider_failures: Vec<(String, Option<String>)> = Vec::new();
+        let mut conflicts = Vec::new();
+        for i in 0..self.providers.len() {
+            let group = &self.providers[i];
+            let name = group.provider.name();
+            if let Some(retry_at) = group.retry_at && retry_at > Instant::now() {
+                info!(
+                    "{} is backing off after {} failures, retrying in {} seconds",
+                    name, group.backoff.failures(), retry_at.saturating_duration_since(Instant::now()).as_secs()
+                );
+                failed += group.lookups.iter().filter(|&&lookup| checked[lookup]).count();
+                exit_code = CrondesError::severest(exit_code, group.exit_code);
+                backing_off.push(true);
+                continue;
+            }
+            backing_off.push(false);
+            // Records, deren IP nicht ermittelt werden konnte, bleiben unverändert und zählen als fehlgeschlagen
+            let undetected = group.lookups.iter().filter(|&&lookup| checked[lookup] && detected[lookup].is_none()).count();
+            if undetected > 0 {
+                failed += undetected;
+                exit_code = CrondesError::severest(exit_code, IP_DETECTION_FAILED);
+            }
+            let report = self.update_group(group, &detected, &checked, revalidate, reread).await?;
+            let group = &mut self.providers[i];
+            group.verified = report.verified;
+            group.confirmed = report.confirmed;
+            for &written in &report.written {
+                group.written[written] = Some(Instant::now());
+            }
+            for (written, content) in report.expected {
+                self.conflicts.remove(group.records[written].label());
+                group.expected[written] = Some(content);
+            }
+            for (conflict, found, public_ip) in report.conflicts {
+                let record = group.records[conflict].label().to_string();
+                // Jeder fremde Inhalt wird nur einmal gemeldet
+                if self.conflicts.get(&record) != Some(&found) {
+                    self.conflicts.insert(record.clone(), found.clone());
+                    let expected = group.expected[conflict].clone().unwrap_or_default();
+                    conflicts.push(Event::Conflict { record, expected, found, new_ip: public_ip });
+                }
+            }
+            METRICS.provider_checked(name, report.failed == 0);
+            if report.failed > 0 {
+                let delay = group.backoff.next_delay();
+                group.retry_at = Some(Instant::now() + delay);
+                group.exit_code = report.exit_code;
+                error!(
+                    "{}: {} of {} records failed ({} in a row), retrying in {} seconds",
+                    name, report.failed, group.records.len(), group.backoff.failures(), delay.as_secs()
+                );
+                provider_failures.push((name.to_string(), Some(format!("{} of {} records failed", report.failed, group.records.len()))));
+            } else {
+                group.backoff.reset();
+                group.retry_at = None;
+                provider_failures.push((name.to_string(), None));
+            }
+            updated += report.updated;
+            pending += report.pending;
+            failed += report.failed;
+            exit_code = CrondesError::severest(exit_code, report.exit_code);
+        }
+        for event in conflicts {
+            self.notifiers.send(event).await;
+        }
+        for (provider, error) in provider_failures {
+            match error {
+                Some(error) => self.component_failed(&provider, &error).await,
+                None => self.component_succeeded(&provider).await,
+            }
+        }
+        let mut sync_failed = false;
+        let mut sync_error = None;
+        if let Some(provider) = &self.sync
+            && checked[0]
+        {
+            match &detected[0] {
+                Some(public_ip) if public_ip.stable => {
+                    let report = self.sync_zone(provider.as_ref(), &public_ip.address).await?;
+                    total += report.total;
+                    updated += report.updated;
+                    failed += report.failed;
+                    exit_code = CrondesError::severest(exit_code, report.exit_code);
+                    sync_failed = report.failed > 0;
+                    sync_error = Some(sync_failed.then(|| format!("{} of {} records failed", report.failed, report.total)));
+                }
+                Some(_) => info!("[sync] Waiting until the public IP is stable before reconciling the zone with SYNC_FILE"),
+                None => {
+                    total += 1;
+                    failed += 1;
+                    exit_code = CrondesError::severest(exit_code, IP_DETECTION_FAILED);
+                }
+            }
+        }
+        match sync_error {
+            Some(Some(error)) => self.component_failed("zone sync", &error).await,
+            Some(None) => self.component_succeeded("zone sync").await,
+            None => {}
+        }
+        info!("{} of {} records in sync.", total.saturating_sub(failed + pending), total);
+        // Jede Adressfamilie wird für sich gespeichert, ein IPv6-Fehler hält den IPv4-State nicht auf
+        for family in [IpFamily::V4, IpFamily::V6] {
+            if let Some(public_ip) = self.family_in_sync(family, &detected, &backing_off)
+                && !(family == IpFamily::V4 && sync_failed)
+            {
+                let public_ip = public_ip.to_string();
+                self.save_state(family, &public_ip);
+            }
+        }
+        if failed > 0 {
+            return Err(CrondesError::Records { failed, total, exit_code });
+        }
+        let public_ip = detected.iter().flatten().map(|ip| ip.address.as_str()).collect::<Vec<_>>().join(", ");
+        Ok(CycleReport { public_ip, updated, total })
+    }
+
+    /// Returns the address to remember for `family` if every record of that family is confirmed to point to the
+    /// address `detected` by its lookup, skipping the providers `backing_off`, or `None` if a record isn't or the family
+    /// wasn't checked in this cycle.
+    fn family_in_sync<'a>(&self, family: IpFamily, detected: &'a [Option<PublicIp>], backing_off: &[bool]) -> Option<&'a str> {
+        let first = (0..self.lookups.len()).find(|&i| self.lookups[i].family == family && detected[i].is_some())?;
+        let in_sync = self.providers.iter().zip(backing_off).all(|(group, &backing_off)| {
+            group.lookups.iter().zip(&group.confirmed).all(|(&lookup, confirmed)| {
+                self.lookups[lookup].family != family
+                    || (!backing_off && detected[lookup].as_ref().is_some_and(|ip| confirmed.as_ref() == Some(&ip.address)))
+            })
+        });
+        in_sync.then(|| detected[first].as_ref().map(|ip| ip.address.as_str())).flatten()
+    }
+
+    /// Returns `true` if the IP of lookup `i` is needed in this cycle: if a record uses it, or for the first lookup if
+    /// `SYNC_FILE` is set or no other lookup is needed.
+    fn lookup_used(&self, i: usize) -> bool {
+        let used = |i| self.providers.iter().any(|group| group.lookups.contains(&i));
+        used(i) || (i == 0 && (self.sync.is_some() || !(1..self.lookups.len()).any(used)))
+    }
+
+    /// Detects the public IP of lookup `i`, refuses it unless it is public and counts in how many consecutive cycles it
+    /// has been seen. The first lookup takes the IP reported by a webhook instead, if any.
+    ///
+    /// # Errors
+    /// Returns an error if no IP could be detected or it is not public and `IP_ALLOW_PRIVATE` is not set.
+    async fn detect(&mut self, i: usize) -> Result<PublicIp, CrondesError> {
+        let lookup = &self.lookups[i];
+        let reported = if i == 0 { self.reported_ip.clone() } else { None };
+        let address = match reported {
+            Some(ip) => {
+                info!("Public IP (reported by webhook): {}", ip);
+                ip
+            }
+            None => {
+                let ip = lookup.detector.detect().await?;
+                if i == 0 {
+                    info!("Public IP: {}", ip);
+                } else {
+                    let sources: Vec<String> = lookup.sources.iter().map(|s| s.to_string()).collect();
+                    info!("Public {} ({}): {}", lookup.family, sources.join(","), ip);
+                }
+                ip
+            }
+        };
+        if !self.config.ip.allow_private && let Err(e) = ip::check_public(&address) {
+            error!(
+                "{}. This usually means this host is behind carrier-grade NAT or the IP source reports a LAN address, \
+                 so the record would point to an unreachable address. Set IP_ALLOW_PRIVATE=true for internal-only records.",
+                e
+            );
+            // Eine abgelehnte gemeldete IP wird nicht erneut versucht
+            if i == 0 {
+                self.reported_ip = None;
+            }
+            return Err(e);
+        }
+        if i == 0 {
+            METRICS.set_public_ip(self.job(), &address);
+            self.public_ip = Some(address.clone());
+        }
+        let seen = self.observe(i, &address);
+        // Eine gemeldete IP stammt vom Router selbst und muss nicht erst mehrfach beobachtet werden
+        let stable = seen >= self.config.ip.stable_checks || (i == 0 && self.reported_ip.is_some());
+        Ok(PublicIp { address, seen, stable })
+    }
+
+    /// Reconciles the zone of `provider` with `SYNC_FILE`: logs every difference as drift, then creates missing records,
+    /// updates differing ones and deletes superfluous ones if `SYNC_PRUNE` is set, up to `UPDATE_CONCURRENCY` at once.
+    ///
+    /// The file is read again every cycle, so edits apply without a reload.
+    ///
+    /// # Errors
+    /// Returns rate limits right away so no further requests are sent. Any other failure is logged and counted in the
+    /// report, an unreadable file or zone as failure of all records.
+    async fn sync_zone(&self, provider: &dyn DnsProvider, public_ip: &str) -> Result<SyncReport, CrondesError> {
+        let desired = match zonesync::read(&self.config.sync_file) {
+            Ok(desired) => desired,
+            Err(e) => {
+                error!("[sync] {}", e);
+                return Ok(SyncReport { total: 1, updated: 0, failed: 1, exit_code: CrondesError::Config(e).exit_code() });
+            }
+        };
+        let total = desired.len();
+        let existing = match provider.list_records().await {
+            Ok(existing) => existing,
+            Err(e @ CrondesError::RateLimited { .. }) => return Err(e),
+            Err(e) => {
+                error!("[sync] Reading the zone from {} failed: {}", provider.name(), e);
+                return Ok(SyncReport { total, updated: 0, failed: total, exit_code: e.exit_code() });
+            }
+        };
+        let changes = zonesync::plan(&desired, &existing, public_ip, self.config.sync_prune);
+        for change in &changes {
+            info!("[sync] Drift: {}", change);
+        }
+        if !changes.is_empty() && let Some(window) = self.quiet_window() {
+            info!("[sync] Not applying {} change(s) during quiet hours ({})", changes.len(), window);
+            return Ok(SyncReport { total, updated: 0, failed: 0, exit_code: 0 });
+        }
+        let rate_limited = AtomicBool::new(false);
+        let rate_limited = &rate_limited;
+        let results = join_all(changes.iter().map(|change| async move {
+            let _permit = self.permits.acquire().await.ok()?;
+            if rate_limited.load(Ordering::Relaxed) {
+                return None;
+            }
+            let result = zonesync::apply(provider, change).await;
+            if matches!(result, Err(CrondesError::RateLimited { .. })) {
+                rate_limited.store(true, Ordering::Relaxed);
+            }
+            Some(result)
+        }))
+        .await;
+        let mut report = SyncReport { total, updated: 0, failed: 0, exit_code: 0 };
+        for (change, result) in changes.iter().zip(results) {
+            let Some(result) = result else { continue };
+            match result {
+                Ok(()) => {
+                    METRICS.record_updated();
+                    info!("[sync] Done: {}", change);
+                    report.updated += 1;
+                }
+                Err(e @ CrondesError::RateLimited { .. }) => return Err(e),
+                Err(e) => {
+                    error!("[sync] Failed to {}: {}", change, e);
+                    METRICS.record_update_failed();
+                    report.failed += 1;
+                    report.exit_code = CrondesError::severest(report.exit_code, e.exit_code());
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Remembers that all records of `family` point to `public_ip` and writes the state file, if configured.
+    ///
+    /// Failing to write the state only costs an extra API call after the next restart, so it is logged, not returned.
+    fn save_state(&mut self, family: IpFamily, public_ip: &str) {
+        if self.config.state_file.is_empty() {
+            return;
+        }
+        self.state.in_sync(family, public_ip, unix_now());
+        if let Err(e) = self.state.save(&self.config.state_file) {
+            warn!("{}", e);
+        }
+    }
+
+    /// Counts in how many consecutive cycles lookup `i` has detected `public_ip`.
+    ///
+    /// An address only counts as stable once it has been seen in `IP_STABLE_CHECKS` cycles in a row, so a flapping
+    /// connection (e.g. PPPoE reconnect storms) doesn't cause an update for every short-lived address.
+    fn observe(&mut self, i: usize, public_ip: &str) -> u32 {
+        let lookup = &mut self.lookups[i];
+        let seen = match &lookup.observed {
+            Some((ip, seen)) if ip == public_ip => seen.saturating_add(1),
+            _ => 1,
+        };
+        lookup.observed = Some((public_ip.to_string(), seen));
+        seen
+    }
+
+    /// Brings each record of a provider up to date with the public IP `detected` by its lookup, up to
+    /// `UPDATE_CONCURRENCY` records at the same time.
+    ///
+    /// The credentials are only checked if they haven't been verified yet, after an authentication error,
+    /// or if `revalidate` is set, so a healthy provider doesn't spend API calls on it every cycle.
+    /// Likewise, a record confirmed to point to its public IP before is only read again if `reread` is set. Reading a
+    /// record proves it exists, so it is only [checked](DnsProvider::check_record) after an update of it failed. Records
+    /// whose public IP is unknown in this cycle are skipped, as are records of lookups not `checked` in this cycle.
+    /// If the provider [supports batches](DnsProvider::supports_batch), all records that have to change to the same IP
+    /// are written in a single request at the end, otherwise each one right after it was read.
+    ///
+    /// # Errors
+    /// Returns the error of the credential check if it is fatal, and rate limits right away so no further
+    /// requests are sent. Any other failure is logged and counted in the report.
+    async fn update_group(
+        &self,
+        group: &ProviderGroup,
+        detected: &[Option<PublicIp>],
+        checked: &[bool],
+        revalidate: bool,
+        reread: bool,
+    ) -> Result<GroupReport, CrondesError> {
+        let provider = group.provider.as_ref();
+        let mut report = GroupReport { verified: true, ..GroupReport::default() };
+        if !group.verified || revalidate {
+            info!("Checking {} credentials...", provider.name());
+            match provider.check_credentials().await {
+                Ok(()) => {}
+                Err(e) if e.is_fatal() || matches!(e, CrondesError::RateLimited { .. }) => return Err(e),
+                Err(e) => {
+                    error!("{} credential check failed: {}", provider.name(), e);
+                    METRICS.record_update_failed();
+                    // Records ohne erkannte IP zählt der Aufrufer bereits als fehlgeschlagen
+                    report.failed = group.lookups.iter().filter(|&&lookup| checked[lookup] && detected[lookup].is_some()).count();
+                    report.exit_code = e.exit_code();
+                    report.verified = false;
+                    report.confirmed = vec![None; group.records.len()];
+                    return Ok(report);
+                }
+            }
+        }
+        report.confirmed = group.confirmed.clone();
+        let batch = provider.supports_batch();
+        // Per Record, der geändert werden muss, sein Index und sein bisheriger Inhalt
+        let mut due: Vec<(usize, String)> = Vec::new();
+        let rate_limited = AtomicBool::new(false);
+        let rate_limited = &rate_limited;
+        // Alle Records laufen gleichzeitig, aber nur so viele auf einmal, wie UPDATE_CONCURRENCY erlaubt
+        let outcomes = join_all(group.records.iter().enumerate().map(|(i, record)| async move {
+            if !checked[group.lookups[i]] {
+                return None;
+            }
+            let Some(public_ip) = &detected[group.lookups[i]] else {
+                let family = self.lookups[group.lookups[i]].family;
+                error!("[{}] Record not updated, the public {} address is unknown", record.label(), family);
+                return None;
+            };
+            if !reread && group.confirmed[i].as_deref() == Some(public_ip.address.as_str()) {
+                info!("[{}] No update needed. Public IP unchanged: {} (not re-read)", record.label(), public_ip.address);
+                return None;
+            }
+            let _permit = self.permits.acquire().await.ok()?;
+            // Nach einem Rate-Limit keine weiteren Requests mehr starten
+            if rate_limited.load(Ordering::Relaxed) {
+                return None;
+            }
+            let outcome = match self.update_record(group, i, public_ip, !batch).await {
+                // Erst nach einem Fehler prüfen, ob der Record existiert, das liefert die genauere Meldung
+                Err(e) if !matches!(e, CrondesError::RateLimited { .. } | CrondesError::Auth(_)) => {
+                    Err(group.provider.check_record(record).await.err().unwrap_or(e))
+                }
+                outcome => outcome,
+            };
+            if matches!(outcome, Err(CrondesError::RateLimited { .. })) {
+                rate_limited.store(true, Ordering::Relaxed);
+            }
+            Some((public_ip, outcome))
+        }))
+        .await;
+        for ((i, record), outcome) in group.records.iter().enumerate().zip(outcomes) {
+            let Some((public_ip, outcome)) = outcome else { continue };
+            let in_sync = matches!(outcome, Ok(RecordOutcome::Updated | RecordOutcome::Unchanged));
+            report.confirmed[i] = in_sync.then(|| public_ip.address.clone());
+            if in_sync && record.record_type != RecordType::Txt {
+                report.expected.push((i, desired_content(record, &public_ip.address)));
+            }
+            match outcome {
+                Ok(RecordOutcome::Updated) => {
+                    report.updated += 1;
+                    report.written.push(i);
+                }
+                Ok(RecordOutcome::Due(current_ip)) => due.push((i, current_ip)),
+                Ok(RecordOutcome::Unchanged) => {}
+                Ok(RecordOutcome::Pending | RecordOutcome::Vetoed) => report.pending += 1,
+                Ok(RecordOutcome::Conflict(found)) => {
+                    report.pending += 1;
+                    report.conflicts.push((i, found, public_ip.address.clone()));
+                }
+                Err(e @ CrondesError::RateLimited { .. }) => return Err(e),
+                Err(e) => report.fail(record, &e),
+            }
+        }
+        let mut lookups: Vec<usize> = due.iter().map(|(i, _)| group.lookups[*i]).collect();
+        lookups.sort_unstable();
+        lookups.dedup();
+        for lookup in lookups {
+            let Some(public_ip) = &detected[lookup] else { continue };
+            let due: Vec<_> = due.iter().filter(|(i, _)| group.lookups[*i] == lookup).cloned().collect();
+            self.write_due(group, &due, &public_ip.address, &mut report).await?;
+        }
+        Ok(report)
+    }
+
+    /// Writes `public_ip` to the records of `group` that are `due`, given by index and current content, and adds the
+    /// outcome to `report`. A single record is written on its own, several in one batch.
+    ///
+    /// # Errors
+    /// Returns rate limits; any other failure is logged and counted in the report.
+    async fn write_due(
+        &self,
+        group: &ProviderGroup,
+        due: &[(usize, String)],
+        public_ip: &str,
+        report: &mut GroupReport,
+    ) -> Result<(), CrondesError> {
+        let provider = group.provider.as_ref();
+        let records: Vec<RecordConfig> = due.iter().map(|(i, _)| group.records[*i].clone()).collect();
+        for (record, (_, current_ip)) in records.iter().zip(due) {
+            info!("[{}] Updating DNS record: {} → {}", record.label(), current_ip, public_ip);
+        }
+        // Ein einzelner Record braucht keinen Batch
+        let result = if let [record] = records.as_slice() {
+            provider.update_ip(record, public_ip).await.map(|response| vec![response])
+        } else {
+            info!("Writing {} records to {} in one batch", records.len(), provider.name());
+            provider.update_batch(&records, public_ip).await
+        };
+        match result {
+            Ok(responses) => {
+                for ((record, (i, current_ip)), response) in records.iter().zip(due).zip(responses) {
+                    self.record_updated(provider, record, current_ip, public_ip, &response).await;
+                    report.confirmed[*i] = Some(public_ip.to_string());
+                    report.updated += 1;
+                    report.written.push(*i);
+                    report.expected.push((*i, public_ip.to_string()));
+                }
+            }
+            Err(e @ CrondesError::RateLimited { .. }) => return Err(e),
+            Err(e) => {
+                for record in &records {
+                    report.fail(record, &e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bringt einen einzelnen Record auf die aktuelle öffentliche IP.
+    ///
+    /// The record with index `i` of `group` is only written if `public_ip` is stable, i.e. it has been detected in
+    /// enough consecutive cycles, and the pre-update hook (if configured) agrees. A vetoed record is retried in the next
+    /// cycle, and so is a record written less than `MIN_UPDATE_COOLDOWN_SECS` ago. With `FORCE_OVERWRITE=false`, an A or
+    /// AAAA record no longer holding the content crondes last wrote or found is a [`RecordOutcome::Conflict`]. Unless
+    /// `write` is set, a record that has to change is not written but returned as [`RecordOutcome::Due`].
+    /// TXT records are written whenever their content differs from the rendered template, see [`desired_content`].
+    async fn update_record(&self, group: &ProviderGroup, i: usize, public_ip: &PublicIp, write: bool) -> Result<RecordOutcome, CrondesError> {
+        let (provider, record) = (group.provider.as_ref(), &group.records[i]);
+        let current_dns_ip = self.current_ip(provider, record, group.via_dns[i]).await?;
+        if record.record_type == RecordType::Txt {
+            info!("[{}] Current DNS content: {}", record.label(), current_dns_ip);
+        } else {
+            info!("[{}] Current DNS IP: {}", record.label(), current_dns_ip);
+        }
+        let (seen, stable, public_ip) = (public_ip.seen, public_ip.stable, public_ip.address.as_str());
+        let content = desired_content(record, public_ip);
+        // Cloudflare liefert TXT-Inhalte in Anführungszeichen zurück
+        let changed = current_dns_ip != content && current_dns_ip.strip_prefix('"').and_then(|c| c.strip_suffix('"')) != Some(&content);
+        let cooldown = Duration::from_secs(self.config.min_update_cooldown_secs);
+        let cooling = group.written[i].map(|written| cooldown.saturating_sub(written.elapsed())).filter(|left| !left.is_zero());
+        // TXT-Inhalte ändern sich mit {timestamp} bei jedem Schreiben und lassen sich nicht vergleichen
+        let expected = group.expected[i].as_deref().filter(|_| !self.config.force_overwrite && record.record_type != RecordType::Txt);
+        if changed && let Some(expected) = expected && expected != current_dns_ip {
+            warn!(
+                "[{}] Record was changed outside of crondes from {} to {}, not overwriting it with {} (FORCE_OVERWRITE=false)",
+                record.label(), expected, current_dns_ip, content
+            );
+            Ok(RecordOutcome::Conflict(current_dns_ip))
+        } else if changed && !stable {
+            info!(
+                "[{}] Public IP changed to {}, waiting until it is stable ({} of {} checks)",
+                record.label(), public_ip, seen, self.config.ip.stable_checks
+            );
+            Ok(RecordOutcome::Pending)
+        } else if changed && let Some(window) = self.quiet_window() {
+            info!("[{}] Would update {} → {}, but records are not changed during quiet hours ({})", record.label(), current_dns_ip, content, window);
+            Ok(RecordOutcome::Pending)
+        } else if changed && let Some(left) = cooling {
+            info!(
+                "[{}] Would update {} → {}, but the record was written recently, waiting {} more seconds (MIN_UPDATE_COOLDOWN_SECS)",
+                record.label(), current_dns_ip, content, left.as_secs()
+            );
+            Ok(RecordOutcome::Pending)
+        } else if changed {
+            if let Some(hook) = &self.pre_update
+                && let Err(e) = hook.check(record.label(), &current_dns_ip, &content).await
+            {
+                warn!("[{}] Update to {} vetoed by the pre-update hook: {}", record.label(), content, e);
+                return Ok(RecordOutcome::Vetoed);
+            }
+            // TXT-Records und Records mit eigener Interface-ID haben jeweils eigenen Inhalt und passen in keinen Batch
+            if !write && record.record_type != RecordType::Txt && record.interface_id.is_empty() {
+                return Ok(RecordOutcome::Due(current_dns_ip));
+            }
+            info!("[{}] Updating DNS record: {} → {}", record.label(), current_dns_ip, content);
+            let response_body = provider.update_ip(record, &content).await?;
+            self.record_updated(provider, record, &current_dns_ip, &content, &response_body).await;
+            Ok(RecordOutcome::Updated)
+        } else {
+            info!("[{}] No update needed. Public IP unchanged: {}", record.label(), public_ip);
+            Ok(RecordOutcome::Unchanged)
+        }
+    }
+
+    /// Handles a record that was just changed from `old_ip` to `public_ip`: counts and logs the update, sends the
+    /// notification, appends it to the history and starts the propagation check.
+    async fn record_updated(&self, provider: &dyn DnsProvider, record: &RecordConfig, old_ip: &str, public_ip: &str, response: &str) {
+        METRICS.record_updated();
+        info!("[{}] DNS record updated successfully. Response: {}", record.label(), response);
+        self.notifiers
+            .send(Event::IpChanged {
+                record: record.label().to_string(),
+                old_ip: old_ip.to_string(),
+                new_ip: public_ip.to_string(),
+            })
+            .await;
+        if !self.config.history_file.is_empty() {
+            let entry = HistoryEntry::now(record.label(), old_ip, public_ip, response);
+            if let Err(e) = history::append(&self.config.history_file, &entry) {
+                warn!("{}", e);
+            }
+        }
+        self.check_propagation(provider, record, public_ip).await;
+    }
+
+    /// Reads the IP a record points to, from its authoritative nameservers if `via_dns` is set.
+    ///
+    /// Falls back to the provider API if the nameservers cannot be queried, so a DNS outage only costs API calls.
+    async fn current_ip(&self, provider: &dyn DnsProvider, record: &RecordConfig, via_dns: bool) -> Result<String, CrondesError> {
+        if via_dns && let Some(authoritative) = &self.authoritative {
+            match authoritative.current_ip(&record.name).await {
+                Ok(ip) => return Ok(ip),
+                Err(e) => warn!("[{}] {}, reading the record from the {} API", record.label(), e, provider.name()),
+            }
+        }
+        provider.current_ip(record).await
+    }
+
+    /// Starts waiting in the background until public resolvers return `public_ip` for the updated record.
+    ///
+    /// A records are looked up as A, AAAA records as AAAA. TXT records, records without a name and records served
+    /// through a proxy are skipped, since resolvers never return their content as address. If the resolvers don't
+    /// catch up within the timeout, a failure notification is sent.
+    async fn check_propagation(&self, provider: &dyn DnsProvider, record: &RecordConfig, public_ip: &str) {
+        let Some(check) = &self.propagation else { return };
+        if record.name.is_empty() {
+            return;
+        }
+        if record.record_type == RecordType::Txt {
+            info!("[{}] TXT records are not checked for propagation", record.label());
+            return;
+        }
+        let Ok(ip) = public_ip.parse() else {
+            info!("[{}] {} is no IP address, skipping the propagation check", record.label(), public_ip);
+            return;
+        };
+        match provider.is_proxied(record).await {
+            Ok(false) => {}
+            Ok(true) => {
+                info!("[{}] Record is proxied, skipping the propagation check", record.label());
+                return;
+            }
+            Err(e) => {
+                warn!("[{}] Could not check whether the record is proxied, skipping the propagation check: {}", record.label(), e);
+                return;
+            }
+        }
+        let check = Arc::clone(check);
+        let notifiers = Arc::clone(&self.notifiers);
+        let name = record.name.clone();
+        let mut propagating = self.propagating.lock().unwrap_or_else(|e| e.into_inner());
+        // Abgeschlossene Prüfungen aufräumen, damit das Set im Daemon nicht wächst
+        while propagating.try_join_next().is_some() {}
+        propagating.spawn(async move {
+            if let Err(error) = check.wait(&name, ip).await {
+                warn!("[{}] DNS propagation failed: {}", name, error);
+                notifiers.send(Event::PropagationFailed { record: name, new_ip: ip.to_string(), error }).await;
+            }
+        });
+    }
+}
+
+/// Timers of the IPv4 and IPv6 records when `UPDATE_INTERVAL_SECS_V6` sets a separate interval for IPv6.
+struct FamilyTimers {
+    v4: Instant,
+    v6: Instant,
+}
+
+impl FamilyTimers {
+    fn new() -> Self {
+        let now = Instant::now();
+        FamilyTimers { v4: now, v6: now }
+    }
+
+    /// Restarts the timers of the families checked by a cycle limited to `ran`, both if `None`, with `v4` and `v6`.
+    ///
+    /// # Returns
+    /// The family the next cycle is limited to, `None` if both are due within a second, and the time until it is due.
+    fn next(&mut self, ran: Option<IpFamily>, v4: Duration, v6: Duration) -> (Option<IpFamily>, Duration) {
+        let now = Instant::now();
+        if ran != Some(IpFamily::V6) {
+            self.v4 = now + v4;
+        }
+        if ran != Some(IpFamily::V4) {
+            self.v6 = now + v6;
+        }
+        let (first, last) = (self.v4.min(self.v6), self.v4.max(self.v6));
+        let family = if last.duration_since(first) < Duration::from_secs(1) {
+            None
+        } else if self.v4 < self.v6 {
+            Some(IpFamily::V4)
+        } else {
+            Some(IpFamily::V6)
+        };
+        (family, first.saturating_duration_since(now))
+    }
+}
+
+/// Adds the listed record `rec` to the records of `group`, unless it is configured already.
+///
+/// # Returns
+/// `true` if the record was added.
+fn adopt(group: &mut ProviderGroup, rec: &RecordInfo, last_ip: &Option<String>) -> bool {
+    if group.records.iter().any(|r| r.id == rec.id) {
+        return false;
+    }
+    group.records.push(RecordConfig { id: rec.id.clone(), name: rec.name.clone(), ..RecordConfig::default() });
+    group.confirmed.push(last_ip.clone());
+    group.via_dns.push(false);
+    group.written.push(None);
+    group.expected.push(None);
+    group.lookups.push(0);
+    true
+}
+
+/// Returns the content `record` should have: `public_ip` itself, its prefix with the record's interface ID for AAAA
+/// records with `interface_id`, or for TXT records its template with `{ip}`, `{timestamp}` and `{record}` filled in.
+/// A template with `{timestamp}` differs every time, so such a record is written whenever it is read, i.e. every
+/// `REREAD_EVERY_N_CYCLES` cycles and after IP changes.
+fn desired_content(record: &RecordConfig, public_ip: &str) -> String {
+    match record.record_type {
+        RecordType::Aaaa if !record.interface_id.is_empty() => {
+            match (public_ip.parse(), ip::parse_interface_id(&record.interface_id)) {
+                (Ok(prefix), Ok(id)) => ip::with_interface_id(prefix, record.prefix_length.unwrap_or(64), id).to_string(),
+                _ => public_ip.to_string(),
+            }
+        }
+        RecordType::A | RecordType::Aaaa => public_ip.to_string(),
+        RecordType::Txt => record
+            .content
+            .replace("{ip}", public_ip)
+            .replace("{timestamp}", &now_rfc3339())
+            .replace("{record}", &record.name),
+    }
+}
+
+/// Returns `true` if `name` matches the glob `pattern`, where `*` stands for any sequence of characters and `?` for a
+/// single one. Case is ignored, like in DNS.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let name: Vec<char> = name.to_ascii_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position des letzten * und der Stelle im Namen, ab der es gerade gilt
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Waits for the next tick of `interval`, or forever without one.
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::config::RecordConfig;
+use crate::error::CrondesError;
+use crate::provider::{DnsProvider, NewRecord, RecordInfo};
+
+/// Placeholder for the detected public IP in the content of a desired record.
+pub const PUBLIC_IP: &str = "@public_ip";
+
+/// A record as it should exist in the zone, one entry of `SYNC_FILE`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DesiredRecord {
+    /// Full name of the record, e.g. `www.example.com`.
+    pub name: String,
+    /// Record type, `A` if not set.
+    #[serde(rename = "type", default = "default_type")]
+    pub record_type: String,
+    /// Content of the record, [`PUBLIC_IP`] for the detected public IP.
+    pub content: String,
+    /// TTL in seconds. An existing record keeps its TTL if not set.
+    #[serde(default)]
+    pub ttl: Option<u32>,
+    /// Whether the record is served through the Cloudflare proxy. An existing record keeps its proxy status if not set.
+    #[serde(default)]
+    pub proxied: Option<bool>,
+}
+
+/// Content of `SYNC_FILE`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SyncFile {
+    records: Vec<DesiredRecord>,
+}
+
+/// A change needed to bring the zone in line with the desired records.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// The record is missing and has to be created.
+    Create(NewRecord),
+    /// The record exists as `current`, but differs from `desired`.
+    Update { current: RecordInfo, desired: NewRecord },
+    /// The record has a name and type listed in the file, but no entry asks for it.
+    Delete(RecordInfo),
+}
+
+fn default_type() -> String {
+    "A".to_string()
+}
+
+/// Reads the desired records from the TOML or YAML file at `path`, a list of `records` with `name`, `type`, `content`,
+/// `ttl` and `proxied` each.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, or if a record lacks its name or content.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<DesiredRecord>, String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read SYNC_FILE {}: {}", path.display(), e))?;
+    let file: SyncFile = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&content).map_err(|e| format!("Invalid TOML in {}: {}", path.display(), e))?,
+        Some("yaml") | Some("yml") => {
+            serde_norway::from_str(&content).map_err(|e| format!("Invalid YAML in {}: {}", path.display(), e))?
+        }
+        _ => return Err(format!("Unsupported SYNC_FILE format: {} (expected .toml, .yaml or .yml)", path.display())),
+    };
+    if let Some(pos) = file.records.iter().position(|r| r.name.trim().is_empty() || r.content.trim().is_empty()) {
+        return Err(format!("Record #{} in {} needs a name and a content", pos + 1, path.display()));
+    }
+    Ok(file.records)
+}
+
+/// Compares the `desired` records with the `existing` records of the zone and returns the changes needed to match.
+///
+/// A desired record matches an existing record with the same name, type and content. Otherwise it takes over the
+/// only existing record of its name and type whose content no other desired record asks for, so a changed content is
+/// updated in place; if there is none or several, the record is created. TTL and proxy status are only compared if
+/// set in both.
+///
+/// With `prune`, existing records of a name and type listed in `desired` that no entry matched are deleted. Other
+/// records are never touched.
+pub fn plan(desired: &[DesiredRecord], existing: &[RecordInfo], public_ip: &str, prune: bool) -> Vec<Change> {
+    let content = |record: &DesiredRecord| -> String {
+        if record.content == PUBLIC_IP { public_ip.to_string() } else { record.content.clone() }
+    };
+    let same = |want: &DesiredRecord, have: &RecordInfo| {
+        have.name.eq_ignore_ascii_case(&want.name) && have.record_type.eq_ignore_ascii_case(&want.record_type)
+    };
+    let mut claimed: Vec<&str> = Vec::new();
+    let mut changes = Vec::new();
+    for want in desired {
+        let wanted = content(want);
+        let candidates: Vec<&RecordInfo> =
+            existing.iter().filter(|have| same(want, have) && !claimed.contains(&have.id.as_str())).collect();
+        let current = candidates.iter().find(|have| have.content == wanted).or_else(|| {
+            // Records, deren Inhalt ein anderer Eintrag verlangt, bleiben diesem Eintrag vorbehalten
+            let free: Vec<_> = candidates
+                .iter()
+                .filter(|have| !desired.iter().any(|other| same(other, have) && content(other) == have.content))
+                .collect();
+            if free.len() == 1 { Some(free[0]) } else { None }
+        });
+        let Some(current) = current else {
+            changes.push(Change::Create(NewRecord {
+                name: want.name.clone(),
+                record_type: want.record_type.to_uppercase(),
+                content: wanted,
+                ttl: want.ttl,
+                proxied: want.proxied,
+            }));
+            continue;
+        };
+        claimed.push(&current.id);
+        let desired = NewRecord {
+            name: current.name.clone(),
+            record_type: current.record_type.clone(),
+            content: wanted,
+            ttl: want.ttl.or(current.ttl),
+            proxied: want.proxied.or(current.proxied),
+        };
+        if !differences(current, &desired).is_empty() {
+            changes.push(Change::Update { current: (*current).clone(), desired });
+        }
+    }
+    if prune {
+        changes.extend(
+            existing
+                .iter()
+                .filter(|have| !claimed.contains(&have.id.as_str()) && desired.iter().any(|want| same(want, have)))
+                .cloned()
+                .map(Change::Delete),
+        );
+    }
+    changes
+}
+
+/// Carries out `change` at `provider`.
+///
+/// # Errors
+/// Returns an error if the request fails or the provider can't create, change or delete records.
+pub async fn apply(provider: &dyn DnsProvider, change: &Change) -> Result<(), CrondesError> {
+    match change {
+        Change::Create(record) => provider.create_record(record).await.map(|_| ()),
+        Change::Update { current, desired } => provider.update_record(&record_config(current), desired).await.map(|_| ()),
+        Change::Delete(current) => provider.delete_record(&record_config(current)).await,
+    }
+}
+
+/// Addresses the existing record `record` by its ID.
+fn record_config(record: &RecordInfo) -> RecordConfig {
+    RecordConfig { id: record.id.clone(), name: record.name.clone(), ..RecordConfig::default() }
+}
+
+/// Lists the values of `current` differing from `desired` as `field old → new`.
+fn differences(current: &RecordInfo, desired: &NewRecord) -> Vec<String> {
+    let mut differences = Vec::new();
+    if current.content != desired.content {
+        differences.push(format!("content {} → {}", current.content, desired.content));
+    }
+    if let (Some(old), Some(new)) = (current.ttl, desired.ttl) && old != new {
+        differences.push(format!("ttl {} → {}", old, new));
+    }
+    if let (Some(old), Some(new)) = (current.proxied, desired.proxied) && old != new {
+        differences.push(format!("proxied {} → {}", old, new));
+    }
+    differences
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Create(record) => {
+                write!(f, "create {} {} {}", record.record_type, record.name, record.content)?;
+                if let Some(ttl) = record.ttl {
+                    write!(f, ", ttl {}", ttl)?;
+                }
+                if let Some(proxied) = record.proxied {
+                    write!(f, ", proxied {}", proxied)?;
+                }
+                Ok(())
+            }
+            Change::Update { current, desired } => {
+                write!(f, "update {} {}: {}", current.record_type, current.name, differences(current, desired).join(", "))
+            }
+            Change::Delete(record) => write!(f, "delete {} {} {}", record.record_type, record.name, record.content),
+        }
+    }
+}
@@ -0,0 +1,1512 @@
+//! Runs full update cycles against a mock Cloudflare API and a mock IP echo service.
+
+use std::time::Duration;
+use crondes::config::{ConfigSource, ConnectivityCheck, IpSourceKind, IpStrategy, ProviderKind, RecordCheck, RecordConfig, Secret, UpdateMode};
+use crondes::{Config, CrondesError, Updater, http, provider};
+use serde_json::{Value, json};
+use wiremock::matchers::{body_json, body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PUBLIC_IP: &str = "203.0.113.7";
+
+/// Wraps `result` in a successful Cloudflare response envelope.
+fn success(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "success": true, "errors": [], "messages": [], "result": result }))
+}
+
+/// A failed Cloudflare response with the given status.
+fn failure(status: u16, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status)
+        .set_body_json(json!({ "success": false, "errors": [{ "code": 1000, "message": message }], "messages": [], "result": null }))
+}
+
+/// The managed record as returned by the Cloudflare API, pointing to `content`.
+fn record(content: &str) -> Value {
+    json!({ "id": "r1", "name": "home.example.com", "type": "A", "content": content, "ttl": 1, "proxied": false })
+}
+
+/// Builds a config pointing the Cloudflare provider and the IP detection at `server`.
+fn config(server: &MockServer) -> Config {
+    let mut config = Config {
+        cloudflare_api_token: Secret::new("test-token"),
+        cloudflare_zone_id: "z1".to_string(),
+        cloudflare_api_url: format!("{}/client/v4", server.uri()),
+        records: vec![RecordConfig { id: "r1".to_string(), name: "home.example.com".to_string(), ..RecordConfig::default() }],
+        ..Config::default()
+    };
+    config.ip.sources = vec![IpSourceKind::Http];
+    config.ip.services = vec![format!("{}/ip", server.uri())];
+    // Die Test-IP stammt aus einem Dokumentationsnetz
+    config.ip.allow_private = true;
+    config.retry.backoff_base_secs = 1;
+    config.retry.backoff_max_secs = 1;
+    config
+}
+
+/// Mounts an IP echo service and a Cloudflare API accepting the token, with `content` in the record.
+async fn mount_healthy(server: &MockServer, content: &str) {
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .and(header("Authorization", "Bearer test-token"))
+        .respond_with(success(json!({ "id": "t1", "status": "active" })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1"))
+        .respond_with(success(json!({ "id": "z1", "name": "example.com", "permissions": ["#dns_records:read", "#dns_records:edit"] })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(content)))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn invalid_token_is_fatal() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .respond_with(failure(401, "Invalid API Token"))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let mut updater = Updater::new(config(&server)).unwrap();
+    let err = updater.update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Auth(_)), "unexpected error: {}", err);
+    assert!(err.is_fatal());
+    assert_eq!(err.exit_code(), 3);
+}
+
+#[tokio::test]
+async fn invalid_token_only_stops_the_daemon_with_exit_on_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .respond_with(failure(401, "Invalid API Token"))
+        .mount(&server)
+        .await;
+
+    // Ohne EXIT_ON_ERROR wird nach einer Sekunde Backoff erneut geprüft, statt den Daemon zu beenden
+    let config = Config { network_watch: false, ..config(&server) };
+    Updater::new(config.clone()).unwrap().run_until(tokio::time::sleep(Duration::from_millis(1500))).await.unwrap();
+    let requests = server.received_requests().await.unwrap();
+    let checks = requests.iter().filter(|r| r.url.path() == "/client/v4/user/tokens/verify").count();
+    assert!(checks >= 2, "credentials were checked {} times", checks);
+
+    let config = Config { exit_on_error: true, ..config };
+    let err = Updater::new(config).unwrap().run_until(std::future::pending()).await.unwrap_err();
+    assert!(matches!(err, CrondesError::Auth(_)), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn missing_record_fails_the_cycle() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .respond_with(success(json!({ "id": "t1", "status": "active" })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1"))
+        .respond_with(success(json!({ "id": "z1", "name": "example.com", "permissions": ["#dns_records:read", "#dns_records:edit"] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(failure(404, "Record not found"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([])))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let mut updater = Updater::new(config(&server)).unwrap();
+    let err = updater.update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Records { failed: 1, total: 1, .. }), "unexpected error: {}", err);
+    // Eine nicht existierende Record-ID ist ein Fehler der Konfiguration
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[tokio::test]
+async fn unreachable_provider_fails_the_records_with_the_network_exit_code() {
+    let ip_server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&ip_server).await;
+    // Nicht aus dem Pool von wiremock, damit der Port beim Drop wirklich geschlossen wird
+    let server = MockServer::builder().start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let mut config = Config { reread_every_n_cycles: 1, ..config(&server) };
+    config.ip.services = vec![format!("{}/ip", ip_server.uri())];
+    let mut updater = Updater::new(config).unwrap();
+    updater.update_once().await.unwrap();
+
+    // Die Zugangsdaten sind schon geprüft, erst das Lesen des Records scheitert am nicht erreichbaren Server
+    drop(server);
+    let err = updater.update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Records { failed: 1, total: 1, .. }), "unexpected error: {}", err);
+    assert_eq!(err.exit_code(), 4);
+}
+
+#[tokio::test]
+async fn undetected_records_are_counted_once_when_the_credential_check_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .respond_with(failure(503, "Service unavailable"))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    // Die IPv6-Erkennung scheitert und die Prüfung der Zugangsdaten ebenso, der AAAA-Record zählt trotzdem nur einmal
+    let mut config = config(&server);
+    config.records.push(
+        serde_json::from_value(json!({ "id": "r6", "type": "AAAA", "ip_services": [format!("{}/ip6-down", server.uri())] })).unwrap(),
+    );
+    let err = Updater::new(config).unwrap().update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Records { failed: 2, total: 2, .. }), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn unchanged_ip_is_not_written() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let mut updater = Updater::new(config(&server)).unwrap();
+    let report = updater.update_once().await.unwrap();
+    assert_eq!(report.public_ip, PUBLIC_IP);
+    assert_eq!(report.updated, 0);
+    assert_eq!(report.total, 1);
+}
+
+#[tokio::test]
+async fn changed_ip_is_written() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, "198.51.100.1").await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_partial_json(json!({ "type": "A", "name": "home.example.com", "content": PUBLIC_IP, "ttl": 1 })))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut updater = Updater::new(config(&server)).unwrap();
+    let report = updater.update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+}
+
+#[tokio::test]
+async fn records_are_not_changed_during_quiet_hours() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, "198.51.100.1").await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    // Das Fenster umfasst den ganzen Tag, die Änderung wird nur protokolliert
+    let config = Config {
+        quiet_hours: vec!["Mon-Sun 00:00-24:00".to_string()],
+        quiet_hours_tz: "UTC".to_string(),
+        ..config(&server)
+    };
+    assert!(config.problems().is_empty(), "unexpected problems: {:?}", config.problems());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 0);
+}
+
+#[tokio::test]
+async fn records_are_written_at_most_once_per_cooldown() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, "198.51.100.1").await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // Die API liefert weiter die alte IP, ohne Cooldown würde der Record in jedem Zyklus erneut geschrieben
+    let config = Config { min_update_cooldown_secs: 3600, reread_every_n_cycles: 1, ..config(&server) };
+    let mut updater = Updater::new(config).unwrap();
+    assert_eq!(updater.update_once().await.unwrap().updated, 1);
+    assert_eq!(updater.update_once().await.unwrap().updated, 0);
+}
+
+#[tokio::test]
+async fn records_changed_outside_of_crondes_are_not_overwritten() {
+    let server = MockServer::start().await;
+    // Zuletzt zeigte der Record auf 198.51.100.1, inzwischen hat ihn jemand von Hand geändert
+    mount_healthy(&server, "192.0.2.50").await;
+    Mock::given(method("PUT")).and(path("/client/v4/zones/z1/dns_records/r1")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(json!({ "event": "conflict", "record": "home.example.com", "expected": "198.51.100.1", "found": "192.0.2.50", "new_ip": PUBLIC_IP })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state_file = std::env::temp_dir().join(format!("crondes-conflict-{}.json", std::process::id()));
+    std::fs::write(&state_file, json!({ "ip": "198.51.100.1" }).to_string()).unwrap();
+
+    // Der Konflikt wird nur einmal gemeldet, auch wenn er über mehrere Zyklen besteht
+    let mut config = Config { force_overwrite: false, state_file: state_file.to_string_lossy().into_owned(), ..config(&server) };
+    config.notify.webhook_urls = vec![Secret::new(format!("{}/hook", server.uri()))];
+    let mut updater = Updater::new(config).unwrap();
+    for _ in 0..2 {
+        assert_eq!(updater.update_once().await.unwrap().updated, 0);
+    }
+    let _ = std::fs::remove_file(&state_file);
+}
+
+#[tokio::test]
+async fn comment_is_stamped_on_update() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, "198.51.100.1").await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_partial_json(json!({ "content": PUBLIC_IP, "comment": "home.example.com managed by crondes, was 198.51.100.1" })))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = Config { cloudflare_comment: "{record} managed by crondes, was {old_ip}".to_string(), ..config(&server) };
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+}
+
+#[tokio::test]
+async fn requests_go_through_the_outbound_proxy() {
+    let proxy = MockServer::start().await;
+    mount_healthy(&proxy, "198.51.100.1").await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&proxy)
+        .await;
+
+    // Die Hosts existieren nicht, erreichbar sind sie nur über den Proxy
+    let mut config = config(&proxy);
+    config.cloudflare_api_url = "http://api.crondes.invalid/client/v4".to_string();
+    config.ip.services = vec!["http://ip.crondes.invalid/ip".to_string()];
+    config.http.proxy = Secret::new(proxy.uri());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+    let requests = proxy.received_requests().await.unwrap();
+    assert!(requests.iter().all(|r| r.url.host_str() != Some("127.0.0.1")), "requests bypassed the proxy");
+}
+
+#[tokio::test]
+async fn requests_are_sent_from_the_bind_address() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    let mut config = config(&server);
+    config.http.bind_address = Some("127.0.0.1".parse().unwrap());
+    let report = Updater::new(config.clone()).unwrap().update_once().await.unwrap();
+    assert_eq!(report.total, 1);
+
+    // Von einer IPv6-Adresse aus ist der Mock-Server unter 127.0.0.1 nicht erreichbar
+    config.http.bind_address = Some("::1".parse().unwrap());
+    assert!(Updater::new(config).unwrap().update_once().await.is_err());
+}
+
+#[tokio::test]
+async fn invalid_ca_file_is_rejected_at_startup() {
+    let server = MockServer::start().await;
+    let ca_file = std::env::temp_dir().join(format!("crondes-ca-{}.pem", std::process::id()));
+    std::fs::write(&ca_file, "kein Zertifikat").unwrap();
+
+    let mut config = config(&server);
+    config.http.ca_file = ca_file.display().to_string();
+    let error = Updater::new(config).err().expect("the CA file must be rejected").to_string();
+    std::fs::remove_file(&ca_file).unwrap();
+    assert!(error.contains("no PEM certificate"), "unexpected error: {}", error);
+}
+
+#[tokio::test]
+async fn hanging_cycle_is_aborted_at_the_deadline() {
+    let server = MockServer::start().await;
+    // Die API antwortet erst nach 10 Sekunden, noch innerhalb des HTTP-Timeouts von 30 Sekunden
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(PUBLIC_IP)).set_delay(Duration::from_secs(10)))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    let config = Config { cycle_deadline_secs: 1, ..config(&server) };
+    let started = std::time::Instant::now();
+    let error = Updater::new(config).unwrap().update_once().await.unwrap_err();
+    assert!(matches!(error, CrondesError::Deadline(_)), "unexpected error: {}", error);
+    assert!(!error.is_fatal());
+    assert_eq!(error.exit_code(), 4);
+    assert!(started.elapsed() < Duration::from_secs(3), "took {:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn failing_ip_service_is_skipped_by_the_circuit_breaker() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/broken"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Service Unavailable</html>"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    // Nach zwei ungültigen Antworten wird der Dienst übersprungen und sofort der zweite gefragt
+    let mut config = config(&server);
+    config.ip.services = vec![format!("{}/broken", server.uri()), format!("{}/ip", server.uri())];
+    config.ip.strategy = IpStrategy::Sequential;
+    config.ip.breaker_threshold = 2;
+    let mut updater = Updater::new(config).unwrap();
+    for _ in 0..4 {
+        assert_eq!(updater.update_once().await.unwrap().public_ip, PUBLIC_IP);
+    }
+}
+
+#[tokio::test]
+async fn ip_is_extracted_from_json_and_regex_responses() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "client": { "ip": PUBLIC_IP } })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("<p>Current IP Address: {}</p>", PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // Beide Dienste müssen übereinstimmen, also muss jede Antwort richtig ausgelesen werden
+    let mut config = config(&server);
+    config.ip.services = vec![
+        format!("{}/json json:/client/ip", server.uri()),
+        format!(r"{}/status regex:Address: ([0-9.]+)", server.uri()),
+    ];
+    config.ip.strategy = IpStrategy::Consensus;
+    assert!(config.problems().is_empty(), "unexpected problems: {:?}", config.problems());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.public_ip, PUBLIC_IP);
+}
+
+#[tokio::test]
+async fn failure_streaks_are_notified_once_and_on_recovery() {
+    let server = MockServer::start().await;
+    // Die ersten drei Abfragen der IP scheitern, danach antwortet der Dienst wieder
+    Mock::given(method("GET"))
+        .and(path("/ip"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(3)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(json!({ "event": "update_failed", "component": "IPv4 detection", "failures": 2 })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(json!({ "event": "recovered", "component": "IPv4 detection", "failures": 3 })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut config = config(&server);
+    config.notify.webhook_urls = vec![Secret::new(format!("{}/hook", server.uri()))];
+    config.notify.failure_threshold = 2;
+    let mut updater = Updater::new(config).unwrap();
+    for _ in 0..3 {
+        assert!(matches!(updater.update_once().await, Err(CrondesError::IpDetection(_))));
+    }
+    updater.update_once().await.unwrap();
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let server = MockServer::start().await;
+    // Der erste Lesezugriff schlägt fehl, danach antwortet die API normal
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(failure(502, "Bad gateway"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, "198.51.100.1").await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // Der Daemon wiederholt den Zyklus nach dem Backoff von höchstens einer Sekunde
+    let updater = Updater::new(config(&server)).unwrap();
+    updater.run_until(tokio::time::sleep(Duration::from_secs(3))).await.unwrap();
+}
+
+#[tokio::test]
+async fn credentials_are_checked_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .respond_with(success(json!({ "id": "t1", "status": "active" })))
+        .expect(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    let mut updater = Updater::new(config(&server)).unwrap();
+    updater.update_once().await.unwrap();
+    updater.update_once().await.unwrap();
+}
+
+#[tokio::test]
+async fn unchanged_records_are_not_reread_every_cycle() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    let mut config = config(&server);
+    config.reread_every_n_cycles = 3;
+    let mut updater = Updater::new(config).unwrap();
+    // Zyklus 1 liest den Record einmal, Zyklus 2 verlässt sich auf den Cache
+    updater.update_once().await.unwrap();
+    updater.update_once().await.unwrap();
+}
+
+#[tokio::test]
+async fn records_are_only_checked_after_a_failed_read() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(failure(404, "Record not found"))
+        .expect(2)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([record(PUBLIC_IP)])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    // Der gescheiterte Lesezugriff löst die Prüfung aus, deren Meldung die genauere ist
+    let err = Updater::new(config(&server)).unwrap().update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Records { failed: 1, exit_code: 2, .. }), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn proxied_records_are_read_from_the_api() {
+    let server = MockServer::start().await;
+    let mut proxied = record(PUBLIC_IP);
+    proxied["proxied"] = json!(true);
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(proxied))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let mut config = config(&server);
+    config.record_check = RecordCheck::Dns;
+    let mut updater = Updater::new(config).unwrap();
+    // Die Nameserver liefern für proxied Records Cloudflare-Adressen, deshalb muss die API gelesen werden
+    let report = updater.update_once().await.unwrap();
+    assert_eq!(report.updated, 0);
+}
+
+#[tokio::test]
+async fn control_api_requires_the_token() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    // Freien Port reservieren und wieder freigeben, damit der Daemon ihn binden kann
+    let bind = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let mut config = config(&server);
+    config.server.bind = bind.clone();
+    config.server.api_token = Secret::new("control-token");
+    let updater = Updater::new(config).unwrap();
+    let daemon = tokio::spawn(updater.run_until(tokio::time::sleep(Duration::from_secs(2))));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let unauthorized = client.get(format!("http://{}/status", bind)).send().await.unwrap();
+    assert_eq!(unauthorized.status(), 401);
+    let records: Value = client
+        .get(format!("http://{}/records", bind))
+        .bearer_auth("control-token")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(records[0]["id"], "r1");
+    assert_eq!(records[0]["ip"], PUBLIC_IP);
+    let cycles: Value =
+        client.get(format!("http://{}/cycles", bind)).bearer_auth("control-token").send().await.unwrap().json().await.unwrap();
+    assert_eq!(cycles[0]["error"], Value::Null);
+    // Die Dashboard-Seite selbst enthält keine Daten und braucht deshalb keinen Token
+    let dashboard = client.get(format!("http://{}/", bind)).send().await.unwrap();
+    assert_eq!(dashboard.status(), 200);
+    assert!(dashboard.text().await.unwrap().contains("/trigger"));
+    let paused = client.post(format!("http://{}/pause", bind)).bearer_auth("control-token").send().await.unwrap();
+    assert!(paused.status().is_success());
+    let status: Value =
+        client.get(format!("http://{}/status", bind)).bearer_auth("control-token").send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["paused"], true);
+    assert_eq!(status["public_ip"], PUBLIC_IP);
+    daemon.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn webhook_mode_writes_the_reported_ip() {
+    let server = MockServer::start().await;
+    // Im Webhook-Modus wird die IP nicht selbst ermittelt
+    Mock::given(method("GET"))
+        .and(path("/ip"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP))
+        .expect(0)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_partial_json(json!({ "content": "198.51.100.9" })))
+        .respond_with(success(record("198.51.100.9")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let bind = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let mut config = config(&server);
+    config.update_mode = UpdateMode::Webhook;
+    config.server.bind = bind.clone();
+    config.server.webhook_token = Secret::new("hook-token");
+    let updater = Updater::new(config).unwrap();
+    let daemon = tokio::spawn(updater.run_until(tokio::time::sleep(Duration::from_secs(2))));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let unauthorized = client.get(format!("http://{}/webhook?token=wrong&ip=198.51.100.9", bind)).send().await.unwrap();
+    assert_eq!(unauthorized.status(), 401);
+    let invalid = client.post(format!("http://{}/webhook?token=hook-token", bind)).body("not an ip").send().await.unwrap();
+    assert_eq!(invalid.status(), 400);
+    let accepted = client
+        .post(format!("http://{}/webhook", bind))
+        .bearer_auth("hook-token")
+        .json(&json!({ "ip": "198.51.100.9" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(accepted.status(), 202);
+    daemon.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn webhooks_report_the_ip_to_a_single_job() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z2"))
+        .respond_with(success(json!({ "id": "z2", "name": "example.org", "permissions": ["#dns_records:read", "#dns_records:edit"] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z2/dns_records/r2"))
+        .respond_with(success(json!({ "id": "r2", "name": "home.example.org", "type": "A", "content": PUBLIC_IP, "ttl": 1, "proxied": false })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z2/dns_records/r2"))
+        .and(body_partial_json(json!({ "content": "198.51.100.9" })))
+        .respond_with(success(json!({ "id": "r2", "name": "home.example.org", "type": "A", "content": "198.51.100.9", "ttl": 1, "proxied": false })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // Der Router eines Haushalts darf die Records des anderen nicht umschreiben
+    Mock::given(method("PUT")).and(path("/client/v4/zones/z1/dns_records/r1")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let bind = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let file = std::env::temp_dir().join(format!("crondes-webhook-jobs-{}.toml", std::process::id()));
+    std::fs::write(
+        &file,
+        format!(
+            r#"
+cloudflare_api_token = "test-token"
+cloudflare_api_url = "{uri}/client/v4"
+update_mode = "webhook"
+network_watch = false
+config_watch = false
+
+[server]
+bind = "{bind}"
+webhook_token = "hook-token"
+
+[ip]
+allow_private = true
+
+[[jobs]]
+name = "home"
+cloudflare_zone_id = "z1"
+records = [{{ id = "r1", name = "home.example.com" }}]
+
+[[jobs]]
+name = "parents"
+cloudflare_zone_id = "z2"
+records = [{{ id = "r2", name = "home.example.org" }}]
+"#,
+            uri = server.uri(),
+            bind = bind
+        ),
+    )
+    .unwrap();
+    let source = ConfigSource { file: Some(file.to_string_lossy().into_owned()), ..ConfigSource::default() };
+    let updaters = ["home", "parents"].map(|name| {
+        let source = source.job(name);
+        Updater::new(source.load().unwrap()).unwrap().reloadable(source)
+    });
+    let _ = std::fs::remove_file(&file);
+    let daemon = tokio::spawn(Updater::run_all_until(updaters.into(), tokio::time::sleep(Duration::from_secs(2))));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let client = reqwest::Client::new();
+    let webhook = |path: &str| client.post(format!("http://{}{}?token=hook-token&ip=198.51.100.9", bind, path)).send();
+    assert_eq!(webhook("/webhook").await.unwrap().status(), 400);
+    assert_eq!(webhook("/webhook/cousins").await.unwrap().status(), 404);
+    assert_eq!(webhook("/webhook/parents").await.unwrap().status(), 202);
+    daemon.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn health_and_status_are_tracked_per_job() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z2"))
+        .respond_with(success(json!({ "id": "z2", "name": "example.org", "permissions": ["#dns_records:read", "#dns_records:edit"] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET")).and(path("/client/v4/zones/z2/dns_records/r2")).respond_with(failure(404, "Record not found")).mount(&server).await;
+
+    let bind = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let file = std::env::temp_dir().join(format!("crondes-health-jobs-{}.toml", std::process::id()));
+    std::fs::write(
+        &file,
+        format!(
+            r#"
+cloudflare_api_token = "test-token"
+cloudflare_api_url = "{uri}/client/v4"
+update_interval_secs = 1
+network_watch = false
+config_watch = false
+
+[server]
+bind = "{bind}"
+api_token = "control-token"
+health_max_intervals = 1
+
+[ip]
+allow_private = true
+sources = ["http"]
+services = ["{uri}/ip"]
+
+[retry]
+backoff_base_secs = 1
+backoff_max_secs = 1
+
+[[jobs]]
+name = "home"
+cloudflare_zone_id = "z1"
+records = [{{ id = "r1", name = "home.example.com" }}]
+
+[[jobs]]
+name = "parents"
+cloudflare_zone_id = "z2"
+records = [{{ id = "r2", name = "home.example.org" }}]
+"#,
+            uri = server.uri(),
+            bind = bind
+        ),
+    )
+    .unwrap();
+    let source = ConfigSource { file: Some(file.to_string_lossy().into_owned()), ..ConfigSource::default() };
+    let updaters = ["home", "parents"].map(|name| {
+        let source = source.job(name);
+        Updater::new(source.load().unwrap()).unwrap().reloadable(source)
+    });
+    let _ = std::fs::remove_file(&file);
+    let daemon = tokio::spawn(Updater::run_all_until(updaters.into(), tokio::time::sleep(Duration::from_secs(4))));
+    tokio::time::sleep(Duration::from_millis(3000)).await;
+
+    let client = reqwest::Client::new();
+    // Der Job "home" ist gesund, der fehlschlagende Job "parents" macht den Daemon trotzdem ungesund
+    let health = client.get(format!("http://{}/healthz", bind)).send().await.unwrap();
+    assert_eq!(health.status(), 503);
+    assert!(health.text().await.unwrap().contains("job parents"));
+    let status: Value =
+        client.get(format!("http://{}/status", bind)).bearer_auth("control-token").send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["public_ip"], PUBLIC_IP);
+    assert!(status["last_success"].as_u64().unwrap() > 0);
+    assert!(status["consecutive_failures"].as_u64().unwrap() > 0);
+    daemon.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn a_job_giving_up_leaves_the_other_jobs_running() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/user/tokens/verify"))
+        .and(header("Authorization", "Bearer revoked-token"))
+        .respond_with(failure(401, "Invalid API Token"))
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    let file = std::env::temp_dir().join(format!("crondes-give-up-jobs-{}.toml", std::process::id()));
+    std::fs::write(
+        &file,
+        format!(
+            r#"
+cloudflare_api_token = "test-token"
+cloudflare_api_url = "{uri}/client/v4"
+update_interval_secs = 1
+reread_every_n_cycles = 1
+exit_on_error = true
+network_watch = false
+config_watch = false
+
+[ip]
+allow_private = true
+sources = ["http"]
+services = ["{uri}/ip"]
+
+[[jobs]]
+name = "home"
+cloudflare_zone_id = "z1"
+records = [{{ id = "r1", name = "home.example.com" }}]
+
+[[jobs]]
+name = "parents"
+cloudflare_api_token = "revoked-token"
+cloudflare_zone_id = "z2"
+records = [{{ id = "r2", name = "home.example.org" }}]
+"#,
+            uri = server.uri()
+        ),
+    )
+    .unwrap();
+    let source = ConfigSource { file: Some(file.to_string_lossy().into_owned()), ..ConfigSource::default() };
+    let updaters = ["home", "parents"].map(|name| {
+        let source = source.job(name);
+        Updater::new(source.load().unwrap()).unwrap().reloadable(source)
+    });
+    let _ = std::fs::remove_file(&file);
+
+    // Der Job "parents" gibt sofort auf, "home" läuft bis zum Shutdown weiter und meldet danach den Fehler
+    let err = Updater::run_all_until(updaters.into(), tokio::time::sleep(Duration::from_millis(2500))).await.unwrap_err();
+    assert!(matches!(err, CrondesError::Auth(_)), "unexpected error: {}", err);
+    let requests = server.received_requests().await.unwrap();
+    let reads = requests.iter().filter(|r| r.url.path() == "/client/v4/zones/z1/dns_records/r1").count();
+    assert!(reads >= 2, "job home ran {} cycles", reads);
+}
+
+#[tokio::test]
+async fn sync_file_creates_and_updates_records() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let www = json!({ "id": "r2", "name": "www.example.com", "type": "CNAME", "content": "old.example.net", "ttl": 3600, "proxied": false });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([record(PUBLIC_IP), www])))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .and(body_partial_json(json!({ "type": "CNAME", "content": "example.github.io", "ttl": 3600 })))
+        .respond_with(success(json!({ "id": "r2", "name": "www.example.com", "type": "CNAME", "content": "example.github.io", "ttl": 3600 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .and(body_partial_json(json!({ "type": "A", "name": "vpn.example.com", "content": PUBLIC_IP, "ttl": 300 })))
+        .respond_with(success(json!({ "id": "r3", "name": "vpn.example.com", "type": "A", "content": PUBLIC_IP, "ttl": 300 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let sync_file = std::env::temp_dir().join(format!("crondes-sync-{}.toml", std::process::id()));
+    std::fs::write(
+        &sync_file,
+        r#"
+[[records]]
+name = "home.example.com"
+content = "@public_ip"
+
+[[records]]
+name = "www.example.com"
+type = "CNAME"
+content = "example.github.io"
+
+[[records]]
+name = "vpn.example.com"
+content = "@public_ip"
+ttl = 300
+"#,
+    )
+    .unwrap();
+    let config = Config { sync_file: sync_file.to_string_lossy().into_owned(), ..config(&server) };
+    let result = Updater::new(config).unwrap().update_once().await;
+    let _ = std::fs::remove_file(&sync_file);
+    let report = result.unwrap();
+    assert_eq!(report.updated, 2);
+    assert_eq!(report.total, 4);
+}
+
+#[tokio::test]
+async fn sync_prune_deletes_records_no_entry_asks_for() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let stale = json!({ "id": "r2", "name": "home.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false });
+    let other = json!({ "id": "r3", "name": "mail.example.com", "type": "A", "content": "198.51.100.2", "ttl": 1, "proxied": false });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([record(PUBLIC_IP), stale, other])))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .respond_with(success(json!({ "id": "r2" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE")).respond_with(success(json!({ "id": "r3" }))).expect(0).mount(&server).await;
+
+    let sync_file = std::env::temp_dir().join(format!("crondes-prune-{}.toml", std::process::id()));
+    std::fs::write(&sync_file, "[[records]]\nname = \"home.example.com\"\ncontent = \"@public_ip\"\n").unwrap();
+    let config = Config { sync_file: sync_file.to_string_lossy().into_owned(), sync_prune: true, ..config(&server) };
+    let result = Updater::new(config).unwrap().update_once().await;
+    let _ = std::fs::remove_file(&sync_file);
+    assert_eq!(result.unwrap().updated, 1);
+}
+
+#[tokio::test]
+async fn set_content_only_changes_the_content() {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_json(json!({ "content": PUBLIC_IP })))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // Kommentar, TTL und Proxy-Status aus der Konfiguration gelten nur für automatische Updates
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let config = Config { cloudflare_comment: "managed by crondes".to_string(), cloudflare_ttl: Some(300), ..config(&server) };
+    let client = http::build_client(&config.http).unwrap();
+    let provider = provider::from_config(ProviderKind::Cloudflare, &config, client).unwrap();
+    let response = provider.set_content(&config.records[0], PUBLIC_IP).await.unwrap();
+    assert_eq!(response, format!("A home.example.com is now {}", PUBLIC_IP));
+}
+
+#[tokio::test]
+async fn record_pattern_updates_all_matching_a_records_in_one_batch() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let nas = json!({ "id": "r2", "name": "nas.home.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false });
+    let vpn = json!({ "id": "r3", "name": "VPN.home.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false });
+    let txt = json!({ "id": "r4", "name": "txt.home.example.com", "type": "TXT", "content": "hello", "ttl": 1, "proxied": false });
+    let other = json!({ "id": "r5", "name": "home.example.com.evil", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([record(PUBLIC_IP), nas.clone(), vpn.clone(), txt, other])))
+        .mount(&server)
+        .await;
+    for (id, rec) in [("r2", nas), ("r3", vpn)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/client/v4/zones/z1/dns_records/{}", id)))
+            .respond_with(success(rec))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/client/v4/zones/z1/dns_records/batch"))
+        .and(body_partial_json(json!({ "patches": [{ "id": "r2", "content": PUBLIC_IP }, { "id": "r3", "content": PUBLIC_IP }] })))
+        .respond_with(success(json!({ "patches": [record(PUBLIC_IP), record(PUBLIC_IP)] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let config = Config { records: Vec::new(), record_pattern: "*.home.example.com".to_string(), ..config(&server) };
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 2);
+    assert_eq!(report.total, 2);
+}
+
+#[tokio::test]
+async fn updates_keep_the_tags_of_the_record() {
+    let server = MockServer::start().await;
+    let mut tagged = record("198.51.100.1");
+    tagged["tags"] = json!(["owner:alice", "env:home"]);
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .respond_with(success(tagged))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    // Ohne CF_TAGS schickt der PUT die vorhandenen Tags unverändert zurück
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_partial_json(json!({ "content": PUBLIC_IP, "tags": ["owner:alice", "env:home"] })))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let report = Updater::new(config(&server)).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+}
+
+#[tokio::test]
+async fn record_tag_discovers_records_and_cf_tags_are_added() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let tagged = json!({
+        "id": "r2", "name": "nas.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false,
+        "tags": ["managed-by:crondes", "owner:alice"],
+    });
+    let untagged = json!({
+        "id": "r3", "name": "vpn.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false,
+        "tags": ["owner:bob"],
+    });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([tagged.clone(), untagged])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .respond_with(success(tagged.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .and(body_partial_json(json!({ "content": PUBLIC_IP, "tags": ["owner:alice", "managed-by:crondes", "env:home"] })))
+        .respond_with(success(tagged))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let config = Config {
+        record_tag: "managed-by:crondes".to_string(),
+        cloudflare_tags: vec!["managed-by:crondes".to_string(), "env:home".to_string()],
+        ..config(&server)
+    };
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+    assert_eq!(report.total, 2);
+}
+
+#[tokio::test]
+async fn records_found_by_tag_keep_the_tag_without_cf_tags() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let tagged = json!({
+        "id": "r2", "name": "nas.example.com", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false,
+        "tags": ["managed-by:crondes", "owner:alice"],
+    });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records"))
+        .respond_with(success(json!([tagged.clone()])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .respond_with(success(tagged.clone()))
+        .mount(&server)
+        .await;
+    // Ein einzelner fälliger Record läuft über den PUT und darf den Tag, über den er gefunden wurde, nicht verlieren
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r2"))
+        .and(body_partial_json(json!({ "content": PUBLIC_IP, "tags": ["managed-by:crondes", "owner:alice"] })))
+        .respond_with(success(tagged))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    let config = Config { record_tag: "managed-by:crondes".to_string(), ..config(&server) };
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+}
+
+#[tokio::test]
+async fn records_override_type_ttl_and_ip_source() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, "198.51.100.1").await;
+    let v6 = "2001:db8::7";
+    Mock::given(method("GET")).and(path("/ip6")).respond_with(ResponseTemplate::new(200).set_body_string(v6)).mount(&server).await;
+    let aaaa = |content: &str| json!({ "id": "r6", "name": "v6.example.com", "type": "AAAA", "content": content, "ttl": 1, "proxied": false });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r6"))
+        .respond_with(success(aaaa("2001:db8::1")))
+        .mount(&server)
+        .await;
+    // Unterschiedliche Ziel-IPs landen nicht im selben Batch
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r1"))
+        .and(body_partial_json(json!({ "type": "A", "content": PUBLIC_IP, "ttl": 1 })))
+        .respond_with(success(record(PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r6"))
+        .and(body_partial_json(json!({ "type": "AAAA", "content": v6, "ttl": 300, "proxied": true })))
+        .respond_with(success(aaaa(v6)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut config = config(&server);
+    config.records.push(
+        serde_json::from_value(json!({
+            "id": "r6",
+            "name": "v6.example.com",
+            "type": "AAAA",
+            "ttl": 300,
+            "proxied": true,
+            "ip_sources": ["http"],
+            "ip_services": [format!("{}/ip6", server.uri())],
+        }))
+        .unwrap(),
+    );
+    assert!(config.problems().is_empty(), "unexpected problems: {:?}", config.problems());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.public_ip, format!("{}, {}", PUBLIC_IP, v6));
+    assert_eq!(report.updated, 2);
+    assert_eq!(report.total, 2);
+}
+
+#[tokio::test]
+async fn aaaa_records_combine_the_prefix_with_their_interface_id() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/ip6"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("2001:db8:1:2::7"))
+        .mount(&server)
+        .await;
+    let mut records = Vec::new();
+    for (id, interface_id, prefix_length, expected) in [
+        ("r8", "02:11:22:33:44:55", None, "2001:db8:1:2:11:22ff:fe33:4455"),
+        ("r9", "0:0:0:1::5", Some(56), "2001:db8:1:1::5"),
+    ] {
+        let aaaa = |content: &str| json!({ "id": id, "name": "host.example.com", "type": "AAAA", "content": content, "ttl": 1, "proxied": false });
+        Mock::given(method("GET"))
+            .and(path(format!("/client/v4/zones/z1/dns_records/{}", id)))
+            .respond_with(success(aaaa("2001:db8:9::1")))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(format!("/client/v4/zones/z1/dns_records/{}", id)))
+            .and(body_partial_json(json!({ "content": expected })))
+            .respond_with(success(aaaa(expected)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        records.push(
+            serde_json::from_value(json!({
+                "id": id,
+                "type": "AAAA",
+                "interface_id": interface_id,
+                "prefix_length": prefix_length,
+                "ip_services": [format!("{}/ip6", server.uri())],
+            }))
+            .unwrap(),
+        );
+    }
+
+    let mut config = config(&server);
+    config.records.extend(records);
+    assert!(config.problems().is_empty(), "unexpected problems: {:?}", config.problems());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 2);
+}
+
+#[tokio::test]
+async fn ipv6_records_run_on_their_own_interval() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let v6 = "2001:db8::7";
+    Mock::given(method("GET")).and(path("/ip6")).respond_with(ResponseTemplate::new(200).set_body_string(v6)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r6"))
+        .respond_with(success(json!({ "id": "r6", "name": "v6.example.com", "type": "AAAA", "content": v6, "ttl": 1, "proxied": false })))
+        .mount(&server)
+        .await;
+
+    let mut config = Config { update_interval_secs: 60, update_interval_secs_v6: 1, network_watch: false, ..config(&server) };
+    config.records.push(
+        serde_json::from_value(json!({ "id": "r6", "type": "AAAA", "ip_services": [format!("{}/ip6", server.uri())] })).unwrap(),
+    );
+    let updater = Updater::new(config).unwrap();
+    updater.run_until(tokio::time::sleep(Duration::from_millis(2500))).await.unwrap();
+
+    // Nach 2,5 Sekunden lief IPv4 nur beim Start, IPv6 zusätzlich nach jeder Sekunde
+    let requests = server.received_requests().await.unwrap();
+    let hits = |p: &str| requests.iter().filter(|r| r.url.path() == p).count();
+    assert_eq!(hits("/ip"), 1);
+    assert_eq!(hits("/ip6"), 3);
+}
+
+#[tokio::test]
+async fn cycles_are_skipped_while_offline() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    // Ein Fehlschlag würde den Daemon beenden, übersprungene Zyklen dürfen das nicht
+    let mut config = Config {
+        update_interval_secs: 1,
+        network_watch: false,
+        connectivity_check: ConnectivityCheck::Tcp,
+        connectivity_target: closed.to_string(),
+        exit_on_error: true,
+        ..config(&server)
+    };
+    config.retry.max_consecutive_failures = 1;
+    Updater::new(config.clone()).unwrap().run_until(tokio::time::sleep(Duration::from_millis(1500))).await.unwrap();
+    assert!(server.received_requests().await.unwrap().is_empty());
+
+    config.connectivity_target = server.address().to_string();
+    Updater::new(config).unwrap().run_until(tokio::time::sleep(Duration::from_millis(500))).await.unwrap();
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path() == "/ip").count(), 1);
+}
+
+#[tokio::test]
+async fn failures_during_the_startup_grace_period_are_retried() {
+    let server = MockServer::start().await;
+    // Beim Booten ist der IP-Dienst zunächst nicht erreichbar
+    Mock::given(method("GET"))
+        .and(path("/ip"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_healthy(&server, PUBLIC_IP).await;
+
+    // Ohne Schonfrist würde der erste Fehlschlag den Daemon beenden
+    let mut config = Config { network_watch: false, exit_on_error: true, startup_grace_secs: 60, ..config(&server) };
+    config.retry.max_consecutive_failures = 1;
+    Updater::new(config).unwrap().run_until(tokio::time::sleep(Duration::from_millis(1500))).await.unwrap();
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path() == "/ip").count(), 2);
+}
+
+#[tokio::test]
+async fn status_file_is_written_after_each_cycle() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let status_file = std::env::temp_dir().join(format!("crondes-status-{}.json", std::process::id()));
+
+    let config = Config { network_watch: false, status_file: status_file.display().to_string(), ..config(&server) };
+    Updater::new(config).unwrap().run_until(tokio::time::sleep(Duration::from_millis(500))).await.unwrap();
+    let status: Value = serde_json::from_str(&std::fs::read_to_string(&status_file).unwrap()).unwrap();
+    std::fs::remove_file(&status_file).unwrap();
+    assert_eq!(status["status"]["consecutive_failures"], 0);
+    assert!(status["status"]["next_cycle"].is_u64());
+    assert_eq!(status["records"][0]["name"], "home.example.com");
+    assert_eq!(status["records"][0]["ip"], PUBLIC_IP);
+}
+
+#[tokio::test]
+async fn state_is_kept_per_address_family() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let v6 = "2001:db8::7";
+    Mock::given(method("GET")).and(path("/ip6")).respond_with(ResponseTemplate::new(200).set_body_string(v6)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r6"))
+        .respond_with(success(json!({ "id": "r6", "name": "v6.example.com", "type": "AAAA", "content": v6, "ttl": 1, "proxied": false })))
+        .mount(&server)
+        .await;
+    let state_file = std::env::temp_dir().join(format!("crondes-state-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&state_file);
+    let config_with = |ip6: &str| {
+        let mut config = Config { state_file: state_file.to_string_lossy().into_owned(), ..config(&server) };
+        config.records.push(
+            serde_json::from_value(json!({ "id": "r6", "type": "AAAA", "ip_services": [format!("{}{}", server.uri(), ip6)] })).unwrap(),
+        );
+        config
+    };
+
+    // Die IPv6-Erkennung scheitert, der IPv4-State wird trotzdem gespeichert
+    let err = Updater::new(config_with("/ip6-down")).unwrap().update_once().await.unwrap_err();
+    assert!(matches!(err, CrondesError::Records { failed: 1, total: 2, .. }), "unexpected error: {}", err);
+    let state: Value = serde_json::from_str(&std::fs::read_to_string(&state_file).unwrap()).unwrap();
+    assert_eq!(state["ip"], PUBLIC_IP);
+    assert!(state.get("ipv6").is_none());
+
+    // Nach dem Neustart wird nur der AAAA-Record gelesen, der A-Record nur einmal im ersten Lauf
+    Updater::new(config_with("/ip6")).unwrap().update_once().await.unwrap();
+    let state: Value = serde_json::from_str(&std::fs::read_to_string(&state_file).unwrap()).unwrap();
+    assert_eq!(state["ipv6"], v6);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path() == "/client/v4/zones/z1/dns_records/r1").count(), 1);
+    let _ = std::fs::remove_file(&state_file);
+}
+
+#[tokio::test]
+async fn txt_record_is_rendered_from_its_template() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let txt = |content: &str| json!({ "id": "r7", "name": "beacon.example.com", "type": "TXT", "content": content, "ttl": 1, "proxied": false });
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z1/dns_records/r7"))
+        .respond_with(success(txt("\"beacon.example.com at 198.51.100.1\"")))
+        .mount(&server)
+        .await;
+    let rendered = format!("beacon.example.com at {}", PUBLIC_IP);
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z1/dns_records/r7"))
+        .and(body_partial_json(json!({ "type": "TXT", "content": rendered })))
+        .respond_with(success(txt(&rendered)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut config = config(&server);
+    config.records.push(
+        serde_json::from_value(json!({ "id": "r7", "name": "beacon.example.com", "type": "TXT", "content": "{record} at {ip}" }))
+            .unwrap(),
+    );
+    assert!(config.problems().is_empty(), "unexpected problems: {:?}", config.problems());
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+    assert_eq!(report.total, 2);
+}
+
+#[tokio::test]
+async fn records_are_updated_concurrently() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    let mut records = Vec::new();
+    for n in 2..8 {
+        let id = format!("r{}", n);
+        let name = format!("host{}.example.com", n);
+        Mock::given(method("GET"))
+            .and(path(format!("/client/v4/zones/z1/dns_records/{}", id)))
+            .respond_with(
+                success(json!({ "id": id, "name": name, "type": "A", "content": PUBLIC_IP, "ttl": 1, "proxied": false }))
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+        records.push(RecordConfig { id, name, ..RecordConfig::default() });
+    }
+
+    // Nacheinander bräuchten die 6 Records mit je zwei Requests mindestens 3,6 Sekunden
+    let config = Config { records, update_concurrency: 6, ..config(&server) };
+    let started = std::time::Instant::now();
+    let report = Updater::new(config).unwrap().update_once().await.unwrap();
+    assert_eq!(report.total, 6);
+    assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn jobs_update_their_own_zones_and_records() {
+    let server = MockServer::start().await;
+    mount_healthy(&server, PUBLIC_IP).await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z2"))
+        .respond_with(success(json!({ "id": "z2", "name": "example.org", "permissions": ["#dns_records:read", "#dns_records:edit"] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/client/v4/zones/z2/dns_records/r2"))
+        .respond_with(success(json!({ "id": "r2", "name": "home.example.org", "type": "A", "content": "198.51.100.1", "ttl": 1, "proxied": false })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/client/v4/zones/z2/dns_records/r2"))
+        .and(body_partial_json(json!({ "content": PUBLIC_IP })))
+        .respond_with(success(json!({ "id": "r2", "name": "home.example.org", "type": "A", "content": PUBLIC_IP, "ttl": 1, "proxied": false })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT")).and(path("/client/v4/zones/z1/dns_records/r1")).respond_with(success(record(PUBLIC_IP))).expect(0).mount(&server).await;
+
+    // Die Einstellungen der obersten Ebene gelten für beide Jobs, jeder Job hat seine eigene Zone
+    let file = std::env::temp_dir().join(format!("crondes-jobs-{}.toml", std::process::id()));
+    let content = format!(
+        r#"
+cloudflare_api_token = "test-token"
+cloudflare_api_url = "{uri}/client/v4"
+update_interval_secs = 300
+
+[ip]
+sources = ["http"]
+services = ["{uri}/ip"]
+allow_private = true
+
+[[jobs]]
+name = "home"
+cloudflare_zone_id = "z1"
+records = [{{ id = "r1", name = "home.example.com" }}]
+
+[[jobs]]
+name = "parents"
+cloudflare_zone_id = "z2"
+update_interval_secs = 600
+records = [{{ id = "r2", name = "home.example.org" }}]
+"#,
+        uri = server.uri()
+    );
+    std::fs::write(&file, &content).unwrap();
+    let source = ConfigSource { file: Some(file.to_string_lossy().into_owned()), ..ConfigSource::default() };
+    let top = source.load();
+    let jobs = ["home", "parents"].map(|name| source.job(name).load());
+    // Jobs, die sich eine Zustandsdatei teilen, werden abgelehnt
+    std::fs::write(&file, format!("state_file = \"/tmp/crondes-state.json\"\n{}", content)).unwrap();
+    let shared = source.load();
+    let _ = std::fs::remove_file(&file);
+
+    assert_eq!(top.unwrap().jobs.len(), 2);
+    let [home, parents] = jobs.map(Result::unwrap);
+    assert_eq!(home.update_interval_secs, 300);
+    assert_eq!(parents.update_interval_secs, 600);
+    assert!(parents.jobs.is_empty());
+    let home = Updater::new(home).unwrap().update_once().await.unwrap();
+    assert_eq!(home.updated, 0);
+    let parents = Updater::new(parents).unwrap().update_once().await.unwrap();
+    assert_eq!(parents.updated, 1);
+    assert!(shared.unwrap_err().contains("share the STATE_FILE"));
+}
+
+/// Builds a config for the dyndns2 provider with its update URL at `server` and the record `name`.
+fn dyndns2_config(server: &MockServer, name: &str) -> Config {
+    let mut config = Config {
+        provider: ProviderKind::Dyndns2,
+        records: vec![RecordConfig { name: name.to_string(), ..RecordConfig::default() }],
+        ..config(server)
+    };
+    config.dyndns2.url = format!("{}/nic/update", server.uri());
+    config.dyndns2.username = "alice".to_string();
+    config.dyndns2.password = Secret::new("secret");
+    config
+}
+
+#[tokio::test]
+async fn hosts_without_an_a_record_are_pushed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/ip")).respond_with(ResponseTemplate::new(200).set_body_string(PUBLIC_IP)).mount(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/nic/update"))
+        .and(header("Authorization", "Basic YWxpY2U6c2VjcmV0"))
+        .and(query_param("hostname", "new.crondes.invalid"))
+        .and(query_param("myip", PUBLIC_IP))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("good {}", PUBLIC_IP)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // Ein frisch angelegter Host hat noch keinen A-Record, das erste Update legt ihn an
+    let report = Updater::new(dyndns2_config(&server, "new.crondes.invalid")).unwrap().update_once().await.unwrap();
+    assert_eq!(report.updated, 1);
+}
+
+#[tokio::test]
+async fn dyndns2_return_codes_are_mapped_to_errors() {
+    for (answer, expected) in [
+        ("good 203.0.113.7", Ok(())),
+        ("nochg 203.0.113.7", Ok(())),
+        ("badauth", Err(3)),
+        ("nohost", Err(2)),
+        ("911", Err(6)),
+    ] {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/nic/update"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(answer))
+            .mount(&server)
+            .await;
+        let config = dyndns2_config(&server, "home.example.com");
+        let client = http::build_client(&config.http).unwrap();
+        let provider = provider::from_config(ProviderKind::Dyndns2, &config, client).unwrap();
+        let result = provider.update_ip(&config.records[0], PUBLIC_IP).await;
+        match (&result, expected) {
+            (Ok(response), Ok(())) => assert_eq!(response, answer),
+            (Err(e), Err(exit_code)) => {
+                assert_eq!(e.exit_code(), exit_code, "{} gave {}", answer, e);
+                // 911 ist eine Störung beim Anbieter und wird im nächsten Zyklus wiederholt
+                assert_eq!(e.is_fatal(), answer != "911", "{} gave {}", answer, e);
+            }
+            _ => panic!("{} gave {:?}", answer, result),
+        }
+    }
+}
+
+#[test]
+fn notification_urls_are_masked_in_debug_output() {
+    let token = "T0000/B0000/XXXXXXXXXXXXXXXX";
+    let set = |key: &str, value: &str| (key.to_string(), value.to_string());
+    let source = ConfigSource {
+        overrides: vec![
+            set("CF_API_TOKEN", "test-token"),
+            set("CF_ZONE_ID", "zone"),
+            set("CF_RECORD_NAME", "home.example.com"),
+            set("NOTIFY_WEBHOOK_URLS", &format!("https://example.com/hook/{}", token)),
+            set("NOTIFY_DISCORD_URLS", &format!("https://discord.com/api/webhooks/{}", token)),
+            set("NOTIFY_SLACK_URLS", &format!("https://hooks.slack.com/services/{}", token)),
+        ],
+        ..ConfigSource::default()
+    };
+    let config = source.load().unwrap();
+    assert_eq!(config.notify.slack_urls[0].expose(), format!("https://hooks.slack.com/services/{}", token));
+    let debug = format!("{:?}", config);
+    assert!(!debug.contains(token), "URL leaked: {}", debug);
+}